@@ -11,7 +11,15 @@ pub struct Model {
   pub id: Uuid,
   pub name: String,
   #[sea_orm(has_many)]
+  pub feature_flag_groups: HasMany<super::feature_flag_group::Entity>,
+  #[sea_orm(has_many)]
+  pub group_direct_permissions: HasMany<super::group_direct_permission::Entity>,
+  #[sea_orm(has_many)]
   pub group_permissions: HasMany<super::group_permission::Entity>,
+  #[sea_orm(has_one)]
+  pub organization_group: HasOne<super::organization_group::Entity>,
+  #[sea_orm(has_many, via = "group_role")]
+  pub roles: HasMany<super::role::Entity>,
   #[sea_orm(has_many, via = "group_user")]
   pub users: HasMany<super::user::Entity>,
 }
@@ -2,12 +2,33 @@
 
 pub mod prelude;
 
+pub mod account_deletion;
+pub mod active_organization;
+pub mod attribute_definition;
+pub mod audit_event;
+pub mod avatar_variant;
+pub mod branding_asset;
+pub mod feature_flag;
+pub mod feature_flag_group;
 pub mod group;
+pub mod group_direct_permission;
 pub mod group_permission;
+pub mod group_role;
 pub mod group_user;
 pub mod invalid_jwt;
 pub mod key;
+pub mod login_event;
+pub mod organization;
+pub mod organization_group;
+pub mod organization_member;
+pub mod role;
+pub mod role_permission;
+pub mod session_revocation;
 pub mod settings;
+pub mod settings_revision;
 pub mod setup;
+pub mod super_admin;
 pub mod user;
+pub mod user_attribute;
 pub mod user_avatar;
+pub mod user_status;
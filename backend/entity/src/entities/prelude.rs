@@ -1,11 +1,32 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 2.0
 
+pub use super::account_deletion::Entity as AccountDeletion;
+pub use super::active_organization::Entity as ActiveOrganization;
+pub use super::attribute_definition::Entity as AttributeDefinition;
+pub use super::audit_event::Entity as AuditEvent;
+pub use super::avatar_variant::Entity as AvatarVariant;
+pub use super::branding_asset::Entity as BrandingAsset;
+pub use super::feature_flag::Entity as FeatureFlag;
+pub use super::feature_flag_group::Entity as FeatureFlagGroup;
 pub use super::group::Entity as Group;
+pub use super::group_direct_permission::Entity as GroupDirectPermission;
 pub use super::group_permission::Entity as GroupPermission;
+pub use super::group_role::Entity as GroupRole;
 pub use super::group_user::Entity as GroupUser;
 pub use super::invalid_jwt::Entity as InvalidJwt;
 pub use super::key::Entity as Key;
+pub use super::login_event::Entity as LoginEvent;
+pub use super::organization::Entity as Organization;
+pub use super::organization_group::Entity as OrganizationGroup;
+pub use super::organization_member::Entity as OrganizationMember;
+pub use super::role::Entity as Role;
+pub use super::role_permission::Entity as RolePermission;
+pub use super::session_revocation::Entity as SessionRevocation;
 pub use super::settings::Entity as Settings;
+pub use super::settings_revision::Entity as SettingsRevision;
 pub use super::setup::Entity as Setup;
+pub use super::super_admin::Entity as SuperAdmin;
 pub use super::user::Entity as User;
+pub use super::user_attribute::Entity as UserAttribute;
 pub use super::user_avatar::Entity as UserAvatar;
+pub use super::user_status::Entity as UserStatus;
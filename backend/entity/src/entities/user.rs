@@ -18,9 +18,27 @@ pub struct Model {
   #[sea_orm(unique)]
   pub oidc_subject: Option<String>,
   #[sea_orm(has_one)]
+  pub account_deletion: HasOne<super::account_deletion::Entity>,
+  #[sea_orm(has_one)]
+  pub active_organization: HasOne<super::active_organization::Entity>,
+  #[sea_orm(has_many)]
+  pub login_events: HasMany<super::login_event::Entity>,
+  #[sea_orm(has_one)]
+  pub session_revocation: HasOne<super::session_revocation::Entity>,
+  #[sea_orm(has_many)]
+  pub settings_revisions: HasMany<super::settings_revision::Entity>,
+  #[sea_orm(has_one)]
+  pub super_admin: HasOne<super::super_admin::Entity>,
+  #[sea_orm(has_many)]
+  pub user_attributes: HasMany<super::user_attribute::Entity>,
+  #[sea_orm(has_one)]
   pub user_avatar: HasOne<super::user_avatar::Entity>,
+  #[sea_orm(has_one)]
+  pub user_status: HasOne<super::user_status::Entity>,
   #[sea_orm(has_many, via = "group_user")]
   pub groups: HasMany<super::group::Entity>,
+  #[sea_orm(has_many, via = "organization_member")]
+  pub organizations: HasMany<super::organization::Entity>,
 }
 
 impl ActiveModelBehavior for ActiveModel {}
@@ -11,6 +11,8 @@ pub struct Model {
   pub user_id: Uuid,
   #[sea_orm(column_type = "VarBinary(StringLen::None)")]
   pub data: Vec<u8>,
+  #[sea_orm(has_many)]
+  pub avatar_variants: HasMany<super::avatar_variant::Entity>,
   #[sea_orm(
     belongs_to,
     from = "user_id",
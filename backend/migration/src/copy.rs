@@ -0,0 +1,312 @@
+use std::fmt;
+
+use chrono::{NaiveDateTime, SubsecRound};
+use sea_orm_migration::{
+  MigratorTrait,
+  sea_orm::{
+    ConnectionTrait, DatabaseBackend, DbConn, DbErr, IdenStatic, IntoActiveModel, Iterable,
+    PaginatorTrait, PrimaryKeyToColumn, PrimaryKeyTrait, QueryOrder, Select, sea_query::OnConflict,
+  },
+};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::{
+  Migrator,
+  tables::{Table, TableVisitor, remove_seeded_rows, visit_tables},
+};
+
+/// Rows read and inserted at once.
+pub const BATCH_SIZE: u64 = 200;
+
+pub struct TableReport {
+  pub table: String,
+  pub rows: u64,
+  /// Rows inserted by this run, rows copied before an interruption are kept.
+  pub copied: u64,
+  pub checksum: String,
+}
+
+impl fmt::Display for TableReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{:<24} {:>8} rows {:>8} copied  {}",
+      self.table,
+      self.rows,
+      self.copied,
+      &self.checksum[..16]
+    )
+  }
+}
+
+/// Copies every row of the application from `source` to `target`, e.g. from
+/// SQLite to Postgres. The target is migrated first and rows it already holds
+/// are updated instead of inserted again, so an interrupted copy continues
+/// when run again. Every table is compared by row count and checksum before
+/// the next one is copied.
+pub async fn copy(
+  source: &DbConn,
+  target: &DbConn,
+  progress: impl FnMut(&TableReport),
+) -> Result<Vec<TableReport>, DbErr> {
+  let pending = Migrator::get_pending_migrations(source).await?;
+  if !pending.is_empty() {
+    return Err(DbErr::Custom(format!(
+      "The source database has {} pending migrations, migrate it first",
+      pending.len()
+    )));
+  }
+  let empty = Migrator::get_applied_migrations(target).await?.is_empty();
+  Migrator::up(target, None).await?;
+  if empty {
+    remove_seeded_rows(target).await?;
+  }
+
+  let mut copier = Copier {
+    source,
+    target,
+    progress,
+    reports: Vec::new(),
+  };
+  visit_tables(&mut copier).await?;
+
+  Ok(copier.reports)
+}
+
+struct Copier<'db, F> {
+  source: &'db DbConn,
+  target: &'db DbConn,
+  progress: F,
+  reports: Vec<TableReport>,
+}
+
+impl<F: FnMut(&TableReport)> TableVisitor for Copier<'_, F> {
+  async fn visit<E: Table>(&mut self) -> Result<(), DbErr> {
+    let table = E::default().table_name().to_string();
+    let rows = E::find().count(self.source).await?;
+    let existing = E::find().count(self.target).await?;
+    if existing > rows {
+      return Err(DbErr::Custom(format!(
+        "The target has more rows in {table} than the source, start over with an empty database"
+      )));
+    }
+
+    let mut pages = ordered::<E>().paginate(self.source, BATCH_SIZE);
+    while let Some(models) = pages.fetch_and_next().await? {
+      E::insert_many(models.into_iter().map(IntoActiveModel::into_active_model))
+        .on_conflict(upsert::<E>())
+        .try_insert()
+        .exec(self.target)
+        .await?;
+    }
+    if self.target.get_database_backend() == DatabaseBackend::Postgres {
+      reset_sequence::<E, _>(self.target).await?;
+    }
+
+    let copied = E::find().count(self.target).await? - existing;
+    let source_checksum = checksum::<E, _>(self.source).await?;
+    if copied + existing != rows || checksum::<E, _>(self.target).await? != source_checksum {
+      return Err(DbErr::Custom(format!(
+        "The rows of {table} differ between source and target, was the source changed during the copy?"
+      )));
+    }
+
+    let report = TableReport {
+      table,
+      rows,
+      copied,
+      checksum: source_checksum,
+    };
+    (self.progress)(&report);
+    self.reports.push(report);
+    Ok(())
+  }
+}
+
+/// All rows, in the same order on every database.
+pub fn ordered<E: Table>() -> Select<E> {
+  E::PrimaryKey::iter().fold(E::find(), |query, key| {
+    query.order_by_asc(key.into_column())
+  })
+}
+
+/// Rows already in the target take the values of the source.
+fn upsert<E: Table>() -> OnConflict {
+  let keys: Vec<E::Column> = E::PrimaryKey::iter().map(|key| key.into_column()).collect();
+  let values: Vec<E::Column> = E::Column::iter()
+    .filter(|column| keys.iter().all(|key| key.as_str() != column.as_str()))
+    .collect();
+
+  let mut on_conflict = OnConflict::columns(keys);
+  if values.is_empty() {
+    on_conflict.do_nothing();
+  } else {
+    on_conflict.update_columns(values);
+  }
+  on_conflict
+}
+
+/// Inserting explicit ids does not advance the sequence of serial columns in
+/// Postgres, so the next generated id would collide.
+pub async fn reset_sequence<E: Table, C: ConnectionTrait>(db: &C) -> Result<(), DbErr> {
+  let mut keys = E::PrimaryKey::iter();
+  let (Some(key), None) = (keys.next(), keys.next()) else {
+    return Ok(());
+  };
+  if !E::PrimaryKey::auto_increment() {
+    return Ok(());
+  }
+
+  let table = E::default().table_name().to_string();
+  let column = key.into_column().as_str().to_string();
+  db.execute_unprepared(&format!(
+    r#"SELECT setval(pg_get_serial_sequence('"{table}"', '{column}'), COALESCE(MAX("{column}"), 0) + 1, false) FROM "{table}""#
+  ))
+  .await?;
+  Ok(())
+}
+
+/// Hash over all rows, independent of their order and of how the database
+/// stores the values.
+pub async fn checksum<E: Table, C: ConnectionTrait>(db: &C) -> Result<String, DbErr> {
+  let mut hashes = Vec::new();
+  let mut pages = ordered::<E>().paginate(db, BATCH_SIZE);
+  while let Some(models) = pages.fetch_and_next().await? {
+    for model in models {
+      let mut value = serde_json::to_value(&model).map_err(|err| DbErr::Json(err.to_string()))?;
+      normalize(&mut value);
+      hashes.push(Sha256::digest(value.to_string()));
+    }
+  }
+  hashes.sort_unstable();
+
+  let mut hasher = Sha256::new();
+  for hash in hashes {
+    hasher.update(hash);
+  }
+  Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Postgres rounds timestamps to microseconds while SQLite keeps them as
+/// written.
+fn normalize(value: &mut Value) {
+  match value {
+    Value::String(text) => {
+      if let Ok(time) = text.parse::<NaiveDateTime>() {
+        *text = time
+          .round_subsecs(6)
+          .format("%Y-%m-%dT%H:%M:%S%.6f")
+          .to_string();
+      }
+    }
+    Value::Array(values) => values.iter_mut().for_each(normalize),
+    Value::Object(fields) => fields.values_mut().for_each(normalize),
+    _ => (),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use entity::{group, group_user, settings, user};
+  use sea_orm_migration::sea_orm::{
+    ActiveModelTrait, Database, EntityTrait, ModelTrait, Set, prelude::Uuid,
+  };
+
+  use super::*;
+
+  async fn database(name: &str) -> (DbConn, std::path::PathBuf) {
+    let path = std::env::temp_dir().join(format!("copy-{}-{name}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let db = Database::connect(format!("sqlite://{}?mode=rwc", path.display()))
+      .await
+      .unwrap();
+    (db, path)
+  }
+
+  #[tokio::test]
+  async fn copy_resumes_and_is_verified() {
+    let (source, source_path) = database("source").await;
+    let (target, target_path) = database("target").await;
+    Migrator::up(&source, None).await.unwrap();
+
+    let user = user::Model {
+      id: Uuid::new_v4(),
+      name: "Jane".into(),
+      email: "jane@example.com".into(),
+      password: "hash".into(),
+      salt: "salt".into(),
+      oidc_user: false,
+      oidc_subject: None,
+    };
+    let group = group::Model {
+      id: Uuid::new_v4(),
+      name: "admin".into(),
+    };
+    user
+      .clone()
+      .into_active_model()
+      .insert(&source)
+      .await
+      .unwrap();
+    group
+      .clone()
+      .into_active_model()
+      .insert(&source)
+      .await
+      .unwrap();
+    group_user::Model {
+      group_id: group.id,
+      user_id: user.id,
+    }
+    .into_active_model()
+    .insert(&source)
+    .await
+    .unwrap();
+    settings::Model {
+      id: 1,
+      content: "{}".into(),
+    }
+    .into_active_model()
+    .insert(&source)
+    .await
+    .unwrap();
+
+    let reports = copy(&source, &target, |_| ()).await.unwrap();
+    let copied = |reports: &[TableReport], table: &str| {
+      reports.iter().find(|r| r.table == table).unwrap().copied
+    };
+    assert_eq!(copied(&reports, "user"), 1);
+    assert_eq!(copied(&reports, "group_user"), 1);
+
+    // An interrupted copy is missing rows, running it again fills them in and
+    // takes over changes made in the meantime.
+    let membership = group_user::Entity::find()
+      .one(&target)
+      .await
+      .unwrap()
+      .unwrap();
+    membership.delete(&target).await.unwrap();
+    let mut renamed = user.into_active_model();
+    renamed.name = Set("Jane Doe".into());
+    renamed.update(&source).await.unwrap();
+
+    let reports = copy(&source, &target, |_| ()).await.unwrap();
+    assert_eq!(copied(&reports, "user"), 0);
+    assert_eq!(copied(&reports, "group_user"), 1);
+    let copied_user = user::Entity::find().one(&target).await.unwrap().unwrap();
+    assert_eq!(copied_user.name, "Jane Doe");
+
+    let _ = std::fs::remove_file(source_path);
+    let _ = std::fs::remove_file(target_path);
+  }
+
+  #[test]
+  fn timestamps_are_compared_in_microseconds() {
+    let mut sqlite = Value::String("2026-10-19T12:00:00.123456789".into());
+    let mut postgres = Value::String("2026-10-19T12:00:00.123457".into());
+    normalize(&mut sqlite);
+    normalize(&mut postgres);
+    assert_eq!(sqlite, postgres);
+  }
+}
@@ -1,5 +1,22 @@
 pub use sea_orm_migration::prelude::*;
 
+mod m20261018_000001_role;
+mod m20261018_000002_audit_event;
+mod m20261019_000001_audit_chain;
+mod m20261019_000002_login_event;
+mod m20261019_000003_audit_impersonator;
+mod m20261019_000004_user_status;
+mod m20261019_000005_user_attribute;
+mod m20261019_000006_account_deletion;
+mod m20261019_000007_avatar_variant;
+mod m20261019_000008_organization;
+mod m20261019_000009_branding_asset;
+mod m20261019_000010_settings_revision;
+mod m20261019_000011_feature_flag;
+
+pub mod copy;
+pub mod tables;
+
 pub struct Migrator;
 
 #[async_trait::async_trait]
@@ -13,6 +30,19 @@ impl MigratorTrait for Migrator {
       Box::new(centaurus::db::migrations::m4_groups::Migration),
       Box::new(centaurus::db::migrations::m5_setup::Migration),
       Box::new(centaurus::db::migrations::m6_user_oidc_subject::Migration),
+      Box::new(m20261018_000001_role::Migration),
+      Box::new(m20261018_000002_audit_event::Migration),
+      Box::new(m20261019_000001_audit_chain::Migration),
+      Box::new(m20261019_000002_login_event::Migration),
+      Box::new(m20261019_000003_audit_impersonator::Migration),
+      Box::new(m20261019_000004_user_status::Migration),
+      Box::new(m20261019_000005_user_attribute::Migration),
+      Box::new(m20261019_000006_account_deletion::Migration),
+      Box::new(m20261019_000007_avatar_variant::Migration),
+      Box::new(m20261019_000008_organization::Migration),
+      Box::new(m20261019_000009_branding_asset::Migration),
+      Box::new(m20261019_000010_settings_revision::Migration),
+      Box::new(m20261019_000011_feature_flag::Migration),
     ]
   }
 }
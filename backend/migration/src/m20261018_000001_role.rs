@@ -0,0 +1,197 @@
+use centaurus::db::migrations::m4_groups::{Group, GroupPermission};
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const ROLE_NAME_INDEX_NAME: &str = "role.role_name";
+const GROUP_ROLE_ROLE_ID_INDEX_NAME: &str = "group_role.role_id";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .create_table(
+        Table::create()
+          .table(Role::Table)
+          .if_not_exists()
+          .col(pk_uuid(Role::Id))
+          .col(string(Role::Name))
+          .col(string(Role::Description))
+          .col(boolean(Role::BuiltIn))
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_index(
+        Index::create()
+          .if_not_exists()
+          .name(ROLE_NAME_INDEX_NAME)
+          .table(Role::Table)
+          .col(Role::Name)
+          .unique()
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_table(
+        Table::create()
+          .table(RolePermission::Table)
+          .if_not_exists()
+          .primary_key(
+            Index::create()
+              .table(RolePermission::Table)
+              .col(RolePermission::RoleId)
+              .col(RolePermission::Permission),
+          )
+          .col(uuid(RolePermission::RoleId))
+          .col(string(RolePermission::Permission))
+          .foreign_key(
+            ForeignKey::create()
+              .from(RolePermission::Table, RolePermission::RoleId)
+              .to(Role::Table, Role::Id)
+              .on_delete(ForeignKeyAction::Cascade)
+              .on_update(ForeignKeyAction::Cascade),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_table(
+        Table::create()
+          .table(GroupRole::Table)
+          .if_not_exists()
+          .primary_key(
+            Index::create()
+              .table(GroupRole::Table)
+              .col(GroupRole::GroupId)
+              .col(GroupRole::RoleId),
+          )
+          .col(uuid(GroupRole::GroupId))
+          .col(uuid(GroupRole::RoleId))
+          .foreign_key(
+            ForeignKey::create()
+              .from(GroupRole::Table, GroupRole::GroupId)
+              .to(Group::Table, Group::Id)
+              .on_delete(ForeignKeyAction::Cascade)
+              .on_update(ForeignKeyAction::Cascade),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .from(GroupRole::Table, GroupRole::RoleId)
+              .to(Role::Table, Role::Id)
+              .on_delete(ForeignKeyAction::Cascade)
+              .on_update(ForeignKeyAction::Cascade),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_index(
+        Index::create()
+          .if_not_exists()
+          .name(GROUP_ROLE_ROLE_ID_INDEX_NAME)
+          .table(GroupRole::Table)
+          .col(GroupRole::RoleId)
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_table(
+        Table::create()
+          .table(GroupDirectPermission::Table)
+          .if_not_exists()
+          .primary_key(
+            Index::create()
+              .table(GroupDirectPermission::Table)
+              .col(GroupDirectPermission::GroupId)
+              .col(GroupDirectPermission::Permission),
+          )
+          .col(uuid(GroupDirectPermission::GroupId))
+          .col(string(GroupDirectPermission::Permission))
+          .foreign_key(
+            ForeignKey::create()
+              .from(GroupDirectPermission::Table, GroupDirectPermission::GroupId)
+              .to(Group::Table, Group::Id)
+              .on_delete(ForeignKeyAction::Cascade)
+              .on_update(ForeignKeyAction::Cascade),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    // every permission granted so far was assigned directly
+    let copy_direct = Query::insert()
+      .into_table(GroupDirectPermission::Table)
+      .columns([
+        GroupDirectPermission::GroupId,
+        GroupDirectPermission::Permission,
+      ])
+      .select_from(
+        Query::select()
+          .columns([GroupPermission::GroupId, GroupPermission::Permission])
+          .from(GroupPermission::Table)
+          .to_owned(),
+      )
+      .map_err(|e| DbErr::Migration(e.to_string()))?
+      .to_owned();
+
+    manager.exec_stmt(copy_direct).await
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_index(Index::drop().name(GROUP_ROLE_ROLE_ID_INDEX_NAME).to_owned())
+      .await?;
+
+    manager
+      .drop_index(Index::drop().name(ROLE_NAME_INDEX_NAME).to_owned())
+      .await?;
+
+    manager
+      .drop_table(
+        Table::drop()
+          .table(GroupDirectPermission::Table)
+          .table(GroupRole::Table)
+          .table(RolePermission::Table)
+          .table(Role::Table)
+          .to_owned(),
+      )
+      .await
+  }
+}
+
+#[derive(DeriveIden)]
+pub enum Role {
+  Table,
+  Id,
+  Name,
+  Description,
+  BuiltIn,
+}
+
+#[derive(DeriveIden)]
+pub enum RolePermission {
+  Table,
+  RoleId,
+  Permission,
+}
+
+#[derive(DeriveIden)]
+pub enum GroupRole {
+  Table,
+  GroupId,
+  RoleId,
+}
+
+#[derive(DeriveIden)]
+pub enum GroupDirectPermission {
+  Table,
+  GroupId,
+  Permission,
+}
@@ -0,0 +1,98 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const AUDIT_EVENT_CREATED_AT_INDEX_NAME: &str = "audit_event.created_at";
+const AUDIT_EVENT_ACTOR_ID_INDEX_NAME: &str = "audit_event.actor_id";
+const AUDIT_EVENT_ACTION_INDEX_NAME: &str = "audit_event.action";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .create_table(
+        Table::create()
+          .table(AuditEvent::Table)
+          .if_not_exists()
+          .col(pk_uuid(AuditEvent::Id))
+          .col(date_time(AuditEvent::CreatedAt))
+          .col(uuid_null(AuditEvent::ActorId))
+          .col(string(AuditEvent::Action))
+          .col(string_null(AuditEvent::TargetType))
+          .col(string_null(AuditEvent::TargetId))
+          .col(json_null(AuditEvent::Before))
+          .col(json_null(AuditEvent::After))
+          .col(string_null(AuditEvent::Ip))
+          .col(string_null(AuditEvent::UserAgent))
+          .col(integer(AuditEvent::Status))
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_index(
+        Index::create()
+          .if_not_exists()
+          .name(AUDIT_EVENT_CREATED_AT_INDEX_NAME)
+          .table(AuditEvent::Table)
+          .col(AuditEvent::CreatedAt)
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_index(
+        Index::create()
+          .if_not_exists()
+          .name(AUDIT_EVENT_ACTOR_ID_INDEX_NAME)
+          .table(AuditEvent::Table)
+          .col(AuditEvent::ActorId)
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_index(
+        Index::create()
+          .if_not_exists()
+          .name(AUDIT_EVENT_ACTION_INDEX_NAME)
+          .table(AuditEvent::Table)
+          .col(AuditEvent::Action)
+          .to_owned(),
+      )
+      .await
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    for index in [
+      AUDIT_EVENT_ACTION_INDEX_NAME,
+      AUDIT_EVENT_ACTOR_ID_INDEX_NAME,
+      AUDIT_EVENT_CREATED_AT_INDEX_NAME,
+    ] {
+      manager
+        .drop_index(Index::drop().name(index).to_owned())
+        .await?;
+    }
+
+    manager
+      .drop_table(Table::drop().table(AuditEvent::Table).to_owned())
+      .await
+  }
+}
+
+#[derive(DeriveIden)]
+pub enum AuditEvent {
+  Table,
+  Id,
+  CreatedAt,
+  ActorId,
+  Action,
+  TargetType,
+  TargetId,
+  Before,
+  After,
+  Ip,
+  UserAgent,
+  Status,
+}
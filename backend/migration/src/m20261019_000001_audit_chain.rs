@@ -0,0 +1,81 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20261018_000002_audit_event::AuditEvent;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const AUDIT_EVENT_SEQUENCE_INDEX_NAME: &str = "audit_event.sequence";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    // one column per statement, sqlite cannot add several at once
+    for column in [
+      big_integer_null(AuditChain::Sequence),
+      string_null(AuditChain::PrevHash),
+      string_null(AuditChain::Hash),
+      text_null(AuditChain::Signature),
+      uuid_null(AuditChain::KeyId),
+    ] {
+      manager
+        .alter_table(
+          Table::alter()
+            .table(AuditEvent::Table)
+            .add_column(column)
+            .to_owned(),
+        )
+        .await?;
+    }
+
+    manager
+      .create_index(
+        Index::create()
+          .if_not_exists()
+          .name(AUDIT_EVENT_SEQUENCE_INDEX_NAME)
+          .table(AuditEvent::Table)
+          .col(AuditChain::Sequence)
+          .unique()
+          .to_owned(),
+      )
+      .await
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_index(
+        Index::drop()
+          .name(AUDIT_EVENT_SEQUENCE_INDEX_NAME)
+          .to_owned(),
+      )
+      .await?;
+
+    for column in [
+      AuditChain::KeyId,
+      AuditChain::Signature,
+      AuditChain::Hash,
+      AuditChain::PrevHash,
+      AuditChain::Sequence,
+    ] {
+      manager
+        .alter_table(
+          Table::alter()
+            .table(AuditEvent::Table)
+            .drop_column(column)
+            .to_owned(),
+        )
+        .await?;
+    }
+
+    Ok(())
+  }
+}
+
+#[derive(DeriveIden)]
+enum AuditChain {
+  Sequence,
+  PrevHash,
+  Hash,
+  Signature,
+  KeyId,
+}
@@ -0,0 +1,121 @@
+use centaurus::db::migrations::m3_user::User;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const LOGIN_EVENT_USER_ID_INDEX_NAME: &str = "login_event.user_id";
+const LOGIN_EVENT_REPORT_TOKEN_INDEX_NAME: &str = "login_event.report_token";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .create_table(
+        Table::create()
+          .table(LoginEvent::Table)
+          .if_not_exists()
+          .col(pk_uuid(LoginEvent::Id))
+          .col(uuid(LoginEvent::UserId))
+          .col(date_time(LoginEvent::CreatedAt))
+          .col(string(LoginEvent::Method))
+          .col(boolean(LoginEvent::Success))
+          .col(string_null(LoginEvent::Ip))
+          .col(string_null(LoginEvent::UserAgent))
+          .col(boolean(LoginEvent::NewDevice))
+          .col(string_null(LoginEvent::ReportToken))
+          .col(date_time_null(LoginEvent::ReportedAt))
+          .foreign_key(
+            ForeignKey::create()
+              .from(LoginEvent::Table, LoginEvent::UserId)
+              .to(User::Table, User::Id)
+              .on_delete(ForeignKeyAction::Cascade)
+              .on_update(ForeignKeyAction::Cascade),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_index(
+        Index::create()
+          .if_not_exists()
+          .name(LOGIN_EVENT_USER_ID_INDEX_NAME)
+          .table(LoginEvent::Table)
+          .col(LoginEvent::UserId)
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_index(
+        Index::create()
+          .if_not_exists()
+          .name(LOGIN_EVENT_REPORT_TOKEN_INDEX_NAME)
+          .table(LoginEvent::Table)
+          .col(LoginEvent::ReportToken)
+          .unique()
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_table(
+        Table::create()
+          .table(SessionRevocation::Table)
+          .if_not_exists()
+          .col(pk_uuid(SessionRevocation::UserId))
+          .col(date_time(SessionRevocation::RevokedAt))
+          .foreign_key(
+            ForeignKey::create()
+              .from(SessionRevocation::Table, SessionRevocation::UserId)
+              .to(User::Table, User::Id)
+              .on_delete(ForeignKeyAction::Cascade)
+              .on_update(ForeignKeyAction::Cascade),
+          )
+          .to_owned(),
+      )
+      .await
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(Table::drop().table(SessionRevocation::Table).to_owned())
+      .await?;
+
+    for index in [
+      LOGIN_EVENT_REPORT_TOKEN_INDEX_NAME,
+      LOGIN_EVENT_USER_ID_INDEX_NAME,
+    ] {
+      manager
+        .drop_index(Index::drop().name(index).to_owned())
+        .await?;
+    }
+
+    manager
+      .drop_table(Table::drop().table(LoginEvent::Table).to_owned())
+      .await
+  }
+}
+
+#[derive(DeriveIden)]
+pub enum LoginEvent {
+  Table,
+  Id,
+  UserId,
+  CreatedAt,
+  Method,
+  Success,
+  Ip,
+  UserAgent,
+  NewDevice,
+  ReportToken,
+  ReportedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum SessionRevocation {
+  Table,
+  UserId,
+  RevokedAt,
+}
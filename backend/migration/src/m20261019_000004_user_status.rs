@@ -0,0 +1,43 @@
+use centaurus::db::migrations::m3_user::User;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .create_table(
+        Table::create()
+          .table(UserStatus::Table)
+          .if_not_exists()
+          .col(pk_uuid(UserStatus::UserId))
+          .col(date_time_null(UserStatus::DisabledAt))
+          .col(date_time_null(UserStatus::DeletedAt))
+          .foreign_key(
+            ForeignKey::create()
+              .from(UserStatus::Table, UserStatus::UserId)
+              .to(User::Table, User::Id)
+              .on_delete(ForeignKeyAction::Cascade)
+              .on_update(ForeignKeyAction::Cascade),
+          )
+          .to_owned(),
+      )
+      .await
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(Table::drop().table(UserStatus::Table).to_owned())
+      .await
+  }
+}
+
+#[derive(DeriveIden)]
+pub enum UserStatus {
+  Table,
+  UserId,
+  DisabledAt,
+  DeletedAt,
+}
@@ -0,0 +1,111 @@
+use centaurus::db::migrations::m3_user::User;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const ATTRIBUTE_KEY_INDEX_NAME: &str = "attribute_definition.key";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .create_table(
+        Table::create()
+          .table(AttributeDefinition::Table)
+          .if_not_exists()
+          .col(pk_uuid(AttributeDefinition::Id))
+          .col(string(AttributeDefinition::Key))
+          .col(string(AttributeDefinition::Label))
+          .col(string(AttributeDefinition::Kind))
+          .col(boolean(AttributeDefinition::Required))
+          .col(json_null(AttributeDefinition::Options))
+          .col(double_null(AttributeDefinition::Min))
+          .col(double_null(AttributeDefinition::Max))
+          .col(string_null(AttributeDefinition::Pattern))
+          .col(string(AttributeDefinition::Visibility))
+          .col(string_null(AttributeDefinition::OidcClaim))
+          .col(integer(AttributeDefinition::Position))
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_index(
+        Index::create()
+          .if_not_exists()
+          .name(ATTRIBUTE_KEY_INDEX_NAME)
+          .table(AttributeDefinition::Table)
+          .col(AttributeDefinition::Key)
+          .unique()
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_table(
+        Table::create()
+          .table(UserAttribute::Table)
+          .if_not_exists()
+          .primary_key(
+            Index::create()
+              .table(UserAttribute::Table)
+              .col(UserAttribute::UserId)
+              .col(UserAttribute::AttributeId),
+          )
+          .col(uuid(UserAttribute::UserId))
+          .col(uuid(UserAttribute::AttributeId))
+          .col(string(UserAttribute::Value))
+          .foreign_key(
+            ForeignKey::create()
+              .from(UserAttribute::Table, UserAttribute::UserId)
+              .to(User::Table, User::Id)
+              .on_delete(ForeignKeyAction::Cascade)
+              .on_update(ForeignKeyAction::Cascade),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .from(UserAttribute::Table, UserAttribute::AttributeId)
+              .to(AttributeDefinition::Table, AttributeDefinition::Id)
+              .on_delete(ForeignKeyAction::Cascade)
+              .on_update(ForeignKeyAction::Cascade),
+          )
+          .to_owned(),
+      )
+      .await
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(Table::drop().table(UserAttribute::Table).to_owned())
+      .await?;
+    manager
+      .drop_table(Table::drop().table(AttributeDefinition::Table).to_owned())
+      .await
+  }
+}
+
+#[derive(DeriveIden)]
+pub enum AttributeDefinition {
+  Table,
+  Id,
+  Key,
+  Label,
+  Kind,
+  Required,
+  Options,
+  Min,
+  Max,
+  Pattern,
+  Visibility,
+  OidcClaim,
+  Position,
+}
+
+#[derive(DeriveIden)]
+pub enum UserAttribute {
+  Table,
+  UserId,
+  AttributeId,
+  Value,
+}
@@ -0,0 +1,48 @@
+use centaurus::db::migrations::m3_user::User;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .create_table(
+        Table::create()
+          .table(AccountDeletion::Table)
+          .if_not_exists()
+          .col(pk_uuid(AccountDeletion::UserId))
+          .col(date_time(AccountDeletion::RequestedAt))
+          .col(string_null(AccountDeletion::Token).unique_key())
+          .col(date_time_null(AccountDeletion::ConfirmedAt))
+          .col(date_time_null(AccountDeletion::DeleteAt))
+          .foreign_key(
+            ForeignKey::create()
+              .from(AccountDeletion::Table, AccountDeletion::UserId)
+              .to(User::Table, User::Id)
+              .on_delete(ForeignKeyAction::Cascade)
+              .on_update(ForeignKeyAction::Cascade),
+          )
+          .to_owned(),
+      )
+      .await
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(Table::drop().table(AccountDeletion::Table).to_owned())
+      .await
+  }
+}
+
+#[derive(DeriveIden)]
+pub enum AccountDeletion {
+  Table,
+  UserId,
+  RequestedAt,
+  /// Hash of the token mailed to confirm the request.
+  Token,
+  ConfirmedAt,
+  DeleteAt,
+}
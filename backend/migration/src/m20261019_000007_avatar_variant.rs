@@ -0,0 +1,53 @@
+use centaurus::db::migrations::m3_user::UserAvatar;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .create_table(
+        Table::create()
+          .table(AvatarVariant::Table)
+          .if_not_exists()
+          .primary_key(
+            Index::create()
+              .table(AvatarVariant::Table)
+              .col(AvatarVariant::UserId)
+              .col(AvatarVariant::Size),
+          )
+          .col(uuid(AvatarVariant::UserId))
+          .col(integer(AvatarVariant::Size))
+          .col(blob(AvatarVariant::Data))
+          .col(string(AvatarVariant::Etag))
+          // variants go away together with the avatar they were made from
+          .foreign_key(
+            ForeignKey::create()
+              .from(AvatarVariant::Table, AvatarVariant::UserId)
+              .to(UserAvatar::Table, UserAvatar::UserId)
+              .on_delete(ForeignKeyAction::Cascade)
+              .on_update(ForeignKeyAction::Cascade),
+          )
+          .to_owned(),
+      )
+      .await
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(Table::drop().table(AvatarVariant::Table).to_owned())
+      .await
+  }
+}
+
+#[derive(DeriveIden)]
+pub enum AvatarVariant {
+  Table,
+  UserId,
+  /// Width and height in pixels.
+  Size,
+  Data,
+  Etag,
+}
@@ -0,0 +1,252 @@
+use centaurus::db::migrations::{
+  m3_user::User,
+  m4_groups::{Group, GroupUser},
+  m5_setup::Setup,
+};
+use sea_orm_migration::{prelude::*, schema::*, sea_orm::prelude::Uuid};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .create_table(
+        Table::create()
+          .table(Organization::Table)
+          .if_not_exists()
+          .col(pk_uuid(Organization::Id))
+          .col(string_uniq(Organization::Name))
+          .col(boolean(Organization::IsDefault))
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_table(
+        Table::create()
+          .table(OrganizationMember::Table)
+          .if_not_exists()
+          .primary_key(
+            Index::create()
+              .table(OrganizationMember::Table)
+              .col(OrganizationMember::OrganizationId)
+              .col(OrganizationMember::UserId),
+          )
+          .col(uuid(OrganizationMember::OrganizationId))
+          .col(uuid(OrganizationMember::UserId))
+          .foreign_key(
+            ForeignKey::create()
+              .from(
+                OrganizationMember::Table,
+                OrganizationMember::OrganizationId,
+              )
+              .to(Organization::Table, Organization::Id)
+              .on_delete(ForeignKeyAction::Cascade)
+              .on_update(ForeignKeyAction::Cascade),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .from(OrganizationMember::Table, OrganizationMember::UserId)
+              .to(User::Table, User::Id)
+              .on_delete(ForeignKeyAction::Cascade)
+              .on_update(ForeignKeyAction::Cascade),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_table(
+        Table::create()
+          .table(OrganizationGroup::Table)
+          .if_not_exists()
+          .col(pk_uuid(OrganizationGroup::GroupId))
+          .col(uuid(OrganizationGroup::OrganizationId))
+          .foreign_key(
+            ForeignKey::create()
+              .from(OrganizationGroup::Table, OrganizationGroup::GroupId)
+              .to(Group::Table, Group::Id)
+              .on_delete(ForeignKeyAction::Cascade)
+              .on_update(ForeignKeyAction::Cascade),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .from(OrganizationGroup::Table, OrganizationGroup::OrganizationId)
+              .to(Organization::Table, Organization::Id)
+              .on_delete(ForeignKeyAction::Cascade)
+              .on_update(ForeignKeyAction::Cascade),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_table(
+        Table::create()
+          .table(ActiveOrganization::Table)
+          .if_not_exists()
+          .col(pk_uuid(ActiveOrganization::UserId))
+          .col(uuid(ActiveOrganization::OrganizationId))
+          .foreign_key(
+            ForeignKey::create()
+              .from(ActiveOrganization::Table, ActiveOrganization::UserId)
+              .to(User::Table, User::Id)
+              .on_delete(ForeignKeyAction::Cascade)
+              .on_update(ForeignKeyAction::Cascade),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .from(
+                ActiveOrganization::Table,
+                ActiveOrganization::OrganizationId,
+              )
+              .to(Organization::Table, Organization::Id)
+              .on_delete(ForeignKeyAction::Cascade)
+              .on_update(ForeignKeyAction::Cascade),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_table(
+        Table::create()
+          .table(SuperAdmin::Table)
+          .if_not_exists()
+          .col(pk_uuid(SuperAdmin::UserId))
+          .foreign_key(
+            ForeignKey::create()
+              .from(SuperAdmin::Table, SuperAdmin::UserId)
+              .to(User::Table, User::Id)
+              .on_delete(ForeignKeyAction::Cascade)
+              .on_update(ForeignKeyAction::Cascade),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    // everything that exists so far belongs to the default organization
+    let default = Uuid::now_v7();
+    let create_default = Query::insert()
+      .into_table(Organization::Table)
+      .columns([
+        Organization::Id,
+        Organization::Name,
+        Organization::IsDefault,
+      ])
+      .values_panic([default.into(), "Default".into(), true.into()])
+      .to_owned();
+    manager.exec_stmt(create_default).await?;
+
+    let add_members = Query::insert()
+      .into_table(OrganizationMember::Table)
+      .columns([
+        OrganizationMember::OrganizationId,
+        OrganizationMember::UserId,
+      ])
+      .select_from(
+        Query::select()
+          .expr(Expr::val(default))
+          .column(User::Id)
+          .from(User::Table)
+          .to_owned(),
+      )
+      .map_err(|e| DbErr::Migration(e.to_string()))?
+      .to_owned();
+    manager.exec_stmt(add_members).await?;
+
+    let add_groups = Query::insert()
+      .into_table(OrganizationGroup::Table)
+      .columns([
+        OrganizationGroup::GroupId,
+        OrganizationGroup::OrganizationId,
+      ])
+      .select_from(
+        Query::select()
+          .column(Group::Id)
+          .expr(Expr::val(default))
+          .from(Group::Table)
+          .to_owned(),
+      )
+      .map_err(|e| DbErr::Migration(e.to_string()))?
+      .to_owned();
+    manager.exec_stmt(add_groups).await?;
+
+    // members of the admin group keep managing the whole instance
+    let add_super_admins = Query::insert()
+      .into_table(SuperAdmin::Table)
+      .columns([SuperAdmin::UserId])
+      .select_from(
+        Query::select()
+          .column(GroupUser::UserId)
+          .from(GroupUser::Table)
+          .and_where(
+            Expr::col(GroupUser::GroupId).in_subquery(
+              Query::select()
+                .column(Setup::AdminGroupCreated)
+                .from(Setup::Table)
+                .to_owned(),
+            ),
+          )
+          .to_owned(),
+      )
+      .map_err(|e| DbErr::Migration(e.to_string()))?
+      .to_owned();
+    manager.exec_stmt(add_super_admins).await
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(
+        Table::drop()
+          .table(SuperAdmin::Table)
+          .table(ActiveOrganization::Table)
+          .table(OrganizationGroup::Table)
+          .table(OrganizationMember::Table)
+          .table(Organization::Table)
+          .to_owned(),
+      )
+      .await
+  }
+}
+
+#[derive(DeriveIden)]
+pub enum Organization {
+  Table,
+  Id,
+  Name,
+  /// Where users signing up without an invitation end up.
+  IsDefault,
+}
+
+#[derive(DeriveIden)]
+pub enum OrganizationMember {
+  Table,
+  OrganizationId,
+  UserId,
+}
+
+/// Owner of every group, a group belongs to exactly one organization.
+#[derive(DeriveIden)]
+pub enum OrganizationGroup {
+  Table,
+  GroupId,
+  OrganizationId,
+}
+
+/// Organization a user currently works in.
+#[derive(DeriveIden)]
+pub enum ActiveOrganization {
+  Table,
+  UserId,
+  OrganizationId,
+}
+
+/// Users managing the whole instance across organizations.
+#[derive(DeriveIden)]
+pub enum SuperAdmin {
+  Table,
+  UserId,
+}
@@ -0,0 +1,57 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20261019_000008_organization::Organization;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .create_table(
+        Table::create()
+          .table(BrandingAsset::Table)
+          .if_not_exists()
+          .primary_key(
+            Index::create()
+              .table(BrandingAsset::Table)
+              .col(BrandingAsset::OrganizationId)
+              .col(BrandingAsset::Kind)
+              .col(BrandingAsset::Size),
+          )
+          .col(uuid(BrandingAsset::OrganizationId))
+          .col(string(BrandingAsset::Kind))
+          .col(integer(BrandingAsset::Size))
+          .col(blob(BrandingAsset::Data))
+          .col(string(BrandingAsset::Etag))
+          .foreign_key(
+            ForeignKey::create()
+              .from(BrandingAsset::Table, BrandingAsset::OrganizationId)
+              .to(Organization::Table, Organization::Id)
+              .on_delete(ForeignKeyAction::Cascade)
+              .on_update(ForeignKeyAction::Cascade),
+          )
+          .to_owned(),
+      )
+      .await
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(Table::drop().table(BrandingAsset::Table).to_owned())
+      .await
+  }
+}
+
+#[derive(DeriveIden)]
+pub enum BrandingAsset {
+  Table,
+  OrganizationId,
+  /// `logo` or `favicon`.
+  Kind,
+  /// Largest side in pixels.
+  Size,
+  Data,
+  Etag,
+}
@@ -0,0 +1,59 @@
+use centaurus::db::migrations::m3_user::User;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .create_table(
+        Table::create()
+          .table(SettingsRevision::Table)
+          .if_not_exists()
+          .primary_key(
+            Index::create()
+              .table(SettingsRevision::Table)
+              .col(SettingsRevision::SettingsId)
+              .col(SettingsRevision::Revision),
+          )
+          .col(integer(SettingsRevision::SettingsId))
+          .col(integer(SettingsRevision::Revision))
+          .col(text(SettingsRevision::Content))
+          .col(uuid_null(SettingsRevision::Author))
+          .col(date_time(SettingsRevision::CreatedAt))
+          .col(integer_null(SettingsRevision::RestoredFrom))
+          // revisions outlive the accounts that made them
+          .foreign_key(
+            ForeignKey::create()
+              .from(SettingsRevision::Table, SettingsRevision::Author)
+              .to(User::Table, User::Id)
+              .on_delete(ForeignKeyAction::SetNull)
+              .on_update(ForeignKeyAction::Cascade),
+          )
+          .to_owned(),
+      )
+      .await
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(Table::drop().table(SettingsRevision::Table).to_owned())
+      .await
+  }
+}
+
+#[derive(DeriveIden)]
+pub enum SettingsRevision {
+  Table,
+  /// Id of the row in the `settings` table.
+  SettingsId,
+  /// Counts up from 1 per settings id.
+  Revision,
+  Content,
+  Author,
+  CreatedAt,
+  /// Revision whose content was restored by a rollback.
+  RestoredFrom,
+}
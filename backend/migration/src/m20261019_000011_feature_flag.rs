@@ -0,0 +1,87 @@
+use centaurus::db::migrations::m4_groups::Group;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .create_table(
+        Table::create()
+          .table(FeatureFlag::Table)
+          .if_not_exists()
+          .col(pk_uuid(FeatureFlag::Id))
+          .col(string_uniq(FeatureFlag::Key))
+          .col(string(FeatureFlag::Description))
+          .col(string(FeatureFlag::Kind))
+          .col(boolean(FeatureFlag::Enabled))
+          .col(json_null(FeatureFlag::Variants))
+          .col(integer(FeatureFlag::Rollout))
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .create_table(
+        Table::create()
+          .table(FeatureFlagGroup::Table)
+          .if_not_exists()
+          .primary_key(
+            Index::create()
+              .table(FeatureFlagGroup::Table)
+              .col(FeatureFlagGroup::FlagId)
+              .col(FeatureFlagGroup::GroupId),
+          )
+          .col(uuid(FeatureFlagGroup::FlagId))
+          .col(uuid(FeatureFlagGroup::GroupId))
+          .col(string_null(FeatureFlagGroup::Variant))
+          .foreign_key(
+            ForeignKey::create()
+              .from(FeatureFlagGroup::Table, FeatureFlagGroup::FlagId)
+              .to(FeatureFlag::Table, FeatureFlag::Id)
+              .on_delete(ForeignKeyAction::Cascade)
+              .on_update(ForeignKeyAction::Cascade),
+          )
+          .foreign_key(
+            ForeignKey::create()
+              .from(FeatureFlagGroup::Table, FeatureFlagGroup::GroupId)
+              .to(Group::Table, Group::Id)
+              .on_delete(ForeignKeyAction::Cascade)
+              .on_update(ForeignKeyAction::Cascade),
+          )
+          .to_owned(),
+      )
+      .await
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(Table::drop().table(FeatureFlagGroup::Table).to_owned())
+      .await?;
+    manager
+      .drop_table(Table::drop().table(FeatureFlag::Table).to_owned())
+      .await
+  }
+}
+
+#[derive(DeriveIden)]
+pub enum FeatureFlag {
+  Table,
+  Id,
+  Key,
+  Description,
+  Kind,
+  Enabled,
+  Variants,
+  Rollout,
+}
+
+#[derive(DeriveIden)]
+pub enum FeatureFlagGroup {
+  Table,
+  FlagId,
+  GroupId,
+  Variant,
+}
@@ -1,6 +1,66 @@
-use sea_orm_migration::prelude::*;
+use clap::{Parser, Subcommand};
+use migration::{Migrator, copy};
+use sea_orm_cli::MigrateSubcommands;
+use sea_orm_migration::{prelude::*, sea_orm::Database};
+
+/// The commands of sea-orm, and copying all data to another database.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+  #[arg(short = 'v', long, global = true, help = "Show debug messages")]
+  verbose: bool,
+  #[arg(global = true, short = 's', long, env = "DATABASE_SCHEMA")]
+  database_schema: Option<String>,
+  #[arg(global = true, short = 'u', long, env = "DATABASE_URL")]
+  database_url: Option<String>,
+  #[command(subcommand)]
+  command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+  /// Copy every row into another database, e.g. from SQLite to Postgres.
+  /// Run it again to resume after an interruption.
+  Copy {
+    /// Database to read from, must be fully migrated.
+    #[arg(long)]
+    from: String,
+    /// Database to write to, migrated before copying.
+    #[arg(long)]
+    to: String,
+  },
+  #[command(flatten)]
+  Migrate(MigrateSubcommands),
+}
 
 #[tokio::main]
 async fn main() {
-  cli::run_cli(migration::Migrator).await;
+  let cli = Cli::parse();
+
+  match cli.command {
+    Some(Command::Copy { from, to }) => {
+      let source = Database::connect(&from)
+        .await
+        .expect("Failed to connect to the source database");
+      let target = Database::connect(&to)
+        .await
+        .expect("Failed to connect to the target database");
+
+      match copy::copy(&source, &target, |report| println!("{report}")).await {
+        Ok(reports) => {
+          let rows: u64 = reports.iter().map(|r| r.rows).sum();
+          println!(
+            "Copied and verified {rows} rows in {} tables",
+            reports.len()
+          );
+        }
+        Err(err) => {
+          eprintln!("Copy failed: {err}");
+          eprintln!("Already copied rows are kept, run the command again to resume");
+          std::process::exit(1);
+        }
+      }
+    }
+    _ => cli::run_cli(Migrator).await,
+  }
 }
@@ -0,0 +1,116 @@
+use entity::*;
+use sea_orm_migration::sea_orm::{ConnectionTrait, DbErr, EntityTrait, IntoActiveModel};
+use serde::{Serialize, de::DeserializeOwned};
+
+/// An entity whose rows can be read from one database and written to another.
+pub trait Table:
+  EntityTrait<
+    Model: Serialize + DeserializeOwned + IntoActiveModel<Self::ActiveModel> + Send + Sync,
+    ActiveModel: Send,
+  >
+{
+}
+
+impl<E> Table for E where
+  E: EntityTrait<
+      Model: Serialize + DeserializeOwned + IntoActiveModel<E::ActiveModel> + Send + Sync,
+      ActiveModel: Send,
+    >
+{
+}
+
+/// Called for every table of the application.
+pub trait TableVisitor {
+  fn visit<E: Table>(&mut self) -> impl Future<Output = Result<(), DbErr>>;
+}
+
+/// Visits every table, referenced tables before the ones referencing them so
+/// rows can be inserted in this order.
+pub async fn visit_tables<V: TableVisitor>(visitor: &mut V) -> Result<(), DbErr> {
+  visitor.visit::<user::Entity>().await?;
+  visitor.visit::<group::Entity>().await?;
+  visitor.visit::<organization::Entity>().await?;
+  visitor.visit::<role::Entity>().await?;
+  visitor.visit::<attribute_definition::Entity>().await?;
+  visitor.visit::<feature_flag::Entity>().await?;
+  visitor.visit::<key::Entity>().await?;
+  visitor.visit::<settings::Entity>().await?;
+  visitor.visit::<setup::Entity>().await?;
+  visitor.visit::<invalid_jwt::Entity>().await?;
+  visitor.visit::<audit_event::Entity>().await?;
+
+  visitor.visit::<group_user::Entity>().await?;
+  visitor.visit::<group_permission::Entity>().await?;
+  visitor.visit::<group_direct_permission::Entity>().await?;
+  visitor.visit::<group_role::Entity>().await?;
+  visitor.visit::<role_permission::Entity>().await?;
+  visitor.visit::<organization_group::Entity>().await?;
+  visitor.visit::<organization_member::Entity>().await?;
+  visitor.visit::<active_organization::Entity>().await?;
+  visitor.visit::<super_admin::Entity>().await?;
+  visitor.visit::<user_status::Entity>().await?;
+  visitor.visit::<account_deletion::Entity>().await?;
+  visitor.visit::<session_revocation::Entity>().await?;
+  visitor.visit::<login_event::Entity>().await?;
+  visitor.visit::<user_attribute::Entity>().await?;
+  visitor.visit::<user_avatar::Entity>().await?;
+  visitor.visit::<avatar_variant::Entity>().await?;
+  visitor.visit::<branding_asset::Entity>().await?;
+  visitor.visit::<feature_flag_group::Entity>().await?;
+  visitor.visit::<settings_revision::Entity>().await?;
+
+  Ok(())
+}
+
+/// Migrating an empty database creates a default organization. A database
+/// that receives all rows of another one gets its organizations from there.
+pub async fn remove_seeded_rows<C: ConnectionTrait>(db: &C) -> Result<(), DbErr> {
+  organization::Entity::delete_many().exec(db).await?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use sea_orm_migration::{
+    MigratorTrait,
+    sea_orm::{Database, Statement},
+  };
+
+  use super::*;
+  use crate::Migrator;
+
+  struct Names(Vec<String>);
+
+  impl TableVisitor for Names {
+    async fn visit<E: Table>(&mut self) -> Result<(), DbErr> {
+      self.0.push(E::default().table_name().to_string());
+      Ok(())
+    }
+  }
+
+  #[tokio::test]
+  async fn every_migrated_table_is_visited() {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    Migrator::up(&db, None).await.unwrap();
+
+    let mut migrated: Vec<String> = db
+      .query_all_raw(Statement::from_string(
+        db.get_database_backend(),
+        "SELECT name FROM sqlite_master WHERE type = 'table' \
+         AND name NOT LIKE 'sqlite_%' AND name != 'seaql_migrations'",
+      ))
+      .await
+      .unwrap()
+      .into_iter()
+      .map(|row| row.try_get::<String>("", "name").unwrap())
+      .collect();
+    migrated.sort_unstable();
+
+    let mut visited = Names(Vec::new());
+    visit_tables(&mut visited).await.unwrap();
+    let mut visited = visited.0;
+    visited.sort_unstable();
+
+    assert_eq!(visited, migrated);
+  }
+}
@@ -0,0 +1,148 @@
+//! Minimal writer and reader for gzip compressed tar archives, enough for
+//! exports that only contain regular files.
+
+use std::io::{self, Read, Write};
+
+use chrono::Utc;
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+
+const BLOCK: usize = 512;
+
+pub struct TarGz {
+  data: Vec<u8>,
+  mtime: i64,
+}
+
+impl Default for TarGz {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl TarGz {
+  pub fn new() -> Self {
+    Self {
+      data: Vec::new(),
+      mtime: Utc::now().timestamp(),
+    }
+  }
+
+  /// Adds a file, paths longer than 100 bytes are cut.
+  pub fn add(&mut self, path: &str, content: &[u8]) {
+    let mut header = [0u8; BLOCK];
+    let name = path.as_bytes();
+    let len = name.len().min(100);
+    header[..len].copy_from_slice(&name[..len]);
+    octal(&mut header[100..108], 0o644);
+    octal(&mut header[108..116], 0);
+    octal(&mut header[116..124], 0);
+    octal(&mut header[124..136], content.len() as u64);
+    octal(&mut header[136..148], self.mtime.max(0) as u64);
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    // the checksum is calculated with its own field set to spaces
+    header[148..156].fill(b' ');
+    let checksum: u64 = header.iter().map(|b| *b as u64).sum();
+    octal(&mut header[148..155], checksum);
+    header[155] = b' ';
+
+    self.data.extend_from_slice(&header);
+    self.data.extend_from_slice(content);
+    let padding = (BLOCK - content.len() % BLOCK) % BLOCK;
+    self.data.extend(std::iter::repeat_n(0, padding));
+  }
+
+  pub fn add_json<T: serde::Serialize>(&mut self, path: &str, value: &T) {
+    let json = serde_json::to_vec_pretty(value).unwrap_or_default();
+    self.add(path, &json);
+  }
+
+  pub fn finish(mut self) -> std::io::Result<Vec<u8>> {
+    // two empty blocks end the archive
+    self.data.extend_from_slice(&[0; BLOCK * 2]);
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&self.data)?;
+    encoder.finish()
+  }
+}
+
+/// Reads the regular files of an archive written by [`TarGz`], in order.
+pub fn read_tar_gz(archive: &[u8]) -> io::Result<Vec<(String, Vec<u8>)>> {
+  let mut tar = Vec::new();
+  GzDecoder::new(archive).read_to_end(&mut tar)?;
+
+  let mut entries = Vec::new();
+  let mut offset = 0;
+  while let Some(header) = tar.get(offset..offset + BLOCK) {
+    if header[0] == 0 {
+      break;
+    }
+
+    let mut blank = header.to_vec();
+    blank[148..156].fill(b' ');
+    let expected: u64 = blank.iter().map(|b| *b as u64).sum();
+    if parse_octal(&header[148..156])? != expected {
+      return Err(invalid("Corrupt tar header"));
+    }
+
+    let name = header[..100].split(|b| *b == 0).next().unwrap_or_default();
+    let name = String::from_utf8(name.to_vec()).map_err(|_| invalid("Invalid file name"))?;
+    let size = parse_octal(&header[124..136])? as usize;
+
+    offset += BLOCK;
+    let content = tar
+      .get(offset..offset + size)
+      .ok_or_else(|| invalid("Truncated archive"))?;
+    if header[156] == b'0' || header[156] == 0 {
+      entries.push((name, content.to_vec()));
+    }
+    offset += size.div_ceil(BLOCK) * BLOCK;
+  }
+  Ok(entries)
+}
+
+fn parse_octal(field: &[u8]) -> io::Result<u64> {
+  let text = std::str::from_utf8(field).map_err(|_| invalid("Invalid number"))?;
+  let text = text.trim_matches(|c: char| c == '\0' || c == ' ');
+  u64::from_str_radix(text, 8).map_err(|_| invalid("Invalid number"))
+}
+
+fn invalid(msg: &str) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Writes a zero padded octal number followed by a NUL into the field.
+fn octal(field: &mut [u8], value: u64) {
+  let digits = field.len() - 1;
+  let text = format!("{value:0digits$o}");
+  let text = &text.as_bytes()[text.len().saturating_sub(digits)..];
+  field[..digits].copy_from_slice(text);
+  field[digits] = 0;
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn writes_readable_archives() {
+    let mut archive = TarGz::new();
+    archive.add("profile.json", b"{}");
+    archive.add("avatar.png", &[1; 700]);
+    archive.add("empty.txt", b"");
+
+    let entries = read_tar_gz(&archive.finish().unwrap()).unwrap();
+    assert_eq!(
+      entries,
+      vec![
+        ("profile.json".to_string(), b"{}".to_vec()),
+        ("avatar.png".to_string(), vec![1; 700]),
+        ("empty.txt".to_string(), Vec::new()),
+      ]
+    );
+    assert!(read_tar_gz(b"not an archive").is_err());
+  }
+}
@@ -0,0 +1,411 @@
+use std::collections::HashMap;
+
+use aide::axum::{
+  ApiRouter,
+  routing::{delete_with, get_with, post_with, put_with},
+};
+use axum::Json;
+use centaurus::{
+  backend::auth::permission::{Permission, UserView},
+  bail,
+  db::init::Connection,
+  error::Result,
+  permission,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+  attribute::value::{AttributeDefinition, AttributeKind, Visibility},
+  audit::{AuditContext, AuditEntry},
+  db::DBTrait,
+  organization::OrgAuth,
+  permissions::PermissionInfo,
+  utils::{UpdateMessage, Updater},
+};
+
+pub mod value;
+
+permission!(AttributeEdit, "attribute:edit");
+
+pub fn router() -> ApiRouter {
+  ApiRouter::new()
+    .api_route("/", get_with(list_attributes, |op| op.id("listAttributes")))
+    .api_route(
+      "/",
+      post_with(create_attribute, |op| op.id("createAttribute")),
+    )
+    .api_route("/", put_with(edit_attribute, |op| op.id("editAttribute")))
+    .api_route(
+      "/",
+      delete_with(delete_attribute, |op| op.id("deleteAttribute")),
+    )
+}
+
+pub fn permissions() -> Vec<PermissionInfo> {
+  vec![
+    PermissionInfo::new(
+      AttributeEdit::name(),
+      "Define the custom attributes of user profiles",
+      "Users",
+    )
+    .implies(&[UserView::name()]),
+  ]
+}
+
+#[derive(Serialize, JsonSchema)]
+struct AttributeInfo {
+  uuid: Uuid,
+  #[serde(flatten)]
+  definition: AttributeDefinition,
+}
+
+async fn list_attributes(
+  _auth: OrgAuth<UserView>,
+  db: Connection,
+) -> Result<Json<Vec<AttributeInfo>>> {
+  let definitions = db.attribute().list_definitions().await?;
+
+  Ok(Json(
+    definitions
+      .iter()
+      .map(|model| AttributeInfo {
+        uuid: model.id,
+        definition: AttributeDefinition::from_model(model),
+      })
+      .collect(),
+  ))
+}
+
+#[derive(Serialize, JsonSchema)]
+struct AttributeCreateResponse {
+  uuid: Uuid,
+}
+
+/// Attribute definitions apply to every organization, so only super admins
+/// change them.
+fn ensure_super_admin(auth: &OrgAuth<AttributeEdit>) -> Result<()> {
+  if !auth.super_admin {
+    bail!(FORBIDDEN, "Only super admins can change attributes");
+  }
+  Ok(())
+}
+
+async fn create_attribute(
+  auth: OrgAuth<AttributeEdit>,
+  db: Connection,
+  updater: Updater,
+  audit: AuditContext,
+  Json(definition): Json<AttributeDefinition>,
+) -> Result<Json<AttributeCreateResponse>> {
+  ensure_super_admin(&auth)?;
+  if let Err(reason) = definition.validate() {
+    bail!(BAD_REQUEST, "{}", reason);
+  }
+  if db
+    .attribute()
+    .find_definition_by_key(&definition.key)
+    .await?
+    .is_some()
+  {
+    bail!(CONFLICT, "An attribute with this key already exists");
+  }
+
+  let uuid = Uuid::now_v7();
+  db.attribute()
+    .create_definition(definition.clone().into_model(uuid))
+    .await?;
+
+  audit
+    .record(
+      &db,
+      &updater,
+      AuditEntry::new("attribute.create")
+        .actor(auth.user_id)
+        .target("attribute", uuid)
+        .after(&definition),
+    )
+    .await;
+  updater.broadcast(UpdateMessage::Attribute { uuid }).await;
+
+  Ok(Json(AttributeCreateResponse { uuid }))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct EditAttributeRequest {
+  uuid: Uuid,
+  #[serde(flatten)]
+  definition: AttributeDefinition,
+}
+
+/// Changes a definition. The kind is fixed once created as stored values would
+/// no longer match it; tighter validation only applies to values set later.
+async fn edit_attribute(
+  auth: OrgAuth<AttributeEdit>,
+  db: Connection,
+  updater: Updater,
+  audit: AuditContext,
+  Json(data): Json<EditAttributeRequest>,
+) -> Result<()> {
+  ensure_super_admin(&auth)?;
+  let Some(existing) = db.attribute().get_definition(data.uuid).await? else {
+    bail!(NOT_FOUND, "Attribute not found");
+  };
+  let before = AttributeDefinition::from_model(&existing);
+  if before.kind != data.definition.kind {
+    bail!(BAD_REQUEST, "The kind of an attribute cannot be changed");
+  }
+  if let Err(reason) = data.definition.validate() {
+    bail!(BAD_REQUEST, "{}", reason);
+  }
+  if let Some(other) = db
+    .attribute()
+    .find_definition_by_key(&data.definition.key)
+    .await?
+    && other.id != data.uuid
+  {
+    bail!(CONFLICT, "An attribute with this key already exists");
+  }
+
+  db.attribute()
+    .update_definition(data.definition.clone().into_model(data.uuid))
+    .await?;
+
+  audit
+    .record(
+      &db,
+      &updater,
+      AuditEntry::new("attribute.edit")
+        .actor(auth.user_id)
+        .target("attribute", data.uuid)
+        .before(&before)
+        .after(&data.definition),
+    )
+    .await;
+  updater
+    .broadcast(UpdateMessage::Attribute { uuid: data.uuid })
+    .await;
+
+  Ok(())
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct DeleteAttributeRequest {
+  uuid: Uuid,
+}
+
+/// Deletes a definition together with the values of every user.
+async fn delete_attribute(
+  auth: OrgAuth<AttributeEdit>,
+  db: Connection,
+  updater: Updater,
+  audit: AuditContext,
+  Json(DeleteAttributeRequest { uuid }): Json<DeleteAttributeRequest>,
+) -> Result<()> {
+  ensure_super_admin(&auth)?;
+  let Some(existing) = db.attribute().get_definition(uuid).await? else {
+    bail!(NOT_FOUND, "Attribute not found");
+  };
+  db.attribute().delete_definition(uuid).await?;
+
+  audit
+    .record(
+      &db,
+      &updater,
+      AuditEntry::new("attribute.delete")
+        .actor(auth.user_id)
+        .target("attribute", uuid)
+        .before(&AttributeDefinition::from_model(&existing)),
+    )
+    .await;
+  updater.broadcast(UpdateMessage::Attribute { uuid }).await;
+
+  Ok(())
+}
+
+/// Attribute of a single user together with what the viewer may do with it.
+#[derive(Serialize, JsonSchema)]
+pub struct AttributeValue {
+  pub key: String,
+  pub label: String,
+  pub kind: AttributeKind,
+  pub required: bool,
+  pub options: Vec<String>,
+  pub visibility: Visibility,
+  pub editable: bool,
+  pub value: Option<Value>,
+}
+
+/// Who is looking at or changing the attributes of a user.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AttributeAccess {
+  /// The user themselves, hidden attributes are left out and only
+  /// self-editable ones can be changed.
+  Own {
+    oidc_user: bool,
+  },
+  Admin,
+}
+
+impl AttributeAccess {
+  fn visible(self, definition: &AttributeDefinition) -> bool {
+    self == Self::Admin || definition.visibility != Visibility::Hidden
+  }
+
+  fn editable(self, definition: &AttributeDefinition) -> bool {
+    match self {
+      Self::Admin => true,
+      // values from the provider are replaced on the next login anyway
+      Self::Own { oidc_user } => {
+        definition.visibility == Visibility::SelfEditable
+          && !(oidc_user && definition.oidc_claim.is_some())
+      }
+    }
+  }
+}
+
+pub async fn user_attributes(
+  db: &Connection,
+  user: Uuid,
+  access: AttributeAccess,
+) -> Result<Vec<AttributeValue>> {
+  let definitions = db.attribute().list_definitions().await?;
+  let values = db.attribute().get_values(user).await?;
+
+  Ok(
+    definitions
+      .iter()
+      .filter_map(|model| {
+        let definition = AttributeDefinition::from_model(model);
+        if !access.visible(&definition) {
+          return None;
+        }
+        let value = values.get(&model.id).map(|v| definition.to_json(v));
+        Some(AttributeValue {
+          editable: access.editable(&definition),
+          key: definition.key,
+          label: definition.label,
+          kind: definition.kind,
+          required: definition.required,
+          options: definition.options,
+          visibility: definition.visibility,
+          value,
+        })
+      })
+      .collect(),
+  )
+}
+
+/// Values of the user by attribute key, as recorded in the audit log.
+async fn values_by_key(db: &Connection, user: Uuid) -> Result<HashMap<String, String>> {
+  let definitions = db.attribute().list_definitions().await?;
+  let mut values = db.attribute().get_values(user).await?;
+
+  Ok(
+    definitions
+      .into_iter()
+      .filter_map(|model| Some((model.key, values.remove(&model.id)?)))
+      .collect(),
+  )
+}
+
+/// Validates and stores the given values by attribute key, `null` clears a
+/// value. Nothing is stored if any value is rejected.
+pub async fn update_attributes(
+  db: &Connection,
+  updater: &Updater,
+  context: &AuditContext,
+  actor: Uuid,
+  user: Uuid,
+  access: AttributeAccess,
+  values: HashMap<String, Value>,
+) -> Result<()> {
+  let definitions = db.attribute().list_definitions().await?;
+
+  let mut changes = Vec::new();
+  let mut errors = Vec::new();
+  for (key, value) in &values {
+    let Some(model) = definitions.iter().find(|model| &model.key == key) else {
+      bail!(BAD_REQUEST, "Unknown attribute {}", key);
+    };
+    let definition = AttributeDefinition::from_model(model);
+    if !access.visible(&definition) {
+      bail!(BAD_REQUEST, "Unknown attribute {}", key);
+    }
+    if !access.editable(&definition) {
+      bail!(FORBIDDEN, "Attribute {} cannot be changed", key);
+    }
+
+    match definition.normalize(value) {
+      Ok(value) => changes.push((model.id, value)),
+      Err(error) => errors.push(error),
+    }
+  }
+  if !errors.is_empty() {
+    errors.sort_unstable();
+    bail!(BAD_REQUEST, "{}", errors.join(", "));
+  }
+
+  let before = values_by_key(db, user).await?;
+  db.attribute().set_values(user, changes).await?;
+  let after = values_by_key(db, user).await?;
+
+  if before != after {
+    context
+      .record(
+        db,
+        updater,
+        AuditEntry::new("user.attributes_edit")
+          .actor(actor)
+          .target("user", user)
+          .before(&before)
+          .after(&after),
+      )
+      .await;
+    updater.broadcast(UpdateMessage::User { uuid: user }).await;
+  }
+
+  Ok(())
+}
+
+/// Takes the values of attributes sourced from OIDC claims. Missing claims
+/// leave the current value, invalid ones are skipped. Returns whether anything
+/// changed.
+pub async fn apply_claims(
+  db: &Connection,
+  user: Uuid,
+  claims: &HashMap<String, Value>,
+) -> Result<bool> {
+  let definitions = db.attribute().list_definitions().await?;
+  let current = db.attribute().get_values(user).await?;
+
+  let mut changes = Vec::new();
+  for model in definitions {
+    let Some(claim) = model.oidc_claim.as_ref() else {
+      continue;
+    };
+    let Some(value) = claims.get(claim).filter(|value| !value.is_null()) else {
+      continue;
+    };
+
+    let definition = AttributeDefinition::from_model(&model);
+    match definition.normalize(value) {
+      Ok(value) if value.as_ref() != current.get(&model.id) => changes.push((model.id, value)),
+      Ok(_) => (),
+      Err(error) => warn!(
+        "Ignoring OIDC claim {} for attribute {}: {}",
+        claim, model.key, error
+      ),
+    }
+  }
+
+  let changed = !changes.is_empty();
+  if changed {
+    db.attribute().set_values(user, changes).await?;
+  }
+
+  Ok(changed)
+}
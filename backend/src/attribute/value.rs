@@ -0,0 +1,372 @@
+use chrono::NaiveDate;
+use entity::attribute_definition;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Number, Value};
+
+/// Longest value stored for a string attribute, regardless of its maximum.
+const MAX_STRING_LENGTH: usize = 1024;
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum AttributeKind {
+  String,
+  Number,
+  Enum,
+  /// Calendar date as `YYYY-MM-DD`.
+  Date,
+}
+
+impl AttributeKind {
+  pub fn as_str(self) -> &'static str {
+    match self {
+      Self::String => "string",
+      Self::Number => "number",
+      Self::Enum => "enum",
+      Self::Date => "date",
+    }
+  }
+
+  pub fn parse(kind: &str) -> Option<Self> {
+    match kind {
+      "string" => Some(Self::String),
+      "number" => Some(Self::Number),
+      "enum" => Some(Self::Enum),
+      "date" => Some(Self::Date),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility {
+  /// Visible to the user and editable by them.
+  SelfEditable,
+  /// Visible to the user, only administrators can change it.
+  AdminOnly,
+  /// Only visible to administrators.
+  Hidden,
+}
+
+impl Visibility {
+  pub fn as_str(self) -> &'static str {
+    match self {
+      Self::SelfEditable => "self_editable",
+      Self::AdminOnly => "admin_only",
+      Self::Hidden => "hidden",
+    }
+  }
+
+  pub fn parse(visibility: &str) -> Option<Self> {
+    match visibility {
+      "self_editable" => Some(Self::SelfEditable),
+      "admin_only" => Some(Self::AdminOnly),
+      "hidden" => Some(Self::Hidden),
+      _ => None,
+    }
+  }
+}
+
+/// An attribute definition as exchanged with the api.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct AttributeDefinition {
+  pub key: String,
+  pub label: String,
+  pub kind: AttributeKind,
+  #[serde(default)]
+  pub required: bool,
+  /// Allowed values of enum attributes.
+  #[serde(default)]
+  pub options: Vec<String>,
+  /// Lower bound of numbers or minimum length of strings.
+  pub min: Option<f64>,
+  /// Upper bound of numbers or maximum length of strings.
+  pub max: Option<f64>,
+  /// Regular expression string values have to match as a whole.
+  pub pattern: Option<String>,
+  pub visibility: Visibility,
+  /// Claim of the OIDC provider the value is taken from on every login.
+  pub oidc_claim: Option<String>,
+  #[serde(default)]
+  pub position: i32,
+}
+
+impl AttributeDefinition {
+  pub fn from_model(model: &attribute_definition::Model) -> Self {
+    Self {
+      key: model.key.clone(),
+      label: model.label.clone(),
+      kind: AttributeKind::parse(&model.kind).unwrap_or(AttributeKind::String),
+      required: model.required,
+      options: model
+        .options
+        .clone()
+        .and_then(|options| serde_json::from_value(options).ok())
+        .unwrap_or_default(),
+      min: model.min,
+      max: model.max,
+      pattern: model.pattern.clone(),
+      visibility: Visibility::parse(&model.visibility).unwrap_or(Visibility::Hidden),
+      oidc_claim: model.oidc_claim.clone(),
+      position: model.position,
+    }
+  }
+
+  pub fn into_model(self, id: uuid::Uuid) -> attribute_definition::Model {
+    attribute_definition::Model {
+      id,
+      key: self.key,
+      label: self.label,
+      kind: self.kind.as_str().to_string(),
+      required: self.required,
+      options: (self.kind == AttributeKind::Enum).then(|| serde_json::json!(self.options)),
+      min: self.min,
+      max: self.max,
+      pattern: self.pattern,
+      visibility: self.visibility.as_str().to_string(),
+      oidc_claim: self.oidc_claim,
+      position: self.position,
+    }
+  }
+
+  /// Checks the definition itself is usable, returning the reason if not.
+  pub fn validate(&self) -> Result<(), String> {
+    let key_valid = self
+      .key
+      .chars()
+      .next()
+      .is_some_and(|c| c.is_ascii_lowercase())
+      && self
+        .key
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if !key_valid || self.key.len() > 64 {
+      return Err(
+        "Key must start with a lowercase letter and only contain lowercase letters, digits and underscores"
+          .into(),
+      );
+    }
+    if self.label.trim().is_empty() {
+      return Err("Label cannot be empty".into());
+    }
+
+    if self.kind == AttributeKind::Enum && self.options.iter().all(|o| o.trim().is_empty()) {
+      return Err("Enum attributes need at least one option".into());
+    }
+    if self.kind != AttributeKind::Enum && !self.options.is_empty() {
+      return Err("Only enum attributes have options".into());
+    }
+
+    if (self.min.is_some() || self.max.is_some())
+      && !matches!(self.kind, AttributeKind::String | AttributeKind::Number)
+    {
+      return Err("Only string and number attributes have bounds".into());
+    }
+    if self.min.is_some_and(|min| !min.is_finite()) || self.max.is_some_and(|max| !max.is_finite())
+    {
+      return Err("Bounds must be finite numbers".into());
+    }
+    if let (Some(min), Some(max)) = (self.min, self.max)
+      && min > max
+    {
+      return Err("Minimum cannot be greater than maximum".into());
+    }
+
+    if let Some(pattern) = &self.pattern {
+      if self.kind != AttributeKind::String {
+        return Err("Only string attributes have a pattern".into());
+      }
+      if Regex::new(pattern).is_err() {
+        return Err("Pattern is not a valid regular expression".into());
+      }
+    }
+
+    if self
+      .oidc_claim
+      .as_ref()
+      .is_some_and(|claim| claim.trim().is_empty())
+    {
+      return Err("OIDC claim cannot be empty".into());
+    }
+
+    Ok(())
+  }
+
+  /// Validates a value and converts it to its stored form. `None` clears the
+  /// value, which is only allowed for optional attributes.
+  pub fn normalize(&self, value: &Value) -> Result<Option<String>, String> {
+    let value = match value {
+      Value::Null => None,
+      Value::String(s) if s.trim().is_empty() => None,
+      value => Some(value),
+    };
+    let Some(value) = value else {
+      if self.required {
+        return Err(format!("{} is required", self.label));
+      }
+      return Ok(None);
+    };
+
+    let normalized = match self.kind {
+      AttributeKind::String => {
+        let Value::String(s) = value else {
+          return Err(format!("{} must be text", self.label));
+        };
+        let s = s.trim();
+        let length = s.chars().count();
+        if length > MAX_STRING_LENGTH || self.max.is_some_and(|max| length as f64 > max) {
+          return Err(format!("{} is too long", self.label));
+        }
+        if self.min.is_some_and(|min| (length as f64) < min) {
+          return Err(format!("{} is too short", self.label));
+        }
+        if let Some(pattern) = &self.pattern {
+          let regex = Regex::new(&format!("^(?:{pattern})$"))
+            .map_err(|_| format!("{} has an invalid pattern", self.label))?;
+          if !regex.is_match(s) {
+            return Err(format!("{} has an invalid format", self.label));
+          }
+        }
+        s.to_string()
+      }
+      AttributeKind::Number => {
+        // values imported from files or claims may be numbers as text
+        let number = match value {
+          Value::Number(n) => Some(n.clone()),
+          Value::String(s) => s.trim().parse::<Number>().ok(),
+          _ => None,
+        };
+        let Some(number) = number.filter(|n| n.as_f64().is_some_and(f64::is_finite)) else {
+          return Err(format!("{} must be a number", self.label));
+        };
+        let float = number.as_f64().unwrap_or_default();
+        if self.min.is_some_and(|min| float < min) {
+          return Err(format!("{} is too small", self.label));
+        }
+        if self.max.is_some_and(|max| float > max) {
+          return Err(format!("{} is too large", self.label));
+        }
+        number.to_string()
+      }
+      AttributeKind::Enum => {
+        let Value::String(s) = value else {
+          return Err(format!("{} must be one of the options", self.label));
+        };
+        if !self.options.iter().any(|option| option == s) {
+          return Err(format!("{} must be one of the options", self.label));
+        }
+        s.clone()
+      }
+      AttributeKind::Date => {
+        let date = match value {
+          Value::String(s) => NaiveDate::parse_from_str(s.trim(), DATE_FORMAT).ok(),
+          _ => None,
+        };
+        let Some(date) = date else {
+          return Err(format!("{} must be a date (YYYY-MM-DD)", self.label));
+        };
+        date.format(DATE_FORMAT).to_string()
+      }
+    };
+
+    Ok(Some(normalized))
+  }
+
+  /// Converts a stored value back to json.
+  pub fn to_json(&self, stored: &str) -> Value {
+    match self.kind {
+      AttributeKind::Number => stored
+        .parse::<Number>()
+        .map(Value::Number)
+        .unwrap_or_else(|_| Value::String(stored.to_string())),
+      _ => Value::String(stored.to_string()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use serde_json::json;
+
+  use super::*;
+
+  fn definition(kind: AttributeKind) -> AttributeDefinition {
+    AttributeDefinition {
+      key: "department".into(),
+      label: "Department".into(),
+      kind,
+      required: false,
+      options: Vec::new(),
+      min: None,
+      max: None,
+      pattern: None,
+      visibility: Visibility::SelfEditable,
+      oidc_claim: None,
+      position: 0,
+    }
+  }
+
+  #[test]
+  fn validates_definitions() {
+    assert!(definition(AttributeKind::String).validate().is_ok());
+
+    let mut invalid_key = definition(AttributeKind::String);
+    invalid_key.key = "Department".into();
+    assert!(invalid_key.validate().is_err());
+
+    assert!(definition(AttributeKind::Enum).validate().is_err());
+
+    let mut bounds = definition(AttributeKind::Number);
+    bounds.min = Some(10.0);
+    bounds.max = Some(1.0);
+    assert!(bounds.validate().is_err());
+
+    let mut pattern = definition(AttributeKind::String);
+    pattern.pattern = Some("(".into());
+    assert!(pattern.validate().is_err());
+  }
+
+  #[test]
+  fn normalizes_values_by_kind() {
+    let mut string = definition(AttributeKind::String);
+    string.pattern = Some("[A-Z]{2}-\\d+".into());
+    assert_eq!(
+      string.normalize(&json!(" AB-12 ")),
+      Ok(Some("AB-12".into()))
+    );
+    assert!(string.normalize(&json!("xAB-12")).is_err());
+    assert!(string.normalize(&json!(12)).is_err());
+
+    let mut number = definition(AttributeKind::Number);
+    number.max = Some(100.0);
+    assert_eq!(number.normalize(&json!(42)), Ok(Some("42".into())));
+    assert_eq!(number.normalize(&json!("4.5")), Ok(Some("4.5".into())));
+    assert!(number.normalize(&json!(101)).is_err());
+    assert_eq!(number.to_json("42"), json!(42));
+
+    let mut choice = definition(AttributeKind::Enum);
+    choice.options = vec!["de".into(), "en".into()];
+    assert_eq!(choice.normalize(&json!("en")), Ok(Some("en".into())));
+    assert!(choice.normalize(&json!("fr")).is_err());
+
+    let date = definition(AttributeKind::Date);
+    assert_eq!(
+      date.normalize(&json!("2026-02-03")),
+      Ok(Some("2026-02-03".into()))
+    );
+    assert!(date.normalize(&json!("2026-02-30")).is_err());
+  }
+
+  #[test]
+  fn clearing_respects_required() {
+    let mut string = definition(AttributeKind::String);
+    assert_eq!(string.normalize(&Value::Null), Ok(None));
+    assert_eq!(string.normalize(&json!("  ")), Ok(None));
+
+    string.required = true;
+    assert!(string.normalize(&Value::Null).is_err());
+  }
+}
@@ -0,0 +1,428 @@
+use std::collections::BTreeMap;
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use centaurus::error::{ErrorReportStatusExt, Result};
+use entity::{audit_event, key};
+use http::StatusCode;
+use rsa::{
+  RsaPrivateKey,
+  pkcs1::{DecodeRsaPrivateKey, EncodeRsaPrivateKey, LineEnding},
+  pkcs1v15::{Signature, SigningKey, VerifyingKey},
+  rand_core::OsRng,
+  signature::{Keypair, SignatureEncoding, Signer, Verifier},
+};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::db::repository::KeyRepository;
+
+/// `prev_hash` of the first record in the chain.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+const KEY_NAME: &str = "audit";
+
+/// RSA key from the `key` table used to sign the hash of every audit record.
+pub struct AuditKey {
+  pub id: Uuid,
+  signing_key: SigningKey<Sha256>,
+  verifying_key: VerifyingKey<Sha256>,
+}
+
+impl AuditKey {
+  fn from_pem(id: Uuid, pem: &str) -> Result<Self> {
+    let private_key = RsaPrivateKey::from_pkcs1_pem(pem)
+      .status_context(StatusCode::INTERNAL_SERVER_ERROR, "Invalid audit key")?;
+    Ok(Self::from_private_key(id, private_key))
+  }
+
+  fn from_private_key(id: Uuid, private_key: RsaPrivateKey) -> Self {
+    let signing_key = SigningKey::<Sha256>::new(private_key);
+    let verifying_key = signing_key.verifying_key();
+
+    Self {
+      id,
+      signing_key,
+      verifying_key,
+    }
+  }
+
+  /// The stored signing key, if signing was ever enabled.
+  pub async fn load(keys: &dyn KeyRepository) -> Result<Option<Self>> {
+    match keys.find_key(KEY_NAME).await? {
+      Some(key) => Ok(Some(Self::from_pem(key.id, &key.private_key)?)),
+      None => Ok(None),
+    }
+  }
+
+  pub async fn load_or_create(keys: &dyn KeyRepository) -> Result<Self> {
+    if let Some(key) = Self::load(keys).await? {
+      return Ok(key);
+    }
+
+    info!("Generating new audit log signing key. This may take a few seconds...");
+    let bits = if cfg!(feature = "test") { 512 } else { 4096 };
+    let private_key = RsaPrivateKey::new(&mut OsRng, bits)?;
+    let pem = private_key
+      .to_pkcs1_pem(LineEnding::CRLF)
+      .status(StatusCode::INTERNAL_SERVER_ERROR)?
+      .to_string();
+
+    let id = Uuid::new_v4();
+    keys
+      .create_key(key::Model {
+        id,
+        name: KEY_NAME.into(),
+        private_key: pem,
+      })
+      .await?;
+
+    Ok(Self::from_private_key(id, private_key))
+  }
+
+  pub fn sign(&self, hash: &str) -> String {
+    let signature = self.signing_key.sign(hash.as_bytes());
+    BASE64_STANDARD.encode(signature.to_bytes())
+  }
+
+  pub fn verify(&self, hash: &str, signature: &str) -> bool {
+    let Ok(bytes) = BASE64_STANDARD.decode(signature) else {
+      return false;
+    };
+    let Ok(signature) = Signature::try_from(bytes.as_slice()) else {
+      return false;
+    };
+
+    self
+      .verifying_key
+      .verify(hash.as_bytes(), &signature)
+      .is_ok()
+  }
+}
+
+/// Serializes JSON with sorted object keys so the hash does not depend on how
+/// the database stores the payload.
+fn canonical(value: &Option<Value>) -> Value {
+  fn sort(value: &Value) -> Value {
+    match value {
+      Value::Object(map) => {
+        let sorted: BTreeMap<_, _> = map.iter().map(|(k, v)| (k.clone(), sort(v))).collect();
+        Value::Object(sorted.into_iter().collect())
+      }
+      Value::Array(values) => Value::Array(values.iter().map(sort).collect()),
+      value => value.clone(),
+    }
+  }
+
+  value.as_ref().map(sort).unwrap_or(Value::Null)
+}
+
+/// Hash over the record's content and its position in the chain.
+pub fn compute_hash(event: &audit_event::Model) -> String {
+  let mut content = serde_json::json!([
+    event.sequence,
+    event.prev_hash,
+    event.id,
+    event.created_at.format("%Y-%m-%dT%H:%M:%S%.6f").to_string(),
+    event.actor_id,
+    event.action,
+    event.target_type,
+    event.target_id,
+    canonical(&event.before),
+    canonical(&event.after),
+    event.ip,
+    event.user_agent,
+    event.status,
+  ]);
+  // added later, only part of the hash when set so older records still verify
+  if let (Some(impersonator), Value::Array(content)) = (event.impersonator_id, &mut content) {
+    content.push(serde_json::json!(impersonator));
+  }
+
+  format!("{:x}", Sha256::digest(content.to_string().as_bytes()))
+}
+
+/// Links the record to its predecessor and signs it if a key is given.
+pub fn seal(
+  event: &mut audit_event::Model,
+  sequence: i64,
+  prev_hash: String,
+  key: Option<&AuditKey>,
+) {
+  event.sequence = Some(sequence);
+  event.prev_hash = Some(prev_hash);
+  let hash = compute_hash(event);
+  event.signature = key.map(|key| key.sign(&hash));
+  event.key_id = key.map(|key| key.id);
+  event.hash = Some(hash);
+}
+
+#[derive(Serialize, JsonSchema, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChainIssueKind {
+  /// Records between the previous and this one are missing.
+  Gap,
+  /// The record does not reference the hash of its predecessor.
+  BrokenLink,
+  /// The record was modified after it was written.
+  HashMismatch,
+  InvalidSignature,
+  /// The record was never added to the chain.
+  Unsealed,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ChainIssue {
+  pub kind: ChainIssueKind,
+  pub sequence: Option<i64>,
+  pub uuid: Uuid,
+  pub detail: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct VerifyReport {
+  pub valid: bool,
+  pub checked: u64,
+  pub signed: u64,
+  /// Head of the chain, can be stored elsewhere to detect truncation later.
+  pub last_sequence: Option<i64>,
+  pub last_hash: Option<String>,
+  pub issues: Vec<ChainIssue>,
+}
+
+/// Walks the chain in sequence order, one record at a time.
+pub struct ChainVerifier<'k> {
+  key: Option<&'k AuditKey>,
+  next_sequence: i64,
+  prev_hash: String,
+  report: VerifyReport,
+}
+
+impl<'k> ChainVerifier<'k> {
+  pub fn new(key: Option<&'k AuditKey>) -> Self {
+    Self {
+      key,
+      next_sequence: 1,
+      prev_hash: GENESIS_HASH.to_string(),
+      report: VerifyReport {
+        valid: true,
+        checked: 0,
+        signed: 0,
+        last_sequence: None,
+        last_hash: None,
+        issues: Vec::new(),
+      },
+    }
+  }
+
+  fn issue(&mut self, kind: ChainIssueKind, event: &audit_event::Model, detail: String) {
+    self.report.valid = false;
+    self.report.issues.push(ChainIssue {
+      kind,
+      sequence: event.sequence,
+      uuid: event.id,
+      detail,
+    });
+  }
+
+  pub fn push(&mut self, event: &audit_event::Model) {
+    self.report.checked += 1;
+
+    let (Some(sequence), Some(prev_hash), Some(hash)) =
+      (event.sequence, &event.prev_hash, &event.hash)
+    else {
+      self.issue(
+        ChainIssueKind::Unsealed,
+        event,
+        "Record is not part of the chain".into(),
+      );
+      return;
+    };
+
+    if sequence != self.next_sequence {
+      self.issue(
+        ChainIssueKind::Gap,
+        event,
+        format!(
+          "Records {} to {} are missing",
+          self.next_sequence,
+          sequence - 1
+        ),
+      );
+    } else if *prev_hash != self.prev_hash {
+      self.issue(
+        ChainIssueKind::BrokenLink,
+        event,
+        "Previous hash does not match the preceding record".into(),
+      );
+    }
+
+    if compute_hash(event) != *hash {
+      self.issue(
+        ChainIssueKind::HashMismatch,
+        event,
+        "Content does not match the stored hash".into(),
+      );
+    }
+
+    if let Some(signature) = &event.signature {
+      self.report.signed += 1;
+      let valid = match self.key {
+        Some(key) if Some(key.id) == event.key_id => key.verify(hash, signature),
+        _ => false,
+      };
+      if !valid {
+        self.issue(
+          ChainIssueKind::InvalidSignature,
+          event,
+          "Signature does not match the audit key".into(),
+        );
+      }
+    }
+
+    self.next_sequence = sequence + 1;
+    self.prev_hash = hash.clone();
+    self.report.last_sequence = Some(sequence);
+    self.report.last_hash = Some(hash.clone());
+  }
+
+  pub fn finish(self) -> VerifyReport {
+    self.report
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::db::repository::memory::MemoryRepository;
+  use chrono::Utc;
+
+  fn event(action: &str) -> audit_event::Model {
+    audit_event::Model {
+      id: Uuid::now_v7(),
+      created_at: Utc::now().naive_utc(),
+      actor_id: Some(Uuid::new_v4()),
+      action: action.into(),
+      target_type: Some("group".into()),
+      target_id: None,
+      before: None,
+      after: Some(serde_json::json!({ "name": "test", "users": [] })),
+      ip: Some("127.0.0.1".into()),
+      user_agent: None,
+      status: 200,
+      sequence: None,
+      prev_hash: None,
+      hash: None,
+      signature: None,
+      key_id: None,
+      impersonator_id: None,
+    }
+  }
+
+  fn chain(len: usize, key: Option<&AuditKey>) -> Vec<audit_event::Model> {
+    let mut prev_hash = GENESIS_HASH.to_string();
+    (1..=len)
+      .map(|sequence| {
+        let mut event = event("group.create");
+        seal(&mut event, sequence as i64, prev_hash.clone(), key);
+        prev_hash = event.hash.clone().unwrap();
+        event
+      })
+      .collect()
+  }
+
+  fn verify(events: &[audit_event::Model], key: Option<&AuditKey>) -> VerifyReport {
+    let mut verifier = ChainVerifier::new(key);
+    for event in events {
+      verifier.push(event);
+    }
+    verifier.finish()
+  }
+
+  fn kinds(report: &VerifyReport) -> Vec<&ChainIssueKind> {
+    report.issues.iter().map(|i| &i.kind).collect()
+  }
+
+  #[test]
+  fn intact_chain_verifies() {
+    let events = chain(3, None);
+    let report = verify(&events, None);
+    assert!(report.valid);
+    assert_eq!(report.checked, 3);
+    assert_eq!(report.last_sequence, Some(3));
+  }
+
+  #[test]
+  fn detects_modified_and_deleted_records() {
+    let mut events = chain(4, None);
+    events[1].action = "group.delete".into();
+    assert_eq!(
+      kinds(&verify(&events, None)),
+      vec![&ChainIssueKind::HashMismatch]
+    );
+
+    let mut events = chain(2, None);
+    events[0].impersonator_id = Some(Uuid::new_v4());
+    assert_eq!(
+      kinds(&verify(&events, None)),
+      vec![&ChainIssueKind::HashMismatch]
+    );
+
+    let mut events = chain(4, None);
+    events.remove(1);
+    assert_eq!(kinds(&verify(&events, None)), vec![&ChainIssueKind::Gap]);
+
+    // rewriting a record including its hash still breaks the next link
+    let mut events = chain(3, None);
+    events[1].action = "group.delete".into();
+    events[1].hash = Some(compute_hash(&events[1]));
+    assert_eq!(
+      kinds(&verify(&events, None)),
+      vec![&ChainIssueKind::BrokenLink]
+    );
+  }
+
+  #[test]
+  fn signatures_are_checked() {
+    let private_key = RsaPrivateKey::new(&mut OsRng, 512).unwrap();
+    let key = AuditKey::from_private_key(Uuid::new_v4(), private_key);
+
+    let mut events = chain(2, Some(&key));
+    let report = verify(&events, Some(&key));
+    assert!(report.valid);
+    assert_eq!(report.signed, 2);
+
+    events[0].signature = events[1].signature.clone();
+    assert_eq!(
+      kinds(&verify(&events, Some(&key))),
+      vec![&ChainIssueKind::InvalidSignature]
+    );
+    assert!(!verify(&events[1..], None).valid);
+  }
+
+  #[tokio::test]
+  async fn stored_key_is_reused() {
+    let repo = MemoryRepository::default();
+    assert!(AuditKey::load(&repo).await.unwrap().is_none());
+
+    let pem = RsaPrivateKey::new(&mut OsRng, 512)
+      .unwrap()
+      .to_pkcs1_pem(LineEnding::CRLF)
+      .unwrap()
+      .to_string();
+    let id = Uuid::new_v4();
+    repo
+      .create_key(key::Model {
+        id,
+        name: KEY_NAME.into(),
+        private_key: pem,
+      })
+      .await
+      .unwrap();
+
+    assert_eq!(AuditKey::load_or_create(&repo).await.unwrap().id, id);
+  }
+}
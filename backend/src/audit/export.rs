@@ -0,0 +1,135 @@
+use axum::{
+  extract::Query,
+  response::{IntoResponse, Response},
+};
+use centaurus::{db::init::Connection, error::Result};
+use chrono::{DateTime, Utc};
+use http::header;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+  audit::AuditView,
+  csv,
+  db::{
+    DBTrait,
+    audit::{AuditEventInfo, AuditFilter},
+  },
+  organization::OrgAuth,
+};
+
+const CSV_COLUMNS: &[&str] = &[
+  "sequence",
+  "uuid",
+  "created_at",
+  "actor",
+  "action",
+  "target_type",
+  "target_id",
+  "before",
+  "after",
+  "ip",
+  "user_agent",
+  "status",
+  "prev_hash",
+  "hash",
+  "signature",
+  "key_id",
+  "impersonator",
+];
+
+#[derive(Deserialize, JsonSchema, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum ExportFormat {
+  #[default]
+  Jsonl,
+  Csv,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub(super) struct ExportQuery {
+  #[serde(default)]
+  format: ExportFormat,
+  from: Option<DateTime<Utc>>,
+  to: Option<DateTime<Utc>>,
+}
+
+pub(super) async fn export_events(
+  auth: OrgAuth<AuditView>,
+  db: Connection,
+  Query(query): Query<ExportQuery>,
+) -> Result<Response> {
+  super::ensure_super_admin(&auth)?;
+  let filter = AuditFilter {
+    from: query.from,
+    to: query.to,
+    ..Default::default()
+  };
+  let events = db.audit().export_events(filter).await?;
+
+  let (content_type, extension, body) = match query.format {
+    ExportFormat::Jsonl => ("application/jsonl", "jsonl", to_jsonl(&events)?),
+    ExportFormat::Csv => ("text/csv", "csv", to_csv(&events)),
+  };
+  let disposition = format!(
+    "attachment; filename=\"audit-{}.{}\"",
+    Utc::now().format("%Y%m%d%H%M%S"),
+    extension
+  );
+
+  Ok(
+    (
+      [
+        (header::CONTENT_TYPE, content_type.to_string()),
+        (header::CONTENT_DISPOSITION, disposition),
+      ],
+      body,
+    )
+      .into_response(),
+  )
+}
+
+fn to_jsonl(events: &[AuditEventInfo]) -> Result<String> {
+  let mut out = String::new();
+  for event in events {
+    out.push_str(&serde_json::to_string(event)?);
+    out.push('\n');
+  }
+  Ok(out)
+}
+
+fn to_csv(events: &[AuditEventInfo]) -> String {
+  let mut out = csv::row(CSV_COLUMNS);
+
+  for event in events {
+    let json = |value: &Option<Value>| value.as_ref().map(Value::to_string);
+    let row = [
+      event.sequence.map(|s| s.to_string()),
+      Some(event.uuid.to_string()),
+      Some(event.created_at.to_rfc3339()),
+      event.actor.map(|a| a.to_string()),
+      Some(event.action.clone()),
+      event.target_type.clone(),
+      event.target_id.clone(),
+      json(&event.before),
+      json(&event.after),
+      event.ip.clone(),
+      event.user_agent.clone(),
+      Some(event.status.to_string()),
+      event.prev_hash.clone(),
+      event.hash.clone(),
+      event.signature.clone(),
+      event.key_id.map(|k| k.to_string()),
+      event.impersonator.map(|i| i.to_string()),
+    ];
+
+    let fields: Vec<&str> = row
+      .iter()
+      .map(|field| field.as_deref().unwrap_or_default())
+      .collect();
+    out.push_str(&csv::row(&fields));
+  }
+
+  out
+}
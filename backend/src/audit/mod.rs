@@ -0,0 +1,532 @@
+use std::{borrow::Cow, net::SocketAddr, sync::Arc};
+
+use aide::{
+  OperationIo,
+  axum::{ApiRouter, routing::get_with},
+};
+use axum::{
+  Extension, Json,
+  body::{Body, to_bytes},
+  extract::{ConnectInfo, FromRequestParts, Query, Request},
+  middleware::Next,
+  response::{IntoResponse, Response},
+};
+use centaurus::{
+  backend::auth::{
+    jwt_auth::JwtAuth, jwt_state::JwtState, permission::Permission, settings::UserSettings,
+  },
+  bail,
+  db::{init::Connection, tables::ConnectionExt},
+  error::Result,
+  mail::MailSettings,
+  permission,
+};
+use chrono::{DateTime, Utc};
+use http::{Method, StatusCode, header, request::Parts};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::Mutex;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{
+  audit::chain::{AuditKey, ChainVerifier, VerifyReport},
+  config::Config,
+  db::{
+    DBTrait,
+    audit::{AuditEventInfo, AuditFilter, NewAuditEvent},
+  },
+  impersonation::Impersonation,
+  organization::OrgAuth,
+  permissions::PermissionInfo,
+  utils::{UpdateMessage, Updater, api_path, issued_session},
+};
+
+pub mod chain;
+mod export;
+
+permission!(AuditView, "audit:view");
+
+const DEFAULT_PAGE_SIZE: u64 = 50;
+const MAX_PAGE_SIZE: u64 = 200;
+const MAX_AUDITED_BODY: usize = 1024 * 1024;
+const VERIFY_BATCH_SIZE: u64 = 1000;
+
+pub fn router() -> ApiRouter {
+  ApiRouter::new()
+    .api_route("/", get_with(list_events, |op| op.id("listAuditEvents")))
+    .api_route(
+      "/verify",
+      get_with(verify_chain, |op| op.id("verifyAuditChain")),
+    )
+    .api_route(
+      "/export",
+      get_with(export::export_events, |op| op.id("exportAuditEvents")),
+    )
+}
+
+/// The returned context records the entries of background jobs, which have no
+/// request to take it from.
+pub async fn state(
+  router: ApiRouter,
+  db: &Connection,
+  config: &Config,
+) -> (ApiRouter, AuditContext) {
+  let key = if config.audit_signing {
+    let key = AuditKey::load_or_create(db)
+      .await
+      .expect("Failed to load audit signing key");
+    Some(Arc::new(key))
+  } else {
+    None
+  };
+
+  let sealed = db
+    .audit()
+    .seal_pending(key.as_deref())
+    .await
+    .expect("Failed to chain pending audit events");
+  if sealed > 0 {
+    info!("Added {} pending audit events to the chain", sealed);
+  }
+
+  let state = AuditState {
+    key,
+    lock: Default::default(),
+  };
+  let context = AuditContext {
+    ip: None,
+    user_agent: None,
+    impersonator: None,
+    state: Some(state.clone()),
+  };
+  (router.layer(Extension(state)), context)
+}
+
+/// Shared by all requests so records are chained one at a time.
+#[derive(Clone)]
+struct AuditState {
+  key: Option<Arc<AuditKey>>,
+  lock: Arc<Mutex<()>>,
+}
+
+pub fn permissions() -> Vec<PermissionInfo> {
+  vec![PermissionInfo::new(
+    AuditView::name(),
+    "View the audit log of security-relevant actions",
+    "Audit",
+  )]
+}
+
+/// A single entry to be written to the audit log.
+pub struct AuditEntry {
+  action: Cow<'static, str>,
+  actor: Option<Uuid>,
+  target_type: Option<&'static str>,
+  target_id: Option<String>,
+  before: Option<Value>,
+  after: Option<Value>,
+  status: StatusCode,
+}
+
+impl AuditEntry {
+  pub fn new(action: impl Into<Cow<'static, str>>) -> Self {
+    Self {
+      action: action.into(),
+      actor: None,
+      target_type: None,
+      target_id: None,
+      before: None,
+      after: None,
+      status: StatusCode::OK,
+    }
+  }
+
+  pub fn actor(mut self, actor: Uuid) -> Self {
+    self.actor = Some(actor);
+    self
+  }
+
+  pub fn target(mut self, target_type: &'static str, target_id: impl ToString) -> Self {
+    self.target_type = Some(target_type);
+    self.target_id = Some(target_id.to_string());
+    self
+  }
+
+  pub fn before<T: Serialize>(mut self, before: &T) -> Self {
+    self.before = serde_json::to_value(before).ok().map(redact);
+    self
+  }
+
+  pub fn after<T: Serialize>(mut self, after: &T) -> Self {
+    self.after = serde_json::to_value(after).ok().map(redact);
+    self
+  }
+}
+
+/// Request metadata attached to every audit entry.
+#[derive(Clone, OperationIo)]
+pub struct AuditContext {
+  ip: Option<String>,
+  user_agent: Option<String>,
+  /// Administrator behind the request if it was made while impersonating.
+  impersonator: Option<Uuid>,
+  state: Option<AuditState>,
+}
+
+impl<S: Sync> FromRequestParts<S> for AuditContext {
+  type Rejection = std::convert::Infallible;
+
+  async fn from_request_parts(
+    parts: &mut Parts,
+    _state: &S,
+  ) -> std::result::Result<Self, Self::Rejection> {
+    let ip = parts
+      .extensions
+      .get::<ConnectInfo<SocketAddr>>()
+      .map(|info| info.0.ip().to_string());
+    let user_agent = parts
+      .headers
+      .get(header::USER_AGENT)
+      .and_then(|value| value.to_str().ok())
+      .map(String::from);
+    let impersonator = Impersonation::from_parts(parts)
+      .await
+      .map(|impersonation| impersonation.admin);
+    let state = parts.extensions.get::<AuditState>().cloned();
+
+    Ok(Self {
+      ip,
+      user_agent,
+      impersonator,
+      state,
+    })
+  }
+}
+
+impl AuditContext {
+  pub fn ip(&self) -> Option<&str> {
+    self.ip.as_deref()
+  }
+
+  pub fn user_agent(&self) -> Option<&str> {
+    self.user_agent.as_deref()
+  }
+
+  /// Stores the entry and notifies everyone allowed to read the audit log.
+  /// Failing to audit is logged but does not fail the already completed action.
+  pub async fn record(&self, db: &Connection, updater: &Updater, entry: AuditEntry) {
+    let event = NewAuditEvent {
+      actor: entry.actor,
+      action: entry.action.to_string(),
+      target_type: entry.target_type.map(String::from),
+      target_id: entry.target_id,
+      before: entry.before,
+      after: entry.after,
+      ip: self.ip.clone(),
+      user_agent: self.user_agent.clone(),
+      status: entry.status.as_u16(),
+      impersonator: self.impersonator,
+    };
+
+    let Some(state) = &self.state else {
+      error!("Audit state missing, dropping event {}", entry.action);
+      return;
+    };
+
+    let result = {
+      let _guard = state.lock.lock().await;
+      db.audit().record(event, state.key.as_deref()).await
+    };
+    let uuid = match result {
+      Ok(uuid) => uuid,
+      Err(err) => {
+        error!("Failed to record audit event {}: {:?}", entry.action, err);
+        return;
+      }
+    };
+
+    match db.role().users_with_permission(AuditView::name()).await {
+      Ok(watchers) => {
+        for user_id in watchers {
+          updater
+            .send_to(user_id, UpdateMessage::Audit { uuid })
+            .await;
+        }
+      }
+      Err(err) => error!("Failed to notify audit log watchers: {:?}", err),
+    }
+  }
+}
+
+/// Whether a field of a payload holds credentials.
+pub fn is_secret(key: &str) -> bool {
+  let key = key.to_lowercase();
+  ["password", "secret", "token"]
+    .iter()
+    .any(|s| key.contains(s))
+}
+
+/// Removes credentials from audited payloads.
+pub fn redact(value: Value) -> Value {
+  match value {
+    Value::Object(map) => Value::Object(
+      map
+        .into_iter()
+        .filter(|(key, _)| !is_secret(key))
+        .map(|(key, value)| (key, redact(value)))
+        .collect(),
+    ),
+    Value::Array(values) => Value::Array(values.into_iter().map(redact).collect()),
+    value => value,
+  }
+}
+
+#[derive(Clone, Copy)]
+enum Snapshot {
+  None,
+  User,
+  Group,
+  UserSettings,
+  MailSettings,
+}
+
+#[derive(Clone, Copy)]
+enum Target {
+  None,
+  /// The user performing the request, e.g. for account changes.
+  Actor,
+  /// The resource referenced by `uuid` in the request body.
+  Body(&'static str),
+  /// A settings section by name.
+  Settings(&'static str),
+}
+
+struct AuditedRoute {
+  action: &'static str,
+  target: Target,
+  snapshot: Snapshot,
+}
+
+/// Endpoints provided by centaurus, which are audited from the outside. The
+/// application's own handlers record their events directly.
+fn audited_route(method: &Method, path: &str) -> Option<AuditedRoute> {
+  use Snapshot as S;
+  use Target as T;
+
+  let (action, target, snapshot) = match (method.as_str(), path) {
+    ("POST", "/auth/password") => ("auth.login", T::Actor, S::None),
+    ("GET", "/auth/oidc/callback") => ("auth.login_oidc", T::Actor, S::None),
+    ("POST", "/auth/logout") => ("auth.logout", T::Actor, S::None),
+    ("POST", "/setup") => ("setup.complete", T::None, S::None),
+    ("POST", "/setup/oidc") => ("setup.oidc", T::None, S::None),
+    ("POST", "/user/account/password") => ("user.password_change", T::Actor, S::None),
+    ("POST", "/user/account/update") => ("user.account_update", T::Actor, S::User),
+    ("POST", "/user/account/avatar") => ("user.avatar_update", T::Actor, S::None),
+    ("POST", "/user/account/email_change_start") => ("user.email_change_start", T::Actor, S::None),
+    ("POST", "/user/account/email_change_confirm") => {
+      ("user.email_change_confirm", T::Actor, S::User)
+    }
+    ("POST", "/user/management") => ("user.create", T::Body("user"), S::None),
+    ("PUT", "/user/management") => ("user.edit", T::Body("user"), S::User),
+    ("DELETE", "/user/management/avatar") => ("user.avatar_reset", T::Body("user"), S::None),
+    ("PUT", "/user/management/password") => ("user.password_reset", T::Body("user"), S::None),
+    ("POST", "/user/management/email") => ("user.email_change", T::Body("user"), S::User),
+    ("PUT", "/user/management/convert-oidc") => ("user.convert_oidc", T::Body("user"), S::User),
+    ("POST", "/group") => ("group.create", T::Body("group"), S::None),
+    ("DELETE", "/group") => ("group.delete", T::Body("group"), S::Group),
+    ("POST", "/settings/user") => ("settings.user", T::Settings("user"), S::UserSettings),
+    ("POST", "/settings/mail") => ("settings.mail", T::Settings("mail"), S::MailSettings),
+    ("POST", "/mail/test") => ("mail.test", T::None, S::None),
+    ("POST", "/mail/reset/send") => ("mail.reset_link", T::None, S::None),
+    ("POST", "/mail/reset/confirm") => ("mail.reset_password", T::None, S::None),
+    _ => return None,
+  };
+
+  Some(AuditedRoute {
+    action,
+    target,
+    snapshot,
+  })
+}
+
+async fn snapshot(db: &Connection, snapshot: Snapshot, target: Option<Uuid>) -> Option<Value> {
+  let value = match (snapshot, target) {
+    (Snapshot::User, Some(target)) => {
+      serde_json::to_value(db.user().user_info(target).await.ok()??).ok()
+    }
+    (Snapshot::Group, Some(target)) => {
+      serde_json::to_value(db.group().group_info(target).await.ok()??).ok()
+    }
+    (Snapshot::UserSettings, _) => {
+      serde_json::to_value(db.settings().get_settings::<UserSettings>().await.ok()?).ok()
+    }
+    (Snapshot::MailSettings, _) => {
+      serde_json::to_value(db.settings().get_settings::<MailSettings>().await.ok()?).ok()
+    }
+    _ => None,
+  };
+
+  value.map(redact)
+}
+
+/// Only the audited routes pay for the session and database lookups, every
+/// other request is passed on untouched.
+pub async fn middleware(request: Request, next: Next) -> Response {
+  let Some(route) = audited_route(request.method(), api_path(request.uri().path())) else {
+    return next.run(request).await;
+  };
+
+  let (mut parts, body) = request.into_parts();
+  let (Ok(db), Ok(jwt), Ok(updater)) = (
+    Connection::from_request_parts(&mut parts, &()).await,
+    JwtState::from_request_parts(&mut parts, &()).await,
+    Updater::from_request_parts(&mut parts, &()).await,
+  ) else {
+    return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+  };
+  let Ok(context) = AuditContext::from_request_parts(&mut parts, &()).await;
+  let auth = Option::<JwtAuth>::from_request_parts(&mut parts, &())
+    .await
+    .ok()
+    .flatten();
+
+  let is_json = parts
+    .headers
+    .get(header::CONTENT_TYPE)
+    .and_then(|value| value.to_str().ok())
+    .is_some_and(|value| value.starts_with("application/json"));
+
+  let (request, body) = if is_json {
+    let Ok(bytes) = to_bytes(body, MAX_AUDITED_BODY).await else {
+      return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+    };
+    let body = serde_json::from_slice::<Value>(&bytes).ok();
+    (Request::from_parts(parts, Body::from(bytes)), body)
+  } else {
+    (Request::from_parts(parts, body), None)
+  };
+
+  let actor = auth.map(|auth| auth.user_id);
+  let body_target = body
+    .as_ref()
+    .and_then(|body| body.get("uuid").or_else(|| body.get("id")))
+    .and_then(Value::as_str)
+    .and_then(|id| Uuid::parse_str(id).ok());
+  let target = match route.target {
+    Target::Actor => actor,
+    Target::Body(_) => body_target,
+    _ => None,
+  };
+  let before = snapshot(&db, route.snapshot, target).await;
+
+  let response = next.run(request).await;
+
+  let actor = issued_session(&jwt, &response).or(actor);
+  let mut entry = AuditEntry::new(route.action);
+  entry = match route.target {
+    Target::None => entry,
+    // logins only know who acted once the response issued a session
+    Target::Actor => match actor {
+      Some(actor) => entry.target("user", actor),
+      None => entry,
+    },
+    Target::Body(target_type) => match body_target {
+      Some(target) => entry.target(target_type, target),
+      None => AuditEntry {
+        target_type: Some(target_type),
+        ..entry
+      },
+    },
+    Target::Settings(section) => entry.target("settings", section),
+  };
+  entry.actor = actor;
+  entry.before = before;
+  entry.after = body.map(redact);
+  entry.status = response.status();
+
+  context.record(&db, &updater, entry).await;
+
+  response
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct AuditQuery {
+  actor: Option<Uuid>,
+  /// Action or action prefix, e.g. `group` or `group.edit`.
+  action: Option<String>,
+  target_type: Option<String>,
+  target_id: Option<String>,
+  from: Option<DateTime<Utc>>,
+  to: Option<DateTime<Utc>>,
+  #[serde(default)]
+  offset: u64,
+  limit: Option<u64>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct AuditEventsResponse {
+  events: Vec<AuditEventInfo>,
+  total: u64,
+}
+
+/// The log covers every organization, so only super admins may read it.
+fn ensure_super_admin(auth: &OrgAuth<AuditView>) -> Result<()> {
+  if !auth.super_admin {
+    bail!(FORBIDDEN, "Only super admins can view the audit log");
+  }
+  Ok(())
+}
+
+async fn list_events(
+  auth: OrgAuth<AuditView>,
+  db: Connection,
+  Query(query): Query<AuditQuery>,
+) -> Result<Json<AuditEventsResponse>> {
+  ensure_super_admin(&auth)?;
+  let filter = AuditFilter {
+    actor: query.actor,
+    action: query.action,
+    target_type: query.target_type,
+    target_id: query.target_id,
+    from: query.from,
+    to: query.to,
+  };
+  let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+
+  let (events, total) = db.audit().list_events(filter, query.offset, limit).await?;
+
+  Ok(Json(AuditEventsResponse { events, total }))
+}
+
+/// Walks the whole chain and reports gaps, modified records and invalid
+/// signatures.
+pub async fn verify(db: &Connection) -> Result<VerifyReport> {
+  let key = AuditKey::load(db).await?;
+  let mut verifier = ChainVerifier::new(key.as_ref());
+
+  for event in db.audit().unsealed().await? {
+    verifier.push(&event);
+  }
+
+  let mut after = 0;
+  loop {
+    let page = db.audit().chain_page(after, VERIFY_BATCH_SIZE).await?;
+    let Some(last) = page.last() else {
+      break;
+    };
+    after = last.sequence.unwrap_or(after);
+
+    for event in &page {
+      verifier.push(event);
+    }
+  }
+
+  Ok(verifier.finish())
+}
+
+async fn verify_chain(auth: OrgAuth<AuditView>, db: Connection) -> Result<Json<VerifyReport>> {
+  ensure_super_admin(&auth)?;
+  Ok(Json(verify(&db).await?))
+}
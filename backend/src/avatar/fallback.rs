@@ -0,0 +1,124 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// What is served for users without an avatar.
+#[derive(Deserialize, JsonSchema, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Fallback {
+  /// Initials of the name on a colored background.
+  #[default]
+  Initials,
+  /// Symmetric pattern derived from the user id.
+  Identicon,
+  /// No image, the request fails with 404.
+  None,
+}
+
+/// Up to two letters from the first and last word of a name.
+fn initials(name: &str) -> String {
+  let mut words = name.split_whitespace();
+  let first = words.next().and_then(|word| word.chars().next());
+  let last = words.next_back().and_then(|word| word.chars().next());
+
+  let initials: String = first
+    .into_iter()
+    .chain(last)
+    .flat_map(char::to_uppercase)
+    .collect();
+  if initials.is_empty() {
+    "?".into()
+  } else {
+    initials
+  }
+}
+
+fn escape(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
+
+fn hash(uuid: Uuid) -> [u8; 32] {
+  Sha256::digest(uuid.as_bytes()).into()
+}
+
+/// Color stable per user, so the same person always looks the same.
+fn color(hash: &[u8; 32]) -> String {
+  let hue = u16::from_be_bytes([hash[30], hash[31]]) % 360;
+  format!("hsl({hue}, 55%, 45%)")
+}
+
+/// SVG of the given fallback kind, `None` for [`Fallback::None`].
+pub fn render(kind: Fallback, uuid: Uuid, name: &str, size: u32) -> Option<String> {
+  let hash = hash(uuid);
+  let content = match kind {
+    Fallback::Initials => format!(
+      r##"<rect width="100" height="100" fill="{}"/><text x="50" y="50" dy="0.35em" text-anchor="middle" font-family="sans-serif" font-size="40" fill="#fff">{}</text>"##,
+      color(&hash),
+      escape(&initials(name)),
+    ),
+    Fallback::Identicon => {
+      // 5x5 cells mirrored around the middle column, one bit of the hash each
+      let mut cells = String::new();
+      for row in 0..5 {
+        for col in 0..3 {
+          if hash[row * 3 + col] & 1 == 0 {
+            continue;
+          }
+          for x in [col, 4 - col] {
+            cells.push_str(&format!(
+              r#"<rect x="{}" y="{}" width="16" height="16"/>"#,
+              10 + x * 16,
+              10 + row * 16,
+            ));
+            if x == 2 {
+              break;
+            }
+          }
+        }
+      }
+      format!(
+        r##"<rect width="100" height="100" fill="#f0f0f0"/><g fill="{}" shape-rendering="crispEdges">{}</g>"##,
+        color(&hash),
+        cells,
+      )
+    }
+    Fallback::None => return None,
+  };
+
+  Some(format!(
+    r#"<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{size}" viewBox="0 0 100 100">{content}</svg>"#
+  ))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn takes_initials_of_first_and_last_word() {
+    assert_eq!(initials("Ada Lovelace"), "AL");
+    assert_eq!(initials(" grace  brewster murray hopper "), "GH");
+    assert_eq!(initials("émile"), "É");
+    assert_eq!(initials(""), "?");
+  }
+
+  #[test]
+  fn renders_escaped_and_stable_svgs() {
+    let uuid = Uuid::new_v4();
+    let svg = render(Fallback::Initials, uuid, "<b> &", 64).unwrap();
+    assert!(svg.contains("&lt;&amp;"));
+    assert!(svg.contains(r#"width="64""#));
+
+    let identicon = render(Fallback::Identicon, uuid, "", 32).unwrap();
+    assert_eq!(
+      render(Fallback::Identicon, uuid, "", 32).unwrap(),
+      identicon
+    );
+    assert!(render(Fallback::None, uuid, "", 32).is_none());
+  }
+}
@@ -0,0 +1,188 @@
+use axum::{
+  Json,
+  extract::{Path, Query},
+  response::{IntoResponse, Response},
+};
+use base64::prelude::*;
+use centaurus::{
+  backend::auth::jwt_auth::JwtAuth,
+  bail,
+  db::{init::Connection, tables::ConnectionExt},
+  error::{ErrorReportStatusExt, Result},
+};
+use entity::avatar_variant;
+use http::{HeaderMap, StatusCode, header};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::task::spawn_blocking;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+  avatar::fallback::Fallback,
+  db::DBTrait,
+  utils::{UpdateMessage, Updater},
+};
+
+pub mod fallback;
+pub mod process;
+
+/// Largest accepted upload, base64 encoded.
+pub const MAX_UPLOAD: usize = 10 * 1024 * 1024;
+/// Size served when none is requested, matching what the avatar used to be.
+const DEFAULT_SIZE: u32 = 128;
+/// Avatars change rarely but should show up right away, so clients keep them
+/// and revalidate with the `ETag` on every use.
+const CACHE_CONTROL: &str = "private, no-cache";
+
+pub fn etag(data: &[u8]) -> String {
+  format!("\"{:x}\"", Sha256::digest(data))
+}
+
+/// Validates and normalizes an image and stores it as the avatar of the user.
+/// Returns whether the avatar changed.
+pub async fn set_avatar(db: &Connection, user: Uuid, data: Vec<u8>) -> Result<bool> {
+  let variants = spawn_blocking(move || process::variants(&process::decode(&data)?))
+    .await
+    .status(StatusCode::INTERNAL_SERVER_ERROR)??;
+  let largest = variants[0].1.clone();
+  if db.user().get_user_avatar(user).await?.as_ref() == Some(&largest) {
+    return Ok(false);
+  }
+
+  db.avatar()
+    .store(user, largest, variant_models(user, variants))
+    .await?;
+  Ok(true)
+}
+
+fn variant_models(user: Uuid, variants: Vec<(u32, Vec<u8>)>) -> Vec<avatar_variant::Model> {
+  variants
+    .into_iter()
+    .map(|(size, data)| avatar_variant::Model {
+      user_id: user,
+      size: size as i32,
+      etag: etag(&data),
+      data,
+    })
+    .collect()
+}
+
+/// Creates the variants of an avatar stored without them, like the ones
+/// fetched from Gravatar on user creation or uploaded before avatars were
+/// normalized. Avatars that cannot be decoded are treated as missing.
+async fn generate_variants(db: &Connection, user: Uuid) -> Result<bool> {
+  let Some(data) = db.user().get_user_avatar(user).await? else {
+    return Ok(false);
+  };
+  let variants = match spawn_blocking(move || process::variants(&process::decode(&data)?)).await {
+    Ok(Ok(variants)) => variants,
+    Ok(Err(e)) => {
+      warn!("Stored avatar of user {} is invalid: {:?}", user, e);
+      return Ok(false);
+    }
+    Err(e) => return Err(e).status(StatusCode::INTERNAL_SERVER_ERROR),
+  };
+  let largest = variants[0].1.clone();
+
+  db.avatar()
+    .store(user, largest, variant_models(user, variants))
+    .await?;
+  Ok(true)
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct AvatarUpdate {
+  /// Image as base64, any format that can be decoded.
+  avatar: String,
+}
+
+pub async fn update_avatar(
+  auth: JwtAuth,
+  db: Connection,
+  updater: Updater,
+  Json(data): Json<AvatarUpdate>,
+) -> Result<()> {
+  if data.avatar.len() > MAX_UPLOAD {
+    bail!(PAYLOAD_TOO_LARGE, "Avatar size exceeds 10MB limit");
+  }
+  let raw = BASE64_STANDARD
+    .decode(data.avatar)
+    .status(StatusCode::BAD_REQUEST)?;
+
+  set_avatar(&db, auth.user_id, raw).await?;
+  updater
+    .broadcast(UpdateMessage::User { uuid: auth.user_id })
+    .await;
+
+  Ok(())
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct AvatarPath {
+  uuid: Uuid,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct AvatarQuery {
+  /// Width and height in pixels, the next larger stored size is served.
+  size: Option<u32>,
+  #[serde(default)]
+  fallback: Fallback,
+}
+
+/// Avatar of a user as WebP, or a generated SVG if they have none.
+pub async fn avatar(
+  _auth: JwtAuth,
+  db: Connection,
+  Path(path): Path<AvatarPath>,
+  Query(query): Query<AvatarQuery>,
+  headers: HeaderMap,
+) -> Result<Response> {
+  let size = process::variant_size(query.size.unwrap_or(DEFAULT_SIZE));
+
+  let mut variant = db.avatar().get_variant(path.uuid, size as i32).await?;
+  if variant.is_none() && generate_variants(&db, path.uuid).await? {
+    variant = db.avatar().get_variant(path.uuid, size as i32).await?;
+  }
+  if let Some(variant) = variant {
+    return Ok(cached(&headers, "image/webp", variant.etag, variant.data));
+  }
+
+  let Some(name) = db.avatar().user_name(path.uuid).await? else {
+    return Ok(StatusCode::NOT_FOUND.into_response());
+  };
+  let Some(svg) = fallback::render(query.fallback, path.uuid, &name, size) else {
+    return Ok(StatusCode::NOT_FOUND.into_response());
+  };
+  let etag = etag(svg.as_bytes());
+  Ok(cached(&headers, "image/svg+xml", etag, svg.into_bytes()))
+}
+
+/// Response with caching headers, empty if the client already has this
+/// version.
+pub fn cached(
+  headers: &HeaderMap,
+  content_type: &'static str,
+  etag: String,
+  data: Vec<u8>,
+) -> Response {
+  let not_modified = headers
+    .get_all(header::IF_NONE_MATCH)
+    .iter()
+    .filter_map(|value| value.to_str().ok())
+    .flat_map(|value| value.split(','))
+    .map(|tag| tag.trim().trim_start_matches("W/"))
+    .any(|tag| tag == etag || tag == "*");
+
+  let cache = [
+    (header::ETAG, etag),
+    (header::CACHE_CONTROL, CACHE_CONTROL.to_string()),
+  ];
+  if not_modified {
+    (StatusCode::NOT_MODIFIED, cache).into_response()
+  } else {
+    (cache, [(header::CONTENT_TYPE, content_type)], data).into_response()
+  }
+}
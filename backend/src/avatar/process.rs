@@ -0,0 +1,126 @@
+use std::io::Cursor;
+
+use centaurus::{
+  bail,
+  error::{ErrorReportStatusExt, Result},
+};
+use http::StatusCode;
+use image::{
+  DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits, imageops::FilterType,
+  metadata::Orientation,
+};
+
+/// Sizes every avatar is stored in, largest first.
+pub const SIZES: [u32; 4] = [256, 128, 64, 32];
+/// Larger images are rejected before decoding them completely.
+const MAX_DIMENSION: u32 = 8192;
+
+/// Decodes an uploaded image, whatever content type it claimed to have. The
+/// EXIF orientation is applied to the pixels as the metadata itself is not
+/// carried over.
+pub fn decode(data: &[u8]) -> Result<DynamicImage> {
+  let mut reader = ImageReader::new(Cursor::new(data))
+    .with_guessed_format()
+    .status(StatusCode::BAD_REQUEST)?;
+  if reader.format().is_none() {
+    bail!(BAD_REQUEST, "Not a supported image");
+  }
+
+  let mut limits = Limits::default();
+  limits.max_image_width = Some(MAX_DIMENSION);
+  limits.max_image_height = Some(MAX_DIMENSION);
+  reader.limits(limits);
+
+  let mut decoder = reader
+    .into_decoder()
+    .status_context(StatusCode::BAD_REQUEST, "Not a supported image")?;
+  let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+  let mut image = DynamicImage::from_decoder(decoder)
+    .status_context(StatusCode::BAD_REQUEST, "Not a supported image")?;
+  image.apply_orientation(orientation);
+
+  Ok(image)
+}
+
+/// Center crops the image to a square and encodes it as WebP in each of the
+/// [`SIZES`].
+pub fn variants(image: &DynamicImage) -> Result<Vec<(u32, Vec<u8>)>> {
+  let side = image.width().min(image.height());
+  let mut current = image.crop_imm(
+    (image.width() - side) / 2,
+    (image.height() - side) / 2,
+    side,
+    side,
+  );
+
+  let mut variants = Vec::with_capacity(SIZES.len());
+  for size in SIZES {
+    // every size is scaled from the previous one, which is close enough and
+    // much cheaper for large uploads
+    current = DynamicImage::ImageRgba8(
+      current
+        .resize_exact(size, size, FilterType::Lanczos3)
+        .into_rgba8(),
+    );
+    let mut buf = Cursor::new(Vec::new());
+    current.write_to(&mut buf, ImageFormat::WebP)?;
+    variants.push((size, buf.into_inner()));
+  }
+
+  Ok(variants)
+}
+
+/// Variant served for a requested size, the smallest one at least as large.
+pub fn variant_size(requested: u32) -> u32 {
+  SIZES
+    .iter()
+    .rev()
+    .copied()
+    .find(|size| *size >= requested)
+    .unwrap_or(SIZES[0])
+}
+
+#[cfg(test)]
+mod test {
+  use image::{ImageBuffer, Rgb, RgbImage};
+
+  use super::*;
+
+  fn encode(image: RgbImage, format: ImageFormat) -> Vec<u8> {
+    let mut buf = Cursor::new(Vec::new());
+    image.write_to(&mut buf, format).unwrap();
+    buf.into_inner()
+  }
+
+  #[test]
+  fn creates_square_webp_variants() {
+    let image = ImageBuffer::from_fn(300, 200, |x, _| Rgb([(x % 256) as u8, 0, 0]));
+    let decoded = decode(&encode(image, ImageFormat::Png)).unwrap();
+
+    let variants = variants(&decoded).unwrap();
+    assert_eq!(variants.len(), SIZES.len());
+    for (size, data) in variants {
+      assert_eq!(image::guess_format(&data).unwrap(), ImageFormat::WebP);
+      let variant = image::load_from_memory(&data).unwrap();
+      assert_eq!((variant.width(), variant.height()), (size, size));
+    }
+  }
+
+  #[test]
+  fn rejects_data_that_is_no_image() {
+    assert!(decode(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>").is_err());
+
+    // a valid signature alone is not enough
+    let mut truncated = encode(RgbImage::new(64, 64), ImageFormat::Png);
+    truncated.truncate(40);
+    assert!(decode(&truncated).is_err());
+  }
+
+  #[test]
+  fn picks_the_next_larger_size() {
+    assert_eq!(variant_size(0), 32);
+    assert_eq!(variant_size(40), 64);
+    assert_eq!(variant_size(128), 128);
+    assert_eq!(variant_size(1000), 256);
+  }
+}
@@ -0,0 +1,380 @@
+//! Portable backups of all data. The archive holds the rows of every table as
+//! JSON and binary columns such as avatars as separate files, so it restores
+//! onto SQLite as well as Postgres, independent of where it was taken.
+
+use std::collections::{BTreeMap, HashMap};
+
+use centaurus::{bail, error::Result};
+use chrono::{DateTime, Utc};
+use migration::{
+  Migrator, MigratorTrait,
+  copy::{self, BATCH_SIZE},
+  tables::{Table, TableVisitor, remove_seeded_rows, visit_tables},
+};
+use sea_orm::{
+  AccessMode, ColumnTrait, ColumnType, ConnectionTrait, DatabaseBackend, DatabaseConnection,
+  DatabaseTransaction, DbErr, IdenStatic, IntoActiveModel, IsolationLevel, Iterable,
+  PaginatorTrait, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::archive::{TarGz, read_tar_gz};
+
+/// Changed whenever the layout of the archive changes.
+const FORMAT: u32 = 1;
+const MANIFEST: &str = "manifest.json";
+
+#[derive(Serialize, Deserialize)]
+pub struct Manifest {
+  pub format: u32,
+  /// Last migration applied to the database the backup was taken from.
+  pub schema: String,
+  pub created: DateTime<Utc>,
+  pub tables: Vec<TableEntry>,
+  /// SHA-256 of every other file in the archive.
+  pub files: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TableEntry {
+  pub name: String,
+  pub rows: u64,
+  /// Checksum over all rows as calculated by [`copy::checksum`].
+  pub checksum: String,
+}
+
+/// Writes every row of the database into an archive. All tables are read in
+/// one transaction, so changes made while the backup runs are either fully
+/// included or not at all.
+pub async fn backup(db: &DatabaseConnection) -> Result<(Manifest, Vec<u8>)> {
+  if !Migrator::get_pending_migrations(db).await?.is_empty() {
+    bail!("The database has pending migrations, start the server once to apply them");
+  }
+  let Some(schema) = Migrator::get_applied_migrations(db).await?.pop() else {
+    bail!("The database has no schema yet");
+  };
+
+  let txn = match db.get_database_backend() {
+    DatabaseBackend::Postgres => {
+      db.begin_with_config(
+        Some(IsolationLevel::RepeatableRead),
+        Some(AccessMode::ReadOnly),
+      )
+      .await?
+    }
+    _ => db.begin().await?,
+  };
+
+  let mut writer = Writer {
+    db: &txn,
+    archive: TarGz::new(),
+    manifest: Manifest {
+      format: FORMAT,
+      schema: schema.name().to_string(),
+      created: Utc::now(),
+      tables: Vec::new(),
+      files: BTreeMap::new(),
+    },
+  };
+  visit_tables(&mut writer).await?;
+  let Writer {
+    mut archive,
+    manifest,
+    ..
+  } = writer;
+  txn.commit().await?;
+
+  archive.add_json(MANIFEST, &manifest);
+  Ok((manifest, archive.finish()?))
+}
+
+/// Restores an archive written by [`backup`] into an empty database. The rows
+/// are read with the entities of this version, so the backup has to be taken
+/// with the same schema. Older backups are restored with the version they were
+/// taken with and updated from there. If the rows cannot be restored none are
+/// kept, the schema stays though, so the database has to be recreated before
+/// trying again.
+pub async fn restore(db: &DatabaseConnection, archive: &[u8]) -> Result<Manifest> {
+  let mut files: HashMap<String, Vec<u8>> = read_tar_gz(archive)?.into_iter().collect();
+  let Some(manifest) = files.remove(MANIFEST) else {
+    bail!("Not a backup, the manifest is missing");
+  };
+  let manifest: Manifest = serde_json::from_slice(&manifest)?;
+  if manifest.format != FORMAT {
+    bail!("Unsupported backup format {}", manifest.format);
+  }
+  for (path, hash) in &manifest.files {
+    if files.get(path).map(|content| sha256(content)).as_ref() != Some(hash) {
+      bail!("The backup is damaged, {} is missing or was modified", path);
+    }
+  }
+
+  let migrations = Migrator::migrations();
+  let Some(schema) = migrations
+    .iter()
+    .position(|migration| migration.name() == manifest.schema)
+  else {
+    bail!(
+      "The backup was taken with schema {}, which is newer than this version, update before restoring",
+      manifest.schema
+    );
+  };
+  if schema + 1 != migrations.len() {
+    bail!(
+      "The backup was taken with the older schema {}, restore it with the version it was taken with and update afterwards",
+      manifest.schema
+    );
+  }
+  if !Migrator::get_applied_migrations(db).await?.is_empty() {
+    bail!("Backups can only be restored into an empty database");
+  }
+
+  Migrator::up(db, None).await?;
+  let txn = db.begin().await?;
+  remove_seeded_rows(&txn).await?;
+  visit_tables(&mut Reader {
+    db: &txn,
+    manifest: &manifest,
+    files: &files,
+  })
+  .await?;
+  txn.commit().await?;
+
+  Ok(manifest)
+}
+
+struct Writer<'db> {
+  db: &'db DatabaseTransaction,
+  archive: TarGz,
+  manifest: Manifest,
+}
+
+impl Writer<'_> {
+  fn add(&mut self, path: String, content: &[u8]) {
+    self.archive.add(&path, content);
+    self.manifest.files.insert(path, sha256(content));
+  }
+}
+
+impl TableVisitor for Writer<'_> {
+  async fn visit<E: Table>(&mut self) -> std::result::Result<(), DbErr> {
+    let name = E::default().table_name().to_string();
+    let blobs = blob_columns::<E>();
+
+    let mut rows = Vec::new();
+    let mut pages = copy::ordered::<E>().paginate(self.db, BATCH_SIZE);
+    while let Some(models) = pages.fetch_and_next().await? {
+      for model in models {
+        let mut row = serde_json::to_value(&model).map_err(json_error)?;
+        for column in &blobs {
+          let data: Vec<u8> = serde_json::from_value(row[*column].take()).map_err(json_error)?;
+          let path = format!("blobs/{name}/{}.{column}", rows.len());
+          self.add(path.clone(), &data);
+          row[*column] = path.into();
+        }
+        rows.push(row);
+      }
+    }
+
+    let content = serde_json::to_vec(&rows).map_err(json_error)?;
+    self.add(format!("tables/{name}.json"), &content);
+    self.manifest.tables.push(TableEntry {
+      checksum: copy::checksum::<E, _>(self.db).await?,
+      rows: rows.len() as u64,
+      name,
+    });
+    Ok(())
+  }
+}
+
+struct Reader<'a> {
+  db: &'a DatabaseTransaction,
+  manifest: &'a Manifest,
+  files: &'a HashMap<String, Vec<u8>>,
+}
+
+impl Reader<'_> {
+  fn file(&self, path: &str) -> std::result::Result<&[u8], DbErr> {
+    self
+      .files
+      .get(path)
+      .map(Vec::as_slice)
+      .ok_or_else(|| DbErr::Custom(format!("The backup is missing {path}")))
+  }
+}
+
+impl TableVisitor for Reader<'_> {
+  async fn visit<E: Table>(&mut self) -> std::result::Result<(), DbErr> {
+    let name = E::default().table_name().to_string();
+    let Some(entry) = self.manifest.tables.iter().find(|table| table.name == name) else {
+      return Err(DbErr::Custom(format!("The backup is missing {name}")));
+    };
+    let blobs = blob_columns::<E>();
+
+    let rows: Vec<Value> =
+      serde_json::from_slice(self.file(&format!("tables/{name}.json"))?).map_err(json_error)?;
+    let mut batch = Vec::new();
+    for mut row in rows {
+      for column in &blobs {
+        let path = row[*column].as_str().unwrap_or_default().to_string();
+        row[*column] = serde_json::to_value(self.file(&path)?).map_err(json_error)?;
+      }
+      let model: E::Model = serde_json::from_value(row).map_err(json_error)?;
+      batch.push(model.into_active_model());
+
+      if batch.len() as u64 == BATCH_SIZE {
+        E::insert_many(batch.drain(..))
+          .exec_without_returning(self.db)
+          .await?;
+      }
+    }
+    if !batch.is_empty() {
+      E::insert_many(batch)
+        .exec_without_returning(self.db)
+        .await?;
+    }
+    if self.db.get_database_backend() == DatabaseBackend::Postgres {
+      copy::reset_sequence::<E, _>(self.db).await?;
+    }
+
+    let rows = E::find().count(self.db).await?;
+    if rows != entry.rows || copy::checksum::<E, _>(self.db).await? != entry.checksum {
+      return Err(DbErr::Custom(format!(
+        "The restored rows of {name} differ from the backup"
+      )));
+    }
+    Ok(())
+  }
+}
+
+/// Binary columns are stored as files of their own instead of JSON arrays.
+fn blob_columns<E: Table>() -> Vec<&'static str> {
+  E::Column::iter()
+    .filter(|column| {
+      matches!(
+        column.def().get_column_type(),
+        ColumnType::Blob | ColumnType::Binary(_) | ColumnType::VarBinary(_)
+      )
+    })
+    .map(|column| column.as_str())
+    .collect()
+}
+
+fn sha256(content: &[u8]) -> String {
+  format!("{:x}", Sha256::digest(content))
+}
+
+fn json_error(err: serde_json::Error) -> DbErr {
+  DbErr::Json(err.to_string())
+}
+
+#[cfg(test)]
+mod test {
+  use entity::{organization, user, user_avatar};
+  use sea_orm::{ActiveModelTrait, Database, EntityTrait};
+  use uuid::Uuid;
+
+  use super::*;
+
+  async fn database(name: &str) -> (DatabaseConnection, std::path::PathBuf) {
+    let path = std::env::temp_dir().join(format!("backup-{}-{name}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let db = Database::connect(format!("sqlite://{}?mode=rwc", path.display()))
+      .await
+      .unwrap();
+    (db, path)
+  }
+
+  /// Replaces a file of the archive without updating the manifest.
+  fn tamper(archive: &[u8], path: &str, content: &[u8]) -> Vec<u8> {
+    let mut tampered = TarGz::new();
+    for (name, data) in read_tar_gz(archive).unwrap() {
+      tampered.add(&name, if name == path { content } else { &data });
+    }
+    tampered.finish().unwrap()
+  }
+
+  fn read_manifest(archive: &[u8]) -> Vec<u8> {
+    read_tar_gz(archive)
+      .unwrap()
+      .into_iter()
+      .find(|(name, _)| name == MANIFEST)
+      .unwrap()
+      .1
+  }
+
+  #[tokio::test]
+  async fn backups_restore_with_blobs_and_are_checked() {
+    let (source, source_path) = database("source").await;
+    let (target, target_path) = database("target").await;
+    Migrator::up(&source, None).await.unwrap();
+
+    let user = user::Model {
+      id: Uuid::new_v4(),
+      name: "Jane".into(),
+      email: "jane@example.com".into(),
+      password: "hash".into(),
+      salt: "salt".into(),
+      oidc_user: false,
+      oidc_subject: None,
+    };
+    user
+      .clone()
+      .into_active_model()
+      .insert(&source)
+      .await
+      .unwrap();
+    user_avatar::Model {
+      user_id: user.id,
+      data: vec![0, 1, 2, 255],
+    }
+    .into_active_model()
+    .insert(&source)
+    .await
+    .unwrap();
+
+    let (manifest, archive) = backup(&source).await.unwrap();
+    assert!(manifest.files.contains_key("blobs/user_avatar/0.data"));
+
+    let damaged = tamper(&archive, "blobs/user_avatar/0.data", &[9]);
+    assert!(restore(&target, &damaged).await.is_err());
+    let mut newer = manifest;
+    newer.schema = "m29991231_000001_future".into();
+    let newer = tamper(&archive, MANIFEST, &serde_json::to_vec(&newer).unwrap());
+    assert!(restore(&target, &newer).await.is_err());
+    let mut older = serde_json::from_slice::<Manifest>(&read_manifest(&archive)).unwrap();
+    older.schema = Migrator::migrations()[0].name().to_string();
+    let older = tamper(&archive, MANIFEST, &serde_json::to_vec(&older).unwrap());
+    let Err(err) = restore(&target, &older).await else {
+      panic!("backups of older schemas cannot be restored");
+    };
+    assert!(err.error.to_string().contains("older schema"));
+    assert!(
+      Migrator::get_applied_migrations(&target)
+        .await
+        .unwrap()
+        .is_empty()
+    );
+
+    restore(&target, &archive).await.unwrap();
+    assert_eq!(user::Entity::find().one(&target).await.unwrap(), Some(user));
+    let avatar = user_avatar::Entity::find()
+      .one(&target)
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(avatar.data, [0, 1, 2, 255]);
+    assert_eq!(
+      organization::Entity::find().all(&target).await.unwrap(),
+      organization::Entity::find().all(&source).await.unwrap()
+    );
+
+    // the target is no longer empty
+    assert!(restore(&target, &archive).await.is_err());
+
+    let _ = std::fs::remove_file(source_path);
+    let _ = std::fs::remove_file(target_path);
+  }
+}
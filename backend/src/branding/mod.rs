@@ -0,0 +1,500 @@
+use std::collections::HashMap;
+
+use aide::axum::{
+  ApiRouter,
+  routing::{delete_with, get_with, post_with},
+};
+use axum::{
+  Json,
+  extract::{DefaultBodyLimit, Path, Query},
+  response::{IntoResponse, Response},
+};
+use base64::prelude::*;
+use centaurus::{
+  backend::auth::permission::{SettingsEdit, SettingsView},
+  bail,
+  db::{init::Connection, tables::ConnectionExt},
+  error::{ErrorReportStatusExt, Result},
+};
+use entity::branding_asset;
+use http::{HeaderMap, StatusCode};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::task::spawn_blocking;
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+  audit::{AuditContext, AuditEntry},
+  avatar,
+  db::DBTrait,
+  organization::OrgAuth,
+  settings::history,
+  utils::{UpdateMessage, Updater},
+};
+
+pub mod process;
+
+/// Largest accepted upload, base64 encoded.
+pub const MAX_UPLOAD: usize = 5 * 1024 * 1024;
+const MAX_NAME: usize = 64;
+const MAX_MESSAGE: usize = 2000;
+const MAX_LEGAL_LINKS: usize = 10;
+
+pub fn router() -> ApiRouter {
+  ApiRouter::new()
+    .api_route("/", get_with(get_branding, |op| op.id("getBranding")))
+    .api_route("/", post_with(save_branding, |op| op.id("saveBranding")))
+    .api_route(
+      "/",
+      delete_with(reset_branding, |op| op.id("resetBranding")),
+    )
+    .api_route(
+      "/{kind}",
+      post_with(upload_asset, |op| op.id("uploadBrandingAsset"))
+        .layer(DefaultBodyLimit::max(MAX_UPLOAD)),
+    )
+    .api_route(
+      "/{kind}",
+      delete_with(delete_asset, |op| op.id("deleteBrandingAsset")),
+    )
+    .api_route(
+      "/{kind}/{organization}",
+      get_with(asset, |op| op.id("brandingAsset")),
+    )
+}
+
+/// How an organization presents itself, on the login page and in the
+/// application. Unset fields use the defaults of the frontend.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Default, PartialEq, Debug)]
+pub struct Branding {
+  #[serde(default)]
+  pub application_name: Option<String>,
+  /// Hex color like `#1e66f5`.
+  #[serde(default)]
+  pub primary_color: Option<String>,
+  /// Shown above the login form.
+  #[serde(default)]
+  pub login_message: Option<String>,
+  #[serde(default)]
+  pub support_email: Option<String>,
+  #[serde(default)]
+  pub support_url: Option<Url>,
+  #[serde(default)]
+  pub legal_links: Vec<LegalLink>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct LegalLink {
+  /// Text of the link, like "Privacy policy".
+  pub label: String,
+  pub url: Url,
+}
+
+impl Branding {
+  fn validate(&self) -> std::result::Result<(), String> {
+    if let Some(name) = &self.application_name
+      && (name.trim().is_empty() || name.chars().count() > MAX_NAME)
+    {
+      return Err(format!(
+        "Application name must be between 1 and {MAX_NAME} characters"
+      ));
+    }
+    if let Some(color) = &self.primary_color
+      && !is_hex_color(color)
+    {
+      return Err("Primary color must be a hex color like #1e66f5".into());
+    }
+    if let Some(message) = &self.login_message
+      && message.chars().count() > MAX_MESSAGE
+    {
+      return Err(format!(
+        "Login message cannot be longer than {MAX_MESSAGE} characters"
+      ));
+    }
+    if let Some(email) = &self.support_email
+      && !email.contains('@')
+    {
+      return Err("Support email is not a valid address".into());
+    }
+    if self.legal_links.len() > MAX_LEGAL_LINKS {
+      return Err(format!("At most {MAX_LEGAL_LINKS} legal links are allowed"));
+    }
+    if self
+      .legal_links
+      .iter()
+      .any(|link| link.label.trim().is_empty())
+    {
+      return Err("Legal links need a label".into());
+    }
+    let urls = self
+      .support_url
+      .iter()
+      .chain(self.legal_links.iter().map(|link| &link.url));
+    for url in urls {
+      if !matches!(url.scheme(), "http" | "https" | "mailto") {
+        return Err(format!("Unsupported link {url}"));
+      }
+    }
+
+    Ok(())
+  }
+}
+
+fn is_hex_color(color: &str) -> bool {
+  color
+    .strip_prefix('#')
+    .is_some_and(|hex| matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Branding of every organization that changed it. Organizations without own
+/// branding use the one of the default organization.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Default, Debug, centaurus::Settings)]
+#[settings(id = 11)]
+pub struct BrandingSettings {
+  #[serde(default)]
+  pub organizations: HashMap<Uuid, Branding>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetKind {
+  Logo,
+  Favicon,
+}
+
+impl AssetKind {
+  fn as_str(self) -> &'static str {
+    match self {
+      AssetKind::Logo => "logo",
+      AssetKind::Favicon => "favicon",
+    }
+  }
+}
+
+/// Branding as served to the login page.
+#[derive(Serialize, JsonSchema)]
+pub struct PublicBranding {
+  #[serde(flatten)]
+  branding: Branding,
+  /// Path of the logo, changes whenever the logo does.
+  logo: Option<String>,
+  favicon: Option<String>,
+}
+
+/// Organization whose branding applies, falling back to the default one.
+async fn effective_organization(
+  db: &Connection,
+  settings: &BrandingSettings,
+  organization: Option<Uuid>,
+) -> Result<Option<Uuid>> {
+  if let Some(organization) = organization
+    && settings.organizations.contains_key(&organization)
+  {
+    return Ok(Some(organization));
+  }
+  Ok(
+    db.organization()
+      .default_organization()
+      .await?
+      .map(|o| o.id),
+  )
+}
+
+/// Organization an asset is served from, the default one if the organization
+/// has none of its own.
+async fn asset_organization(
+  db: &Connection,
+  organization: Uuid,
+  kind: AssetKind,
+) -> Result<Option<(Uuid, String)>> {
+  if let Some(version) = db
+    .branding()
+    .asset_version(organization, kind.as_str())
+    .await?
+  {
+    return Ok(Some((organization, version)));
+  }
+  let Some(default) = db.organization().default_organization().await? else {
+    return Ok(None);
+  };
+  Ok(
+    db.branding()
+      .asset_version(default.id, kind.as_str())
+      .await?
+      .map(|version| (default.id, version)),
+  )
+}
+
+async fn asset_path(
+  db: &Connection,
+  organization: Uuid,
+  kind: AssetKind,
+) -> Result<Option<String>> {
+  Ok(
+    asset_organization(db, organization, kind)
+      .await?
+      .map(|(owner, version)| {
+        format!(
+          "/api/settings/branding/{}/{}?v={}",
+          kind.as_str(),
+          owner,
+          version.trim_matches('"').get(..16).unwrap_or_default()
+        )
+      }),
+  )
+}
+
+/// Branding of the organization, or of the default organization if it has
+/// none or is unknown.
+pub async fn public_branding(
+  db: &Connection,
+  organization: Option<Uuid>,
+) -> Result<PublicBranding> {
+  let settings: BrandingSettings = db.settings().get_settings().await?;
+  let Some(effective) = effective_organization(db, &settings, organization).await? else {
+    return Ok(PublicBranding {
+      branding: Branding::default(),
+      logo: None,
+      favicon: None,
+    });
+  };
+  let assets_of = organization.unwrap_or(effective);
+
+  Ok(PublicBranding {
+    branding: settings
+      .organizations
+      .get(&effective)
+      .cloned()
+      .unwrap_or_default(),
+    logo: asset_path(db, assets_of, AssetKind::Logo).await?,
+    favicon: asset_path(db, assets_of, AssetKind::Favicon).await?,
+  })
+}
+
+/// Drops the branding of a deleted organization, its assets are removed with
+/// it by the database.
+pub async fn forget(db: &Connection, author: Uuid, organization: Uuid) -> Result<()> {
+  let mut settings: BrandingSettings = db.settings().get_settings().await?;
+  if settings.organizations.remove(&organization).is_some() {
+    history::save(db, author, &settings).await?;
+  }
+  Ok(())
+}
+
+#[derive(Serialize, JsonSchema)]
+struct BrandingResponse {
+  branding: Branding,
+  /// The organization has no branding of its own and shows the default one.
+  inherited: bool,
+}
+
+async fn get_branding(
+  auth: OrgAuth<SettingsView>,
+  db: Connection,
+) -> Result<Json<BrandingResponse>> {
+  let settings: BrandingSettings = db.settings().get_settings().await?;
+  if let Some(branding) = settings.organizations.get(&auth.organization) {
+    return Ok(Json(BrandingResponse {
+      branding: branding.clone(),
+      inherited: false,
+    }));
+  }
+
+  let effective = effective_organization(&db, &settings, None).await?;
+  Ok(Json(BrandingResponse {
+    branding: effective
+      .and_then(|organization| settings.organizations.get(&organization))
+      .cloned()
+      .unwrap_or_default(),
+    inherited: true,
+  }))
+}
+
+async fn save_branding(
+  auth: OrgAuth<SettingsEdit>,
+  db: Connection,
+  updater: Updater,
+  audit: AuditContext,
+  Json(branding): Json<Branding>,
+) -> Result<()> {
+  if let Err(reason) = branding.validate() {
+    bail!(BAD_REQUEST, "{}", reason);
+  }
+
+  let mut settings: BrandingSettings = db.settings().get_settings().await?;
+  let before = settings
+    .organizations
+    .insert(auth.organization, branding.clone());
+  history::save(&db, auth.user_id, &settings).await?;
+
+  audit
+    .record(
+      &db,
+      &updater,
+      AuditEntry::new("settings.branding")
+        .actor(auth.user_id)
+        .target("organization", auth.organization)
+        .before(&before)
+        .after(&branding),
+    )
+    .await;
+  updater.broadcast(UpdateMessage::Settings).await;
+
+  Ok(())
+}
+
+/// Removes the branding of the organization including its images.
+async fn reset_branding(
+  auth: OrgAuth<SettingsEdit>,
+  db: Connection,
+  updater: Updater,
+  audit: AuditContext,
+) -> Result<()> {
+  let mut settings: BrandingSettings = db.settings().get_settings().await?;
+  let before = settings.organizations.remove(&auth.organization);
+  history::save(&db, auth.user_id, &settings).await?;
+  for kind in [AssetKind::Logo, AssetKind::Favicon] {
+    db.branding()
+      .delete_asset(auth.organization, kind.as_str())
+      .await?;
+  }
+
+  audit
+    .record(
+      &db,
+      &updater,
+      AuditEntry::new("settings.branding_reset")
+        .actor(auth.user_id)
+        .target("organization", auth.organization)
+        .before(&before),
+    )
+    .await;
+  updater.broadcast(UpdateMessage::Settings).await;
+
+  Ok(())
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct AssetKindPath {
+  kind: AssetKind,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct AssetUpload {
+  /// Image as base64, any format that can be decoded.
+  image: String,
+}
+
+async fn upload_asset(
+  auth: OrgAuth<SettingsEdit>,
+  db: Connection,
+  updater: Updater,
+  audit: AuditContext,
+  Path(AssetKindPath { kind }): Path<AssetKindPath>,
+  Json(data): Json<AssetUpload>,
+) -> Result<()> {
+  if data.image.len() > MAX_UPLOAD {
+    bail!(PAYLOAD_TOO_LARGE, "Image size exceeds 5MB limit");
+  }
+  let raw = BASE64_STANDARD
+    .decode(data.image)
+    .status(StatusCode::BAD_REQUEST)?;
+
+  let variants = spawn_blocking(move || process::variants(kind, &avatar::process::decode(&raw)?))
+    .await
+    .status(StatusCode::INTERNAL_SERVER_ERROR)??;
+  let models = variants
+    .into_iter()
+    .map(|(size, data)| branding_asset::Model {
+      organization_id: auth.organization,
+      kind: kind.as_str().to_string(),
+      size: size as i32,
+      etag: avatar::etag(&data),
+      data,
+    })
+    .collect();
+  db.branding()
+    .store_asset(auth.organization, kind.as_str(), models)
+    .await?;
+
+  audit
+    .record(
+      &db,
+      &updater,
+      AuditEntry::new("settings.branding_asset")
+        .actor(auth.user_id)
+        .target("organization", auth.organization)
+        .after(&serde_json::json!({ "kind": kind })),
+    )
+    .await;
+  updater.broadcast(UpdateMessage::Settings).await;
+
+  Ok(())
+}
+
+async fn delete_asset(
+  auth: OrgAuth<SettingsEdit>,
+  db: Connection,
+  updater: Updater,
+  audit: AuditContext,
+  Path(AssetKindPath { kind }): Path<AssetKindPath>,
+) -> Result<()> {
+  if !db
+    .branding()
+    .delete_asset(auth.organization, kind.as_str())
+    .await?
+  {
+    bail!(NOT_FOUND, "No {} uploaded", kind.as_str());
+  }
+
+  audit
+    .record(
+      &db,
+      &updater,
+      AuditEntry::new("settings.branding_asset_delete")
+        .actor(auth.user_id)
+        .target("organization", auth.organization)
+        .before(&serde_json::json!({ "kind": kind })),
+    )
+    .await;
+  updater.broadcast(UpdateMessage::Settings).await;
+
+  Ok(())
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct AssetPath {
+  kind: AssetKind,
+  organization: Uuid,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct AssetQuery {
+  /// Longer side in pixels, the next larger stored size is served.
+  size: Option<u32>,
+}
+
+/// Logo or favicon of an organization, needed before signing in.
+async fn asset(
+  db: Connection,
+  Path(path): Path<AssetPath>,
+  Query(query): Query<AssetQuery>,
+  headers: HeaderMap,
+) -> Result<Response> {
+  let size = process::variant_size(path.kind, query.size);
+  let Some(asset) = db
+    .branding()
+    .get_asset(path.organization, path.kind.as_str(), size as i32)
+    .await?
+  else {
+    return Ok(StatusCode::NOT_FOUND.into_response());
+  };
+
+  Ok(avatar::cached(
+    &headers,
+    process::content_type(path.kind),
+    asset.etag,
+    asset.data,
+  ))
+}
@@ -0,0 +1,81 @@
+use std::io::Cursor;
+
+use centaurus::error::Result;
+use image::{DynamicImage, ImageFormat, imageops::FilterType};
+
+use crate::branding::AssetKind;
+
+/// Sizes a logo is stored in, as the length of its longer side, largest
+/// first.
+pub const LOGO_SIZES: [u32; 2] = [512, 128];
+/// Sizes a favicon is stored in, largest first.
+pub const FAVICON_SIZES: [u32; 3] = [192, 64, 32];
+
+pub fn sizes(kind: AssetKind) -> &'static [u32] {
+  match kind {
+    AssetKind::Logo => &LOGO_SIZES,
+    AssetKind::Favicon => &FAVICON_SIZES,
+  }
+}
+
+/// Logos keep their aspect ratio and are stored as WebP, favicons are cropped
+/// to a square and stored as PNG which every browser accepts as an icon.
+pub fn variants(kind: AssetKind, image: &DynamicImage) -> Result<Vec<(u32, Vec<u8>)>> {
+  let mut current = match kind {
+    AssetKind::Logo => image.clone(),
+    AssetKind::Favicon => {
+      let side = image.width().min(image.height());
+      image.crop_imm(
+        (image.width() - side) / 2,
+        (image.height() - side) / 2,
+        side,
+        side,
+      )
+    }
+  };
+
+  let mut variants = Vec::with_capacity(sizes(kind).len());
+  for &size in sizes(kind) {
+    // small logos are not scaled up
+    if current.width().max(current.height()) > size {
+      current = DynamicImage::ImageRgba8(
+        current
+          .resize(size, size, FilterType::Lanczos3)
+          .into_rgba8(),
+      );
+    }
+    let mut buf = Cursor::new(Vec::new());
+    current.write_to(&mut buf, format(kind))?;
+    variants.push((size, buf.into_inner()));
+  }
+
+  Ok(variants)
+}
+
+fn format(kind: AssetKind) -> ImageFormat {
+  match kind {
+    AssetKind::Logo => ImageFormat::WebP,
+    AssetKind::Favicon => ImageFormat::Png,
+  }
+}
+
+pub fn content_type(kind: AssetKind) -> &'static str {
+  match kind {
+    AssetKind::Logo => "image/webp",
+    AssetKind::Favicon => "image/png",
+  }
+}
+
+/// Variant served for a requested size, the smallest one at least as large.
+pub fn variant_size(kind: AssetKind, requested: Option<u32>) -> u32 {
+  let sizes = sizes(kind);
+  let Some(requested) = requested else {
+    return sizes[0];
+  };
+  sizes
+    .iter()
+    .rev()
+    .copied()
+    .find(|size| *size >= requested)
+    .unwrap_or(sizes[0])
+}
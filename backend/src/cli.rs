@@ -0,0 +1,229 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use centaurus::{
+  db::init::{connect_db, init_db},
+  logging::init_logging,
+};
+use clap::{Parser, Subcommand};
+
+use crate::{
+  audit, backup,
+  config::{Config, ConfigSource},
+};
+
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+  /// TOML or YAML config file, overrides `CONFIG_FILE`
+  #[arg(long, global = true)]
+  config: Option<PathBuf>,
+  /// Profile section of the config file to apply, overrides `CONFIG_PROFILE`
+  #[arg(long, global = true)]
+  profile: Option<String>,
+  #[command(subcommand)]
+  command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+  /// Run the server (default)
+  Serve,
+  /// Inspect the audit log
+  #[command(subcommand)]
+  Audit(AuditCommand),
+  /// Inspect the configuration
+  #[command(subcommand)]
+  Config(ConfigCommand),
+  /// Write all data into an archive that restores onto SQLite or Postgres.
+  /// It contains password hashes and keys, store it accordingly
+  Backup {
+    /// Archive to write, e.g. `backup.tar.gz`
+    file: PathBuf,
+  },
+  /// Restore an archive written by `backup` into an empty database, the
+  /// backup has to be taken with the same schema
+  Restore {
+    /// Archive to read
+    file: PathBuf,
+  },
+}
+
+#[derive(Subcommand)]
+enum AuditCommand {
+  /// Walk the hash chain and report gaps, modified records and bad signatures
+  Verify {
+    /// Print the full report as JSON
+    #[arg(long)]
+    json: bool,
+  },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+  /// Validate the configuration without starting the server
+  Check {
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+  },
+}
+
+impl Cli {
+  pub async fn run(self) -> ExitCode {
+    let source = ConfigSource {
+      file: self.config,
+      profile: self.profile,
+    };
+
+    match self.command.unwrap_or(Command::Serve) {
+      Command::Serve => {
+        crate::serve(source).await;
+        ExitCode::SUCCESS
+      }
+      Command::Audit(AuditCommand::Verify { json }) => verify_audit(source, json).await,
+      Command::Config(ConfigCommand::Check { json }) => check_config(source, json),
+      Command::Backup { file } => write_backup(source, file).await,
+      Command::Restore { file } => restore_backup(source, file).await,
+    }
+  }
+}
+
+fn check_config(source: ConfigSource, json: bool) -> ExitCode {
+  let (config, report) = Config::load(source);
+  let valid = config.is_some() && !report.is_blocking(!cfg!(debug_assertions));
+
+  if json {
+    println!(
+      "{}",
+      serde_json::to_string_pretty(&report).expect("Failed to serialize report")
+    );
+  } else if report.is_empty() {
+    println!("Configuration is valid");
+  } else {
+    print!("{report}");
+  }
+
+  if valid {
+    ExitCode::SUCCESS
+  } else {
+    ExitCode::FAILURE
+  }
+}
+
+async fn verify_audit(source: ConfigSource, json: bool) -> ExitCode {
+  let config = Config::parse_from(source);
+  init_logging(config.base.log_level);
+  let db = init_db::<migration::Migrator>(&config.db, &config.db_url).await;
+
+  let report = match audit::verify(&db).await {
+    Ok(report) => report,
+    Err(err) => {
+      eprintln!("Failed to verify audit log: {err:?}");
+      return ExitCode::from(2);
+    }
+  };
+
+  if json {
+    println!(
+      "{}",
+      serde_json::to_string_pretty(&report).expect("Failed to serialize report")
+    );
+  } else {
+    for issue in &report.issues {
+      println!(
+        "{:?} at sequence {} ({}): {}",
+        issue.kind,
+        issue
+          .sequence
+          .map(|s| s.to_string())
+          .unwrap_or_else(|| "-".into()),
+        issue.uuid,
+        issue.detail
+      );
+    }
+    println!(
+      "Checked {} records ({} signed), {} issues, head {} {}",
+      report.checked,
+      report.signed,
+      report.issues.len(),
+      report.last_sequence.unwrap_or_default(),
+      report.last_hash.as_deref().unwrap_or("-")
+    );
+  }
+
+  if report.valid {
+    ExitCode::SUCCESS
+  } else {
+    ExitCode::FAILURE
+  }
+}
+
+async fn write_backup(source: ConfigSource, file: PathBuf) -> ExitCode {
+  let config = Config::parse_from(source);
+  init_logging(config.base.log_level);
+  let db = connect_db(&config.db, &config.db_url).await;
+
+  let manifest = match backup::backup(&db).await {
+    Ok((manifest, archive)) => match std::fs::write(&file, archive) {
+      Ok(()) => manifest,
+      Err(err) => {
+        eprintln!("Failed to write {}: {err}", file.display());
+        return ExitCode::FAILURE;
+      }
+    },
+    Err(err) => {
+      eprintln!("Failed to back up: {err:?}");
+      return ExitCode::FAILURE;
+    }
+  };
+
+  print_tables(&manifest);
+  println!(
+    "Backed up {} rows at schema {} to {}",
+    manifest.tables.iter().map(|t| t.rows).sum::<u64>(),
+    manifest.schema,
+    file.display()
+  );
+  ExitCode::SUCCESS
+}
+
+async fn restore_backup(source: ConfigSource, file: PathBuf) -> ExitCode {
+  let config = Config::parse_from(source);
+  init_logging(config.base.log_level);
+
+  let archive = match std::fs::read(&file) {
+    Ok(archive) => archive,
+    Err(err) => {
+      eprintln!("Failed to read {}: {err}", file.display());
+      return ExitCode::FAILURE;
+    }
+  };
+  let db = connect_db(&config.db, &config.db_url).await;
+
+  match backup::restore(&db, &archive).await {
+    Ok(manifest) => {
+      print_tables(&manifest);
+      println!(
+        "Restored and verified {} rows from schema {}",
+        manifest.tables.iter().map(|t| t.rows).sum::<u64>(),
+        manifest.schema
+      );
+      ExitCode::SUCCESS
+    }
+    Err(err) => {
+      eprintln!("Failed to restore: {err:?}");
+      ExitCode::FAILURE
+    }
+  }
+}
+
+fn print_tables(manifest: &backup::Manifest) {
+  for table in &manifest.tables {
+    println!(
+      "{:<24} {:>8} rows  {}",
+      table.name,
+      table.rows,
+      &table.checksum[..16]
+    );
+  }
+}
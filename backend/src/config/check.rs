@@ -0,0 +1,347 @@
+use std::fmt;
+
+use centaurus::{
+  backend::{
+    auth::settings::{AuthConfig, UserSettings},
+    config::{BaseConfig, MetricsConfig, SiteConfig},
+  },
+  db::config::DBConfig,
+  mail::MailSettings,
+};
+use figment::{Figment, error::Kind};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::config::{Config, DEFAULT_PEPPER};
+
+const MIN_PEPPER_LENGTH: usize = 16;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+  /// The configuration cannot work.
+  Error,
+  /// Works, but must not be used in production. Stops release builds.
+  Insecure,
+  Warning,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Problem {
+  pub severity: Severity,
+  /// Configuration key, as set in the environment or the config file.
+  pub key: String,
+  pub message: String,
+}
+
+/// Everything wrong with a configuration, collected instead of stopping at
+/// the first problem.
+#[derive(Serialize, Default, Debug)]
+pub struct ConfigReport {
+  pub problems: Vec<Problem>,
+}
+
+impl ConfigReport {
+  fn push(&mut self, severity: Severity, key: impl Into<String>, message: impl Into<String>) {
+    self.problems.push(Problem {
+      severity,
+      key: key.into(),
+      message: message.into(),
+    });
+  }
+
+  pub fn error(&mut self, key: impl Into<String>, message: impl Into<String>) {
+    self.push(Severity::Error, key, message);
+  }
+
+  pub fn insecure(&mut self, key: impl Into<String>, message: impl Into<String>) {
+    self.push(Severity::Insecure, key, message);
+  }
+
+  pub fn warning(&mut self, key: impl Into<String>, message: impl Into<String>) {
+    self.push(Severity::Warning, key, message);
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.problems.is_empty()
+  }
+
+  /// Whether the application must not start, insecure settings only block
+  /// release builds.
+  pub fn is_blocking(&self, release: bool) -> bool {
+    self.problems.iter().any(|problem| match problem.severity {
+      Severity::Error => true,
+      Severity::Insecure => release,
+      Severity::Warning => false,
+    })
+  }
+}
+
+impl fmt::Display for ConfigReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(f, "Configuration has {} problem(s):", self.problems.len())?;
+    for problem in &self.problems {
+      let severity = match problem.severity {
+        Severity::Error => "error",
+        Severity::Insecure => "insecure",
+        Severity::Warning => "warning",
+      };
+      if problem.key.is_empty() {
+        writeln!(f, "  {severity:<8} {}", problem.message)?;
+      } else {
+        writeln!(
+          f,
+          "  {severity:<8} {}: {}",
+          problem.key.to_uppercase(),
+          problem.message
+        )?;
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Deserializes the configuration, reporting every value that does not fit.
+/// The flattened sections are tried on their own first as serde loses the
+/// key of errors within flattened structs.
+pub fn extract(figment: Figment, report: &mut ConfigReport) -> Option<Config> {
+  let sections = [
+    section::<BaseConfig>(&figment, report),
+    section::<DBConfig>(&figment, report),
+    section::<MetricsConfig>(&figment, report),
+    section::<SiteConfig>(&figment, report),
+    section::<AuthConfig>(&figment, report),
+    section::<MailSettings>(&figment, report),
+    section::<UserSettings>(&figment, report),
+  ];
+  if sections.contains(&false) {
+    return None;
+  }
+
+  match figment.extract::<Config>() {
+    Ok(config) => Some(config),
+    Err(errors) => {
+      add_errors(errors, report);
+      None
+    }
+  }
+}
+
+fn section<T: DeserializeOwned>(figment: &Figment, report: &mut ConfigReport) -> bool {
+  match figment.extract::<T>() {
+    Ok(_) => true,
+    Err(errors) => {
+      add_errors(errors, report);
+      false
+    }
+  }
+}
+
+fn add_errors(errors: figment::Error, report: &mut ConfigReport) {
+  for err in errors {
+    let key = match (&err.kind, err.path.is_empty()) {
+      (Kind::MissingField(field), true) => field.to_string(),
+      _ => err.path.join("."),
+    };
+    let message = match &err.metadata {
+      Some(metadata) => format!("{} (from {})", err.kind, metadata.name),
+      None => err.kind.to_string(),
+    };
+    report.error(key, message);
+  }
+}
+
+/// Checks values which deserialize fine but cannot work together or are
+/// unsafe to run with.
+pub fn validate(config: &Config, report: &mut ConfigReport) {
+  if config.db_url.is_empty() {
+    report.error("db_url", "Database URL is not set");
+  } else if !["sqlite:", "postgres:", "postgresql:"]
+    .iter()
+    .any(|scheme| config.db_url.starts_with(scheme))
+  {
+    report.error("db_url", "Only SQLite and Postgres databases are supported");
+  }
+
+  let pepper = &config.auth.auth_pepper;
+  if pepper == DEFAULT_PEPPER {
+    report.insecure(
+      "auth_pepper",
+      "The default pepper is public, set a random secret",
+    );
+  } else if pepper.len() < MIN_PEPPER_LENGTH {
+    report.insecure(
+      "auth_pepper",
+      format!("The pepper should be at least {MIN_PEPPER_LENGTH} characters"),
+    );
+  }
+  if config.auth.auth_jwt_expiration <= 0 {
+    report.error(
+      "auth_jwt_expiration",
+      "Sessions must last longer than 0 seconds",
+    );
+  }
+
+  let site_url = &config.site.site_url;
+  if !matches!(site_url.scheme(), "http" | "https") {
+    report.error("site_url", "The site URL must use http or https");
+  } else if site_url.query().is_some() || site_url.fragment().is_some() {
+    report.error("site_url", "The site URL cannot have a query or fragment");
+  } else if site_url.scheme() == "http"
+    && !matches!(
+      site_url.host_str(),
+      Some("localhost" | "127.0.0.1" | "[::1]")
+    )
+  {
+    report.insecure(
+      "site_url",
+      "Sessions of a public site must not be sent over plain http",
+    );
+  }
+  if config
+    .base
+    .allowed_origins
+    .split(',')
+    .any(|o| o.trim() == "*")
+  {
+    report.insecure("allowed_origins", "Any website may call the API");
+  }
+
+  if config.user_retention_days < 0 {
+    report.error("user_retention_days", "The retention cannot be negative");
+  }
+  if config.account_deletion_grace_days < 0 {
+    report.error(
+      "account_deletion_grace_days",
+      "The grace period cannot be negative",
+    );
+  }
+  if config.db.database_min_connections > config.db.database_max_connections {
+    report.error(
+      "database_min_connections",
+      "More connections are required than allowed by DATABASE_MAX_CONNECTIONS",
+    );
+  }
+
+  if config.rate_limit_replenish_seconds == 0 || config.rate_limit_burst == 0 {
+    report.error(
+      "rate_limit_replenish_seconds",
+      "Rate limits must allow at least one request",
+    );
+  }
+
+  if config.metrics.metrics_enabled && config.metrics.metrics_port == Some(config.base.port) {
+    report.error("metrics_port", "Metrics cannot share the port of the API");
+  }
+
+  let mail = &config.mail;
+  if mail.smtp_enabled == Some(true) {
+    if mail.smtp_server.is_none() {
+      report.error("smtp_server", "Mail is enabled without a server");
+    }
+    if mail.smtp_from_address.is_none() {
+      report.error("smtp_from_address", "Mail is enabled without a sender");
+    }
+    if mail.smtp_use_tls == Some(false) && mail.smtp_password.is_some() {
+      report.insecure("smtp_use_tls", "The SMTP password is sent unencrypted");
+    }
+  }
+  if mail.smtp_username.is_some() != mail.smtp_password.is_some() {
+    report.warning(
+      "smtp_username",
+      "SMTP username and password are only used together",
+    );
+  }
+
+  let oidc = &config.oidc;
+  if oidc.oidc_enabled == Some(true) {
+    if oidc.oidc_issuer.is_none() {
+      report.error("oidc_issuer", "OIDC is enabled without an issuer");
+    }
+    if oidc.oidc_client_id.is_none() {
+      report.error("oidc_client_id", "OIDC is enabled without a client id");
+    }
+  }
+  if oidc.sso_instant_redirect == Some(true) && oidc.oidc_enabled == Some(false) {
+    report.error(
+      "sso_instant_redirect",
+      "Instant redirects need OIDC to be enabled",
+    );
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use figment::providers::{Format, Serialized, Toml};
+
+  use super::*;
+
+  fn config() -> Config {
+    Config {
+      db_url: "sqlite::memory:".into(),
+      ..Default::default()
+    }
+  }
+
+  fn keys(report: &ConfigReport, severity: Severity) -> Vec<&str> {
+    report
+      .problems
+      .iter()
+      .filter(|problem| problem.severity == severity)
+      .map(|problem| problem.key.as_str())
+      .collect()
+  }
+
+  #[test]
+  fn default_pepper_only_blocks_release_builds() {
+    let mut report = ConfigReport::default();
+    validate(&config(), &mut report);
+
+    assert_eq!(keys(&report, Severity::Insecure), ["auth_pepper"]);
+    assert!(keys(&report, Severity::Error).is_empty());
+    assert!(!report.is_blocking(false));
+    assert!(report.is_blocking(true));
+  }
+
+  #[test]
+  fn all_problems_are_collected() {
+    let mut config = config();
+    config.db_url = String::new();
+    config.auth.auth_pepper = "a-long-random-pepper-value".into();
+    config.site.site_url = "ftp://example.com".parse().unwrap();
+    config.metrics.metrics_enabled = true;
+    config.metrics.metrics_port = Some(config.base.port);
+    config.mail.smtp_enabled = Some(true);
+
+    let mut report = ConfigReport::default();
+    validate(&config, &mut report);
+
+    assert_eq!(
+      keys(&report, Severity::Error),
+      [
+        "db_url",
+        "site_url",
+        "metrics_port",
+        "smtp_server",
+        "smtp_from_address"
+      ]
+    );
+    assert!(
+      report
+        .to_string()
+        .contains("DB_URL: Database URL is not set")
+    );
+  }
+
+  #[test]
+  fn every_invalid_value_is_reported() {
+    let figment = Figment::new()
+      .merge(Serialized::defaults(Config::default()))
+      .merge(Toml::string("port = \"http\"\nsite_url = \"not a url\""));
+
+    let mut report = ConfigReport::default();
+    assert!(extract(figment, &mut report).is_none());
+    let keys: Vec<&str> = report.problems.iter().map(|p| p.key.as_str()).collect();
+    assert_eq!(keys, ["port", "site_url"]);
+    assert!(report.is_blocking(false));
+  }
+}
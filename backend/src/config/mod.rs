@@ -0,0 +1,319 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use aide::OperationIo;
+use axum::{Extension, extract::FromRequestParts};
+use centaurus::{
+  Config,
+  backend::{
+    auth::settings::{AuthConfig, UserSettings},
+    config::{BaseConfig, MetricsConfig, SiteConfig},
+  },
+  db::config::DBConfig,
+  mail::MailSettings,
+};
+use figment::{
+  Figment,
+  providers::{Env, Format, Serialized, Toml, Yaml},
+  value::{Dict, Value as FigmentValue},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tracing::instrument;
+
+pub use crate::config::check::ConfigReport;
+
+mod check;
+pub mod reload;
+
+const PIN_PREFIX: &str = "SETTINGS_PIN_";
+const DEFAULT_PEPPER: &str = "__{{project-name}}_PEPPER__";
+const FILE_VAR: &str = "CONFIG_FILE";
+const PROFILE_VAR: &str = "CONFIG_PROFILE";
+const SECRET_FILE_SUFFIX: &str = "_FILE";
+
+/// Where the configuration is read from besides the environment. Both can be
+/// given on the command line or through `CONFIG_FILE` and `CONFIG_PROFILE`.
+#[derive(Default, Clone, Debug)]
+pub struct ConfigSource {
+  /// TOML or YAML file, picked by its extension.
+  pub file: Option<PathBuf>,
+  /// Section of `profiles` in the file applied on top of it, `dev` in debug
+  /// and `prod` in release builds by default.
+  pub profile: Option<String>,
+}
+
+impl ConfigSource {
+  fn resolve(self) -> Self {
+    let profile = self
+      .profile
+      .or_else(|| std::env::var(PROFILE_VAR).ok())
+      .unwrap_or_else(|| {
+        if cfg!(debug_assertions) {
+          "dev".into()
+        } else {
+          "prod".into()
+        }
+      });
+
+    Self {
+      file: self
+        .file
+        .or_else(|| std::env::var_os(FILE_VAR).map(PathBuf::from)),
+      profile: Some(profile),
+    }
+  }
+}
+
+#[derive(Deserialize, Serialize, Clone, FromRequestParts, Config, OperationIo)]
+#[from_request(via(Extension))]
+pub struct Config {
+  #[base]
+  #[serde(flatten)]
+  pub base: BaseConfig,
+  #[serde(flatten)]
+  pub db: DBConfig,
+  #[metrics]
+  #[serde(flatten)]
+  pub metrics: MetricsConfig,
+  #[site]
+  #[serde(flatten)]
+  pub site: SiteConfig,
+  #[auth]
+  #[serde(flatten)]
+  pub auth: AuthConfig,
+  #[mail]
+  #[serde(flatten)]
+  pub mail: MailSettings,
+  #[oidc]
+  #[serde(flatten)]
+  pub oidc: UserSettings,
+
+  pub db_url: String,
+  pub admin_group: String,
+  /// Sign audit records with a key stored in the `key` table.
+  pub audit_signing: bool,
+  /// Days a deleted user is kept and can be restored before being purged.
+  pub user_retention_days: i64,
+  /// Days between a confirmed self-service deletion and the account being
+  /// removed, the user can cancel it until then.
+  pub account_deletion_grace_days: i64,
+  /// Seconds until a client gets another request to rate limited routes once
+  /// its burst is used up.
+  pub rate_limit_replenish_seconds: u32,
+  /// Requests a client may send at once before being rate limited.
+  pub rate_limit_burst: u32,
+  /// Fields of module settings fixed by the environment, set as
+  /// `SETTINGS_PIN_<KEY>__<FIELD>`, e.g. `SETTINGS_PIN_PROFILE_SYNC__NAME`.
+  /// They are shown read-only and cannot be changed through the API.
+  #[serde(default)]
+  pub pinned_settings: HashMap<String, Map<String, Value>>,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      base: BaseConfig::default(),
+      db: DBConfig::default(),
+      site: SiteConfig::default(),
+      mail: MailSettings::default(),
+      oidc: UserSettings::default(),
+      db_url: "".to_string(),
+      admin_group: "Admin".to_string(),
+      audit_signing: false,
+      user_retention_days: 30,
+      account_deletion_grace_days: 14,
+      rate_limit_replenish_seconds: 10,
+      rate_limit_burst: 20,
+      pinned_settings: HashMap::new(),
+      metrics: MetricsConfig {
+        metrics_name: "{{project-name}}".to_string(),
+        ..Default::default()
+      },
+      auth: AuthConfig {
+        auth_pepper: DEFAULT_PEPPER.to_string(),
+        auth_jwt_expiration: 60 * 60 * 24, // 1 days
+        ..Default::default()
+      },
+    }
+  }
+}
+
+impl Config {
+  pub fn parse() -> Self {
+    Self::parse_from(ConfigSource::default())
+  }
+
+  /// Loads the configuration and prints its problems. Invalid configurations
+  /// and, in release builds, insecure ones stop the process.
+  pub fn parse_from(source: ConfigSource) -> Self {
+    let (config, report) = Self::load(source);
+    if !report.is_empty() {
+      eprintln!("{report}");
+    }
+
+    match config {
+      Some(config) if !report.is_blocking(!cfg!(debug_assertions)) => config,
+      _ => {
+        eprintln!("Refusing to start with this configuration");
+        std::process::exit(1);
+      }
+    }
+  }
+
+  /// Layers the configuration, later layers win: defaults, the config file,
+  /// its profile section, environment variables, secrets read from files
+  /// named by `<VAR>_FILE` and finally the pinned module settings. Every
+  /// problem found on the way is collected in the report.
+  #[instrument]
+  pub fn load(source: ConfigSource) -> (Option<Self>, ConfigReport) {
+    let source = source.resolve();
+    let mut report = ConfigReport::default();
+    let mut config = Figment::new().merge(Serialized::defaults(Self::default()));
+
+    if let Some(path) = &source.file {
+      let file = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => Some(Figment::from(Toml::file_exact(path))),
+        Some("yaml" | "yml") => Some(Figment::from(Yaml::file_exact(path))),
+        _ => {
+          report.error(
+            FILE_VAR,
+            format!("{} must be a TOML or YAML file", path.display()),
+          );
+          None
+        }
+      };
+      if let Some(file) = file {
+        config = config.merge(file.clone());
+        if let Some(profile) = &source.profile {
+          config = config.merge(file.focus(&format!("profiles.{profile}")));
+        }
+      }
+    }
+
+    config = config.merge(Env::raw().global());
+    match secret_files(std::env::vars()) {
+      Ok(secrets) => config = config.merge(Serialized::globals(secrets)),
+      Err(err) => report.error(SECRET_FILE_SUFFIX, err),
+    }
+    let config = config.merge(
+      Env::prefixed(PIN_PREFIX)
+        .split("__")
+        .map(|key| format!("pinned_settings.{key}").into()),
+    );
+
+    let Some(mut config) = check::extract(config, &mut report) else {
+      return (None, report);
+    };
+    check::validate(&config, &mut report);
+
+    if config.db_url.starts_with("sqlite") {
+      config.db.validate_sqlite();
+    }
+
+    (Some(config), report)
+  }
+}
+
+/// Reads `<VAR>_FILE` variables, e.g. `DB_URL_FILE` or `AUTH_PEPPER_FILE`,
+/// into `<var>` so secrets can come from mounted files. Only configuration
+/// keys are read, other variables like `CARGO_PKG_LICENSE_FILE` are left
+/// alone. Setting both the variable and its file is rejected as it is
+/// unclear which one should win.
+fn secret_files(vars: impl IntoIterator<Item = (String, String)>) -> Result<Dict, String> {
+  let vars: HashMap<String, String> = vars.into_iter().collect();
+  let keys = match serde_json::to_value(Config::default()) {
+    Ok(Value::Object(keys)) => keys,
+    _ => Map::new(),
+  };
+  let mut secrets = Dict::new();
+
+  for (name, path) in &vars {
+    let Some(var) = name.strip_suffix(SECRET_FILE_SUFFIX) else {
+      continue;
+    };
+    if !keys.contains_key(&var.to_lowercase()) {
+      continue;
+    }
+    if vars.contains_key(var) {
+      return Err(format!("Only one of {var} and {name} may be set"));
+    }
+
+    let content =
+      std::fs::read_to_string(path).map_err(|err| format!("Failed to read {name}: {err}"))?;
+    let content = content.trim_end_matches(['\r', '\n']);
+    secrets.insert(var.to_lowercase(), FigmentValue::from(content.to_string()));
+  }
+
+  Ok(secrets)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::Path;
+
+  use super::*;
+
+  fn temp_file(name: &str, content: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("config-test-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, content).unwrap();
+    path
+  }
+
+  fn layered(file: &Path, profile: &str) -> Figment {
+    let file = Figment::from(Toml::file_exact(file));
+    Figment::new()
+      .merge(Serialized::defaults(Config::default()))
+      .merge(file.clone())
+      .merge(file.focus(&format!("profiles.{profile}")))
+  }
+
+  #[test]
+  fn profiles_override_the_file() {
+    let path = temp_file(
+      "config.toml",
+      r#"
+        db_url = "sqlite::memory:"
+        admin_group = "Operators"
+        user_retention_days = 10
+
+        [profiles.prod]
+        user_retention_days = 90
+      "#,
+    );
+
+    let dev: Config = layered(&path, "dev").extract().unwrap();
+    assert_eq!(dev.admin_group, "Operators");
+    assert_eq!(dev.user_retention_days, 10);
+    assert_eq!(dev.account_deletion_grace_days, 14);
+
+    let prod: Config = layered(&path, "prod").extract().unwrap();
+    assert_eq!(prod.user_retention_days, 90);
+  }
+
+  #[test]
+  fn secrets_are_read_from_files() {
+    let path = temp_file("pepper", "s3cret\n");
+    let vars = [
+      ("AUTH_PEPPER_FILE".to_string(), path.display().to_string()),
+      ("CONFIG_FILE".to_string(), "/etc/app.toml".to_string()),
+      ("CARGO_PKG_LICENSE".to_string(), "MIT".to_string()),
+      ("CARGO_PKG_LICENSE_FILE".to_string(), String::new()),
+    ];
+
+    let secrets = secret_files(vars.clone()).unwrap();
+    assert_eq!(secrets.len(), 1);
+    let config: Config = Figment::new()
+      .merge(Serialized::defaults(Config::default()))
+      .merge(Serialized::globals(secrets))
+      .extract()
+      .unwrap();
+    assert_eq!(config.auth.auth_pepper, "s3cret");
+
+    let both = [vars[0].clone(), ("AUTH_PEPPER".into(), "other".into())];
+    assert!(secret_files(both).is_err());
+    let missing = [("DB_URL_FILE".to_string(), "/nonexistent".to_string())];
+    assert!(secret_files(missing).is_err());
+  }
+}
@@ -0,0 +1,342 @@
+use std::{
+  collections::BTreeSet,
+  path::Path,
+  sync::{Arc, RwLock},
+  time::{Duration, SystemTime},
+};
+
+use axum::{
+  Extension,
+  extract::Request,
+  http::StatusCode,
+  middleware::Next,
+  response::{IntoResponse, Response},
+};
+use centaurus::{db::init::Connection, mail::Mailer};
+use serde_json::Value;
+use tracing::{info, level_filters::LevelFilter, warn};
+use tracing_error::ErrorLayer;
+use tracing_subscriber::{Layer, Registry, layer::SubscriberExt, reload, util::SubscriberInitExt};
+
+use crate::{
+  config::{Config, ConfigSource},
+  settings::{
+    history::{self, SettingsChange},
+    section,
+  },
+};
+
+/// How often the config file is checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Keys applied to the running application on reload. Everything else, like
+/// the database, the pepper or CORS, is wired up on startup and only changes
+/// with a restart. `metrics_enabled` is only live for the exporter under
+/// `/api/metrics`, one on its own `metrics_port` is bound on startup.
+const LIVE_KEYS: &[&str] = &[
+  "log_level",
+  "metrics_enabled",
+  "rate_limit_replenish_seconds",
+  "rate_limit_burst",
+  "smtp_enabled",
+  "smtp_server",
+  "smtp_port",
+  "smtp_username",
+  "smtp_password",
+  "smtp_from_address",
+  "smtp_from_name",
+  "smtp_use_tls",
+];
+
+pub type LogHandle = reload::Handle<LevelFilter, Registry>;
+
+/// Sets up logging like centaurus does, with a level that can be changed
+/// later on.
+pub fn init_logging(level: LevelFilter) -> LogHandle {
+  color_eyre::install().expect("Failed to install color_eyre");
+
+  let (filter, handle) = reload::Layer::new(level);
+  let layer = tracing_subscriber::fmt::layer()
+    .with_writer(std::io::stdout)
+    .with_ansi(true)
+    .with_filter(filter);
+
+  tracing_subscriber::registry()
+    .with(layer)
+    .with(ErrorLayer::default())
+    .init();
+
+  handle
+}
+
+/// The configuration in effect, handlers get it through the [`Config`]
+/// extractor so reloaded values are seen by the next request.
+#[derive(Clone)]
+pub struct LiveConfig(Arc<RwLock<Config>>);
+
+impl LiveConfig {
+  pub fn new(config: Config) -> Self {
+    Self(Arc::new(RwLock::new(config)))
+  }
+
+  pub fn get(&self) -> Config {
+    self.0.read().expect("Live config poisoned").clone()
+  }
+
+  /// The configuration to build the router from. The exporter under
+  /// `/api/metrics` is wired up even when disabled so metrics can be turned
+  /// on without a restart, [`metrics_gate`] hides it in the meantime.
+  pub fn router_config(&self) -> Config {
+    let mut config = self.get();
+    if config.metrics.metrics_port.is_none() {
+      config.metrics.metrics_enabled = true;
+    }
+    config
+  }
+
+  fn set(&self, config: Config) {
+    *self.0.write().expect("Live config poisoned") = config;
+  }
+}
+
+/// Replaces the configuration of the request with the live one.
+pub async fn middleware(
+  Extension(live): Extension<LiveConfig>,
+  mut req: Request,
+  next: Next,
+) -> Response {
+  let config = live.get();
+  req.extensions_mut().insert(config.mail.clone());
+  req.extensions_mut().insert(config);
+
+  next.run(req).await
+}
+
+/// Hides the metrics exporter under `/api/metrics` while metrics are disabled.
+/// It is always wired up, see [`LiveConfig::router_config`].
+pub async fn metrics_gate(
+  Extension(live): Extension<LiveConfig>,
+  req: Request,
+  next: Next,
+) -> Response {
+  if req.uri().path() == "/api/metrics" && !live.get().metrics.metrics_enabled {
+    return StatusCode::NOT_FOUND.into_response();
+  }
+
+  next.run(req).await
+}
+
+/// Where a reload reads the configuration from and the logging to adjust.
+pub struct Reloader {
+  pub source: ConfigSource,
+  pub log: LogHandle,
+}
+
+/// Reloads the configuration on SIGHUP and whenever the config file changes.
+pub fn start(reloader: Reloader, live: LiveConfig, db: Connection, mailer: Mailer) {
+  let source = reloader.source.resolve();
+  let file = source.file.clone();
+
+  tokio::spawn(async move {
+    let mut hangup = Hangup::new();
+    let mut interval = tokio::time::interval(WATCH_INTERVAL);
+    let mut modified = file.as_deref().and_then(modified_at);
+
+    loop {
+      tokio::select! {
+        _ = interval.tick() => {
+          let current = file.as_deref().and_then(modified_at);
+          if current == modified {
+            continue;
+          }
+          modified = current;
+          info!("Config file changed, reloading configuration");
+        }
+        _ = hangup.recv() => info!("Received SIGHUP, reloading configuration"),
+      }
+
+      reload(&source, &reloader.log, &live, &db, &mailer).await;
+    }
+  });
+}
+
+fn modified_at(path: &Path) -> Option<SystemTime> {
+  std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+struct Hangup {
+  #[cfg(unix)]
+  signal: tokio::signal::unix::Signal,
+}
+
+impl Hangup {
+  fn new() -> Self {
+    Self {
+      #[cfg(unix)]
+      signal: tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+        .expect("Failed to listen for SIGHUP"),
+    }
+  }
+
+  async fn recv(&mut self) {
+    #[cfg(unix)]
+    self.signal.recv().await;
+    #[cfg(not(unix))]
+    std::future::pending::<()>().await;
+  }
+}
+
+async fn reload(
+  source: &ConfigSource,
+  log: &LogHandle,
+  live: &LiveConfig,
+  db: &Connection,
+  mailer: &Mailer,
+) {
+  let (loaded, report) = Config::load(source.clone());
+  let loaded = match loaded {
+    Some(loaded) if !report.is_blocking(!cfg!(debug_assertions)) => loaded,
+    _ => {
+      warn!("Keeping the running configuration, the new one is invalid\n{report}");
+      return;
+    }
+  };
+  if !report.is_empty() {
+    warn!("{report}");
+  }
+
+  let current = live.get();
+  let plan = plan(&current, &loaded);
+  for key in &plan.restart {
+    warn!("{key} changed but only takes effect after a restart, keeping the running value");
+  }
+  if plan.applied.is_empty() {
+    info!("No configuration to apply");
+    return;
+  }
+  for change in &plan.applied {
+    info!(
+      key = change.path,
+      before = ?change.before,
+      after = ?change.after,
+      redacted = change.redacted,
+      "Configuration changed"
+    );
+  }
+
+  if let Err(err) = log.reload(plan.next.base.log_level) {
+    warn!("Failed to change the log level: {err}");
+  }
+  if plan.applied.iter().any(|c| c.path.starts_with("smtp_"))
+    && let Err(err) = section::init_mailer(db, &plan.next, mailer).await
+  {
+    warn!("Failed to apply the mail configuration: {err:?}");
+  }
+  live.set(plan.next);
+}
+
+struct Plan {
+  /// The running configuration with the live keys taken over.
+  next: Config,
+  applied: Vec<SettingsChange>,
+  /// Changed keys which need a restart.
+  restart: BTreeSet<String>,
+}
+
+fn plan(current: &Config, loaded: &Config) -> Plan {
+  let before = serde_json::to_value(current).unwrap_or(Value::Null);
+  let after = serde_json::to_value(loaded).unwrap_or(Value::Null);
+  let mut changes = Vec::new();
+  history::diff(String::new(), Some(&before), Some(&after), &mut changes);
+
+  let key = |change: &SettingsChange| {
+    change
+      .path
+      .split('.')
+      .next()
+      .unwrap_or_default()
+      .to_string()
+  };
+  let live = |change: &SettingsChange| match key(change).as_str() {
+    "metrics_enabled" => current.metrics.metrics_port.is_none(),
+    key => LIVE_KEYS.contains(&key),
+  };
+  let (applied, restart): (Vec<_>, Vec<_>) = changes.into_iter().partition(live);
+
+  let mut next = current.clone();
+  next.base.log_level = loaded.base.log_level;
+  next.rate_limit_replenish_seconds = loaded.rate_limit_replenish_seconds;
+  next.rate_limit_burst = loaded.rate_limit_burst;
+  next.mail = loaded.mail.clone();
+  if current.metrics.metrics_port.is_none() {
+    next.metrics.metrics_enabled = loaded.metrics.metrics_enabled;
+  }
+
+  Plan {
+    next,
+    applied,
+    restart: restart.iter().map(key).collect(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn config() -> Config {
+    Config {
+      db_url: "sqlite::memory:".into(),
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn only_live_keys_are_applied() {
+    let current = config();
+    let mut loaded = config();
+    loaded.base.log_level = LevelFilter::DEBUG;
+    loaded.rate_limit_burst = 5;
+    loaded.mail.smtp_password = Some("secret".into());
+    loaded.db_url = "postgres://db/app".into();
+    loaded.base.allowed_origins = "https://example.com".into();
+
+    let plan = plan(&current, &loaded);
+
+    assert_eq!(plan.next.base.log_level, LevelFilter::DEBUG);
+    assert_eq!(plan.next.rate_limit_burst, 5);
+    assert_eq!(plan.next.mail.smtp_password.as_deref(), Some("secret"));
+    assert_eq!(plan.next.db_url, current.db_url);
+    assert_eq!(plan.next.base.allowed_origins, current.base.allowed_origins);
+
+    let applied: Vec<&str> = plan.applied.iter().map(|c| c.path.as_str()).collect();
+    assert_eq!(applied, ["log_level", "rate_limit_burst", "smtp_password"]);
+    assert!(plan.applied[2].redacted && plan.applied[2].after.is_none());
+    assert_eq!(
+      plan.restart.into_iter().collect::<Vec<_>>(),
+      ["allowed_origins", "db_url"]
+    );
+  }
+
+  #[test]
+  fn metrics_are_only_live_without_their_own_port() {
+    let current = config();
+    let mut loaded = config();
+    loaded.metrics.metrics_enabled = true;
+
+    let applied = plan(&current, &loaded);
+    assert!(applied.next.metrics.metrics_enabled);
+    assert!(applied.restart.is_empty());
+
+    let mut current = config();
+    current.metrics.metrics_port = Some(9000);
+    loaded.metrics.metrics_port = Some(9000);
+
+    let plan = plan(&current, &loaded);
+    assert!(!plan.next.metrics.metrics_enabled);
+    assert!(plan.applied.is_empty());
+    assert_eq!(
+      plan.restart.into_iter().collect::<Vec<_>>(),
+      ["metrics_enabled"]
+    );
+  }
+}
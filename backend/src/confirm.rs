@@ -0,0 +1,46 @@
+//! Pages behind links in mails that change something. Mail scanners and link
+//! previews open every link they find, so the link only shows what is about
+//! to happen and the change is made once the form on the page is submitted.
+
+use axum::response::Html;
+use serde::Deserialize;
+
+use crate::utils::escape_html;
+
+/// Query of the mailed link and body of the submitted form.
+#[derive(Deserialize)]
+pub struct TokenForm {
+  pub token: String,
+}
+
+/// `action` is resolved relative to the link, the form posts the token there.
+pub fn page(title: &str, text: &str, button: &str, action: &str, token: &str) -> Html<String> {
+  let title = escape_html(title);
+  let text = escape_html(text);
+  let button = escape_html(button);
+  let action = escape_html(action);
+  let token = escape_html(token);
+
+  Html(format!(
+    r#"
+  <!DOCTYPE html>
+  <html lang="en">
+    <head>
+      <meta charset="UTF-8">
+      <meta name="viewport" content="width=device-width, initial-scale=1.0">
+      <title>{title}</title>
+    </head>
+    <body>
+      <div style="display: flex; flex-direction: column; align-items: center; justify-content: center;">
+        <h2>{title}</h2>
+        <p>{text}</p>
+        <form method="post" action="{action}">
+          <input type="hidden" name="token" value="{token}">
+          <button type="submit">{button}</button>
+        </form>
+      </div>
+    </body>
+  </html>
+  "#
+  ))
+}
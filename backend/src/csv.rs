@@ -0,0 +1,102 @@
+//! Just enough RFC 4180 to read and write the CSV files of imports and
+//! exports.
+
+/// Quotes the value if it contains a separator, quote or line break.
+pub fn field(value: &str) -> String {
+  if value.contains([',', '"', '\n', '\r']) {
+    format!("\"{}\"", value.replace('"', "\"\""))
+  } else {
+    value.to_string()
+  }
+}
+
+pub fn row<S: AsRef<str>>(fields: &[S]) -> String {
+  let fields: Vec<String> = fields.iter().map(|f| field(f.as_ref())).collect();
+  let mut row = fields.join(",");
+  row.push('\n');
+  row
+}
+
+/// Splits the text into records of fields. Empty lines are skipped.
+pub fn parse(text: &str) -> Result<Vec<Vec<String>>, String> {
+  let mut records = Vec::new();
+  let mut record = Vec::new();
+  let mut field = String::new();
+  let mut quoted = false;
+  let mut line = 1;
+  let mut chars = text.chars().peekable();
+
+  while let Some(c) = chars.next() {
+    match (quoted, c) {
+      (true, '"') if chars.peek() == Some(&'"') => {
+        chars.next();
+        field.push('"');
+      }
+      (true, '"') => quoted = false,
+      (true, c) => {
+        if c == '\n' {
+          line += 1;
+        }
+        field.push(c);
+      }
+      (false, '"') if field.is_empty() => quoted = true,
+      (false, '"') => return Err(format!("Unexpected quote on line {line}")),
+      (false, ',') => record.push(std::mem::take(&mut field)),
+      (false, '\r') if chars.peek() == Some(&'\n') => {}
+      (false, '\n') => {
+        line += 1;
+        record.push(std::mem::take(&mut field));
+        if record.iter().any(|f| !f.is_empty()) || record.len() > 1 {
+          records.push(std::mem::take(&mut record));
+        } else {
+          record.clear();
+        }
+      }
+      (false, c) => field.push(c),
+    }
+  }
+
+  if quoted {
+    return Err(format!("Unterminated quote on line {line}"));
+  }
+  if !field.is_empty() || !record.is_empty() {
+    record.push(field);
+    records.push(record);
+  }
+
+  Ok(records)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn written_rows_parse_back() {
+    let fields = ["plain", "with,comma", "with \"quote\"", "multi\nline", ""];
+    let text = format!("{}{}", row(&["a", "b", "c", "d", "e"]), row(&fields));
+
+    let records = parse(&text).unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[1], fields);
+  }
+
+  #[test]
+  fn handles_crlf_and_blank_lines() {
+    let records = parse("name,email\r\n\r\nA,a@example.com\r\nB,b@example.com").unwrap();
+    assert_eq!(
+      records,
+      vec![
+        vec!["name", "email"],
+        vec!["A", "a@example.com"],
+        vec!["B", "b@example.com"],
+      ]
+    );
+  }
+
+  #[test]
+  fn rejects_broken_quotes() {
+    assert!(parse("a,\"b\n").is_err());
+    assert!(parse("a,b\"c\"\n").is_err());
+  }
+}
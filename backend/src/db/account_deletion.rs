@@ -0,0 +1,104 @@
+use centaurus::error::Result;
+use chrono::{NaiveDateTime, Utc};
+use entity::account_deletion;
+use sea_orm::{
+  ActiveValue::{Set, Unchanged},
+  QuerySelect,
+  prelude::*,
+  sea_query::OnConflict,
+};
+
+pub struct AccountDeletionTable<'db> {
+  db: &'db DatabaseConnection,
+}
+
+impl<'db> AccountDeletionTable<'db> {
+  pub fn new(db: &'db DatabaseConnection) -> Self {
+    Self { db }
+  }
+
+  pub async fn get(&self, user_id: Uuid) -> Result<Option<account_deletion::Model>> {
+    Ok(
+      account_deletion::Entity::find_by_id(user_id)
+        .one(self.db)
+        .await?,
+    )
+  }
+
+  /// Starts a new request, replacing an earlier one. Without a token the
+  /// request counts as confirmed right away.
+  pub async fn request(
+    &self,
+    user_id: Uuid,
+    token: Option<String>,
+    delete_at: NaiveDateTime,
+  ) -> Result<()> {
+    let now = Utc::now().naive_utc();
+    let confirmed = token.is_none();
+    account_deletion::Entity::insert(account_deletion::ActiveModel {
+      user_id: Set(user_id),
+      requested_at: Set(now),
+      token: Set(token),
+      confirmed_at: Set(confirmed.then_some(now)),
+      delete_at: Set(confirmed.then_some(delete_at)),
+    })
+    .on_conflict(
+      OnConflict::column(account_deletion::Column::UserId)
+        .update_columns([
+          account_deletion::Column::RequestedAt,
+          account_deletion::Column::Token,
+          account_deletion::Column::ConfirmedAt,
+          account_deletion::Column::DeleteAt,
+        ])
+        .to_owned(),
+    )
+    .exec(self.db)
+    .await?;
+
+    Ok(())
+  }
+
+  pub async fn find_by_token(&self, token: &str) -> Result<Option<account_deletion::Model>> {
+    Ok(
+      account_deletion::Entity::find()
+        .filter(account_deletion::Column::Token.eq(token))
+        .one(self.db)
+        .await?,
+    )
+  }
+
+  /// Confirms the request, the token can only be used once.
+  pub async fn confirm(&self, user_id: Uuid, delete_at: NaiveDateTime) -> Result<()> {
+    account_deletion::ActiveModel {
+      user_id: Unchanged(user_id),
+      token: Set(None),
+      confirmed_at: Set(Some(Utc::now().naive_utc())),
+      delete_at: Set(Some(delete_at)),
+      ..Default::default()
+    }
+    .update(self.db)
+    .await?;
+
+    Ok(())
+  }
+
+  pub async fn cancel(&self, user_id: Uuid) -> Result<()> {
+    account_deletion::Entity::delete_by_id(user_id)
+      .exec(self.db)
+      .await?;
+    Ok(())
+  }
+
+  /// Users whose confirmed deletion is due.
+  pub async fn due(&self, now: NaiveDateTime) -> Result<Vec<Uuid>> {
+    Ok(
+      account_deletion::Entity::find()
+        .filter(account_deletion::Column::DeleteAt.lte(now))
+        .select_only()
+        .column(account_deletion::Column::UserId)
+        .into_tuple()
+        .all(self.db)
+        .await?,
+    )
+  }
+}
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use centaurus::error::Result;
+use entity::{attribute_definition, user_attribute};
+use sea_orm::{
+  IntoActiveModel, QueryOrder, Set, TransactionTrait, prelude::*, sea_query::OnConflict,
+};
+
+pub struct AttributeTable<'db> {
+  db: &'db DatabaseConnection,
+}
+
+impl<'db> AttributeTable<'db> {
+  pub fn new(db: &'db DatabaseConnection) -> Self {
+    Self { db }
+  }
+
+  pub async fn list_definitions(&self) -> Result<Vec<attribute_definition::Model>> {
+    Ok(
+      attribute_definition::Entity::find()
+        .order_by_asc(attribute_definition::Column::Position)
+        .order_by_asc(attribute_definition::Column::Key)
+        .all(self.db)
+        .await?,
+    )
+  }
+
+  pub async fn get_definition(&self, id: Uuid) -> Result<Option<attribute_definition::Model>> {
+    Ok(
+      attribute_definition::Entity::find_by_id(id)
+        .one(self.db)
+        .await?,
+    )
+  }
+
+  pub async fn find_definition_by_key(
+    &self,
+    key: &str,
+  ) -> Result<Option<attribute_definition::Model>> {
+    Ok(
+      attribute_definition::Entity::find()
+        .filter(attribute_definition::Column::Key.eq(key))
+        .one(self.db)
+        .await?,
+    )
+  }
+
+  pub async fn create_definition(&self, definition: attribute_definition::Model) -> Result<()> {
+    definition.into_active_model().insert(self.db).await?;
+    Ok(())
+  }
+
+  pub async fn update_definition(&self, definition: attribute_definition::Model) -> Result<()> {
+    definition
+      .into_active_model()
+      .reset_all()
+      .update(self.db)
+      .await?;
+    Ok(())
+  }
+
+  /// Removes the definition and every value stored for it.
+  pub async fn delete_definition(&self, id: Uuid) -> Result<()> {
+    attribute_definition::Entity::delete_by_id(id)
+      .exec(self.db)
+      .await?;
+    Ok(())
+  }
+
+  /// Stored values of the user by attribute id.
+  pub async fn get_values(&self, user_id: Uuid) -> Result<HashMap<Uuid, String>> {
+    Ok(
+      user_attribute::Entity::find()
+        .filter(user_attribute::Column::UserId.eq(user_id))
+        .all(self.db)
+        .await?
+        .into_iter()
+        .map(|value| (value.attribute_id, value.value))
+        .collect(),
+    )
+  }
+
+  /// Sets or, for `None`, removes the given values in one go.
+  pub async fn set_values(&self, user_id: Uuid, values: Vec<(Uuid, Option<String>)>) -> Result<()> {
+    let txn = self.db.begin().await?;
+    for (attribute_id, value) in values {
+      match value {
+        Some(value) => {
+          user_attribute::Entity::insert(user_attribute::ActiveModel {
+            user_id: Set(user_id),
+            attribute_id: Set(attribute_id),
+            value: Set(value),
+          })
+          .on_conflict(
+            OnConflict::columns([
+              user_attribute::Column::UserId,
+              user_attribute::Column::AttributeId,
+            ])
+            .update_column(user_attribute::Column::Value)
+            .to_owned(),
+          )
+          .exec(&txn)
+          .await?;
+        }
+        None => {
+          user_attribute::Entity::delete_by_id((user_id, attribute_id))
+            .exec(&txn)
+            .await?;
+        }
+      }
+    }
+    txn.commit().await?;
+
+    Ok(())
+  }
+}
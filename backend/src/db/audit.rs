@@ -0,0 +1,269 @@
+use centaurus::error::Result;
+use chrono::{DateTime, SubsecRound, Utc};
+use entity::audit_event;
+use sea_orm::{
+  ActiveValue::Unchanged, ExprTrait, QueryOrder, QuerySelect, Select, Set, prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::audit::chain::{self, AuditKey, GENESIS_HASH};
+
+pub struct AuditTable<'db> {
+  db: &'db DatabaseConnection,
+}
+
+#[derive(Serialize, Deserialize, schemars::JsonSchema)]
+pub struct AuditEventInfo {
+  pub uuid: Uuid,
+  pub created_at: DateTime<Utc>,
+  pub actor: Option<Uuid>,
+  pub action: String,
+  pub target_type: Option<String>,
+  pub target_id: Option<String>,
+  pub before: Option<Value>,
+  pub after: Option<Value>,
+  pub ip: Option<String>,
+  pub user_agent: Option<String>,
+  pub status: u16,
+  pub sequence: Option<i64>,
+  pub prev_hash: Option<String>,
+  pub hash: Option<String>,
+  pub signature: Option<String>,
+  pub key_id: Option<Uuid>,
+  /// Administrator who acted while impersonating `actor`.
+  pub impersonator: Option<Uuid>,
+}
+
+impl From<audit_event::Model> for AuditEventInfo {
+  fn from(event: audit_event::Model) -> Self {
+    Self {
+      uuid: event.id,
+      created_at: event.created_at.and_utc(),
+      actor: event.actor_id,
+      action: event.action,
+      target_type: event.target_type,
+      target_id: event.target_id,
+      before: event.before,
+      after: event.after,
+      ip: event.ip,
+      user_agent: event.user_agent,
+      status: event.status as u16,
+      sequence: event.sequence,
+      prev_hash: event.prev_hash,
+      hash: event.hash,
+      signature: event.signature,
+      key_id: event.key_id,
+      impersonator: event.impersonator_id,
+    }
+  }
+}
+
+pub struct NewAuditEvent {
+  pub actor: Option<Uuid>,
+  pub action: String,
+  pub target_type: Option<String>,
+  pub target_id: Option<String>,
+  pub before: Option<Value>,
+  pub after: Option<Value>,
+  pub ip: Option<String>,
+  pub user_agent: Option<String>,
+  pub status: u16,
+  pub impersonator: Option<Uuid>,
+}
+
+#[derive(Default)]
+pub struct AuditFilter {
+  pub actor: Option<Uuid>,
+  /// Matches the action itself or every action below it, so `group` also
+  /// matches `group.edit`.
+  pub action: Option<String>,
+  pub target_type: Option<String>,
+  pub target_id: Option<String>,
+  pub from: Option<DateTime<Utc>>,
+  pub to: Option<DateTime<Utc>>,
+}
+
+impl<'db> AuditTable<'db> {
+  pub fn new(db: &'db DatabaseConnection) -> Self {
+    Self { db }
+  }
+
+  /// Stores the event and appends it to the hash chain. Callers serialize
+  /// writes so records are chained in the order they were created.
+  pub async fn record(&self, event: NewAuditEvent, key: Option<&AuditKey>) -> Result<Uuid> {
+    let id = Uuid::now_v7();
+    audit_event::ActiveModel {
+      id: Set(id),
+      // the hash covers the timestamp, keep what every backend can store
+      created_at: Set(Utc::now().naive_utc().trunc_subsecs(6)),
+      actor_id: Set(event.actor),
+      action: Set(event.action),
+      target_type: Set(event.target_type),
+      target_id: Set(event.target_id),
+      before: Set(event.before),
+      after: Set(event.after),
+      ip: Set(event.ip),
+      user_agent: Set(event.user_agent),
+      status: Set(event.status as i32),
+      sequence: Set(None),
+      prev_hash: Set(None),
+      hash: Set(None),
+      signature: Set(None),
+      key_id: Set(None),
+      impersonator_id: Set(event.impersonator),
+    }
+    .insert(self.db)
+    .await?;
+
+    self.seal_pending(key).await?;
+
+    Ok(id)
+  }
+
+  /// Appends every record that is not yet part of the chain, oldest first.
+  pub async fn seal_pending(&self, key: Option<&AuditKey>) -> Result<u64> {
+    let head = audit_event::Entity::find()
+      .filter(audit_event::Column::Sequence.is_not_null())
+      .order_by_desc(audit_event::Column::Sequence)
+      .one(self.db)
+      .await?;
+    let (mut sequence, mut prev_hash) = match head {
+      Some(head) => (
+        head.sequence.unwrap_or_default(),
+        head.hash.unwrap_or_default(),
+      ),
+      None => (0, GENESIS_HASH.to_string()),
+    };
+
+    let pending = self.unsealed().await?;
+    let count = pending.len() as u64;
+    for mut event in pending {
+      sequence += 1;
+      chain::seal(&mut event, sequence, prev_hash, key);
+      prev_hash = event.hash.clone().unwrap_or_default();
+
+      audit_event::ActiveModel {
+        id: Unchanged(event.id),
+        sequence: Set(event.sequence),
+        prev_hash: Set(event.prev_hash),
+        hash: Set(event.hash),
+        signature: Set(event.signature),
+        key_id: Set(event.key_id),
+        ..Default::default()
+      }
+      .update(self.db)
+      .await?;
+    }
+
+    Ok(count)
+  }
+
+  pub async fn unsealed(&self) -> Result<Vec<audit_event::Model>> {
+    Ok(
+      audit_event::Entity::find()
+        .filter(audit_event::Column::Sequence.is_null())
+        .order_by_asc(audit_event::Column::CreatedAt)
+        .order_by_asc(audit_event::Column::Id)
+        .all(self.db)
+        .await?,
+    )
+  }
+
+  /// Chained records with a sequence number above `after`, in chain order.
+  pub async fn chain_page(&self, after: i64, limit: u64) -> Result<Vec<audit_event::Model>> {
+    Ok(
+      audit_event::Entity::find()
+        .filter(audit_event::Column::Sequence.gt(after))
+        .order_by_asc(audit_event::Column::Sequence)
+        .limit(limit)
+        .all(self.db)
+        .await?,
+    )
+  }
+
+  fn filtered(filter: AuditFilter) -> Select<audit_event::Entity> {
+    let mut query = audit_event::Entity::find();
+    if let Some(actor) = filter.actor {
+      query = query.filter(audit_event::Column::ActorId.eq(actor));
+    }
+    if let Some(action) = filter.action {
+      query = query.filter(
+        audit_event::Column::Action
+          .eq(action.clone())
+          .or(audit_event::Column::Action.starts_with(format!("{action}."))),
+      );
+    }
+    if let Some(target_type) = filter.target_type {
+      query = query.filter(audit_event::Column::TargetType.eq(target_type));
+    }
+    if let Some(target_id) = filter.target_id {
+      query = query.filter(audit_event::Column::TargetId.eq(target_id));
+    }
+    if let Some(from) = filter.from {
+      query = query.filter(audit_event::Column::CreatedAt.gte(from.naive_utc()));
+    }
+    if let Some(to) = filter.to {
+      query = query.filter(audit_event::Column::CreatedAt.lte(to.naive_utc()));
+    }
+    query
+  }
+
+  pub async fn list_events(
+    &self,
+    filter: AuditFilter,
+    offset: u64,
+    limit: u64,
+  ) -> Result<(Vec<AuditEventInfo>, u64)> {
+    let query = Self::filtered(filter);
+
+    let total = query.clone().count(self.db).await?;
+    let events = query
+      .order_by_desc(audit_event::Column::CreatedAt)
+      .order_by_desc(audit_event::Column::Id)
+      .offset(offset)
+      .limit(limit)
+      .all(self.db)
+      .await?
+      .into_iter()
+      .map(AuditEventInfo::from)
+      .collect();
+
+    Ok((events, total))
+  }
+
+  /// Every matching record in chain order.
+  pub async fn export_events(&self, filter: AuditFilter) -> Result<Vec<AuditEventInfo>> {
+    let events = Self::filtered(filter)
+      .order_by_asc(audit_event::Column::Sequence)
+      .order_by_asc(audit_event::Column::CreatedAt)
+      .all(self.db)
+      .await?
+      .into_iter()
+      .map(AuditEventInfo::from)
+      .collect();
+
+    Ok(events)
+  }
+
+  /// Records made by the user or about them, oldest first.
+  pub async fn events_about_user(&self, user_id: Uuid) -> Result<Vec<AuditEventInfo>> {
+    let events = audit_event::Entity::find()
+      .filter(
+        audit_event::Column::ActorId.eq(user_id).or(
+          audit_event::Column::TargetType
+            .eq("user")
+            .and(audit_event::Column::TargetId.eq(user_id.to_string())),
+        ),
+      )
+      .order_by_asc(audit_event::Column::CreatedAt)
+      .order_by_asc(audit_event::Column::Id)
+      .all(self.db)
+      .await?
+      .into_iter()
+      .map(AuditEventInfo::from)
+      .collect();
+
+    Ok(events)
+  }
+}
@@ -0,0 +1,88 @@
+use centaurus::error::Result;
+use entity::{avatar_variant, user, user_avatar};
+use sea_orm::{
+  DatabaseTransaction, IntoActiveModel, Set, TransactionTrait, prelude::*, sea_query::OnConflict,
+};
+
+pub struct AvatarTable<'db> {
+  db: &'db DatabaseConnection,
+}
+
+impl<'db> AvatarTable<'db> {
+  pub fn new(db: &'db DatabaseConnection) -> Self {
+    Self { db }
+  }
+
+  pub async fn get_variant(
+    &self,
+    user_id: Uuid,
+    size: i32,
+  ) -> Result<Option<avatar_variant::Model>> {
+    Ok(
+      avatar_variant::Entity::find_by_id((user_id, size))
+        .one(self.db)
+        .await?,
+    )
+  }
+
+  /// Name of the user to generate an avatar from, `None` for unknown users.
+  pub async fn user_name(&self, user_id: Uuid) -> Result<Option<String>> {
+    Ok(
+      user::Entity::find_by_id(user_id)
+        .one(self.db)
+        .await?
+        .map(|user| user.name),
+    )
+  }
+
+  /// Replaces the avatar of the user together with all of its variants.
+  pub async fn store(
+    &self,
+    user_id: Uuid,
+    data: Vec<u8>,
+    variants: Vec<avatar_variant::Model>,
+  ) -> Result<()> {
+    let txn = self.db.begin().await?;
+    user_avatar::Entity::insert(user_avatar::ActiveModel {
+      user_id: Set(user_id),
+      data: Set(data),
+    })
+    .on_conflict(
+      OnConflict::column(user_avatar::Column::UserId)
+        .update_column(user_avatar::Column::Data)
+        .to_owned(),
+    )
+    .exec(&txn)
+    .await?;
+
+    self.store_variants_in(&txn, user_id, variants).await?;
+    txn.commit().await?;
+
+    Ok(())
+  }
+
+  async fn store_variants_in(
+    &self,
+    txn: &DatabaseTransaction,
+    user_id: Uuid,
+    variants: Vec<avatar_variant::Model>,
+  ) -> Result<()> {
+    avatar_variant::Entity::delete_many()
+      .filter(avatar_variant::Column::UserId.eq(user_id))
+      .exec(txn)
+      .await?;
+    if !variants.is_empty() {
+      // concurrent requests may generate the same missing variants
+      avatar_variant::Entity::insert_many(variants.into_iter().map(|v| v.into_active_model()))
+        .on_conflict(
+          OnConflict::columns([avatar_variant::Column::UserId, avatar_variant::Column::Size])
+            .update_columns([avatar_variant::Column::Data, avatar_variant::Column::Etag])
+            .to_owned(),
+        )
+        .exec(txn)
+        .await?;
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,73 @@
+use centaurus::error::Result;
+use entity::branding_asset;
+use sea_orm::{IntoActiveModel, QueryOrder, TransactionTrait, prelude::*};
+
+/// Processed logos and favicons of the organizations.
+pub struct BrandingTable<'db> {
+  db: &'db DatabaseConnection,
+}
+
+impl<'db> BrandingTable<'db> {
+  pub fn new(db: &'db DatabaseConnection) -> Self {
+    Self { db }
+  }
+
+  pub async fn get_asset(
+    &self,
+    organization_id: Uuid,
+    kind: &str,
+    size: i32,
+  ) -> Result<Option<branding_asset::Model>> {
+    Ok(
+      branding_asset::Entity::find_by_id((organization_id, kind.to_string(), size))
+        .one(self.db)
+        .await?,
+    )
+  }
+
+  /// Etag of the largest stored variant, used to version asset urls.
+  pub async fn asset_version(&self, organization_id: Uuid, kind: &str) -> Result<Option<String>> {
+    Ok(
+      branding_asset::Entity::find()
+        .filter(branding_asset::Column::OrganizationId.eq(organization_id))
+        .filter(branding_asset::Column::Kind.eq(kind))
+        .order_by_desc(branding_asset::Column::Size)
+        .one(self.db)
+        .await?
+        .map(|asset| asset.etag),
+    )
+  }
+
+  /// Replaces all variants of the asset.
+  pub async fn store_asset(
+    &self,
+    organization_id: Uuid,
+    kind: &str,
+    variants: Vec<branding_asset::Model>,
+  ) -> Result<()> {
+    let txn = self.db.begin().await?;
+    branding_asset::Entity::delete_many()
+      .filter(branding_asset::Column::OrganizationId.eq(organization_id))
+      .filter(branding_asset::Column::Kind.eq(kind))
+      .exec(&txn)
+      .await?;
+    if !variants.is_empty() {
+      branding_asset::Entity::insert_many(variants.into_iter().map(|v| v.into_active_model()))
+        .exec(&txn)
+        .await?;
+    }
+    txn.commit().await?;
+
+    Ok(())
+  }
+
+  /// Returns whether there was an asset to remove.
+  pub async fn delete_asset(&self, organization_id: Uuid, kind: &str) -> Result<bool> {
+    let res = branding_asset::Entity::delete_many()
+      .filter(branding_asset::Column::OrganizationId.eq(organization_id))
+      .filter(branding_asset::Column::Kind.eq(kind))
+      .exec(self.db)
+      .await?;
+    Ok(res.rows_affected > 0)
+  }
+}
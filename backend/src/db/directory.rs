@@ -0,0 +1,320 @@
+use centaurus::{
+  db::tables::{
+    group::{GroupInfo, SimpleUserInfo},
+    user::SimpleGroupInfo,
+  },
+  error::Result,
+};
+use entity::{
+  attribute_definition, group, group_permission, group_user, organization_group,
+  organization_member, user, user_attribute, user_status,
+};
+use schemars::JsonSchema;
+use sea_orm::{
+  ExprTrait, LoaderTrait, PaginatorTrait, Select,
+  prelude::*,
+  sea_query::{Func, LikeExpr, Query},
+};
+use serde::Deserialize;
+
+use crate::{
+  attribute::value::Visibility,
+  pagination::{Page, PageRequest, contains_pattern},
+};
+
+/// Searchable, paginated listings of users and groups.
+pub struct DirectoryTable<'db> {
+  db: &'db DatabaseConnection,
+}
+
+#[derive(Deserialize, JsonSchema, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum UserSort {
+  #[default]
+  Name,
+  Email,
+  Created,
+}
+
+impl UserSort {
+  pub fn key(self) -> &'static str {
+    match self {
+      Self::Name => "name",
+      Self::Email => "email",
+      Self::Created => "created",
+    }
+  }
+
+  fn column(self) -> Option<user::Column> {
+    match self {
+      Self::Name => Some(user::Column::Name),
+      Self::Email => Some(user::Column::Email),
+      // ids are time ordered
+      Self::Created => None,
+    }
+  }
+
+  fn value(self, user: &user::Model) -> Option<String> {
+    match self {
+      Self::Name => Some(user.name.clone()),
+      Self::Email => Some(user.email.clone()),
+      Self::Created => None,
+    }
+  }
+}
+
+#[derive(Deserialize, JsonSchema, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupSort {
+  #[default]
+  Name,
+  Created,
+}
+
+impl GroupSort {
+  pub fn key(self) -> &'static str {
+    match self {
+      Self::Name => "name",
+      Self::Created => "created",
+    }
+  }
+}
+
+pub struct UserFilter {
+  /// Only members of this organization.
+  pub organization: Option<Uuid>,
+  /// Matches name, email or attribute values, ignoring case.
+  pub search: Option<String>,
+  /// Search admin-only and hidden attributes too, for callers who may see
+  /// them. Otherwise only self-editable ones are matched.
+  pub all_attributes: bool,
+  /// Only members of this group.
+  pub group: Option<Uuid>,
+  /// Only OIDC users if true, only local users if false.
+  pub oidc: Option<bool>,
+  pub include_disabled: bool,
+  pub include_deleted: bool,
+}
+
+impl Default for UserFilter {
+  fn default() -> Self {
+    Self {
+      organization: None,
+      search: None,
+      all_attributes: false,
+      group: None,
+      oidc: None,
+      include_disabled: true,
+      include_deleted: false,
+    }
+  }
+}
+
+impl<'db> DirectoryTable<'db> {
+  pub fn new(db: &'db DatabaseConnection) -> Self {
+    Self { db }
+  }
+
+  fn filtered_users(filter: UserFilter) -> Select<user::Entity> {
+    let mut query = user::Entity::find();
+    if let Some(organization) = filter.organization {
+      query = query.filter(
+        user::Column::Id.in_subquery(
+          Query::select()
+            .column(organization_member::Column::UserId)
+            .from(organization_member::Entity)
+            .and_where(organization_member::Column::OrganizationId.eq(organization))
+            .to_owned(),
+        ),
+      );
+    }
+    if let Some(search) = filter.search.filter(|s| !s.trim().is_empty()) {
+      let pattern = contains_pattern(search.trim());
+      let matches = |column: user::Column| {
+        Expr::expr(Func::lower(Expr::col((user::Entity, column))))
+          .like(LikeExpr::new(pattern.clone()).escape('\\'))
+      };
+      let mut attributes = Query::select()
+        .column((user_attribute::Entity, user_attribute::Column::UserId))
+        .from(user_attribute::Entity)
+        .inner_join(
+          attribute_definition::Entity,
+          Expr::col((
+            attribute_definition::Entity,
+            attribute_definition::Column::Id,
+          ))
+          .equals((user_attribute::Entity, user_attribute::Column::AttributeId)),
+        )
+        .and_where(
+          Expr::expr(Func::lower(Expr::col((
+            user_attribute::Entity,
+            user_attribute::Column::Value,
+          ))))
+          .like(LikeExpr::new(pattern.clone()).escape('\\')),
+        )
+        .to_owned();
+      if !filter.all_attributes {
+        attributes.and_where(
+          Expr::col((
+            attribute_definition::Entity,
+            attribute_definition::Column::Visibility,
+          ))
+          .eq(Visibility::SelfEditable.as_str()),
+        );
+      }
+      let attribute_matches = user::Column::Id.in_subquery(attributes);
+      query = query.filter(
+        matches(user::Column::Name)
+          .or(matches(user::Column::Email))
+          .or(attribute_matches),
+      );
+    }
+    if let Some(group) = filter.group {
+      query = query.filter(
+        user::Column::Id.in_subquery(
+          Query::select()
+            .column(group_user::Column::UserId)
+            .from(group_user::Entity)
+            .and_where(group_user::Column::GroupId.eq(group))
+            .to_owned(),
+        ),
+      );
+    }
+    if let Some(oidc) = filter.oidc {
+      query = query.filter(user::Column::OidcUser.eq(oidc));
+    }
+
+    let inactive = |column: user_status::Column| {
+      Query::select()
+        .column(user_status::Column::UserId)
+        .from(user_status::Entity)
+        .and_where(Expr::col(column).is_not_null())
+        .to_owned()
+    };
+    if !filter.include_disabled {
+      query =
+        query.filter(user::Column::Id.not_in_subquery(inactive(user_status::Column::DisabledAt)));
+    }
+    if !filter.include_deleted {
+      query =
+        query.filter(user::Column::Id.not_in_subquery(inactive(user_status::Column::DeletedAt)));
+    }
+
+    query
+  }
+
+  pub async fn list_users(
+    &self,
+    filter: UserFilter,
+    sort: UserSort,
+    page: &PageRequest,
+  ) -> Result<Page<user::Model>> {
+    let query = Self::filtered_users(filter);
+    let total = query.clone().count(self.db).await?;
+    let users = page
+      .apply(query, sort.column(), user::Column::Id)
+      .all(self.db)
+      .await?;
+
+    Ok(page.page(users, total, |user| (sort.value(user), user.id)))
+  }
+
+  /// Groups of each user within the organization, in the same order.
+  pub async fn user_groups(
+    &self,
+    organization: Uuid,
+    users: &[user::Model],
+  ) -> Result<Vec<Vec<SimpleGroupInfo>>> {
+    let owned: Vec<Uuid> = organization_group::Entity::find()
+      .filter(organization_group::Column::OrganizationId.eq(organization))
+      .all(self.db)
+      .await?
+      .into_iter()
+      .map(|g| g.group_id)
+      .collect();
+    let groups = users
+      .load_many_to_many(group::Entity, group_user::Entity, self.db)
+      .await?;
+
+    Ok(
+      groups
+        .into_iter()
+        .map(|groups| {
+          groups
+            .into_iter()
+            .filter(|group| owned.contains(&group.id))
+            .map(|group| SimpleGroupInfo {
+              uuid: group.id,
+              name: group.name,
+            })
+            .collect()
+        })
+        .collect(),
+    )
+  }
+
+  /// Groups of the organization.
+  pub async fn list_groups(
+    &self,
+    organization: Uuid,
+    search: Option<String>,
+    sort: GroupSort,
+    page: &PageRequest,
+  ) -> Result<Page<GroupInfo>> {
+    let mut query = group::Entity::find().filter(
+      group::Column::Id.in_subquery(
+        Query::select()
+          .column(organization_group::Column::GroupId)
+          .from(organization_group::Entity)
+          .and_where(organization_group::Column::OrganizationId.eq(organization))
+          .to_owned(),
+      ),
+    );
+    if let Some(search) = search.filter(|s| !s.trim().is_empty()) {
+      query = query.filter(
+        Expr::expr(Func::lower(Expr::col((group::Entity, group::Column::Name))))
+          .like(LikeExpr::new(contains_pattern(search.trim())).escape('\\')),
+      );
+    }
+    let total = query.clone().count(self.db).await?;
+
+    let column = match sort {
+      GroupSort::Name => Some(group::Column::Name),
+      GroupSort::Created => None,
+    };
+    let groups = page
+      .apply(query, column, group::Column::Id)
+      .all(self.db)
+      .await?;
+    let page = page.page(groups, total, |group| {
+      let value = matches!(sort, GroupSort::Name).then(|| group.name.clone());
+      (value, group.id)
+    });
+
+    let users = page
+      .items
+      .load_many_to_many(user::Entity, group_user::Entity, self.db)
+      .await?;
+    let permissions = page
+      .items
+      .load_many(group_permission::Entity, self.db)
+      .await?;
+    let mut details = users.into_iter().zip(permissions);
+
+    Ok(page.map(|group| {
+      let (users, permissions) = details.next().unwrap_or_default();
+      GroupInfo {
+        id: group.id,
+        name: group.name,
+        permissions: permissions.into_iter().map(|p| p.permission).collect(),
+        users: users
+          .into_iter()
+          .map(|user| SimpleUserInfo {
+            id: user.id,
+            name: user.name,
+          })
+          .collect(),
+      }
+    }))
+  }
+}
@@ -0,0 +1,103 @@
+use centaurus::error::Result;
+use entity::{feature_flag, feature_flag_group, group};
+use sea_orm::{IntoActiveModel, QueryOrder, TransactionTrait, prelude::*};
+
+pub struct FlagTable<'db> {
+  db: &'db DatabaseConnection,
+}
+
+/// A flag together with the groups it targets.
+pub type FlagWithTargets = (feature_flag::Model, Vec<feature_flag_group::Model>);
+
+impl<'db> FlagTable<'db> {
+  pub fn new(db: &'db DatabaseConnection) -> Self {
+    Self { db }
+  }
+
+  pub async fn list(&self) -> Result<Vec<FlagWithTargets>> {
+    let flags = feature_flag::Entity::find()
+      .order_by_asc(feature_flag::Column::Key)
+      .all(self.db)
+      .await?;
+    let targets = flags.load_many(feature_flag_group::Entity, self.db).await?;
+
+    Ok(flags.into_iter().zip(targets).collect())
+  }
+
+  pub async fn get(&self, id: Uuid) -> Result<Option<FlagWithTargets>> {
+    let Some(flag) = feature_flag::Entity::find_by_id(id).one(self.db).await? else {
+      return Ok(None);
+    };
+    let targets = feature_flag_group::Entity::find()
+      .filter(feature_flag_group::Column::FlagId.eq(id))
+      .all(self.db)
+      .await?;
+
+    Ok(Some((flag, targets)))
+  }
+
+  pub async fn find_by_key(&self, key: &str) -> Result<Option<feature_flag::Model>> {
+    Ok(
+      feature_flag::Entity::find()
+        .filter(feature_flag::Column::Key.eq(key))
+        .one(self.db)
+        .await?,
+    )
+  }
+
+  pub async fn create(
+    &self,
+    flag: feature_flag::Model,
+    targets: Vec<feature_flag_group::Model>,
+  ) -> Result<()> {
+    let txn = self.db.begin().await?;
+    flag.into_active_model().insert(&txn).await?;
+    if !targets.is_empty() {
+      feature_flag_group::Entity::insert_many(targets.into_iter().map(|t| t.into_active_model()))
+        .exec(&txn)
+        .await?;
+    }
+    txn.commit().await?;
+    Ok(())
+  }
+
+  /// Replaces the flag and its targets.
+  pub async fn update(
+    &self,
+    flag: feature_flag::Model,
+    targets: Vec<feature_flag_group::Model>,
+  ) -> Result<()> {
+    let txn = self.db.begin().await?;
+    let id = flag.id;
+    flag.into_active_model().reset_all().update(&txn).await?;
+    feature_flag_group::Entity::delete_many()
+      .filter(feature_flag_group::Column::FlagId.eq(id))
+      .exec(&txn)
+      .await?;
+    if !targets.is_empty() {
+      feature_flag_group::Entity::insert_many(targets.into_iter().map(|t| t.into_active_model()))
+        .exec(&txn)
+        .await?;
+    }
+    txn.commit().await?;
+    Ok(())
+  }
+
+  pub async fn delete(&self, id: Uuid) -> Result<()> {
+    feature_flag::Entity::delete_by_id(id).exec(self.db).await?;
+    Ok(())
+  }
+
+  /// Returns the first of the given group ids that does not exist.
+  pub async fn find_missing_group(&self, groups: &[Uuid]) -> Result<Option<Uuid>> {
+    let existing: Vec<Uuid> = group::Entity::find()
+      .filter(group::Column::Id.is_in(groups.to_vec()))
+      .all(self.db)
+      .await?
+      .into_iter()
+      .map(|g| g.id)
+      .collect();
+
+    Ok(groups.iter().find(|id| !existing.contains(id)).copied())
+  }
+}
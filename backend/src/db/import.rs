@@ -0,0 +1,83 @@
+use centaurus::error::Result;
+use entity::{group_user, organization_member, user};
+use sea_orm::{ConnectionTrait, IntoActiveModel, TransactionTrait, prelude::*};
+
+pub struct ImportTable<'db> {
+  db: &'db DatabaseConnection,
+}
+
+/// A validated user ready to be stored.
+pub struct NewUser {
+  pub name: String,
+  pub email: String,
+  pub password: String,
+  pub salt: String,
+  /// Organization the user joins, owning all of the groups.
+  pub organization: Uuid,
+  pub groups: Vec<Uuid>,
+}
+
+impl<'db> ImportTable<'db> {
+  pub fn new(db: &'db DatabaseConnection) -> Self {
+    Self { db }
+  }
+
+  async fn insert<C: ConnectionTrait>(conn: &C, new: NewUser) -> Result<Uuid> {
+    let id = Uuid::now_v7();
+    user::Model {
+      id,
+      name: new.name,
+      email: new.email,
+      password: new.password,
+      salt: new.salt,
+      oidc_user: false,
+      oidc_subject: None,
+    }
+    .into_active_model()
+    .insert(conn)
+    .await?;
+    organization_member::Model {
+      organization_id: new.organization,
+      user_id: id,
+    }
+    .into_active_model()
+    .insert(conn)
+    .await?;
+
+    if !new.groups.is_empty() {
+      let memberships = new.groups.into_iter().map(|group_id| {
+        group_user::Model {
+          group_id,
+          user_id: id,
+        }
+        .into_active_model()
+      });
+      group_user::Entity::insert_many(memberships)
+        .exec(conn)
+        .await?;
+    }
+
+    Ok(id)
+  }
+
+  /// Creates the user together with their group memberships.
+  pub async fn create_user(&self, new: NewUser) -> Result<Uuid> {
+    let txn = self.db.begin().await?;
+    let id = Self::insert(&txn, new).await?;
+    txn.commit().await?;
+
+    Ok(id)
+  }
+
+  /// Creates all users or, if any of them fails, none.
+  pub async fn create_users(&self, users: Vec<NewUser>) -> Result<Vec<Uuid>> {
+    let txn = self.db.begin().await?;
+    let mut ids = Vec::with_capacity(users.len());
+    for new in users {
+      ids.push(Self::insert(&txn, new).await?);
+    }
+    txn.commit().await?;
+
+    Ok(ids)
+  }
+}
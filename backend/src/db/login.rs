@@ -0,0 +1,205 @@
+use centaurus::error::Result;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use entity::{login_event, session_revocation};
+use sea_orm::{
+  ActiveValue::{Set, Unchanged},
+  QueryOrder, QuerySelect,
+  prelude::*,
+  sea_query::OnConflict,
+};
+use serde::{Deserialize, Serialize};
+
+pub struct LoginTable<'db> {
+  db: &'db DatabaseConnection,
+}
+
+#[derive(Serialize, Deserialize, schemars::JsonSchema)]
+pub struct LoginInfo {
+  pub uuid: Uuid,
+  pub created_at: DateTime<Utc>,
+  pub method: String,
+  pub success: bool,
+  pub ip: Option<String>,
+  pub user_agent: Option<String>,
+  pub new_device: bool,
+  pub reported_at: Option<DateTime<Utc>>,
+}
+
+impl From<login_event::Model> for LoginInfo {
+  fn from(event: login_event::Model) -> Self {
+    Self {
+      uuid: event.id,
+      created_at: event.created_at.and_utc(),
+      method: event.method,
+      success: event.success,
+      ip: event.ip,
+      user_agent: event.user_agent,
+      new_device: event.new_device,
+      reported_at: event.reported_at.map(|at| at.and_utc()),
+    }
+  }
+}
+
+pub struct NewLogin {
+  pub user_id: Uuid,
+  pub method: String,
+  pub success: bool,
+  pub ip: Option<String>,
+  pub user_agent: Option<String>,
+  pub new_device: bool,
+  pub report_token: Option<String>,
+}
+
+impl<'db> LoginTable<'db> {
+  pub fn new(db: &'db DatabaseConnection) -> Self {
+    Self { db }
+  }
+
+  pub async fn record(&self, login: NewLogin) -> Result<Uuid> {
+    let id = Uuid::now_v7();
+    login_event::ActiveModel {
+      id: Set(id),
+      user_id: Set(login.user_id),
+      created_at: Set(Utc::now().naive_utc()),
+      method: Set(login.method),
+      success: Set(login.success),
+      ip: Set(login.ip),
+      user_agent: Set(login.user_agent),
+      new_device: Set(login.new_device),
+      report_token: Set(login.report_token),
+      reported_at: Set(None),
+    }
+    .insert(self.db)
+    .await?;
+
+    Ok(id)
+  }
+
+  pub async fn has_logins(&self, user_id: Uuid) -> Result<bool> {
+    let count = login_event::Entity::find()
+      .filter(login_event::Column::UserId.eq(user_id))
+      .filter(login_event::Column::Success.eq(true))
+      .count(self.db)
+      .await?;
+    Ok(count > 0)
+  }
+
+  /// Whether the user already logged in successfully from this exact IP and
+  /// user agent.
+  pub async fn is_known_device(
+    &self,
+    user_id: Uuid,
+    ip: Option<&str>,
+    user_agent: Option<&str>,
+  ) -> Result<bool> {
+    let ip = match ip {
+      Some(ip) => login_event::Column::Ip.eq(ip),
+      None => login_event::Column::Ip.is_null(),
+    };
+    let user_agent = match user_agent {
+      Some(user_agent) => login_event::Column::UserAgent.eq(user_agent),
+      None => login_event::Column::UserAgent.is_null(),
+    };
+
+    let count = login_event::Entity::find()
+      .filter(login_event::Column::UserId.eq(user_id))
+      .filter(login_event::Column::Success.eq(true))
+      .filter(ip)
+      .filter(user_agent)
+      .count(self.db)
+      .await?;
+    Ok(count > 0)
+  }
+
+  pub async fn list_logins(
+    &self,
+    user_id: Uuid,
+    offset: u64,
+    limit: u64,
+  ) -> Result<(Vec<LoginInfo>, u64)> {
+    let query = login_event::Entity::find().filter(login_event::Column::UserId.eq(user_id));
+
+    let total = query.clone().count(self.db).await?;
+    let logins = query
+      .order_by_desc(login_event::Column::CreatedAt)
+      .order_by_desc(login_event::Column::Id)
+      .offset(offset)
+      .limit(limit)
+      .all(self.db)
+      .await?
+      .into_iter()
+      .map(LoginInfo::from)
+      .collect();
+
+    Ok((logins, total))
+  }
+
+  /// The whole login history of the user, newest first.
+  pub async fn all_logins(&self, user_id: Uuid) -> Result<Vec<LoginInfo>> {
+    Ok(
+      login_event::Entity::find()
+        .filter(login_event::Column::UserId.eq(user_id))
+        .order_by_desc(login_event::Column::CreatedAt)
+        .order_by_desc(login_event::Column::Id)
+        .all(self.db)
+        .await?
+        .into_iter()
+        .map(LoginInfo::from)
+        .collect(),
+    )
+  }
+
+  pub async fn get_login(&self, id: Uuid) -> Result<Option<login_event::Model>> {
+    Ok(login_event::Entity::find_by_id(id).one(self.db).await?)
+  }
+
+  pub async fn find_by_report_token(&self, token: &str) -> Result<Option<login_event::Model>> {
+    Ok(
+      login_event::Entity::find()
+        .filter(login_event::Column::ReportToken.eq(token))
+        .one(self.db)
+        .await?,
+    )
+  }
+
+  /// Marks the login as not made by the user. The report token can only be
+  /// used once.
+  pub async fn mark_reported(&self, id: Uuid) -> Result<()> {
+    login_event::ActiveModel {
+      id: Unchanged(id),
+      reported_at: Set(Some(Utc::now().naive_utc())),
+      report_token: Set(None),
+      ..Default::default()
+    }
+    .update(self.db)
+    .await?;
+
+    Ok(())
+  }
+
+  /// Invalidates every session of the user issued before now.
+  pub async fn revoke_sessions(&self, user_id: Uuid) -> Result<()> {
+    session_revocation::Entity::insert(session_revocation::ActiveModel {
+      user_id: Set(user_id),
+      revoked_at: Set(Utc::now().naive_utc()),
+    })
+    .on_conflict(
+      OnConflict::column(session_revocation::Column::UserId)
+        .update_column(session_revocation::Column::RevokedAt)
+        .to_owned(),
+    )
+    .exec(self.db)
+    .await?;
+
+    Ok(())
+  }
+
+  pub async fn sessions_revoked_at(&self, user_id: Uuid) -> Result<Option<NaiveDateTime>> {
+    Ok(
+      session_revocation::Entity::find_by_id(user_id)
+        .one(self.db)
+        .await?
+        .map(|revocation| revocation.revoked_at),
+    )
+  }
+}
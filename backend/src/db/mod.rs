@@ -1,6 +1,93 @@
 use centaurus::db::init::Connection;
 
-#[allow(unused)]
-pub trait DBTrait {}
+use crate::db::{
+  account_deletion::AccountDeletionTable, attribute::AttributeTable, audit::AuditTable,
+  avatar::AvatarTable, branding::BrandingTable, directory::DirectoryTable, flag::FlagTable,
+  import::ImportTable, login::LoginTable, organization::OrganizationTable, role::RoleTable,
+  settings_history::SettingsHistoryTable, user_status::UserStatusTable,
+};
 
-impl DBTrait for Connection {}
+pub mod account_deletion;
+pub mod attribute;
+pub mod audit;
+pub mod avatar;
+pub mod branding;
+pub mod directory;
+pub mod flag;
+pub mod import;
+pub mod login;
+pub mod organization;
+pub mod repository;
+pub mod role;
+pub mod settings_history;
+pub mod user_status;
+
+pub trait DBTrait {
+  fn audit(&self) -> AuditTable<'_>;
+  fn login(&self) -> LoginTable<'_>;
+  fn role(&self) -> RoleTable<'_>;
+  fn user_status(&self) -> UserStatusTable<'_>;
+  fn directory(&self) -> DirectoryTable<'_>;
+  fn import(&self) -> ImportTable<'_>;
+  fn attribute(&self) -> AttributeTable<'_>;
+  fn account_deletion(&self) -> AccountDeletionTable<'_>;
+  fn avatar(&self) -> AvatarTable<'_>;
+  fn organization(&self) -> OrganizationTable<'_>;
+  fn branding(&self) -> BrandingTable<'_>;
+  fn settings_history(&self) -> SettingsHistoryTable<'_>;
+  fn flag(&self) -> FlagTable<'_>;
+}
+
+impl DBTrait for Connection {
+  fn audit(&self) -> AuditTable<'_> {
+    AuditTable::new(self)
+  }
+
+  fn login(&self) -> LoginTable<'_> {
+    LoginTable::new(self)
+  }
+
+  fn role(&self) -> RoleTable<'_> {
+    RoleTable::new(self)
+  }
+
+  fn user_status(&self) -> UserStatusTable<'_> {
+    UserStatusTable::new(self)
+  }
+
+  fn directory(&self) -> DirectoryTable<'_> {
+    DirectoryTable::new(self)
+  }
+
+  fn import(&self) -> ImportTable<'_> {
+    ImportTable::new(self)
+  }
+
+  fn attribute(&self) -> AttributeTable<'_> {
+    AttributeTable::new(self)
+  }
+
+  fn account_deletion(&self) -> AccountDeletionTable<'_> {
+    AccountDeletionTable::new(self)
+  }
+
+  fn avatar(&self) -> AvatarTable<'_> {
+    AvatarTable::new(self)
+  }
+
+  fn organization(&self) -> OrganizationTable<'_> {
+    OrganizationTable::new(self)
+  }
+
+  fn branding(&self) -> BrandingTable<'_> {
+    BrandingTable::new(self)
+  }
+
+  fn settings_history(&self) -> SettingsHistoryTable<'_> {
+    SettingsHistoryTable::new(self)
+  }
+
+  fn flag(&self) -> FlagTable<'_> {
+    FlagTable::new(self)
+  }
+}
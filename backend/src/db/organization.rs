@@ -0,0 +1,401 @@
+use centaurus::error::Result;
+use entity::{
+  active_organization, group, group_permission, group_user, organization, organization_group,
+  organization_member, super_admin, user,
+};
+use sea_orm::{
+  ActiveValue::Set,
+  PaginatorTrait, QueryOrder, QuerySelect, TransactionTrait,
+  prelude::*,
+  sea_query::{OnConflict, Query},
+};
+
+/// Organizations, their members and the groups they own.
+pub struct OrganizationTable<'db> {
+  db: &'db DatabaseConnection,
+}
+
+impl<'db> OrganizationTable<'db> {
+  pub fn new(db: &'db DatabaseConnection) -> Self {
+    Self { db }
+  }
+
+  pub async fn list(&self) -> Result<Vec<organization::Model>> {
+    Ok(
+      organization::Entity::find()
+        .order_by_asc(organization::Column::Name)
+        .all(self.db)
+        .await?,
+    )
+  }
+
+  /// Organizations the user is a member of, oldest first.
+  pub async fn list_for_user(&self, user_id: Uuid) -> Result<Vec<organization::Model>> {
+    Ok(
+      organization::Entity::find()
+        .filter(
+          organization::Column::Id.in_subquery(
+            Query::select()
+              .column(organization_member::Column::OrganizationId)
+              .from(organization_member::Entity)
+              .and_where(organization_member::Column::UserId.eq(user_id))
+              .to_owned(),
+          ),
+        )
+        .order_by_asc(organization::Column::Id)
+        .all(self.db)
+        .await?,
+    )
+  }
+
+  pub async fn get(&self, id: Uuid) -> Result<Option<organization::Model>> {
+    Ok(organization::Entity::find_by_id(id).one(self.db).await?)
+  }
+
+  pub async fn find_by_name(&self, name: &str) -> Result<Option<organization::Model>> {
+    Ok(
+      organization::Entity::find()
+        .filter(organization::Column::Name.eq(name))
+        .one(self.db)
+        .await?,
+    )
+  }
+
+  /// Organization new users end up in when nobody added them to one.
+  pub async fn default_organization(&self) -> Result<Option<organization::Model>> {
+    Ok(
+      organization::Entity::find()
+        .filter(organization::Column::IsDefault.eq(true))
+        .one(self.db)
+        .await?,
+    )
+  }
+
+  pub async fn create(&self, name: String) -> Result<Uuid> {
+    let id = Uuid::now_v7();
+    organization::ActiveModel {
+      id: Set(id),
+      name: Set(name),
+      is_default: Set(false),
+    }
+    .insert(self.db)
+    .await?;
+
+    Ok(id)
+  }
+
+  pub async fn rename(&self, id: Uuid, name: String) -> Result<()> {
+    organization::ActiveModel {
+      id: Set(id),
+      name: Set(name),
+      ..Default::default()
+    }
+    .update(self.db)
+    .await?;
+
+    Ok(())
+  }
+
+  pub async fn delete(&self, id: Uuid) -> Result<()> {
+    organization::Entity::delete_by_id(id).exec(self.db).await?;
+    Ok(())
+  }
+
+  pub async fn members(&self, organization_id: Uuid) -> Result<Vec<user::Model>> {
+    Ok(
+      user::Entity::find()
+        .filter(
+          user::Column::Id.in_subquery(
+            Query::select()
+              .column(organization_member::Column::UserId)
+              .from(organization_member::Entity)
+              .and_where(organization_member::Column::OrganizationId.eq(organization_id))
+              .to_owned(),
+          ),
+        )
+        .order_by_asc(user::Column::Name)
+        .all(self.db)
+        .await?,
+    )
+  }
+
+  pub async fn is_member(&self, organization_id: Uuid, user_id: Uuid) -> Result<bool> {
+    Ok(
+      organization_member::Entity::find_by_id((organization_id, user_id))
+        .one(self.db)
+        .await?
+        .is_some(),
+    )
+  }
+
+  /// Returns the first of the given users that is not a member.
+  pub async fn find_non_member(
+    &self,
+    organization_id: Uuid,
+    users: &[Uuid],
+  ) -> Result<Option<Uuid>> {
+    let members: Vec<Uuid> = organization_member::Entity::find()
+      .filter(organization_member::Column::OrganizationId.eq(organization_id))
+      .filter(organization_member::Column::UserId.is_in(users.to_vec()))
+      .all(self.db)
+      .await?
+      .into_iter()
+      .map(|m| m.user_id)
+      .collect();
+
+    Ok(users.iter().find(|id| !members.contains(id)).copied())
+  }
+
+  pub async fn add_member(&self, organization_id: Uuid, user_id: Uuid) -> Result<()> {
+    organization_member::Entity::insert(organization_member::ActiveModel {
+      organization_id: Set(organization_id),
+      user_id: Set(user_id),
+    })
+    .on_conflict_do_nothing()
+    .exec(self.db)
+    .await?;
+
+    Ok(())
+  }
+
+  /// Removes the user together with their memberships in the groups of the
+  /// organization.
+  pub async fn remove_member(&self, organization_id: Uuid, user_id: Uuid) -> Result<()> {
+    let txn = self.db.begin().await?;
+    group_user::Entity::delete_many()
+      .filter(group_user::Column::UserId.eq(user_id))
+      .filter(group_user::Column::GroupId.in_subquery(Self::groups_query(organization_id)))
+      .exec(&txn)
+      .await?;
+    active_organization::Entity::delete_many()
+      .filter(active_organization::Column::UserId.eq(user_id))
+      .filter(active_organization::Column::OrganizationId.eq(organization_id))
+      .exec(&txn)
+      .await?;
+    organization_member::Entity::delete_by_id((organization_id, user_id))
+      .exec(&txn)
+      .await?;
+    txn.commit().await?;
+
+    Ok(())
+  }
+
+  fn groups_query(organization_id: Uuid) -> sea_orm::sea_query::SelectStatement {
+    Query::select()
+      .column(organization_group::Column::GroupId)
+      .from(organization_group::Entity)
+      .and_where(organization_group::Column::OrganizationId.eq(organization_id))
+      .to_owned()
+  }
+
+  pub async fn groups(&self, organization_id: Uuid) -> Result<Vec<group::Model>> {
+    Ok(
+      group::Entity::find()
+        .filter(group::Column::Id.in_subquery(Self::groups_query(organization_id)))
+        .order_by_asc(group::Column::Name)
+        .all(self.db)
+        .await?,
+    )
+  }
+
+  pub async fn count_groups(&self, organization_id: Uuid) -> Result<u64> {
+    Ok(
+      organization_group::Entity::find()
+        .filter(organization_group::Column::OrganizationId.eq(organization_id))
+        .count(self.db)
+        .await?,
+    )
+  }
+
+  /// Organization owning the group, `None` for unknown groups.
+  pub async fn group_organization(&self, group_id: Uuid) -> Result<Option<Uuid>> {
+    Ok(
+      organization_group::Entity::find_by_id(group_id)
+        .one(self.db)
+        .await?
+        .map(|g| g.organization_id),
+    )
+  }
+
+  /// Group with this name within the organization.
+  pub async fn find_group_by_name(
+    &self,
+    organization_id: Uuid,
+    name: &str,
+  ) -> Result<Option<Uuid>> {
+    Ok(
+      group::Entity::find()
+        .filter(group::Column::Name.eq(name))
+        .filter(group::Column::Id.in_subquery(Self::groups_query(organization_id)))
+        .one(self.db)
+        .await?
+        .map(|g| g.id),
+    )
+  }
+
+  /// Returns the first of the given groups not owned by the organization.
+  pub async fn find_foreign_group(
+    &self,
+    organization_id: Uuid,
+    groups: &[Uuid],
+  ) -> Result<Option<Uuid>> {
+    let owned: Vec<Uuid> = organization_group::Entity::find()
+      .filter(organization_group::Column::OrganizationId.eq(organization_id))
+      .filter(organization_group::Column::GroupId.is_in(groups.to_vec()))
+      .all(self.db)
+      .await?
+      .into_iter()
+      .map(|g| g.group_id)
+      .collect();
+
+    Ok(groups.iter().find(|id| !owned.contains(id)).copied())
+  }
+
+  /// Creates a group owned by the organization.
+  pub async fn create_group(&self, organization_id: Uuid, name: String) -> Result<Uuid> {
+    let id = Uuid::now_v7();
+    let txn = self.db.begin().await?;
+    group::ActiveModel {
+      id: Set(id),
+      name: Set(name),
+    }
+    .insert(&txn)
+    .await?;
+    organization_group::ActiveModel {
+      group_id: Set(id),
+      organization_id: Set(organization_id),
+    }
+    .insert(&txn)
+    .await?;
+    txn.commit().await?;
+
+    Ok(id)
+  }
+
+  /// Assigns groups created outside of any organization, like the admin group
+  /// of centaurus, to the given one.
+  pub async fn adopt_groups(&self, organization_id: Uuid) -> Result<()> {
+    let orphans = group::Entity::find()
+      .filter(
+        group::Column::Id.not_in_subquery(
+          Query::select()
+            .column(organization_group::Column::GroupId)
+            .from(organization_group::Entity)
+            .to_owned(),
+        ),
+      )
+      .all(self.db)
+      .await?;
+    if orphans.is_empty() {
+      return Ok(());
+    }
+
+    organization_group::Entity::insert_many(orphans.into_iter().map(|group| {
+      organization_group::ActiveModel {
+        group_id: Set(group.id),
+        organization_id: Set(organization_id),
+      }
+    }))
+    .exec(self.db)
+    .await?;
+
+    Ok(())
+  }
+
+  /// Groups of the user within the organization.
+  pub async fn user_groups(&self, organization_id: Uuid, user_id: Uuid) -> Result<Vec<Uuid>> {
+    Ok(
+      group_user::Entity::find()
+        .filter(group_user::Column::UserId.eq(user_id))
+        .filter(group_user::Column::GroupId.in_subquery(Self::groups_query(organization_id)))
+        .all(self.db)
+        .await?
+        .into_iter()
+        .map(|g| g.group_id)
+        .collect(),
+    )
+  }
+
+  /// Permissions the user holds through the groups of the organization.
+  pub async fn user_permissions(
+    &self,
+    organization_id: Uuid,
+    user_id: Uuid,
+  ) -> Result<Vec<String>> {
+    let groups = self.user_groups(organization_id, user_id).await?;
+    let mut permissions: Vec<String> = group_permission::Entity::find()
+      .filter(group_permission::Column::GroupId.is_in(groups))
+      .select_only()
+      .column(group_permission::Column::Permission)
+      .distinct()
+      .into_tuple()
+      .all(self.db)
+      .await?;
+    permissions.sort_unstable();
+
+    Ok(permissions)
+  }
+
+  pub async fn active(&self, user_id: Uuid) -> Result<Option<Uuid>> {
+    Ok(
+      active_organization::Entity::find_by_id(user_id)
+        .one(self.db)
+        .await?
+        .map(|a| a.organization_id),
+    )
+  }
+
+  pub async fn set_active(&self, user_id: Uuid, organization_id: Uuid) -> Result<()> {
+    active_organization::Entity::insert(active_organization::ActiveModel {
+      user_id: Set(user_id),
+      organization_id: Set(organization_id),
+    })
+    .on_conflict(
+      OnConflict::column(active_organization::Column::UserId)
+        .update_column(active_organization::Column::OrganizationId)
+        .to_owned(),
+    )
+    .exec(self.db)
+    .await?;
+
+    Ok(())
+  }
+
+  pub async fn is_super_admin(&self, user_id: Uuid) -> Result<bool> {
+    Ok(
+      super_admin::Entity::find_by_id(user_id)
+        .one(self.db)
+        .await?
+        .is_some(),
+    )
+  }
+
+  pub async fn super_admins(&self) -> Result<Vec<Uuid>> {
+    Ok(
+      super_admin::Entity::find()
+        .all(self.db)
+        .await?
+        .into_iter()
+        .map(|s| s.user_id)
+        .collect(),
+    )
+  }
+
+  pub async fn add_super_admin(&self, user_id: Uuid) -> Result<()> {
+    super_admin::Entity::insert(super_admin::ActiveModel {
+      user_id: Set(user_id),
+    })
+    .on_conflict_do_nothing()
+    .exec(self.db)
+    .await?;
+
+    Ok(())
+  }
+
+  pub async fn remove_super_admin(&self, user_id: Uuid) -> Result<()> {
+    super_admin::Entity::delete_by_id(user_id)
+      .exec(self.db)
+      .await?;
+    Ok(())
+  }
+}
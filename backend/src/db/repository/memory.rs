@@ -0,0 +1,161 @@
+use std::{
+  collections::{BTreeMap, BTreeSet, HashMap},
+  sync::{Arc, Mutex, MutexGuard},
+};
+
+use async_trait::async_trait;
+use centaurus::{bail, error::Result};
+use entity::{key, user};
+use uuid::Uuid;
+
+use crate::db::repository::{GroupRepository, KeyRepository, SettingsRepository, UserRepository};
+
+#[derive(Default)]
+struct Data {
+  users: BTreeMap<Uuid, user::Model>,
+  groups: BTreeSet<Uuid>,
+  /// Memberships as `(group, user)`.
+  members: BTreeSet<(Uuid, Uuid)>,
+  settings: HashMap<i32, String>,
+  keys: Vec<key::Model>,
+}
+
+/// Keeps everything in memory, for tests of code written against the
+/// repositories.
+#[derive(Clone, Default)]
+pub struct MemoryRepository(Arc<Mutex<Data>>);
+
+impl MemoryRepository {
+  fn data(&self) -> MutexGuard<'_, Data> {
+    self.0.lock().expect("Memory repository poisoned")
+  }
+
+  pub fn insert_user(&self, user: user::Model) {
+    self.data().users.insert(user.id, user);
+  }
+
+  pub fn insert_group(&self, group: Uuid, members: &[Uuid]) {
+    let mut data = self.data();
+    data.groups.insert(group);
+    data
+      .members
+      .extend(members.iter().map(|user| (group, *user)));
+  }
+}
+
+#[async_trait]
+impl UserRepository for MemoryRepository {
+  async fn get_user(&self, id: Uuid) -> Result<Option<user::Model>> {
+    Ok(self.data().users.get(&id).cloned())
+  }
+
+  async fn find_user_by_email(&self, email: &str) -> Result<Option<user::Model>> {
+    Ok(
+      self
+        .data()
+        .users
+        .values()
+        .find(|user| user.email == email)
+        .cloned(),
+    )
+  }
+
+  async fn user_groups(&self, id: Uuid) -> Result<Vec<Uuid>> {
+    Ok(
+      self
+        .data()
+        .members
+        .iter()
+        .filter(|(_, user)| *user == id)
+        .map(|(group, _)| *group)
+        .collect(),
+    )
+  }
+
+  async fn delete_user(&self, id: Uuid) -> Result<()> {
+    let mut data = self.data();
+    data.users.remove(&id);
+    data.members.retain(|(_, user)| *user != id);
+    Ok(())
+  }
+}
+
+#[async_trait]
+impl GroupRepository for MemoryRepository {
+  async fn group_members(&self, id: Uuid) -> Result<Vec<Uuid>> {
+    Ok(
+      self
+        .data()
+        .members
+        .iter()
+        .filter(|(group, _)| *group == id)
+        .map(|(_, user)| *user)
+        .collect(),
+    )
+  }
+
+  async fn is_member(&self, group: Uuid, user: Uuid) -> Result<bool> {
+    Ok(self.data().members.contains(&(group, user)))
+  }
+
+  async fn delete_group(&self, id: Uuid) -> Result<()> {
+    let mut data = self.data();
+    if !data.groups.remove(&id) {
+      bail!(NOT_FOUND, "Group not found");
+    }
+    data.members.retain(|(group, _)| *group != id);
+    Ok(())
+  }
+}
+
+#[async_trait]
+impl SettingsRepository for MemoryRepository {
+  async fn load_settings(&self, id: i32) -> Result<Option<String>> {
+    Ok(self.data().settings.get(&id).cloned())
+  }
+
+  async fn store_settings(&self, id: i32, content: String) -> Result<()> {
+    self.data().settings.insert(id, content);
+    Ok(())
+  }
+}
+
+#[async_trait]
+impl KeyRepository for MemoryRepository {
+  async fn find_key(&self, name: &str) -> Result<Option<key::Model>> {
+    Ok(
+      self
+        .data()
+        .keys
+        .iter()
+        .find(|key| key.name == name)
+        .cloned(),
+    )
+  }
+
+  async fn create_key(&self, key: key::Model) -> Result<()> {
+    self.data().keys.push(key);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn deleting_removes_memberships() {
+    let repo = MemoryRepository::default();
+    let (group, user, other) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+    repo.insert_group(group, &[user, other]);
+
+    assert!(!repo.is_last_member(group, user).await.unwrap());
+    repo.delete_user(other).await.unwrap();
+    assert!(repo.is_last_member(group, user).await.unwrap());
+    assert_eq!(repo.user_groups(user).await.unwrap(), [group]);
+
+    repo.delete_group(group).await.unwrap();
+    assert!(!repo.is_member(group, user).await.unwrap());
+    assert!(repo.delete_group(group).await.is_err());
+  }
+}
@@ -0,0 +1,70 @@
+use async_trait::async_trait;
+use centaurus::{db::settings::Settings, error::Result};
+use entity::{key, user};
+use uuid::Uuid;
+
+#[cfg(test)]
+pub mod memory;
+mod sql;
+
+/// Users, independent of where they are stored. Code written against this
+/// instead of the connection can be tested with the in-memory store. Only the
+/// checks of handlers which need nothing else use it so far, e.g. the
+/// attribute access or deleting groups, everything else still goes through
+/// the tables of [`crate::db::DBTrait`].
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+  async fn get_user(&self, id: Uuid) -> Result<Option<user::Model>>;
+  async fn find_user_by_email(&self, email: &str) -> Result<Option<user::Model>>;
+  /// Ids of the groups the user is a member of.
+  async fn user_groups(&self, id: Uuid) -> Result<Vec<Uuid>>;
+  /// Deletes the user together with everything that cascades from them.
+  async fn delete_user(&self, id: Uuid) -> Result<()>;
+}
+
+/// Groups and their members.
+#[async_trait]
+pub trait GroupRepository: Send + Sync {
+  async fn group_members(&self, id: Uuid) -> Result<Vec<Uuid>>;
+  async fn is_member(&self, group: Uuid, user: Uuid) -> Result<bool>;
+  /// Fails with not found for unknown groups.
+  async fn delete_group(&self, id: Uuid) -> Result<()>;
+
+  /// Whether the user is the only member left, e.g. of the admin group.
+  async fn is_last_member(&self, group: Uuid, user: Uuid) -> Result<bool> {
+    Ok(self.group_members(group).await? == [user])
+  }
+}
+
+/// Settings stored as JSON by their id, see [`SettingsRepositoryExt`] for
+/// typed access.
+#[async_trait]
+pub trait SettingsRepository: Send + Sync {
+  async fn load_settings(&self, id: i32) -> Result<Option<String>>;
+  async fn store_settings(&self, id: i32, content: String) -> Result<()>;
+}
+
+#[async_trait]
+pub trait SettingsRepositoryExt: SettingsRepository {
+  /// The stored settings, or their defaults until they are first saved.
+  async fn get_settings<S: Settings + Send>(&self) -> Result<S> {
+    match self.load_settings(S::id()).await? {
+      Some(content) => Ok(serde_json::from_str(&content)?),
+      None => Ok(S::default()),
+    }
+  }
+
+  async fn save_settings<S: Settings + Sync>(&self, settings: &S) -> Result<()> {
+    let content = serde_json::to_string(settings)?;
+    self.store_settings(S::id(), content).await
+  }
+}
+
+impl<T: SettingsRepository + ?Sized> SettingsRepositoryExt for T {}
+
+/// Named private keys, e.g. for signing.
+#[async_trait]
+pub trait KeyRepository: Send + Sync {
+  async fn find_key(&self, name: &str) -> Result<Option<key::Model>>;
+  async fn create_key(&self, key: key::Model) -> Result<()>;
+}
@@ -0,0 +1,115 @@
+use async_trait::async_trait;
+use centaurus::{bail, db::init::Connection, error::Result};
+use entity::{group, group_user, key, settings, user};
+use sea_orm::{IntoActiveModel, PaginatorTrait, prelude::*, sea_query::OnConflict};
+use uuid::Uuid;
+
+use crate::db::repository::{GroupRepository, KeyRepository, SettingsRepository, UserRepository};
+
+#[async_trait]
+impl UserRepository for Connection {
+  async fn get_user(&self, id: Uuid) -> Result<Option<user::Model>> {
+    Ok(user::Entity::find_by_id(id).one(&**self).await?)
+  }
+
+  async fn find_user_by_email(&self, email: &str) -> Result<Option<user::Model>> {
+    Ok(
+      user::Entity::find()
+        .filter(user::Column::Email.eq(email))
+        .one(&**self)
+        .await?,
+    )
+  }
+
+  async fn user_groups(&self, id: Uuid) -> Result<Vec<Uuid>> {
+    Ok(
+      group_user::Entity::find()
+        .filter(group_user::Column::UserId.eq(id))
+        .all(&**self)
+        .await?
+        .into_iter()
+        .map(|membership| membership.group_id)
+        .collect(),
+    )
+  }
+
+  async fn delete_user(&self, id: Uuid) -> Result<()> {
+    user::Entity::delete_by_id(id).exec(&**self).await?;
+    Ok(())
+  }
+}
+
+#[async_trait]
+impl GroupRepository for Connection {
+  async fn group_members(&self, id: Uuid) -> Result<Vec<Uuid>> {
+    Ok(
+      group_user::Entity::find()
+        .filter(group_user::Column::GroupId.eq(id))
+        .all(&**self)
+        .await?
+        .into_iter()
+        .map(|membership| membership.user_id)
+        .collect(),
+    )
+  }
+
+  async fn is_member(&self, group: Uuid, user: Uuid) -> Result<bool> {
+    Ok(
+      group_user::Entity::find()
+        .filter(group_user::Column::GroupId.eq(group))
+        .filter(group_user::Column::UserId.eq(user))
+        .count(&**self)
+        .await?
+        > 0,
+    )
+  }
+
+  async fn delete_group(&self, id: Uuid) -> Result<()> {
+    let res = group::Entity::delete_by_id(id).exec(&**self).await?;
+    if res.rows_affected == 0 {
+      bail!(NOT_FOUND, "Group not found");
+    }
+    Ok(())
+  }
+}
+
+#[async_trait]
+impl SettingsRepository for Connection {
+  async fn load_settings(&self, id: i32) -> Result<Option<String>> {
+    Ok(
+      settings::Entity::find_by_id(id)
+        .one(&**self)
+        .await?
+        .map(|settings| settings.content),
+    )
+  }
+
+  async fn store_settings(&self, id: i32, content: String) -> Result<()> {
+    settings::Entity::insert(settings::Model { id, content }.into_active_model())
+      .on_conflict(
+        OnConflict::column(settings::Column::Id)
+          .update_column(settings::Column::Content)
+          .to_owned(),
+      )
+      .exec(&**self)
+      .await?;
+    Ok(())
+  }
+}
+
+#[async_trait]
+impl KeyRepository for Connection {
+  async fn find_key(&self, name: &str) -> Result<Option<key::Model>> {
+    Ok(
+      key::Entity::find()
+        .filter(key::Column::Name.eq(name))
+        .one(&**self)
+        .await?,
+    )
+  }
+
+  async fn create_key(&self, key: key::Model) -> Result<()> {
+    key.into_active_model().insert(&**self).await?;
+    Ok(())
+  }
+}
@@ -0,0 +1,464 @@
+use centaurus::{db::tables::user::SimpleGroupInfo, error::Result};
+use entity::{
+  group, group_direct_permission, group_permission, group_role, group_user, role, role_permission,
+};
+use sea_orm::{IntoActiveModel, Set, TransactionTrait, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::permissions;
+
+pub struct RoleTable<'db> {
+  db: &'db DatabaseConnection,
+}
+
+#[derive(Serialize, Deserialize, schemars::JsonSchema)]
+pub struct RoleInfo {
+  pub uuid: Uuid,
+  pub name: String,
+  pub description: String,
+  pub built_in: bool,
+  pub permissions: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, schemars::JsonSchema)]
+pub struct RoleDetails {
+  pub uuid: Uuid,
+  pub name: String,
+  pub description: String,
+  pub built_in: bool,
+  pub permissions: Vec<String>,
+  pub groups: Vec<SimpleGroupInfo>,
+}
+
+#[derive(Serialize, Deserialize, schemars::JsonSchema)]
+pub struct SimpleRoleInfo {
+  pub uuid: Uuid,
+  pub name: String,
+}
+
+impl<'db> RoleTable<'db> {
+  pub fn new(db: &'db DatabaseConnection) -> Self {
+    Self { db }
+  }
+
+  pub async fn list_roles(&self) -> Result<Vec<RoleInfo>> {
+    let roles = role::Entity::find().all(self.db).await?;
+    let permissions = roles.load_many(role_permission::Entity, self.db).await?;
+
+    Ok(
+      roles
+        .into_iter()
+        .zip(permissions)
+        .map(|(role, permissions)| RoleInfo {
+          uuid: role.id,
+          name: role.name,
+          description: role.description,
+          built_in: role.built_in,
+          permissions: permissions.into_iter().map(|p| p.permission).collect(),
+        })
+        .collect(),
+    )
+  }
+
+  pub async fn list_roles_simple(&self) -> Result<Vec<SimpleRoleInfo>> {
+    let roles = role::Entity::find()
+      .all(self.db)
+      .await?
+      .into_iter()
+      .map(|r| SimpleRoleInfo {
+        uuid: r.id,
+        name: r.name,
+      })
+      .collect();
+
+    Ok(roles)
+  }
+
+  pub async fn role_info(&self, role_id: Uuid) -> Result<Option<RoleDetails>> {
+    let Some(role) = role::Entity::find_by_id(role_id).one(self.db).await? else {
+      return Ok(None);
+    };
+
+    let permissions = self.get_role_permissions(&[role_id]).await?;
+    let groups = group_role::Entity::find()
+      .filter(group_role::Column::RoleId.eq(role_id))
+      .find_also_related(group::Entity)
+      .all(self.db)
+      .await?
+      .into_iter()
+      .filter_map(|(_, group)| {
+        group.map(|g| SimpleGroupInfo {
+          uuid: g.id,
+          name: g.name,
+        })
+      })
+      .collect();
+
+    Ok(Some(RoleDetails {
+      uuid: role.id,
+      name: role.name,
+      description: role.description,
+      built_in: role.built_in,
+      permissions,
+      groups,
+    }))
+  }
+
+  /// Returns the first of the given role ids that does not exist.
+  pub async fn find_missing_role(&self, role_ids: &[Uuid]) -> Result<Option<Uuid>> {
+    let existing: Vec<Uuid> = role::Entity::find()
+      .filter(role::Column::Id.is_in(role_ids.to_vec()))
+      .all(self.db)
+      .await?
+      .into_iter()
+      .map(|r| r.id)
+      .collect();
+
+    Ok(role_ids.iter().find(|id| !existing.contains(id)).copied())
+  }
+
+  pub async fn find_role_by_name(&self, name: &str) -> Result<Option<role::Model>> {
+    Ok(
+      role::Entity::find()
+        .filter(role::Column::Name.eq(name))
+        .one(self.db)
+        .await?,
+    )
+  }
+
+  pub async fn create_role(
+    &self,
+    name: String,
+    description: String,
+    permissions: Vec<String>,
+    built_in: bool,
+  ) -> Result<Uuid> {
+    let role_id = Uuid::now_v7();
+    let txn = self.db.begin().await?;
+    role::ActiveModel {
+      id: Set(role_id),
+      name: Set(name),
+      description: Set(description),
+      built_in: Set(built_in),
+    }
+    .insert(&txn)
+    .await?;
+    set_role_permissions(&txn, role_id, permissions).await?;
+    txn.commit().await?;
+
+    Ok(role_id)
+  }
+
+  pub async fn edit_role(
+    &self,
+    role_id: Uuid,
+    name: String,
+    description: String,
+    permissions: Vec<String>,
+  ) -> Result<()> {
+    let txn = self.db.begin().await?;
+    let mut model = role::Entity::find_by_id(role_id)
+      .one(&txn)
+      .await?
+      .ok_or(DbErr::RecordNotFound("Role not found".to_string()))?
+      .into_active_model();
+    model.name = Set(name);
+    model.description = Set(description);
+    model.update(&txn).await?;
+    set_role_permissions(&txn, role_id, permissions).await?;
+    txn.commit().await?;
+
+    Ok(())
+  }
+
+  pub async fn delete_role(&self, role_id: Uuid) -> Result<()> {
+    role::Entity::delete_by_id(role_id).exec(self.db).await?;
+    Ok(())
+  }
+
+  pub async fn get_role_permissions(&self, role_ids: &[Uuid]) -> Result<Vec<String>> {
+    let permissions = role_permission::Entity::find()
+      .filter(role_permission::Column::RoleId.is_in(role_ids.to_vec()))
+      .all(self.db)
+      .await?
+      .into_iter()
+      .map(|rp| rp.permission)
+      .collect();
+
+    Ok(permissions)
+  }
+
+  pub async fn get_group_roles(&self, group_id: Uuid) -> Result<Vec<SimpleRoleInfo>> {
+    let roles = group_role::Entity::find()
+      .filter(group_role::Column::GroupId.eq(group_id))
+      .find_also_related(role::Entity)
+      .all(self.db)
+      .await?
+      .into_iter()
+      .filter_map(|(_, role)| {
+        role.map(|r| SimpleRoleInfo {
+          uuid: r.id,
+          name: r.name,
+        })
+      })
+      .collect();
+
+    Ok(roles)
+  }
+
+  pub async fn get_role_group_ids(&self, role_id: Uuid) -> Result<Vec<Uuid>> {
+    let groups = group_role::Entity::find()
+      .filter(group_role::Column::RoleId.eq(role_id))
+      .all(self.db)
+      .await?
+      .into_iter()
+      .map(|gr| gr.group_id)
+      .collect();
+
+    Ok(groups)
+  }
+
+  /// Stores the group with its members, direct permissions, roles and the
+  /// effective permissions resolved from them, all of it or nothing.
+  pub async fn edit_group(
+    &self,
+    group_id: Uuid,
+    name: String,
+    users: Vec<Uuid>,
+    direct_permissions: Vec<String>,
+    role_ids: Vec<Uuid>,
+    effective_permissions: Vec<String>,
+  ) -> Result<()> {
+    let txn = self.db.begin().await?;
+    let mut model = group::Entity::find_by_id(group_id)
+      .one(&txn)
+      .await?
+      .ok_or(DbErr::RecordNotFound("Group not found".to_string()))?
+      .into_active_model();
+    model.name = Set(name);
+    model.update(&txn).await?;
+
+    group_user::Entity::delete_many()
+      .filter(group_user::Column::GroupId.eq(group_id))
+      .exec(&txn)
+      .await?;
+    if !users.is_empty() {
+      let models = users
+        .into_iter()
+        .map(|user_id| group_user::Model { group_id, user_id }.into_active_model());
+      group_user::Entity::insert_many(models).exec(&txn).await?;
+    }
+
+    group_direct_permission::Entity::delete_many()
+      .filter(group_direct_permission::Column::GroupId.eq(group_id))
+      .exec(&txn)
+      .await?;
+    if !direct_permissions.is_empty() {
+      let models = direct_permissions.into_iter().map(|permission| {
+        group_direct_permission::Model {
+          group_id,
+          permission,
+        }
+        .into_active_model()
+      });
+      group_direct_permission::Entity::insert_many(models)
+        .exec(&txn)
+        .await?;
+    }
+
+    group_role::Entity::delete_many()
+      .filter(group_role::Column::GroupId.eq(group_id))
+      .exec(&txn)
+      .await?;
+    if !role_ids.is_empty() {
+      let models = role_ids
+        .into_iter()
+        .map(|role_id| group_role::Model { group_id, role_id }.into_active_model());
+      group_role::Entity::insert_many(models).exec(&txn).await?;
+    }
+
+    set_group_permissions(&txn, group_id, effective_permissions).await?;
+    txn.commit().await?;
+
+    Ok(())
+  }
+
+  pub async fn get_direct_permissions(&self, group_id: Uuid) -> Result<Vec<String>> {
+    let permissions = group_direct_permission::Entity::find()
+      .filter(group_direct_permission::Column::GroupId.eq(group_id))
+      .all(self.db)
+      .await?
+      .into_iter()
+      .map(|gp| gp.permission)
+      .collect();
+
+    Ok(permissions)
+  }
+
+  pub async fn add_direct_permissions(
+    &self,
+    group_id: Uuid,
+    permissions: Vec<String>,
+  ) -> Result<()> {
+    let existing = self.get_direct_permissions(group_id).await?;
+    let models: Vec<_> = permissions
+      .into_iter()
+      .filter(|p| !existing.contains(p))
+      .map(|permission| {
+        group_direct_permission::Model {
+          group_id,
+          permission,
+        }
+        .into_active_model()
+      })
+      .collect();
+
+    if models.is_empty() {
+      return Ok(());
+    }
+
+    group_direct_permission::Entity::insert_many(models)
+      .exec(self.db)
+      .await?;
+
+    Ok(())
+  }
+
+  /// Effective permissions of a group: its direct permissions combined with
+  /// the permissions of every attached role, including implied ones.
+  pub async fn resolve_permissions(
+    &self,
+    direct: Vec<String>,
+    role_ids: &[Uuid],
+  ) -> Result<Vec<String>> {
+    let mut permissions = direct;
+    permissions.extend(self.get_role_permissions(role_ids).await?);
+
+    Ok(permissions::expand(permissions))
+  }
+
+  pub async fn resolve_group_permissions(&self, group_id: Uuid) -> Result<Vec<String>> {
+    let direct = self.get_direct_permissions(group_id).await?;
+    let role_ids: Vec<Uuid> = self
+      .get_group_roles(group_id)
+      .await?
+      .into_iter()
+      .map(|r| r.uuid)
+      .collect();
+
+    self.resolve_permissions(direct, &role_ids).await
+  }
+
+  /// Stores the effective permissions of a group in `group_permission`, which
+  /// is what every permission check reads. Returns whether anything changed.
+  pub async fn sync_group_permissions(&self, group_id: Uuid) -> Result<bool> {
+    let effective = self.resolve_group_permissions(group_id).await?;
+
+    let mut current: Vec<String> = group_permission::Entity::find()
+      .filter(group_permission::Column::GroupId.eq(group_id))
+      .all(self.db)
+      .await?
+      .into_iter()
+      .map(|gp| gp.permission)
+      .collect();
+    current.sort_unstable();
+
+    if current == effective {
+      return Ok(false);
+    }
+
+    let txn = self.db.begin().await?;
+    set_group_permissions(&txn, group_id, effective).await?;
+    txn.commit().await?;
+
+    Ok(true)
+  }
+
+  /// Users that are granted the given permission through any of their groups.
+  pub async fn users_with_permission(&self, permission: &str) -> Result<Vec<Uuid>> {
+    let groups: Vec<Uuid> = group_permission::Entity::find()
+      .filter(group_permission::Column::Permission.eq(permission))
+      .all(self.db)
+      .await?
+      .into_iter()
+      .map(|gp| gp.group_id)
+      .collect();
+
+    let mut users: Vec<Uuid> = group_user::Entity::find()
+      .filter(group_user::Column::GroupId.is_in(groups))
+      .all(self.db)
+      .await?
+      .into_iter()
+      .map(|gu| gu.user_id)
+      .collect();
+    users.sort_unstable();
+    users.dedup();
+
+    Ok(users)
+  }
+
+  pub async fn sync_all_groups(&self) -> Result<()> {
+    let groups = group::Entity::find().all(self.db).await?;
+    for group in groups {
+      self.sync_group_permissions(group.id).await?;
+    }
+
+    Ok(())
+  }
+}
+
+async fn set_role_permissions<C: ConnectionTrait>(
+  db: &C,
+  role_id: Uuid,
+  permissions: Vec<String>,
+) -> Result<()> {
+  role_permission::Entity::delete_many()
+    .filter(role_permission::Column::RoleId.eq(role_id))
+    .exec(db)
+    .await?;
+
+  if permissions.is_empty() {
+    return Ok(());
+  }
+
+  let models = permissions.into_iter().map(|permission| {
+    role_permission::Model {
+      role_id,
+      permission,
+    }
+    .into_active_model()
+  });
+  role_permission::Entity::insert_many(models)
+    .exec(db)
+    .await?;
+
+  Ok(())
+}
+
+async fn set_group_permissions<C: ConnectionTrait>(
+  db: &C,
+  group_id: Uuid,
+  permissions: Vec<String>,
+) -> Result<()> {
+  group_permission::Entity::delete_many()
+    .filter(group_permission::Column::GroupId.eq(group_id))
+    .exec(db)
+    .await?;
+
+  if permissions.is_empty() {
+    return Ok(());
+  }
+
+  let models = permissions.into_iter().map(|permission| {
+    group_permission::Model {
+      group_id,
+      permission,
+    }
+    .into_active_model()
+  });
+  group_permission::Entity::insert_many(models)
+    .exec(db)
+    .await?;
+
+  Ok(())
+}
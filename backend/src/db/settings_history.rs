@@ -0,0 +1,160 @@
+use centaurus::error::Result;
+use chrono::Utc;
+use entity::{settings, settings_revision};
+use sea_orm::{
+  ActiveValue::Set, QueryOrder, QuerySelect, TransactionTrait, prelude::*, sea_query::OnConflict,
+};
+
+/// Revisions of the rows in the `settings` table.
+pub struct SettingsHistoryTable<'db> {
+  db: &'db DatabaseConnection,
+}
+
+impl<'db> SettingsHistoryTable<'db> {
+  pub fn new(db: &'db DatabaseConnection) -> Self {
+    Self { db }
+  }
+
+  /// Content currently stored, `None` if the settings were never saved.
+  pub async fn current(&self, settings_id: i32) -> Result<Option<String>> {
+    current_in(self.db, settings_id).await
+  }
+
+  pub async fn latest(&self, settings_id: i32) -> Result<Option<settings_revision::Model>> {
+    latest_in(self.db, settings_id).await
+  }
+
+  /// Newest first.
+  pub async fn list(&self, settings_id: i32, limit: u64) -> Result<Vec<settings_revision::Model>> {
+    Ok(
+      settings_revision::Entity::find()
+        .filter(settings_revision::Column::SettingsId.eq(settings_id))
+        .order_by_desc(settings_revision::Column::Revision)
+        .limit(limit)
+        .all(self.db)
+        .await?,
+    )
+  }
+
+  pub async fn get(
+    &self,
+    settings_id: i32,
+    revision: i32,
+  ) -> Result<Option<settings_revision::Model>> {
+    Ok(
+      settings_revision::Entity::find_by_id((settings_id, revision))
+        .one(self.db)
+        .await?,
+    )
+  }
+
+  /// Stores the content as the next revision and returns its number.
+  pub async fn append(
+    &self,
+    settings_id: i32,
+    content: String,
+    author: Option<Uuid>,
+    restored_from: Option<i32>,
+  ) -> Result<i32> {
+    let txn = self.db.begin().await?;
+    let revision = append_in(&txn, settings_id, content, author, restored_from).await?;
+    txn.commit().await?;
+
+    Ok(revision)
+  }
+
+  /// Writes the content back into the `settings` table.
+  pub async fn restore(&self, settings_id: i32, content: String) -> Result<()> {
+    restore_in(self.db, settings_id, content).await
+  }
+
+  /// Replaces the content of several settings at once, either all of them are
+  /// stored with a new revision for every changed one or none is.
+  pub async fn replace_all(&self, contents: Vec<(i32, String)>, author: Uuid) -> Result<()> {
+    let txn = self.db.begin().await?;
+    for (settings_id, content) in contents {
+      let latest = match latest_in(&txn, settings_id).await? {
+        Some(latest) => Some(latest.content),
+        // settings saved before revisions existed become the first revision
+        None => match current_in(&txn, settings_id).await? {
+          Some(current) => {
+            append_in(&txn, settings_id, current.clone(), None, None).await?;
+            Some(current)
+          }
+          None => None,
+        },
+      };
+
+      restore_in(&txn, settings_id, content.clone()).await?;
+      if latest.as_ref() != Some(&content) {
+        append_in(&txn, settings_id, content, Some(author), None).await?;
+      }
+    }
+    txn.commit().await?;
+
+    Ok(())
+  }
+}
+
+async fn current_in<C: ConnectionTrait>(db: &C, settings_id: i32) -> Result<Option<String>> {
+  Ok(
+    settings::Entity::find_by_id(settings_id)
+      .one(db)
+      .await?
+      .map(|row| row.content),
+  )
+}
+
+async fn latest_in<C: ConnectionTrait>(
+  db: &C,
+  settings_id: i32,
+) -> Result<Option<settings_revision::Model>> {
+  Ok(
+    settings_revision::Entity::find()
+      .filter(settings_revision::Column::SettingsId.eq(settings_id))
+      .order_by_desc(settings_revision::Column::Revision)
+      .one(db)
+      .await?,
+  )
+}
+
+async fn append_in<C: ConnectionTrait>(
+  db: &C,
+  settings_id: i32,
+  content: String,
+  author: Option<Uuid>,
+  restored_from: Option<i32>,
+) -> Result<i32> {
+  let revision = latest_in(db, settings_id)
+    .await?
+    .map_or(1, |latest| latest.revision + 1);
+
+  settings_revision::ActiveModel {
+    settings_id: Set(settings_id),
+    revision: Set(revision),
+    content: Set(content),
+    author: Set(author),
+    created_at: Set(Utc::now().naive_utc()),
+    restored_from: Set(restored_from),
+  }
+  .insert(db)
+  .await?;
+
+  Ok(revision)
+}
+
+async fn restore_in<C: ConnectionTrait>(db: &C, settings_id: i32, content: String) -> Result<()> {
+  settings::Entity::insert(settings::ActiveModel {
+    id: Set(settings_id),
+    content: Set(content),
+  })
+  .on_conflict(
+    OnConflict::column(settings::Column::Id)
+      .update_column(settings::Column::Content)
+      .to_owned(),
+  )
+  .exec(db)
+  .await?;
+
+  Ok(())
+}
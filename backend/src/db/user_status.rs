@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use centaurus::error::Result;
+use chrono::{NaiveDateTime, Utc};
+use entity::user_status;
+use sea_orm::{ActiveValue::Set, prelude::*, sea_query::OnConflict};
+
+pub struct UserStatusTable<'db> {
+  db: &'db DatabaseConnection,
+}
+
+impl<'db> UserStatusTable<'db> {
+  pub fn new(db: &'db DatabaseConnection) -> Self {
+    Self { db }
+  }
+
+  /// Users without a status row are active.
+  pub async fn get_status(&self, user_id: Uuid) -> Result<Option<user_status::Model>> {
+    Ok(
+      user_status::Entity::find_by_id(user_id)
+        .one(self.db)
+        .await?,
+    )
+  }
+
+  pub async fn is_active(&self, user_id: Uuid) -> Result<bool> {
+    Ok(
+      self
+        .get_status(user_id)
+        .await?
+        .is_none_or(|status| status.disabled_at.is_none() && status.deleted_at.is_none()),
+    )
+  }
+
+  pub async fn list_statuses(&self, users: Vec<Uuid>) -> Result<HashMap<Uuid, user_status::Model>> {
+    Ok(
+      user_status::Entity::find()
+        .filter(user_status::Column::UserId.is_in(users))
+        .all(self.db)
+        .await?
+        .into_iter()
+        .map(|status| (status.user_id, status))
+        .collect(),
+    )
+  }
+
+  pub async fn set_disabled(&self, user_id: Uuid, disabled: bool) -> Result<()> {
+    let disabled_at = disabled.then(|| Utc::now().naive_utc());
+    self
+      .upsert(user_id, user_status::Column::DisabledAt, disabled_at)
+      .await
+  }
+
+  pub async fn set_deleted(&self, user_id: Uuid, deleted: bool) -> Result<()> {
+    let deleted_at = deleted.then(|| Utc::now().naive_utc());
+    self
+      .upsert(user_id, user_status::Column::DeletedAt, deleted_at)
+      .await
+  }
+
+  async fn upsert(
+    &self,
+    user_id: Uuid,
+    column: user_status::Column,
+    value: Option<NaiveDateTime>,
+  ) -> Result<()> {
+    let mut model = user_status::ActiveModel {
+      user_id: Set(user_id),
+      disabled_at: Set(None),
+      deleted_at: Set(None),
+    };
+    match column {
+      user_status::Column::DisabledAt => model.disabled_at = Set(value),
+      user_status::Column::DeletedAt => model.deleted_at = Set(value),
+      user_status::Column::UserId => {}
+    }
+
+    user_status::Entity::insert(model)
+      .on_conflict(
+        OnConflict::column(user_status::Column::UserId)
+          .update_column(column)
+          .to_owned(),
+      )
+      .exec(self.db)
+      .await?;
+
+    Ok(())
+  }
+
+  /// Users soft deleted before the given time, due to be purged.
+  pub async fn deleted_before(&self, before: NaiveDateTime) -> Result<Vec<Uuid>> {
+    Ok(
+      user_status::Entity::find()
+        .filter(user_status::Column::DeletedAt.lt(before))
+        .all(self.db)
+        .await?
+        .into_iter()
+        .map(|status| status.user_id)
+        .collect(),
+    )
+  }
+}
@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+
+use aide::{
+  OperationIo,
+  axum::{
+    ApiRouter,
+    routing::{delete_with, get_with, post_with, put_with},
+  },
+};
+use axum::{
+  Json,
+  extract::{FromRequestParts, Path},
+};
+use centaurus::{
+  backend::auth::{jwt_auth::JwtAuth, permission::Permission},
+  bail,
+  db::init::Connection,
+  error::{ErrorReport, Result},
+  permission,
+};
+use http::request::Parts;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+  audit::{AuditContext, AuditEntry},
+  db::{DBTrait, repository::UserRepository},
+  flag::value::{FlagDefinition, FlagValue},
+  organization::OrgAuth,
+  permissions::PermissionInfo,
+  utils::{UpdateMessage, Updater},
+};
+
+pub mod value;
+
+permission!(FlagEdit, "flag:edit");
+
+pub fn router() -> ApiRouter {
+  ApiRouter::new()
+    .api_route("/", get_with(list_flags, |op| op.id("listFlags")))
+    .api_route("/", post_with(create_flag, |op| op.id("createFlag")))
+    .api_route("/", put_with(edit_flag, |op| op.id("editFlag")))
+    .api_route("/", delete_with(delete_flag, |op| op.id("deleteFlag")))
+    .api_route(
+      "/evaluated",
+      get_with(evaluated_flags, |op| op.id("evaluatedFlags")),
+    )
+    .api_route(
+      "/evaluated/{key}",
+      get_with(check_flag, |op| op.id("checkFlag")),
+    )
+}
+
+pub fn permissions() -> Vec<PermissionInfo> {
+  vec![PermissionInfo::new(
+    FlagEdit::name(),
+    "Manage feature flags and their rollout",
+    "Settings",
+  )]
+}
+
+/// Every flag evaluated for the user.
+pub async fn evaluate(db: &Connection, user: Uuid) -> Result<HashMap<String, FlagValue>> {
+  let groups = db.user_groups(user).await?;
+
+  Ok(
+    db.flag()
+      .list()
+      .await?
+      .iter()
+      .map(|(flag, targets)| {
+        let definition = FlagDefinition::from_model(flag, targets);
+        (flag.key.clone(), definition.evaluate(user, &groups))
+      })
+      .collect(),
+  )
+}
+
+/// Flags evaluated for the signed in user, for handlers which behave
+/// differently depending on a flag. Unknown flags are off.
+#[derive(OperationIo)]
+pub struct Flags {
+  values: HashMap<String, FlagValue>,
+}
+
+impl Flags {
+  /// Whether a boolean flag is on.
+  pub fn enabled(&self, key: &str) -> bool {
+    self.values.get(key) == Some(&FlagValue::Boolean(true))
+  }
+
+  /// The variant of a multivariate flag.
+  pub fn variant(&self, key: &str) -> Option<&str> {
+    match self.values.get(key) {
+      Some(FlagValue::Variant(variant)) => Some(variant),
+      _ => None,
+    }
+  }
+}
+
+impl<S: Sync> FromRequestParts<S> for Flags {
+  type Rejection = ErrorReport;
+
+  async fn from_request_parts(
+    parts: &mut Parts,
+    state: &S,
+  ) -> std::result::Result<Self, Self::Rejection> {
+    let auth = <JwtAuth as FromRequestParts<S>>::from_request_parts(parts, state).await?;
+    let Some(db) = parts.extensions.get::<Connection>().cloned() else {
+      bail!("Database connection missing");
+    };
+
+    Ok(Self {
+      values: evaluate(&db, auth.user_id).await?,
+    })
+  }
+}
+
+/// Flags of the caller, refetched by the frontend on flag updates.
+async fn evaluated_flags(flags: Flags) -> Json<HashMap<String, FlagValue>> {
+  Json(flags.values)
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct FlagPath {
+  key: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct FlagCheck {
+  /// Whether a boolean flag is on, `false` for multivariate flags.
+  enabled: bool,
+  /// The served variant of a multivariate flag.
+  variant: Option<String>,
+}
+
+/// A single flag of the caller, unknown flags are off.
+async fn check_flag(flags: Flags, Path(path): Path<FlagPath>) -> Json<FlagCheck> {
+  Json(FlagCheck {
+    enabled: flags.enabled(&path.key),
+    variant: flags.variant(&path.key).map(str::to_string),
+  })
+}
+
+#[derive(Serialize, JsonSchema)]
+struct FlagInfo {
+  uuid: Uuid,
+  #[serde(flatten)]
+  definition: FlagDefinition,
+}
+
+async fn list_flags(_auth: OrgAuth<FlagEdit>, db: Connection) -> Result<Json<Vec<FlagInfo>>> {
+  let flags = db.flag().list().await?;
+
+  Ok(Json(
+    flags
+      .iter()
+      .map(|(flag, targets)| FlagInfo {
+        uuid: flag.id,
+        definition: FlagDefinition::from_model(flag, targets),
+      })
+      .collect(),
+  ))
+}
+
+/// Flags apply to users of every organization, so only super admins change
+/// them.
+fn ensure_super_admin(auth: &OrgAuth<FlagEdit>) -> Result<()> {
+  if !auth.super_admin {
+    bail!(FORBIDDEN, "Only super admins can change feature flags");
+  }
+  Ok(())
+}
+
+async fn validate(db: &Connection, definition: &FlagDefinition, uuid: Uuid) -> Result<()> {
+  if let Err(reason) = definition.validate() {
+    bail!(BAD_REQUEST, "{}", reason);
+  }
+  if let Some(other) = db.flag().find_by_key(&definition.key).await?
+    && other.id != uuid
+  {
+    bail!(CONFLICT, "A flag with this key already exists");
+  }
+  let groups: Vec<Uuid> = definition.groups.iter().map(|t| t.group).collect();
+  if let Some(missing) = db.flag().find_missing_group(&groups).await? {
+    bail!(BAD_REQUEST, "Group {} does not exist", missing);
+  }
+  Ok(())
+}
+
+#[derive(Serialize, JsonSchema)]
+struct FlagCreateResponse {
+  uuid: Uuid,
+}
+
+async fn create_flag(
+  auth: OrgAuth<FlagEdit>,
+  db: Connection,
+  updater: Updater,
+  audit: AuditContext,
+  Json(definition): Json<FlagDefinition>,
+) -> Result<Json<FlagCreateResponse>> {
+  ensure_super_admin(&auth)?;
+  let uuid = Uuid::now_v7();
+  validate(&db, &definition, uuid).await?;
+
+  let (flag, targets) = definition.clone().into_model(uuid);
+  db.flag().create(flag, targets).await?;
+
+  audit
+    .record(
+      &db,
+      &updater,
+      AuditEntry::new("flag.create")
+        .actor(auth.user_id)
+        .target("flag", uuid)
+        .after(&definition),
+    )
+    .await;
+  updater.broadcast(UpdateMessage::FeatureFlag { uuid }).await;
+
+  Ok(Json(FlagCreateResponse { uuid }))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct EditFlagRequest {
+  uuid: Uuid,
+  #[serde(flatten)]
+  definition: FlagDefinition,
+}
+
+async fn edit_flag(
+  auth: OrgAuth<FlagEdit>,
+  db: Connection,
+  updater: Updater,
+  audit: AuditContext,
+  Json(data): Json<EditFlagRequest>,
+) -> Result<()> {
+  ensure_super_admin(&auth)?;
+  let Some((existing, targets)) = db.flag().get(data.uuid).await? else {
+    bail!(NOT_FOUND, "Flag not found");
+  };
+  validate(&db, &data.definition, data.uuid).await?;
+
+  let (flag, new_targets) = data.definition.clone().into_model(data.uuid);
+  db.flag().update(flag, new_targets).await?;
+
+  audit
+    .record(
+      &db,
+      &updater,
+      AuditEntry::new("flag.edit")
+        .actor(auth.user_id)
+        .target("flag", data.uuid)
+        .before(&FlagDefinition::from_model(&existing, &targets))
+        .after(&data.definition),
+    )
+    .await;
+  updater
+    .broadcast(UpdateMessage::FeatureFlag { uuid: data.uuid })
+    .await;
+
+  Ok(())
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct DeleteFlagRequest {
+  uuid: Uuid,
+}
+
+async fn delete_flag(
+  auth: OrgAuth<FlagEdit>,
+  db: Connection,
+  updater: Updater,
+  audit: AuditContext,
+  Json(DeleteFlagRequest { uuid }): Json<DeleteFlagRequest>,
+) -> Result<()> {
+  ensure_super_admin(&auth)?;
+  let Some((existing, targets)) = db.flag().get(uuid).await? else {
+    bail!(NOT_FOUND, "Flag not found");
+  };
+  db.flag().delete(uuid).await?;
+
+  audit
+    .record(
+      &db,
+      &updater,
+      AuditEntry::new("flag.delete")
+        .actor(auth.user_id)
+        .target("flag", uuid)
+        .before(&FlagDefinition::from_model(&existing, &targets)),
+    )
+    .await;
+  updater.broadcast(UpdateMessage::FeatureFlag { uuid }).await;
+
+  Ok(())
+}
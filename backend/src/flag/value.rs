@@ -0,0 +1,378 @@
+use std::{collections::HashSet, sync::LazyLock};
+
+use entity::{feature_flag, feature_flag_group};
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+const MAX_KEY_LENGTH: usize = 64;
+static KEY_PATTERN: LazyLock<Regex> =
+  LazyLock::new(|| Regex::new("^[a-z0-9][a-z0-9_.-]*$").expect("valid key pattern"));
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum FlagKind {
+  /// Evaluates to `true` or `false`.
+  Boolean,
+  /// Evaluates to the name of one of its variants.
+  Multivariate,
+}
+
+impl FlagKind {
+  pub fn as_str(self) -> &'static str {
+    match self {
+      Self::Boolean => "boolean",
+      Self::Multivariate => "multivariate",
+    }
+  }
+
+  pub fn parse(kind: &str) -> Option<Self> {
+    match kind {
+      "boolean" => Some(Self::Boolean),
+      "multivariate" => Some(Self::Multivariate),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct FlagVariant {
+  pub name: String,
+  /// Share of the rollout relative to the other variants.
+  pub weight: u32,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct GroupTarget {
+  pub group: Uuid,
+  /// Variant served to the members, only for multivariate flags.
+  #[serde(default)]
+  pub variant: Option<String>,
+}
+
+/// A feature flag as exchanged with the api.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct FlagDefinition {
+  pub key: String,
+  #[serde(default)]
+  pub description: String,
+  pub kind: FlagKind,
+  /// Disabled flags are off for everyone, regardless of their targeting.
+  pub enabled: bool,
+  /// Variants of multivariate flags, the first one is served to everyone
+  /// outside the rollout.
+  #[serde(default)]
+  pub variants: Vec<FlagVariant>,
+  /// Percentage of users the flag is rolled out to. Users keep their place in
+  /// the rollout, so raising it only adds users.
+  #[serde(default)]
+  pub rollout: u8,
+  /// Members of these groups get the flag regardless of the rollout.
+  #[serde(default)]
+  pub groups: Vec<GroupTarget>,
+}
+
+/// Value of a flag for a single user.
+#[derive(Serialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+#[serde(untagged)]
+pub enum FlagValue {
+  Boolean(bool),
+  Variant(String),
+}
+
+/// Stable number in `0..buckets` for the user, different for every flag.
+fn bucket(key: &str, purpose: &str, user: Uuid, buckets: u64) -> u64 {
+  let hash = Sha256::digest(format!("{key}:{purpose}:{user}"));
+  let mut bytes = [0; 8];
+  bytes.copy_from_slice(&hash[..8]);
+  u64::from_be_bytes(bytes) % buckets
+}
+
+impl FlagDefinition {
+  pub fn validate(&self) -> Result<(), String> {
+    if self.key.len() > MAX_KEY_LENGTH || !KEY_PATTERN.is_match(&self.key) {
+      return Err(format!(
+        "The key must be at most {MAX_KEY_LENGTH} lowercase letters, digits, `_`, `.` or `-`"
+      ));
+    }
+    if self.rollout > 100 {
+      return Err("The rollout must be a percentage".into());
+    }
+
+    let mut groups = HashSet::new();
+    if let Some(target) = self.groups.iter().find(|t| !groups.insert(t.group)) {
+      return Err(format!("Group {} is targeted twice", target.group));
+    }
+
+    match self.kind {
+      FlagKind::Boolean => {
+        if !self.variants.is_empty() {
+          return Err("Boolean flags have no variants".into());
+        }
+        if self.groups.iter().any(|t| t.variant.is_some()) {
+          return Err("Boolean flags cannot serve variants to groups".into());
+        }
+      }
+      FlagKind::Multivariate => {
+        if self.variants.len() < 2 {
+          return Err("Multivariate flags need at least two variants".into());
+        }
+        let mut names = HashSet::new();
+        for variant in &self.variants {
+          if variant.name.trim().is_empty() || !names.insert(variant.name.as_str()) {
+            return Err("Variant names must be unique and not empty".into());
+          }
+        }
+        if self.variants.iter().all(|v| v.weight == 0) {
+          return Err("At least one variant needs a weight".into());
+        }
+        for target in &self.groups {
+          match &target.variant {
+            Some(variant) if names.contains(variant.as_str()) => (),
+            _ => return Err(format!("Group {} needs one of the variants", target.group)),
+          }
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// The value for users outside the targeting.
+  fn off(&self) -> FlagValue {
+    match self.kind {
+      FlagKind::Boolean => FlagValue::Boolean(false),
+      FlagKind::Multivariate => FlagValue::Variant(
+        self
+          .variants
+          .first()
+          .map(|v| v.name.clone())
+          .unwrap_or_default(),
+      ),
+    }
+  }
+
+  fn weighted_variant(&self, user: Uuid) -> FlagValue {
+    let total: u64 = self.variants.iter().map(|v| v.weight as u64).sum();
+    if total == 0 {
+      return self.off();
+    }
+    let mut point = bucket(&self.key, "variant", user, total);
+    for variant in &self.variants {
+      if point < variant.weight as u64 {
+        return FlagValue::Variant(variant.name.clone());
+      }
+      point -= variant.weight as u64;
+    }
+    self.off()
+  }
+
+  pub fn evaluate(&self, user: Uuid, user_groups: &[Uuid]) -> FlagValue {
+    if !self.enabled {
+      return self.off();
+    }
+
+    if let Some(target) = self.groups.iter().find(|t| user_groups.contains(&t.group)) {
+      return match (&self.kind, &target.variant) {
+        (FlagKind::Multivariate, Some(variant)) => FlagValue::Variant(variant.clone()),
+        (FlagKind::Multivariate, None) => self.off(),
+        (FlagKind::Boolean, _) => FlagValue::Boolean(true),
+      };
+    }
+
+    if bucket(&self.key, "rollout", user, 100) < self.rollout as u64 {
+      return match self.kind {
+        FlagKind::Boolean => FlagValue::Boolean(true),
+        FlagKind::Multivariate => self.weighted_variant(user),
+      };
+    }
+
+    self.off()
+  }
+
+  pub fn from_model(flag: &feature_flag::Model, targets: &[feature_flag_group::Model]) -> Self {
+    Self {
+      key: flag.key.clone(),
+      description: flag.description.clone(),
+      kind: FlagKind::parse(&flag.kind).unwrap_or(FlagKind::Boolean),
+      enabled: flag.enabled,
+      variants: flag
+        .variants
+        .clone()
+        .and_then(|variants| serde_json::from_value(variants).ok())
+        .unwrap_or_default(),
+      rollout: flag.rollout.clamp(0, 100) as u8,
+      groups: targets
+        .iter()
+        .map(|target| GroupTarget {
+          group: target.group_id,
+          variant: target.variant.clone(),
+        })
+        .collect(),
+    }
+  }
+
+  pub fn into_model(self, id: Uuid) -> (feature_flag::Model, Vec<feature_flag_group::Model>) {
+    let targets = self
+      .groups
+      .into_iter()
+      .map(|target| feature_flag_group::Model {
+        flag_id: id,
+        group_id: target.group,
+        variant: target.variant,
+      })
+      .collect();
+    let flag = feature_flag::Model {
+      id,
+      key: self.key,
+      description: self.description,
+      kind: self.kind.as_str().to_string(),
+      enabled: self.enabled,
+      variants: match self.kind {
+        FlagKind::Boolean => None,
+        FlagKind::Multivariate => serde_json::to_value(self.variants).ok(),
+      },
+      rollout: self.rollout as i32,
+    };
+
+    (flag, targets)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn boolean(rollout: u8) -> FlagDefinition {
+    FlagDefinition {
+      key: "new_editor".into(),
+      description: String::new(),
+      kind: FlagKind::Boolean,
+      enabled: true,
+      variants: Vec::new(),
+      rollout,
+      groups: Vec::new(),
+    }
+  }
+
+  fn users(count: usize) -> Vec<Uuid> {
+    (0..count).map(|_| Uuid::new_v4()).collect()
+  }
+
+  #[test]
+  fn rollout_is_stable_and_grows_monotonically() {
+    let users = users(2000);
+    let at = |rollout| -> Vec<bool> {
+      users
+        .iter()
+        .map(|user| boolean(rollout).evaluate(*user, &[]) == FlagValue::Boolean(true))
+        .collect()
+    };
+
+    let quarter = at(25);
+    assert_eq!(quarter, at(25));
+    let share = quarter.iter().filter(|on| **on).count();
+    assert!((400..600).contains(&share), "{share} of 2000 users");
+
+    let half = at(50);
+    assert!(quarter.iter().zip(&half).all(|(q, h)| !q || *h));
+    assert!(at(0).iter().all(|on| !on));
+    assert!(at(100).iter().all(|on| *on));
+  }
+
+  #[test]
+  fn groups_are_targeted_and_disabled_flags_are_off() {
+    let group = Uuid::new_v4();
+    let user = Uuid::new_v4();
+    let mut flag = boolean(0);
+    flag.groups.push(GroupTarget {
+      group,
+      variant: None,
+    });
+
+    assert_eq!(flag.evaluate(user, &[group]), FlagValue::Boolean(true));
+    assert_eq!(flag.evaluate(user, &[]), FlagValue::Boolean(false));
+    flag.enabled = false;
+    assert_eq!(flag.evaluate(user, &[group]), FlagValue::Boolean(false));
+  }
+
+  #[test]
+  fn multivariate_flags_split_by_weight() {
+    let flag = FlagDefinition {
+      kind: FlagKind::Multivariate,
+      variants: vec![
+        FlagVariant {
+          name: "control".into(),
+          weight: 0,
+        },
+        FlagVariant {
+          name: "blue".into(),
+          weight: 1,
+        },
+        FlagVariant {
+          name: "green".into(),
+          weight: 3,
+        },
+      ],
+      ..boolean(100)
+    };
+    assert_eq!(flag.validate(), Ok(()));
+
+    let values: Vec<FlagValue> = users(2000)
+      .into_iter()
+      .map(|user| flag.evaluate(user, &[]))
+      .collect();
+    let count = |name: &str| {
+      values
+        .iter()
+        .filter(|v| **v == FlagValue::Variant(name.into()))
+        .count()
+    };
+    assert_eq!(count("control"), 0);
+    assert!((400..600).contains(&count("blue")));
+    assert!((1400..1600).contains(&count("green")));
+
+    let outside = FlagDefinition { rollout: 0, ..flag };
+    assert_eq!(
+      outside.evaluate(Uuid::new_v4(), &[]),
+      FlagValue::Variant("control".into())
+    );
+  }
+
+  #[test]
+  fn invalid_definitions_are_rejected() {
+    let mut flag = boolean(10);
+    flag.key = "New Editor".into();
+    assert!(flag.validate().is_err());
+
+    let mut flag = boolean(101);
+    assert!(flag.validate().is_err());
+    flag.rollout = 100;
+    flag.variants.push(FlagVariant {
+      name: "a".into(),
+      weight: 1,
+    });
+    assert!(flag.validate().is_err());
+
+    let flag = FlagDefinition {
+      kind: FlagKind::Multivariate,
+      variants: vec![
+        FlagVariant {
+          name: "a".into(),
+          weight: 1,
+        },
+        FlagVariant {
+          name: "b".into(),
+          weight: 1,
+        },
+      ],
+      groups: vec![GroupTarget {
+        group: Uuid::new_v4(),
+        variant: Some("c".into()),
+      }],
+      ..boolean(10)
+    };
+    assert!(flag.validate().is_err());
+  }
+}
@@ -0,0 +1,509 @@
+use aide::axum::{
+  ApiRouter,
+  routing::{delete_with, get_with, post_with, put_with},
+};
+use axum::{
+  Json,
+  extract::{Path, Query},
+};
+use centaurus::{
+  backend::auth::permission::{GroupEdit, GroupView, Permission, UserView},
+  bail,
+  db::{
+    init::Connection,
+    tables::{
+      ConnectionExt,
+      group::{GroupInfo, SimpleUserInfo},
+    },
+  },
+  error::Result,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+  audit::{AuditContext, AuditEntry},
+  db::{
+    DBTrait,
+    directory::{GroupSort, UserFilter, UserSort},
+    repository::GroupRepository,
+    role::SimpleRoleInfo,
+  },
+  organization::OrgAuth,
+  pagination::{Page, PageQuery, PageRequest},
+  permissions::{self, PermissionInfo},
+  utils::{UpdateMessage, Updater},
+};
+
+pub fn router() -> ApiRouter {
+  ApiRouter::new()
+    .api_route("/", get_with(list_groups, |op| op.id("listGroups")))
+    .api_route("/", post_with(create_group, |op| op.id("createGroup")))
+    .api_route("/", delete_with(delete_group, |op| op.id("deleteGroup")))
+    .api_route("/", put_with(edit_group, |op| op.id("editGroup")))
+    .api_route("/{uuid}", get_with(group_info, |op| op.id("groupInfo")))
+    .api_route(
+      "/users",
+      get_with(list_users_simple, |op| op.id("listUsersSimple")),
+    )
+    .api_route(
+      "/roles",
+      get_with(list_roles_simple, |op| op.id("listRolesSimple")),
+    )
+    .api_route(
+      "/permissions",
+      get_with(list_permissions, |op| op.id("listPermissions")),
+    )
+}
+
+pub fn permissions() -> Vec<PermissionInfo> {
+  vec![
+    PermissionInfo::new(GroupView::name(), "View groups and their members", "Groups"),
+    PermissionInfo::new(
+      GroupEdit::name(),
+      "Create, edit and delete groups",
+      "Groups",
+    )
+    .implies(&[GroupView::name()]),
+  ]
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct GroupListQuery {
+  #[serde(default)]
+  sort: GroupSort,
+  /// Matches the group name, ignoring case.
+  search: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct ListGroupResponse {
+  #[serde(flatten)]
+  groups: Page<GroupInfo>,
+  admin_group: Option<Uuid>,
+}
+
+async fn list_groups(
+  auth: OrgAuth<GroupView>,
+  db: Connection,
+  Query(query): Query<GroupListQuery>,
+  Query(page): Query<PageQuery>,
+) -> Result<Json<ListGroupResponse>> {
+  let page = PageRequest::new(page, query.sort.key())?;
+  let groups = db
+    .directory()
+    .list_groups(auth.organization, query.search, query.sort, &page)
+    .await?;
+  let admin_group = db.setup().get_admin_group_id().await?;
+
+  Ok(Json(ListGroupResponse {
+    groups,
+    admin_group,
+  }))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct UserSimpleQuery {
+  #[serde(default)]
+  sort: UserSort,
+  /// Matches name, email or the attribute values the caller may see, ignoring
+  /// case.
+  search: Option<String>,
+  /// Only members of this group.
+  group: Option<Uuid>,
+}
+
+/// Users to pick group members from, deleted users are left out.
+async fn list_users_simple(
+  auth: OrgAuth<GroupView>,
+  db: Connection,
+  Query(query): Query<UserSimpleQuery>,
+  Query(page): Query<PageQuery>,
+) -> Result<Json<Page<SimpleUserInfo>>> {
+  let page = PageRequest::new(page, query.sort.key())?;
+  let all_attributes = auth
+    .permissions(&db)
+    .await?
+    .iter()
+    .any(|permission| permission == UserView::name());
+  let filter = UserFilter {
+    organization: Some(auth.organization),
+    search: query.search,
+    all_attributes,
+    group: query.group,
+    ..Default::default()
+  };
+  let users = db.directory().list_users(filter, query.sort, &page).await?;
+
+  Ok(Json(users.map(|user| SimpleUserInfo {
+    id: user.id,
+    name: user.name,
+  })))
+}
+
+async fn list_permissions(_auth: OrgAuth<GroupView>) -> Json<Vec<PermissionInfo>> {
+  Json(permissions::registry())
+}
+
+async fn list_roles_simple(
+  _auth: OrgAuth<GroupView>,
+  db: Connection,
+) -> Result<Json<Vec<SimpleRoleInfo>>> {
+  Ok(Json(db.role().list_roles_simple().await?))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct GroupViewPath {
+  uuid: Uuid,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct GroupDetails {
+  id: Uuid,
+  name: String,
+  /// Effective permissions, including those granted through roles.
+  permissions: Vec<String>,
+  /// Permissions assigned to the group itself.
+  direct_permissions: Vec<String>,
+  roles: Vec<SimpleRoleInfo>,
+  users: Vec<SimpleUserInfo>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct GroupDetailsResponse {
+  group: GroupDetails,
+  admin_group: Uuid,
+}
+
+async fn group_info(
+  auth: OrgAuth<GroupView>,
+  db: Connection,
+  Path(path): Path<GroupViewPath>,
+) -> Result<Json<GroupDetailsResponse>> {
+  auth.ensure_group(&db, path.uuid).await?;
+  let Some(info) = db.group().group_info(path.uuid).await? else {
+    bail!(NOT_FOUND, "Group not found");
+  };
+
+  let Some(admin_group) = db.setup().get_admin_group_id().await? else {
+    bail!(INTERNAL_SERVER_ERROR, "Admin group not configured");
+  };
+
+  let mut direct_permissions = db.role().get_direct_permissions(path.uuid).await?;
+  direct_permissions.sort_unstable();
+  let roles = db.role().get_group_roles(path.uuid).await?;
+
+  Ok(Json(GroupDetailsResponse {
+    group: GroupDetails {
+      id: info.id,
+      name: info.name,
+      permissions: info.permissions,
+      direct_permissions,
+      roles,
+      users: info.users,
+    },
+    admin_group,
+  }))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct CreateGroupRequest {
+  name: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct GroupCreateResponse {
+  uuid: Uuid,
+}
+
+/// Creates a group in the current organization, names are unique within it.
+async fn create_group(
+  auth: OrgAuth<GroupEdit>,
+  db: Connection,
+  updater: Updater,
+  Json(data): Json<CreateGroupRequest>,
+) -> Result<Json<GroupCreateResponse>> {
+  if data.name.trim().is_empty() {
+    bail!(BAD_REQUEST, "Group name cannot be empty");
+  }
+  if db
+    .organization()
+    .find_group_by_name(auth.organization, &data.name)
+    .await?
+    .is_some()
+  {
+    bail!(CONFLICT, "A group with this name already exists");
+  }
+
+  let uuid = db
+    .organization()
+    .create_group(auth.organization, data.name)
+    .await?;
+  updater.broadcast(UpdateMessage::Group { uuid }).await;
+
+  Ok(Json(GroupCreateResponse { uuid }))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct DeleteGroupRequest {
+  uuid: Uuid,
+}
+
+async fn delete_group(
+  auth: OrgAuth<GroupEdit>,
+  db: Connection,
+  updater: Updater,
+  Json(DeleteGroupRequest { uuid }): Json<DeleteGroupRequest>,
+) -> Result<()> {
+  auth.ensure_group(&db, uuid).await?;
+  let admin_group = db.setup().get_admin_group_id().await?;
+  let users = remove_group(&db, admin_group, uuid).await?;
+
+  updater.broadcast(UpdateMessage::Group { uuid }).await;
+  for user_id in users {
+    updater
+      .send_to(user_id, UpdateMessage::UserPermissions)
+      .await;
+  }
+
+  Ok(())
+}
+
+/// Deletes the group and returns its former members, whose permissions
+/// changed.
+async fn remove_group(
+  groups: &dyn GroupRepository,
+  admin_group: Option<Uuid>,
+  uuid: Uuid,
+) -> Result<Vec<Uuid>> {
+  if admin_group == Some(uuid) {
+    bail!(BAD_REQUEST, "Cannot delete the admin group");
+  }
+
+  let users = groups.group_members(uuid).await?;
+  groups.delete_group(uuid).await?;
+  Ok(users)
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct EditGroupRequest {
+  uuid: Uuid,
+  name: String,
+  permissions: Vec<String>,
+  /// Roles attached to the group, left unchanged when omitted.
+  #[serde(default)]
+  roles: Option<Vec<Uuid>>,
+  users: Vec<Uuid>,
+}
+
+/// Group as recorded in the audit log.
+#[derive(Serialize)]
+struct AuditedGroup {
+  name: String,
+  permissions: Vec<String>,
+  roles: Vec<Uuid>,
+  effective_permissions: Vec<String>,
+  users: Vec<Uuid>,
+}
+
+async fn edit_group(
+  auth: OrgAuth<GroupEdit>,
+  db: Connection,
+  updater: Updater,
+  audit: AuditContext,
+  Json(data): Json<EditGroupRequest>,
+) -> Result<()> {
+  auth.ensure_group(&db, data.uuid).await?;
+  if data.name.trim().is_empty() {
+    bail!(BAD_REQUEST, "Group name cannot be empty");
+  }
+
+  if let Some(unknown) = data.permissions.iter().find(|p| !permissions::is_known(p)) {
+    bail!(BAD_REQUEST, "Unknown permission {}", unknown);
+  }
+  let mut direct_permissions = data.permissions;
+  direct_permissions.sort_unstable();
+  direct_permissions.dedup();
+
+  let roles = match data.roles {
+    Some(mut roles) => {
+      roles.sort_unstable();
+      roles.dedup();
+      if let Some(missing) = db.role().find_missing_role(&roles).await? {
+        bail!(NOT_FOUND, "Role {} not found", missing);
+      }
+      roles
+    }
+    None => db
+      .role()
+      .get_group_roles(data.uuid)
+      .await?
+      .into_iter()
+      .map(|r| r.uuid)
+      .collect(),
+  };
+
+  let new_permissions = db
+    .role()
+    .resolve_permissions(direct_permissions.clone(), &roles)
+    .await?;
+
+  if let Some(existing_group) = db
+    .organization()
+    .find_group_by_name(auth.organization, &data.name)
+    .await?
+    && existing_group != data.uuid
+  {
+    bail!(CONFLICT, "A group with this name already exists");
+  }
+  if let Some(outsider) = db
+    .organization()
+    .find_non_member(auth.organization, &data.users)
+    .await?
+  {
+    bail!(NOT_FOUND, "User {} not found", outsider);
+  }
+
+  let Some(group) = db.group().group_info(data.uuid).await? else {
+    bail!(NOT_FOUND, "Group not found");
+  };
+
+  let user_permissions = auth.permissions(&db).await?;
+  if group
+    .permissions
+    .iter()
+    .any(|perm| !user_permissions.contains(perm))
+  {
+    bail!(
+      FORBIDDEN,
+      "Cannot edit a group with permissions you do not have"
+    );
+  }
+  if new_permissions
+    .iter()
+    .any(|perm| !user_permissions.contains(perm))
+  {
+    bail!(
+      FORBIDDEN,
+      "Cannot assign permissions you do not have to a group"
+    );
+  }
+
+  if let Some(admin_group) = db.setup().get_admin_group_id().await?
+    && admin_group == data.uuid
+  {
+    if group
+      .permissions
+      .iter()
+      .any(|p| !new_permissions.contains(p))
+    {
+      bail!(BAD_REQUEST, "Cannot change permissions of the admin group");
+    } else if data.users.is_empty() {
+      bail!(NOT_ACCEPTABLE, "Admin group must have at least one user");
+    }
+  }
+
+  let old_users = db.group_members(data.uuid).await?;
+  let before = AuditedGroup {
+    name: group.name.clone(),
+    permissions: db.role().get_direct_permissions(data.uuid).await?,
+    roles: db
+      .role()
+      .get_group_roles(data.uuid)
+      .await?
+      .into_iter()
+      .map(|r| r.uuid)
+      .collect(),
+    effective_permissions: group.permissions.clone(),
+    users: old_users.clone(),
+  };
+  let after = AuditedGroup {
+    name: data.name.clone(),
+    permissions: direct_permissions.clone(),
+    roles: roles.clone(),
+    effective_permissions: new_permissions.clone(),
+    users: data.users.clone(),
+  };
+
+  db.role()
+    .edit_group(
+      data.uuid,
+      data.name,
+      data.users.clone(),
+      direct_permissions,
+      roles,
+      new_permissions.clone(),
+    )
+    .await?;
+
+  audit
+    .record(
+      &db,
+      &updater,
+      AuditEntry::new("group.edit")
+        .actor(auth.user_id)
+        .target("group", data.uuid)
+        .before(&before)
+        .after(&after),
+    )
+    .await;
+
+  updater
+    .broadcast(UpdateMessage::Group { uuid: data.uuid })
+    .await;
+
+  let permissions_changed = group.permissions.len() != new_permissions.len()
+    || group
+      .permissions
+      .iter()
+      .any(|perm| !new_permissions.contains(perm));
+
+  let mut users_to_notify = old_users.clone();
+  users_to_notify.extend(data.users.clone());
+  users_to_notify.sort_unstable();
+  users_to_notify.dedup();
+
+  // Only notify users that where added or removed
+  if !permissions_changed {
+    users_to_notify.retain(|user_id| old_users.contains(user_id) != data.users.contains(user_id));
+  }
+
+  for user_id in users_to_notify {
+    updater
+      .send_to(user_id, UpdateMessage::UserPermissions)
+      .await;
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use http::StatusCode;
+
+  use super::*;
+  use crate::db::repository::memory::MemoryRepository;
+
+  #[tokio::test]
+  async fn removed_groups_return_their_members() {
+    let repo = MemoryRepository::default();
+    let (admin_group, group, user) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+    repo.insert_group(admin_group, &[user]);
+    repo.insert_group(group, &[user]);
+
+    let err = remove_group(&repo, Some(admin_group), admin_group)
+      .await
+      .unwrap_err();
+    assert_eq!(err.status, StatusCode::BAD_REQUEST);
+    assert!(repo.is_member(admin_group, user).await.unwrap());
+
+    let users = remove_group(&repo, Some(admin_group), group).await.unwrap();
+    assert_eq!(users, [user]);
+    assert!(!repo.is_member(group, user).await.unwrap());
+    let err = remove_group(&repo, Some(admin_group), group)
+      .await
+      .unwrap_err();
+    assert_eq!(err.status, StatusCode::NOT_FOUND);
+  }
+}
@@ -0,0 +1,290 @@
+use std::collections::{HashMap, HashSet};
+
+use aide::{
+  OperationIo,
+  axum::{
+    ApiRouter,
+    routing::{delete_with, post_with},
+  },
+};
+use axum::{
+  Json,
+  extract::{FromRequestParts, OriginalUri},
+};
+use axum_extra::extract::CookieJar;
+use centaurus::{
+  backend::auth::{
+    jwt::jwt_from_request,
+    jwt_state::{JWT_COOKIE_NAME, JwtClaims, JwtInvalidState, JwtState},
+    permission::Permission,
+  },
+  bail,
+  db::{init::Connection, tables::ConnectionExt},
+  error::Result,
+  permission,
+};
+use chrono::{DateTime, Duration, Utc};
+use http::{Method, request::Parts};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+  audit::{AuditContext, AuditEntry},
+  db::DBTrait,
+  organization::{OrgAuth, scoped_permissions},
+  permissions::PermissionInfo,
+  utils::{Updater, api_path},
+};
+
+permission!(UserImpersonate, "user:impersonate");
+
+/// Impersonation sessions end on their own after this time.
+const IMPERSONATION_MINUTES: i64 = 30;
+const IMPERSONATION_CLAIM: &str = "impersonation";
+
+pub fn router() -> ApiRouter {
+  ApiRouter::new()
+    .api_route(
+      "/",
+      post_with(start_impersonation, |op| op.id("startImpersonation")),
+    )
+    .api_route(
+      "/",
+      delete_with(end_impersonation, |op| op.id("endImpersonation")),
+    )
+}
+
+pub fn permissions() -> Vec<PermissionInfo> {
+  vec![PermissionInfo::new(
+    UserImpersonate::name(),
+    "Sign in as another user to see what they see",
+    "Users",
+  )]
+}
+
+/// Marks a session token as issued for an administrator acting as the user in
+/// `sub`.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct ImpersonationClaim {
+  pub admin: Uuid,
+  pub exp: i64,
+}
+
+impl ImpersonationClaim {
+  pub fn from_claims(claims: &JwtClaims) -> Option<Self> {
+    serde_json::from_value(claims.additional_claims.get(IMPERSONATION_CLAIM)?.clone()).ok()
+  }
+
+  pub fn expires_at(&self) -> DateTime<Utc> {
+    DateTime::from_timestamp(self.exp, 0).unwrap_or_default()
+  }
+
+  /// Checks run for every authenticated request made while impersonating.
+  pub fn check(&self, parts: &Parts) -> Result<()> {
+    if Utc::now().timestamp() > self.exp {
+      bail!(UNAUTHORIZED, "Impersonation has expired");
+    }
+
+    let path = parts
+      .extensions
+      .get::<OriginalUri>()
+      .map(|uri| uri.path())
+      .unwrap_or(parts.uri.path());
+    if is_sensitive(&parts.method, api_path(path)) {
+      bail!(FORBIDDEN, "Not allowed while impersonating a user");
+    }
+
+    Ok(())
+  }
+}
+
+/// Actions only the user themselves may take, even if an administrator can
+/// see their account.
+fn is_sensitive(method: &Method, path: &str) -> bool {
+  matches!(
+    (method.as_str(), path),
+    ("POST", "/user/account/password")
+      | ("POST", "/user/account/email_change_start")
+      | ("POST", "/user/account/email_change_confirm")
+      | ("POST", "/user/account/logins/report")
+      | ("GET", "/user/account/export")
+      | ("POST", "/user/account/deletion")
+      | ("POST", "/user/impersonate")
+  )
+}
+
+/// The impersonation the current request is made under, if any.
+#[derive(OperationIo)]
+pub struct Impersonation {
+  pub admin: Uuid,
+  pub user: Uuid,
+  pub expires_at: DateTime<Utc>,
+  token: String,
+  token_exp: i64,
+}
+
+impl Impersonation {
+  pub async fn from_parts(parts: &mut Parts) -> Option<Self> {
+    let token = jwt_from_request(parts, JWT_COOKIE_NAME).await.ok()?;
+    let jwt = parts.extensions.get::<JwtState>()?;
+    let claims = jwt.validate_token(&token).ok()?;
+    let claim = ImpersonationClaim::from_claims(&claims)?;
+
+    Some(Self {
+      admin: claim.admin,
+      user: claims.sub,
+      expires_at: claim.expires_at(),
+      token,
+      token_exp: claims.exp,
+    })
+  }
+}
+
+impl<S: Sync> FromRequestParts<S> for Impersonation {
+  type Rejection = centaurus::error::ErrorReport;
+
+  async fn from_request_parts(
+    parts: &mut Parts,
+    _state: &S,
+  ) -> std::result::Result<Self, Self::Rejection> {
+    match Self::from_parts(parts).await {
+      Some(impersonation) => Ok(impersonation),
+      None => bail!(BAD_REQUEST, "Not impersonating a user"),
+    }
+  }
+}
+
+impl<S: Sync> axum::extract::OptionalFromRequestParts<S> for Impersonation {
+  type Rejection = std::convert::Infallible;
+
+  async fn from_request_parts(
+    parts: &mut Parts,
+    _state: &S,
+  ) -> std::result::Result<Option<Self>, Self::Rejection> {
+    Ok(Self::from_parts(parts).await)
+  }
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct StartImpersonation {
+  uuid: Uuid,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct ImpersonationResponse {
+  /// User the session now belongs to, none if it ended.
+  user: Option<Uuid>,
+  expires_at: Option<DateTime<Utc>>,
+}
+
+async fn start_impersonation(
+  auth: OrgAuth<UserImpersonate>,
+  db: Connection,
+  jwt: JwtState,
+  updater: Updater,
+  context: AuditContext,
+  mut cookies: CookieJar,
+  Json(StartImpersonation { uuid }): Json<StartImpersonation>,
+) -> Result<(CookieJar, Json<ImpersonationResponse>)> {
+  if uuid == auth.user_id {
+    bail!(BAD_REQUEST, "Cannot impersonate yourself");
+  }
+  // the session is valid in every organization of the user
+  auth.ensure_managed(&db, uuid).await?;
+  let user = db.user().get_user_by_id(uuid).await?;
+
+  // impersonating must not grant more than the administrator already has,
+  // super admins hold rights beyond every organization
+  let super_admin = db.organization().is_super_admin(user.id).await?;
+  if super_admin && !auth.super_admin {
+    bail!(FORBIDDEN, "Only super admins can impersonate super admins");
+  }
+  let own: HashSet<_> = auth.permissions(&db).await?.into_iter().collect();
+  let theirs = scoped_permissions(&db, auth.organization, user.id, super_admin).await?;
+  if theirs.iter().any(|permission| !own.contains(permission)) {
+    bail!(
+      FORBIDDEN,
+      "Cannot impersonate a user with permissions you do not have"
+    );
+  }
+
+  let expires_at = Utc::now() + Duration::minutes(IMPERSONATION_MINUTES);
+  let claim = ImpersonationClaim {
+    admin: auth.user_id,
+    exp: expires_at.timestamp(),
+  };
+  let token = jwt.create_raw_token_custom(
+    user.id,
+    HashMap::from([(IMPERSONATION_CLAIM.to_string(), serde_json::json!(claim))]),
+  )?;
+  let mut cookie = jwt.create_cookie(JWT_COOKIE_NAME, token);
+  cookie.set_max_age(time::Duration::minutes(IMPERSONATION_MINUTES));
+  cookies = cookies.add(cookie);
+
+  let entry = AuditEntry::new("user.impersonate_start")
+    .actor(auth.user_id)
+    .target("user", user.id)
+    .after(&serde_json::json!({ "expires_at": expires_at }));
+  context.record(&db, &updater, entry).await;
+
+  Ok((
+    cookies,
+    Json(ImpersonationResponse {
+      user: Some(user.id),
+      expires_at: Some(expires_at),
+    }),
+  ))
+}
+
+/// Drops the impersonation session and signs the administrator back in, unless
+/// it already expired.
+async fn end_impersonation(
+  impersonation: Impersonation,
+  db: Connection,
+  jwt: JwtState,
+  invalid: JwtInvalidState,
+  updater: Updater,
+  context: AuditContext,
+  mut cookies: CookieJar,
+) -> Result<(CookieJar, Json<ImpersonationResponse>)> {
+  if !db
+    .invalid_jwt()
+    .is_token_valid(&impersonation.token)
+    .await?
+  {
+    bail!(UNAUTHORIZED, "Impersonation already ended");
+  }
+  db.invalid_jwt()
+    .invalidate_jwt(
+      impersonation.token.clone(),
+      DateTime::from_timestamp(impersonation.token_exp, 0).unwrap_or_else(Utc::now),
+      invalid.count.clone(),
+    )
+    .await?;
+
+  let entry = AuditEntry::new("user.impersonate_end")
+    .actor(impersonation.admin)
+    .target("user", impersonation.user);
+  context.record(&db, &updater, entry).await;
+
+  // an expired impersonation only signs out, the administrator has to log in
+  // again
+  let admin = if impersonation.expires_at < Utc::now() {
+    cookies = cookies.remove(jwt.create_cookie(JWT_COOKIE_NAME, String::new()));
+    None
+  } else {
+    // the administrator may have been removed in the meantime
+    let admin = db.user().get_user_by_id(impersonation.admin).await?;
+    cookies = cookies.add(jwt.create_token(admin.id)?);
+    Some(admin.id)
+  };
+
+  Ok((
+    cookies,
+    Json(ImpersonationResponse {
+      user: admin,
+      expires_at: None,
+    }),
+  ))
+}
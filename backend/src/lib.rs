@@ -3,31 +3,73 @@ use axum::{Extension, Router};
 use centaurus::{
   backend::{
     auth,
-    endpoints::{self, group, mail, setup, user, websocket},
+    endpoints::{
+      self, mail,
+      mail::state::ResetPasswordState,
+      setup,
+      websocket::{self, state::UpdateState},
+    },
     init::{listener_setup, run_app_connect_info},
     middleware::rate_limiter::RateLimiter,
     router::build_router,
   },
   db::init::init_db,
-  logging::init_logging,
+  mail::{MailSettings, Mailer},
   version_header,
 };
 use tokio::net::TcpListener;
-use tracing::info;
+use tracing::{info, warn};
 
-use crate::{config::Config, utils::UpdateMessage};
+use crate::{
+  config::{
+    Config, ConfigSource,
+    reload::{self, LiveConfig, Reloader},
+  },
+  rate_limit::RateLimits,
+  utils::UpdateMessage,
+};
 
+mod archive;
+mod attribute;
+mod audit;
+mod avatar;
+mod backup;
+mod branding;
+mod cli;
 mod config;
+mod confirm;
+mod csv;
 mod db;
 mod dummy;
+mod flag;
+mod group;
+mod impersonation;
+mod login;
+mod oidc;
+mod organization;
+mod pagination;
+mod permissions;
+mod rate_limit;
+mod role;
 mod settings;
+mod user;
 mod utils;
 
-pub async fn serve() {
-  let config = Config::parse();
-  init_logging(config.base.log_level);
+/// Entry point of the binary, runs the subcommand given on the command line.
+pub async fn run() -> std::process::ExitCode {
+  use clap::Parser;
+
+  cli::Cli::parse().run().await
+}
+
+pub async fn serve(source: ConfigSource) {
+  let config = Config::parse_from(source.clone());
+  let log = reload::init_logging(config.base.log_level);
 
-  App::from_config(config).await.run().await;
+  App::build(config, Some(Reloader { source, log }))
+    .await
+    .run()
+    .await;
 }
 
 pub struct App {
@@ -41,8 +83,20 @@ impl App {
   }
 
   pub async fn from_config(config: Config) -> App {
+    App::build(config, None).await
+  }
+
+  async fn build(config: Config, reloader: Option<Reloader>) -> App {
     let listener = listener_setup(config.base.port).await;
-    let mut app = build_router(api_router, state, config).await;
+    let live = LiveConfig::new(config);
+    let mut app = build_router(
+      api_router,
+      |router, _| state(router, live.clone(), reloader),
+      live.router_config(),
+    )
+    .await
+    .layer(axum::middleware::from_fn(reload::metrics_gate))
+    .layer(Extension(live.clone()));
     version_header!(app);
 
     App { app, listener }
@@ -66,31 +120,77 @@ fn api_router(rate_limiter: &mut RateLimiter) -> ApiRouter {
   ApiRouter::new()
     .nest("/ws", websocket::router::<UpdateMessage>())
     .nest("/setup", setup::router())
-    .nest("/auth", auth::router::<UpdateMessage>(rate_limiter))
-    .nest("/user", user::router::<UpdateMessage>(rate_limiter))
+    .nest("/auth", oidc::auth_router())
+    .nest("/user", user::router())
     .nest("/settings", settings::router())
-    .nest("/mail", mail::router(rate_limiter))
-    .nest("/group", group::router::<UpdateMessage>())
+    // centaurus keeps its fixed limiter inside, the configured one applies on
+    // top of it
+    .nest(
+      "/mail",
+      mail::router(rate_limiter).layer(axum::middleware::from_fn_with_state(
+        "mail",
+        rate_limit::limit,
+      )),
+    )
+    .nest("/group", group::router())
+    .nest("/role", role::router())
     .nest("/dummy", dummy::router())
+    .nest("/audit", audit::router())
+    .nest("/attribute", attribute::router())
+    .nest("/organization", organization::router())
+    .nest("/flag", flag::router())
+    .layer(axum::middleware::from_fn(settings::history::middleware))
+    .layer(axum::middleware::from_fn(organization::middleware))
+    .layer(axum::middleware::from_fn(audit::middleware))
+    .layer(axum::middleware::from_fn(login::middleware))
+    .layer(axum::middleware::from_fn(reload::middleware))
 }
 
-async fn state(mut router: ApiRouter, config: Config) -> ApiRouter {
+async fn state(mut router: ApiRouter, live: LiveConfig, reloader: Option<Reloader>) -> ApiRouter {
+  let config = live.get();
   let db = init_db::<migration::Migrator>(&config.db, &config.db_url).await;
   centaurus::backend::endpoints::setup::create_admin_group(
     &db,
-    utils::permissions(),
+    permissions::names(),
     Some(config.admin_group.clone()),
   )
   .await
   .expect("Failed to create admin group");
+  organization::init(&db)
+    .await
+    .expect("Failed to initialize organizations");
+  role::init(&db).await.expect("Failed to initialize roles");
+  settings::registry::check_pins(&config).expect("Invalid pinned settings");
+  user::status::start_purge(db.clone(), config.user_retention_days);
 
   router = endpoints::user::state(router);
+  router = login::state(router, &config, &db).await;
   router = auth::state(router, &config, &db).await;
-  router = mail::state(router, &db, &config).await;
+  router = oidc::state(router);
+  let mailer = Mailer::new(MailSettings::default()).await;
+  if let Err(err) = settings::section::init_mailer(&db, &config, &mailer).await {
+    warn!("Failed to set up mail: {err:?}");
+  }
+  router = router
+    .layer(Extension(mailer.clone()))
+    .layer(Extension(ResetPasswordState::default()));
   router = dummy::state(router);
-  router = websocket::state::<UpdateMessage>(router).await;
+  let audit_context;
+  (router, audit_context) = audit::state(router, &db, &config).await;
+  let (updates, updater) = UpdateState::<UpdateMessage>::init().await;
+  router = router
+    .layer(Extension(updates))
+    .layer(Extension(updater.clone()));
+  user::privacy::start_deletion(db.clone(), audit_context, updater);
+
+  if let Some(reloader) = reloader {
+    reload::start(reloader, live.clone(), db.clone(), mailer);
+  }
 
-  router.layer(Extension(db))
+  router
+    .layer(Extension(live))
+    .layer(Extension(RateLimits::new()))
+    .layer(Extension(db))
 }
 
 #[cfg(test)]
@@ -0,0 +1,385 @@
+use aide::{
+  OperationIo,
+  axum::{
+    ApiRouter,
+    routing::{get_with, post_with},
+  },
+};
+use axum::{
+  Extension, Form, Json,
+  body::{Body, to_bytes},
+  extract::{FromRequestParts, Query, Request},
+  middleware::Next,
+  response::{Html, IntoResponse, Response},
+  routing::{get, post},
+};
+use centaurus::{
+  backend::{
+    auth::{jwt_auth::JwtAuth, jwt_state::JwtState},
+    config::SiteConfig,
+    endpoints::mail::{state::ResetPasswordState, template as mail_template},
+    request::redirect::Redirect,
+  },
+  bail,
+  db::{init::Connection, tables::ConnectionExt},
+  error::Result,
+  mail::Mailer,
+};
+use chrono::{Duration, Utc};
+use entity::login_event;
+use http::{Method, StatusCode};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::spawn;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+use crate::{
+  audit::{AuditContext, AuditEntry},
+  config::Config,
+  confirm::{self, TokenForm},
+  db::{
+    DBTrait,
+    login::{LoginInfo, NewLogin},
+    repository::UserRepository,
+  },
+  login::session::SessionAuth,
+  utils::{UpdateMessage, Updater, api_path, hash_token, issued_session, site_link},
+};
+
+mod session;
+mod template;
+
+const DEFAULT_PAGE_SIZE: u64 = 50;
+const MAX_PAGE_SIZE: u64 = 200;
+const MAX_LOGIN_BODY: usize = 64 * 1024;
+/// How long the "this wasn't me" link of a new device mail can be used.
+const REPORT_LINK_VALIDITY_DAYS: i64 = 7;
+
+pub fn router() -> ApiRouter {
+  ApiRouter::new()
+    .api_route("/", get_with(list_logins, |op| op.id("listLogins")))
+    .api_route(
+      "/report",
+      post_with(report_login, |op| op.id("reportLogin")),
+    )
+    // opened from the mail, not part of the api
+    .route("/report", get(report_login_page))
+    .route("/report/confirm", post(report_login_link))
+}
+
+/// Replaces the `JwtState` of centaurus with one that also rejects revoked
+/// sessions. The innermost extension wins, so this has to be layered before
+/// `auth::state`.
+pub async fn state(router: ApiRouter, config: &Config, db: &Connection) -> ApiRouter {
+  let auth = SessionAuth {
+    expiration: config.auth.auth_jwt_expiration,
+  };
+  let jwt = JwtState::init_with_auth(&config.auth, db, auth).await;
+
+  router.layer(Extension(jwt))
+}
+
+/// Login endpoints provided by centaurus.
+fn login_method(method: &Method, path: &str) -> Option<&'static str> {
+  match (method.as_str(), path) {
+    ("POST", "/auth/password") => Some("password"),
+    ("GET", "/auth/oidc/callback") => Some("oidc"),
+    _ => None,
+  }
+}
+
+pub async fn middleware(
+  db: Connection,
+  jwt: JwtState,
+  context: AuditContext,
+  request: Request,
+  next: Next,
+) -> Response {
+  let Some(method) = login_method(request.method(), api_path(request.uri().path())) else {
+    return next.run(request).await;
+  };
+  let mailer = request.extensions().get::<Mailer>().cloned();
+  let site = request.extensions().get::<SiteConfig>().cloned();
+
+  // failed password logins are attributed to the account they tried
+  let (request, email) = if method == "password" {
+    let (parts, body) = request.into_parts();
+    let Ok(bytes) = to_bytes(body, MAX_LOGIN_BODY).await else {
+      return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+    };
+    let email = serde_json::from_slice::<Value>(&bytes)
+      .ok()
+      .and_then(|body| body.get("email")?.as_str().map(String::from));
+    (Request::from_parts(parts, Body::from(bytes)), email)
+  } else {
+    (request, None)
+  };
+
+  let mut response = next.run(request).await;
+
+  let (user_id, success) = match issued_session(&jwt, &response) {
+    // the session of an inactive user is never handed out
+    Some(user_id) if !db.user_status().is_active(user_id).await.unwrap_or(false) => {
+      response = (StatusCode::FORBIDDEN, "User is disabled").into_response();
+      (user_id, false)
+    }
+    Some(user_id) => (user_id, true),
+    None => {
+      let Some(email) = email else {
+        return response;
+      };
+      match db.find_user_by_email(&email).await {
+        Ok(Some(user)) => (user.id, false),
+        _ => return response,
+      }
+    }
+  };
+
+  let login = LoginAttempt {
+    user_id,
+    method,
+    success,
+    ip: context.ip().map(String::from),
+    user_agent: context.user_agent().map(String::from),
+  };
+  if let Err(err) = record_login(&db, mailer, site, login).await {
+    error!("Failed to record login of {}: {:?}", user_id, err);
+  }
+
+  response
+}
+
+struct LoginAttempt {
+  user_id: Uuid,
+  method: &'static str,
+  success: bool,
+  ip: Option<String>,
+  user_agent: Option<String>,
+}
+
+/// Stores the login and notifies the user if it came from a device that was
+/// never used with the account before. The very first login is not reported.
+async fn record_login(
+  db: &Connection,
+  mailer: Option<Mailer>,
+  site: Option<SiteConfig>,
+  login: LoginAttempt,
+) -> Result<()> {
+  let new_device = login.success
+    && db.login().has_logins(login.user_id).await?
+    && !db
+      .login()
+      .is_known_device(
+        login.user_id,
+        login.ip.as_deref(),
+        login.user_agent.as_deref(),
+      )
+      .await?;
+  let report_token = new_device.then(|| Uuid::new_v4().to_string());
+
+  db.login()
+    .record(NewLogin {
+      user_id: login.user_id,
+      method: login.method.to_string(),
+      success: login.success,
+      ip: login.ip.clone(),
+      user_agent: login.user_agent.clone(),
+      new_device,
+      report_token: report_token.as_deref().map(hash_token),
+    })
+    .await?;
+
+  let (Some(token), Some(mailer), Some(site)) = (report_token, mailer, site) else {
+    return Ok(());
+  };
+  let user = db.user().get_user_by_id(login.user_id).await?;
+
+  let report_link = site_link(
+    &site,
+    &["api", "user", "account", "logins", "report"],
+    Some(&token),
+  );
+  let body = template::new_device(
+    &Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+    login.ip.as_deref().unwrap_or("unknown"),
+    login.user_agent.as_deref().unwrap_or("unknown"),
+    report_link.as_str(),
+    site.site_url.as_str(),
+  );
+
+  spawn(async move {
+    if let Err(err) = mailer
+      .send_mail(
+        user.name,
+        user.email.clone(),
+        "New Sign-in to your Account".to_string(),
+        body,
+      )
+      .await
+    {
+      warn!(
+        "Failed to send new device notification to {}: {:?}",
+        user.email, err
+      );
+    }
+  });
+
+  Ok(())
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct LoginQuery {
+  #[serde(default)]
+  offset: u64,
+  limit: Option<u64>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct LoginsResponse {
+  logins: Vec<LoginInfo>,
+  total: u64,
+}
+
+async fn list_logins(
+  auth: JwtAuth,
+  db: Connection,
+  Query(query): Query<LoginQuery>,
+) -> Result<Json<LoginsResponse>> {
+  let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+  let (logins, total) = db
+    .login()
+    .list_logins(auth.user_id, query.offset, limit)
+    .await?;
+
+  Ok(Json(LoginsResponse { logins, total }))
+}
+
+/// Everything needed to lock a compromised account.
+#[derive(Clone, FromRequestParts, OperationIo)]
+struct AccountRecovery {
+  mailer: Mailer,
+  reset: ResetPasswordState,
+  site: SiteConfig,
+  updater: Updater,
+  context: AuditContext,
+}
+
+impl AccountRecovery {
+  /// Revokes every session of the user and replaces the password, so whoever
+  /// logged in cannot do so again. The user gets a reset link to choose a new
+  /// password.
+  async fn secure(
+    &self,
+    db: &Connection,
+    login: login_event::Model,
+    actor: Option<Uuid>,
+  ) -> Result<()> {
+    db.login().mark_reported(login.id).await?;
+    db.login().revoke_sessions(login.user_id).await?;
+
+    let user = db.user().get_user_by_id(login.user_id).await?;
+    if !user.oidc_user {
+      // no password hash ever matches a random plain value
+      db.user()
+        .update_user_password(user.id, Uuid::new_v4().to_string())
+        .await?;
+
+      let token = self.reset.generate_token(user.email.clone()).await;
+      let reset_link = site_link(&self.site, &["password", "reset"], Some(&token));
+      let body = mail_template::reset_link(reset_link.as_str(), self.site.site_url.as_str());
+      let mailer = self.mailer.clone();
+      let (name, email) = (user.name.clone(), user.email.clone());
+      spawn(async move {
+        if let Err(err) = mailer
+          .send_mail(name, email.clone(), "Password Reset".to_string(), body)
+          .await
+        {
+          warn!(
+            "Failed to send password reset email to {}: {:?}",
+            email, err
+          );
+        }
+      });
+    }
+
+    let mut entry = AuditEntry::new("auth.login_reported")
+      .target("user", user.id)
+      .after(&serde_json::json!({
+        "login": login.id,
+        "method": login.method,
+        "ip": login.ip,
+        "user_agent": login.user_agent,
+      }));
+    if let Some(actor) = actor {
+      entry = entry.actor(actor);
+    }
+    self.context.record(db, &self.updater, entry).await;
+    self
+      .updater
+      .broadcast(UpdateMessage::User { uuid: user.id })
+      .await;
+
+    Ok(())
+  }
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct ReportLogin {
+  uuid: Uuid,
+}
+
+async fn report_login(
+  auth: JwtAuth,
+  db: Connection,
+  recovery: AccountRecovery,
+  Json(ReportLogin { uuid }): Json<ReportLogin>,
+) -> Result<()> {
+  let Some(login) = db.login().get_login(uuid).await? else {
+    bail!(NOT_FOUND, "Login not found");
+  };
+  if login.user_id != auth.user_id {
+    bail!(NOT_FOUND, "Login not found");
+  }
+
+  recovery.secure(&db, login, Some(auth.user_id)).await
+}
+
+async fn find_reported_login(db: &Connection, token: &str) -> Result<login_event::Model> {
+  let login = db.login().find_by_report_token(&hash_token(token)).await?;
+  let valid_after = Utc::now().naive_utc() - Duration::days(REPORT_LINK_VALIDITY_DAYS);
+  let Some(login) = login.filter(|login| login.created_at > valid_after) else {
+    bail!(NOT_FOUND, "Invalid or expired link");
+  };
+  Ok(login)
+}
+
+/// Target of the link in new device mails, only asks for confirmation.
+async fn report_login_page(
+  db: Connection,
+  Query(TokenForm { token }): Query<TokenForm>,
+) -> Result<Html<String>> {
+  find_reported_login(&db, &token).await?;
+
+  Ok(confirm::page(
+    "This wasn't me",
+    "All sessions of your account are signed out and your password is reset. You get a mail to choose a new one.",
+    "Secure my account",
+    "report/confirm",
+    &token,
+  ))
+}
+
+/// Submitted from the page behind the mailed link, works without a session.
+async fn report_login_link(
+  db: Connection,
+  recovery: AccountRecovery,
+  Form(TokenForm { token }): Form<TokenForm>,
+) -> Result<Redirect> {
+  let login = find_reported_login(&db, &token).await?;
+  recovery.secure(&db, login, None).await?;
+
+  let login_page = site_link(&recovery.site, &["login"], None);
+  Ok(Redirect::found(login_page.to_string()))
+}
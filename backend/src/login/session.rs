@@ -0,0 +1,55 @@
+use centaurus::{
+  backend::auth::{
+    jwt_auth::{Auth, StatelessAuth},
+    jwt_state::JwtClaims,
+  },
+  bail,
+  db::init::Connection,
+  error::ErrorReport,
+};
+use chrono::{DateTime, SubsecRound};
+use http::request::Parts;
+
+use crate::{db::DBTrait, impersonation::ImpersonationClaim};
+
+/// Rejects sessions of disabled or deleted users and sessions issued before the
+/// user's sessions were revoked, and enforces the limits of impersonation
+/// sessions, on top of the logout check done by centaurus.
+pub struct SessionAuth {
+  /// Lifetime of a session, used to derive when a token was issued.
+  pub expiration: i64,
+}
+
+#[async_trait::async_trait]
+impl Auth for SessionAuth {
+  async fn check(
+    &self,
+    db: &Connection,
+    parts: &mut Parts,
+    token: &str,
+    claims: &JwtClaims,
+  ) -> Result<(), ErrorReport> {
+    StatelessAuth.check(db, parts, token, claims).await?;
+    if let Some(impersonation) = ImpersonationClaim::from_claims(claims) {
+      impersonation.check(parts)?;
+    }
+
+    if !db.user_status().is_active(claims.sub).await? {
+      bail!(UNAUTHORIZED, "user is disabled");
+    }
+
+    let Some(revoked_at) = db.login().sessions_revoked_at(claims.sub).await? else {
+      return Ok(());
+    };
+    let issued_at = DateTime::from_timestamp(claims.exp - self.expiration, 0)
+      .map(|issued_at| issued_at.naive_utc())
+      .unwrap_or_default();
+    // tokens only carry second precision, so a session issued in the same
+    // second as the revocation counts as revoked
+    if issued_at <= revoked_at.trunc_subsecs(0) {
+      bail!(UNAUTHORIZED, "session was revoked");
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,42 @@
+use crate::utils::escape_html;
+
+/// The user agent is sent by the client, so everything describing the login is
+/// escaped.
+pub fn new_device(time: &str, ip: &str, user_agent: &str, report_link: &str, link: &str) -> String {
+  let time = escape_html(time);
+  let ip = escape_html(ip);
+  let user_agent = escape_html(user_agent);
+
+  format!(
+    r#"
+  <!DOCTYPE html>
+  <html lang="en">
+    <head>
+      <meta charset="UTF-8">
+      <meta name="viewport" content="width=device-width, initial-scale=1.0">
+      <title>New Sign-in</title>
+    </head>
+    <body>
+      <div style="display: flex; flex-direction: column;">
+        <header style="padding: 1rem; display: flex; flex-direction: column; align-items: center; justify-content: center;">
+          <h2 style="margin: 0;">New Sign-in</h2>
+          <p style="margin: 0;">Your account was just used from a device we have not seen before</p>
+        </header>
+        <div style="display: flex; align-items: center; justify-content: center; flex-direction: column;">
+          <p style="margin: 0;">Time: {time}</p>
+          <p style="margin: 0;">IP address: {ip}</p>
+          <p style="margin: 0;">Device: {user_agent}</p>
+        </div>
+        <div style="display: flex; align-items: center; justify-content: center; flex-direction: column;">
+          <p>If this was you, you can ignore this email. Otherwise sign out this session and reset your password:</p>
+          <a href="{report_link}">This wasn't me</a>
+        </div>
+        <footer style="display: flex; align-items: center; justify-content: center;">
+          <p>Mail send from <a href="{link}">{link}</a></p>
+        </footer>
+      </div>
+    </body>
+  </html>
+  "#
+  )
+}
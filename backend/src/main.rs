@@ -1,10 +1,12 @@
+use std::process::ExitCode;
+
 #[cfg(debug_assertions)]
 use dotenvy::dotenv;
 
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
   #[cfg(debug_assertions)]
   dotenv().ok();
 
-  backend::serve().await;
+  backend::run().await
 }
@@ -0,0 +1,686 @@
+use std::{
+  collections::HashMap,
+  sync::{Arc, Mutex},
+  time::{Duration, Instant},
+};
+
+use aide::{
+  OperationIo,
+  axum::{ApiRouter, routing::get},
+};
+use axum::{
+  Extension, Json,
+  extract::{FromRequestParts, Query},
+  middleware::from_fn_with_state,
+};
+use axum_extra::extract::{CookieJar, cookie::Cookie};
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use centaurus::{
+  backend::{
+    auth::{
+      config,
+      jwt_state::JwtState,
+      logout,
+      oidc::{AuthInfo, OIDC_STATE, OidcState},
+      password,
+      settings::{OidcSettings, UserSettings},
+      test_token,
+    },
+    request::redirect::Redirect,
+  },
+  bail,
+  db::{init::Connection, tables::ConnectionExt},
+  error::Result,
+  overwrite_with_env_config,
+};
+use http::header;
+use jsonwebtoken::{
+  DecodingKey, Validation,
+  jwk::{AlgorithmParameters, JwkSet},
+};
+use reqwest::{Client, redirect::Policy};
+use rsa::rand_core::{OsRng, RngCore};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex as AsyncMutex;
+use tracing::{debug, error, info, warn};
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+  attribute, avatar,
+  config::Config,
+  db::{DBTrait, repository::GroupRepository},
+  organization, rate_limit,
+  settings::{ProfileSyncSettings, SyncPolicy, registry},
+  utils::{UpdateMessage, Updater},
+};
+
+/// Pending logins are dropped if the provider does not redirect back in time.
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(600);
+/// Largest picture taken over from the provider.
+const MAX_PICTURE_SIZE: usize = 5 * 1024 * 1024;
+const PICTURE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Authentication routes of centaurus with the OIDC login replaced by the one
+/// below, which also has access to the claims of the provider.
+pub fn auth_router() -> ApiRouter {
+  ApiRouter::new()
+    .nest("/password", password_router())
+    .nest("/logout", logout::router())
+    .nest("/test_token", test_token::router())
+    .nest("/oidc", router())
+    .nest("/config", config::router())
+}
+
+/// The password routes of centaurus, limited like the other routes.
+fn password_router() -> ApiRouter {
+  ApiRouter::new()
+    .api_route("/", password::authenticate_route())
+    .layer(from_fn_with_state("password", rate_limit::limit))
+    .api_route("/", password::key_route())
+}
+
+fn router() -> ApiRouter {
+  ApiRouter::new()
+    .api_route("/url", get(oidc_url))
+    .layer(from_fn_with_state("oidc", rate_limit::limit))
+    .api_route("/callback", get(oidc_callback))
+}
+
+pub fn state(router: ApiRouter) -> ApiRouter {
+  router.layer(Extension(OidcLogin::default()))
+}
+
+struct PendingLogin {
+  created: Instant,
+  nonce: String,
+  code_verifier: Option<String>,
+  redirect_to: Option<String>,
+}
+
+/// Endpoints of a provider found through discovery.
+#[derive(Clone)]
+struct Provider {
+  settings_issuer: Url,
+  issuer: String,
+  authorization_endpoint: Url,
+  token_endpoint: Url,
+  userinfo_endpoint: Url,
+  jwk_set: JwkSet,
+}
+
+#[derive(Deserialize)]
+struct Discovery {
+  issuer: String,
+  authorization_endpoint: Url,
+  token_endpoint: Url,
+  userinfo_endpoint: Url,
+  jwks_uri: Url,
+}
+
+/// State of the OIDC login. Whether OIDC is enabled is still decided by the
+/// [`OidcState`] of centaurus, which the settings endpoints keep up to date.
+#[derive(Clone, FromRequestParts, OperationIo)]
+#[from_request(via(Extension))]
+pub struct OidcLogin {
+  client: Client,
+  provider: Arc<AsyncMutex<Option<Provider>>>,
+  pending: Arc<Mutex<HashMap<Uuid, PendingLogin>>>,
+}
+
+impl Default for OidcLogin {
+  fn default() -> Self {
+    Self {
+      client: Client::builder()
+        .redirect(Policy::none())
+        .build()
+        .expect("Failed to build http client"),
+      provider: Default::default(),
+      pending: Default::default(),
+    }
+  }
+}
+
+impl OidcLogin {
+  /// Effective settings, values from the environment take precedence over the
+  /// stored ones.
+  async fn settings(db: &Connection, config: &Config) -> Result<Option<OidcSettings>> {
+    let mut settings: UserSettings = db.settings().get_settings().await?;
+    let env = Some(&config.oidc);
+    overwrite_with_env_config!(
+      settings,
+      env,
+      oidc_issuer,
+      oidc_client_id,
+      oidc_client_secret,
+      oidc_scopes,
+      oidc_group_claim,,
+      oidc_enabled,
+      oidc_group_sync,
+      oidc_image_sync,
+      oidc_pkce,
+      sso_create_user,
+      sso_instant_redirect
+    );
+
+    Ok(settings.oidc_settings())
+  }
+
+  /// Discovers the provider, reusing the last result while the issuer stays
+  /// the same.
+  async fn provider(&self, settings: &OidcSettings) -> Result<Provider> {
+    let mut lock = self.provider.lock().await;
+    if let Some(provider) = lock.as_ref()
+      && provider.settings_issuer == settings.issuer
+    {
+      return Ok(provider.clone());
+    }
+
+    let mut url = settings.issuer.clone();
+    if let Ok(mut segments) = url.path_segments_mut() {
+      segments
+        .pop_if_empty()
+        .push(".well-known")
+        .push("openid-configuration");
+    }
+    let res = self.client.get(url.clone()).send().await?;
+    if !res.status().is_success() {
+      bail!("Failed to retrieve OIDC configuration from {}", url);
+    }
+    let discovery: Discovery = res.json().await?;
+
+    let res = self.client.get(discovery.jwks_uri.clone()).send().await?;
+    if !res.status().is_success() {
+      bail!("Failed to retrieve JWKs from {}", discovery.jwks_uri);
+    }
+    let jwk_set: JwkSet = res.json().await?;
+
+    let provider = Provider {
+      settings_issuer: settings.issuer.clone(),
+      issuer: discovery.issuer,
+      authorization_endpoint: discovery.authorization_endpoint,
+      token_endpoint: discovery.token_endpoint,
+      userinfo_endpoint: discovery.userinfo_endpoint,
+      jwk_set,
+    };
+    *lock = Some(provider.clone());
+
+    Ok(provider)
+  }
+
+  fn start(&self, login: PendingLogin) -> Uuid {
+    let state = Uuid::new_v4();
+    let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+    pending.retain(|_, login| login.created.elapsed() < LOGIN_TIMEOUT);
+    pending.insert(state, login);
+    state
+  }
+
+  fn finish(&self, state: Uuid) -> Option<PendingLogin> {
+    let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+    pending
+      .remove(&state)
+      .filter(|login| login.created.elapsed() < LOGIN_TIMEOUT)
+  }
+}
+
+fn random_string() -> String {
+  let mut bytes = [0u8; 48];
+  OsRng.fill_bytes(&mut bytes);
+  BASE64_URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Reduces a redirect target to a path on this site.
+fn same_origin_path(redirect_to: &str) -> Option<String> {
+  let base = Url::parse("http://localhost").ok()?;
+  let url = base.join(redirect_to).ok()?;
+  if url.host_str() != Some("localhost") || url.origin() != base.origin() {
+    return None;
+  }
+
+  let query = url.query().map(|q| format!("?{q}")).unwrap_or_default();
+  let fragment = url.fragment().map(|f| format!("#{f}")).unwrap_or_default();
+  Some(format!("{}{}{}", url.path(), query, fragment))
+}
+
+#[derive(Serialize, JsonSchema)]
+struct OidcResponse {
+  url: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct OidcUrlQuery {
+  redirect_to: Option<String>,
+}
+
+async fn oidc_url(
+  oidc: OidcState,
+  login: OidcLogin,
+  db: Connection,
+  config: Config,
+  jwt: JwtState,
+  mut cookies: CookieJar,
+  Query(OidcUrlQuery { redirect_to }): Query<OidcUrlQuery>,
+) -> Result<(CookieJar, Json<OidcResponse>)> {
+  if !oidc.is_enabled().await {
+    bail!(BAD_REQUEST, "OIDC not configured");
+  }
+  let Some(settings) = OidcLogin::settings(&db, &config).await? else {
+    bail!(BAD_REQUEST, "OIDC not configured");
+  };
+  let provider = login.provider(&settings).await?;
+
+  let redirect_to = match redirect_to {
+    Some(redirect_to) => match same_origin_path(&redirect_to) {
+      Some(path) => Some(path),
+      None => bail!("Invalid redirect url"),
+    },
+    None => None,
+  };
+
+  let nonce = random_string();
+  let mut url = provider.authorization_endpoint.clone();
+  {
+    let mut query = url.query_pairs_mut();
+    query
+      .append_pair("response_type", "code")
+      .append_pair("client_id", &settings.client_id)
+      .append_pair("nonce", &nonce);
+    if !settings.scopes.is_empty() {
+      query.append_pair("scope", &settings.scopes.join(" "));
+    }
+  }
+
+  let code_verifier = settings.pkce.then(random_string);
+  if let Some(code_verifier) = &code_verifier {
+    let challenge = BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+    url
+      .query_pairs_mut()
+      .append_pair("code_challenge", &challenge)
+      .append_pair("code_challenge_method", "S256");
+  }
+
+  let state = login.start(PendingLogin {
+    created: Instant::now(),
+    nonce,
+    code_verifier,
+    redirect_to,
+  });
+  url
+    .query_pairs_mut()
+    .append_pair("state", &state.to_string());
+  cookies = cookies.add(jwt.create_cookie(OIDC_STATE, state.to_string()));
+
+  Ok((
+    cookies,
+    Json(OidcResponse {
+      url: url.to_string(),
+    }),
+  ))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct OidcCallbackQuery {
+  code: Option<String>,
+  state: Option<Uuid>,
+  error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+  id_token: String,
+}
+
+#[derive(Deserialize)]
+struct TokenError {
+  error: String,
+}
+
+/// Everything the callback needs besides the query.
+#[derive(Clone, FromRequestParts, OperationIo)]
+struct CallbackState {
+  oidc: OidcState,
+  login: OidcLogin,
+  db: Connection,
+  config: Config,
+  jwt: JwtState,
+  updater: Updater,
+}
+
+/// Sends the browser back to the site, to the page the login started from or
+/// to the login page with the error.
+async fn oidc_callback(
+  state: CallbackState,
+  cookies: CookieJar,
+  Query(query): Query<OidcCallbackQuery>,
+) -> Result<(CookieJar, Redirect)> {
+  let (path, error, mut cookies) = match check_code(&state, cookies.clone(), query).await? {
+    Ok((path, cookies)) => (path, None, cookies),
+    Err(error) => ("/login".to_string(), Some(error), cookies),
+  };
+  cookies = cookies.remove(Cookie::from(OIDC_STATE));
+
+  let mut url = state.config.site.site_url.clone();
+  let target = Url::parse("http://localhost")
+    .and_then(|base| base.join(&path))
+    .unwrap_or_else(|_| Url::parse("http://localhost/").unwrap());
+  url.set_path(target.path());
+  url.set_fragment(target.fragment());
+
+  let mut query = target.query().map(str::to_string).unwrap_or_default();
+  if let Some(error) = error {
+    if !query.is_empty() {
+      query.push('&');
+    }
+    query.push_str("error=");
+    query.push_str(&error);
+  }
+  url.set_query((!query.is_empty()).then_some(query.as_str()));
+
+  Ok((cookies, Redirect::found(url.to_string())))
+}
+
+/// Logs the user in. Failures the user should see are returned as the error
+/// code put into the redirect.
+async fn check_code(
+  state: &CallbackState,
+  mut cookies: CookieJar,
+  query: OidcCallbackQuery,
+) -> Result<std::result::Result<(String, CookieJar), String>> {
+  let db = &state.db;
+  let settings = match OidcLogin::settings(db, &state.config).await? {
+    Some(settings) if state.oidc.is_enabled().await => settings,
+    _ => return Ok(Err("oidc_not_configured".into())),
+  };
+  if let Some(error) = query.error {
+    return Ok(Err(error));
+  }
+
+  let Some(login_state) = query.state else {
+    return Ok(Err("invalid_state".into()));
+  };
+  let Some(pending) = state.login.finish(login_state) else {
+    return Ok(Err("invalid_state".into()));
+  };
+  if cookies
+    .get(OIDC_STATE)
+    .is_none_or(|cookie| cookie.value() != login_state.to_string())
+  {
+    return Ok(Err("invalid_state".into()));
+  }
+  let Some(code) = query.code else {
+    return Ok(Err("missing_code".into()));
+  };
+
+  let provider = state.login.provider(&settings).await?;
+  let mut form = vec![
+    ("grant_type", "authorization_code".to_string()),
+    ("code", code),
+  ];
+  if let Some(code_verifier) = pending.code_verifier {
+    form.push(("code_verifier", code_verifier));
+  }
+
+  let res = state
+    .login
+    .client
+    .post(provider.token_endpoint.clone())
+    .basic_auth(&settings.client_id, Some(&settings.client_secret))
+    .form(&form)
+    .send()
+    .await?;
+  if !res.status().is_success() {
+    let body = res.text().await.unwrap_or_default();
+    error!("OIDC token request failed: {}", body);
+    return Ok(Err(
+      serde_json::from_str::<TokenError>(&body)
+        .map(|e| e.error)
+        .unwrap_or_else(|_| "invalid_code".into()),
+    ));
+  }
+  let TokenResponse { id_token } = res.json().await?;
+  let id_claims = validate_id_token(&provider, &settings, &id_token, &pending.nonce)?;
+
+  let res = state
+    .login
+    .client
+    .get(provider.userinfo_endpoint.clone())
+    .bearer_auth(&id_token)
+    .send()
+    .await?;
+  if !res.status().is_success() {
+    error!(
+      "OIDC userinfo request failed: {}",
+      res.text().await.unwrap_or_default()
+    );
+    return Ok(Err("invalid_token".into()));
+  }
+  let info: AuthInfo = res.json().await?;
+
+  // providers may put custom claims only into the id token
+  let mut claims = id_claims;
+  claims.extend(info.extra.clone());
+
+  let user = match db.user().resolve_oidc_user(&info.sub, &info.email).await? {
+    Some(user) => user.id,
+    None if settings.create_user => create_user(db, &info).await?,
+    None => return Ok(Err("user_not_found".into())),
+  };
+  sync_user(state, user, &info, &settings, &claims, id_token).await?;
+
+  debug!("OIDC user authenticated: {}", user);
+  cookies = cookies.add(state.jwt.create_token(user)?);
+
+  Ok(Ok((pending.redirect_to.unwrap_or("/".into()), cookies)))
+}
+
+/// Checks signature, issuer, audience, expiry and nonce of the id token and
+/// returns its claims.
+fn validate_id_token(
+  provider: &Provider,
+  settings: &OidcSettings,
+  token: &str,
+  nonce: &str,
+) -> Result<HashMap<String, Value>> {
+  let header = jsonwebtoken::decode_header(token)?;
+  let Some(jwk) = header
+    .kid
+    .as_ref()
+    .and_then(|kid| provider.jwk_set.find(kid))
+  else {
+    bail!(UNAUTHORIZED, "Unknown key for the id token");
+  };
+  let key = match &jwk.algorithm {
+    AlgorithmParameters::RSA(rsa) => DecodingKey::from_rsa_components(&rsa.n, &rsa.e)?,
+    _ => bail!(UNAUTHORIZED, "Unsupported key for the id token"),
+  };
+
+  let mut validation = Validation::new(header.alg);
+  validation.set_audience(std::slice::from_ref(&settings.client_id));
+  validation.set_issuer(&[&provider.issuer]);
+  let data = jsonwebtoken::decode::<HashMap<String, Value>>(token, &key, &validation)?;
+
+  if data.claims.get("nonce").and_then(Value::as_str) != Some(nonce) {
+    bail!(UNAUTHORIZED, "Invalid nonce in the id token");
+  }
+
+  Ok(data.claims)
+}
+
+/// Creates a user for the provider account. The very first user becomes an
+/// administrator and completes the setup.
+async fn create_user(db: &Connection, info: &AuthInfo) -> Result<Uuid> {
+  let user = db
+    .user()
+    .create_user(
+      info.name.clone(),
+      info.email.clone(),
+      String::new(),
+      random_string(),
+      true,
+      Some(info.sub.clone()),
+    )
+    .await?;
+  organization::join_default(db, user).await?;
+
+  if !db.setup().is_setup().await? || db.user().count_users().await? == 1 {
+    let Some(admin_group) = db.setup().get_admin_group_id().await? else {
+      bail!(
+        INTERNAL_SERVER_ERROR,
+        "Admin group has not been created yet, cannot create initial user"
+      );
+    };
+    db.group()
+      .add_user_to_groups(user, vec![admin_group])
+      .await?;
+    db.organization().add_super_admin(user).await?;
+    db.setup().mark_completed().await?;
+    info!("Setup completed via OIDC, created user with ID {}", user);
+  }
+
+  Ok(user)
+}
+
+/// Takes over profile, groups, picture and attributes from the provider.
+async fn sync_user(
+  state: &CallbackState,
+  user: Uuid,
+  info: &AuthInfo,
+  settings: &OidcSettings,
+  claims: &HashMap<String, Value>,
+  id_token: String,
+) -> Result<()> {
+  let db = &state.db;
+  let policy: ProfileSyncSettings = registry::load(db, &state.config).await?;
+  let current = db.user().get_user_by_id(user).await?;
+  let name = match policy.name {
+    SyncPolicy::Always => &info.name,
+    SyncPolicy::OnCreate => &current.name,
+  };
+  let email = match policy.email {
+    SyncPolicy::Always => &info.email,
+    SyncPolicy::OnCreate => &current.email,
+  };
+  let mut changed = db.user().sync_from_oidc(user, name, email).await?;
+  changed |= attribute::apply_claims(db, user, claims).await?;
+
+  if settings.group_sync {
+    let groups = info.groups(&settings.group_claim);
+    // names are only unique within an organization
+    let mut group_ids = Vec::new();
+    for organization in db.organization().list_for_user(user).await? {
+      for name in &groups {
+        if let Some(group) = db
+          .organization()
+          .find_group_by_name(organization.id, name)
+          .await?
+        {
+          group_ids.push(group);
+        }
+      }
+    }
+    if let Some(admin_group) = db.setup().get_admin_group_id().await? {
+      // the last administrator keeps access even if the provider disagrees
+      if db.is_last_member(admin_group, user).await? && !group_ids.contains(&admin_group) {
+        group_ids.push(admin_group);
+      }
+      db.user().clear_user_groups(user).await?;
+      db.group().add_user_to_groups(user, group_ids).await?;
+      state
+        .updater
+        .send_to(user, UpdateMessage::UserPermissions)
+        .await;
+      changed = true;
+    }
+  }
+
+  if settings.image_sync
+    && let Some(picture) = &info.picture
+  {
+    let picture = fetch_picture(&state.login.client, picture, settings, &id_token).await;
+    match picture {
+      Ok(data) => match avatar::set_avatar(db, user, data).await {
+        Ok(updated) => changed |= updated,
+        Err(e) => warn!("Ignoring OIDC picture of user {}: {:?}", user, e),
+      },
+      Err(reason) => warn!("Ignoring OIDC picture of user {}: {}", user, reason),
+    }
+  }
+
+  if changed {
+    state
+      .updater
+      .broadcast(UpdateMessage::User { uuid: user })
+      .await;
+  }
+
+  Ok(())
+}
+
+/// Downloads the picture of a user. Only images up to [`MAX_PICTURE_SIZE`] are
+/// accepted and the id token is only sent along to the provider itself.
+async fn fetch_picture(
+  client: &Client,
+  picture: &str,
+  settings: &OidcSettings,
+  id_token: &str,
+) -> std::result::Result<Vec<u8>, String> {
+  let url = Url::parse(picture).map_err(|e| e.to_string())?;
+  if !matches!(url.scheme(), "http" | "https") {
+    return Err(format!("unsupported scheme {}", url.scheme()));
+  }
+
+  let mut req = client.get(url.clone()).timeout(PICTURE_TIMEOUT);
+  if url.origin() == settings.issuer.origin() {
+    req = req.bearer_auth(id_token);
+  }
+  let mut res = req.send().await.map_err(|e| e.to_string())?;
+  if !res.status().is_success() {
+    return Err(format!("provider responded with {}", res.status()));
+  }
+
+  let content_type = res
+    .headers()
+    .get(header::CONTENT_TYPE)
+    .and_then(|value| value.to_str().ok())
+    .unwrap_or_default();
+  if !content_type.starts_with("image/") {
+    return Err(format!("unexpected content type {content_type:?}"));
+  }
+  if res
+    .content_length()
+    .is_some_and(|length| length > MAX_PICTURE_SIZE as u64)
+  {
+    return Err("picture is too large".into());
+  }
+
+  // the length header is optional, so the body is limited while reading
+  let mut data = Vec::new();
+  while let Some(chunk) = res.chunk().await.map_err(|e| e.to_string())? {
+    if data.len() + chunk.len() > MAX_PICTURE_SIZE {
+      return Err("picture is too large".into());
+    }
+    data.extend_from_slice(&chunk);
+  }
+
+  Ok(data)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn redirect_stays_on_site() {
+    assert_eq!(
+      same_origin_path("/users?page=2#top").as_deref(),
+      Some("/users?page=2#top")
+    );
+    assert_eq!(same_origin_path("settings").as_deref(), Some("/settings"));
+    assert_eq!(same_origin_path("https://evil.example/"), None);
+    assert_eq!(same_origin_path("//evil.example/path"), None);
+  }
+}
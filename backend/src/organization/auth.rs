@@ -0,0 +1,165 @@
+use std::marker::PhantomData;
+
+use aide::OperationIo;
+use axum::extract::FromRequestParts;
+use centaurus::{
+  backend::auth::{
+    jwt_auth::JwtAuth,
+    permission::{NoPerm, Permission},
+  },
+  bail,
+  db::init::Connection,
+  error::{ErrorReport, Result},
+};
+use http::request::Parts;
+use uuid::Uuid;
+
+use crate::{db::DBTrait, permissions};
+
+/// Authenticated user acting within their current organization. Permissions
+/// are only taken from the groups of that organization, super admins hold all
+/// of them everywhere.
+#[derive(OperationIo)]
+pub struct OrgAuth<P: Permission = NoPerm> {
+  pub user_id: Uuid,
+  pub organization: Uuid,
+  pub super_admin: bool,
+  _perm: PhantomData<P>,
+}
+
+impl<P: Permission> OrgAuth<P> {
+  /// Permissions of the user within the organization.
+  pub async fn permissions(&self, db: &Connection) -> Result<Vec<String>> {
+    scoped_permissions(db, self.organization, self.user_id, self.super_admin).await
+  }
+
+  /// Fails with not found if the user is not a member of the organization, so
+  /// users of other organizations cannot be told apart from missing ones.
+  pub async fn ensure_member(&self, db: &Connection, user: Uuid) -> Result<()> {
+    if !db.organization().is_member(self.organization, user).await? {
+      bail!(NOT_FOUND, "User not found");
+    }
+    Ok(())
+  }
+
+  /// Checks the caller may change the account of the user itself, see
+  /// [`ensure_managed`].
+  pub async fn ensure_managed(&self, db: &Connection, user: Uuid) -> Result<()> {
+    ensure_managed(db, self.organization, self.super_admin, user).await
+  }
+
+  /// Fails with not found if the group belongs to another organization.
+  pub async fn ensure_group(&self, db: &Connection, group: Uuid) -> Result<()> {
+    if db.organization().group_organization(group).await? != Some(self.organization) {
+      bail!(NOT_FOUND, "Group not found");
+    }
+    Ok(())
+  }
+}
+
+impl<S: Sync, P: Permission> FromRequestParts<S> for OrgAuth<P> {
+  type Rejection = ErrorReport;
+
+  async fn from_request_parts(
+    parts: &mut Parts,
+    state: &S,
+  ) -> std::result::Result<Self, Self::Rejection> {
+    let auth = <JwtAuth as FromRequestParts<S>>::from_request_parts(parts, state).await?;
+    let Some(db) = parts.extensions.get::<Connection>().cloned() else {
+      bail!("Database connection missing");
+    };
+
+    let (organization, super_admin) = authorize(&db, auth.user_id, P::name()).await?;
+
+    Ok(Self {
+      user_id: auth.user_id,
+      organization,
+      super_admin,
+      _perm: PhantomData,
+    })
+  }
+}
+
+/// Resolves the current organization of the user and checks the permission
+/// within it. An empty permission only requires an organization.
+pub async fn authorize(db: &Connection, user: Uuid, permission: &str) -> Result<(Uuid, bool)> {
+  let super_admin = db.organization().is_super_admin(user).await?;
+  let Some(organization) = current_organization(db, user, super_admin).await? else {
+    bail!(FORBIDDEN, "Not a member of any organization");
+  };
+
+  if !permission.is_empty()
+    && !super_admin
+    && !db
+      .organization()
+      .user_permissions(organization, user)
+      .await?
+      .iter()
+      .any(|p| p == permission)
+  {
+    bail!(FORBIDDEN, "insufficient permissions");
+  }
+
+  Ok((organization, super_admin))
+}
+
+/// Accounts are shared by all organizations of a user, so changes to the
+/// account itself are limited to users only the current organization manages.
+/// Super admins may change every account.
+pub async fn ensure_managed(
+  db: &Connection,
+  organization: Uuid,
+  super_admin: bool,
+  user: Uuid,
+) -> Result<()> {
+  if !db.organization().is_member(organization, user).await? {
+    bail!(NOT_FOUND, "User not found");
+  }
+  if !super_admin && db.organization().list_for_user(user).await?.len() > 1 {
+    bail!(
+      FORBIDDEN,
+      "User also belongs to other organizations, only super admins can change their account"
+    );
+  }
+  Ok(())
+}
+
+/// The organization the user switched to, falling back to their oldest
+/// membership. Super admins may work in organizations they are not part of.
+pub async fn current_organization(
+  db: &Connection,
+  user: Uuid,
+  super_admin: bool,
+) -> Result<Option<Uuid>> {
+  if let Some(active) = db.organization().active(user).await?
+    && (db.organization().is_member(active, user).await? || super_admin)
+  {
+    return Ok(Some(active));
+  }
+
+  if let Some(first) = db.organization().list_for_user(user).await?.first() {
+    return Ok(Some(first.id));
+  }
+  if super_admin {
+    return Ok(
+      db.organization()
+        .default_organization()
+        .await?
+        .map(|o| o.id),
+    );
+  }
+
+  Ok(None)
+}
+
+pub async fn scoped_permissions(
+  db: &Connection,
+  organization: Uuid,
+  user: Uuid,
+  super_admin: bool,
+) -> Result<Vec<String>> {
+  if super_admin {
+    return Ok(permissions::names().into_iter().map(String::from).collect());
+  }
+  db.organization().user_permissions(organization, user).await
+}
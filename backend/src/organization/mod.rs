@@ -0,0 +1,595 @@
+use aide::axum::{
+  ApiRouter,
+  routing::{delete_with, get_with, post_with, put_with},
+};
+use axum::{
+  Json,
+  body::{Body, to_bytes},
+  extract::Request,
+  middleware::Next,
+  response::{IntoResponse, Response},
+};
+use centaurus::{
+  backend::auth::{
+    jwt_auth::JwtAuth,
+    permission::{Permission, SettingsView, UserEdit},
+  },
+  bail,
+  db::{init::Connection, tables::ConnectionExt},
+  error::Result,
+};
+use http::{Method, StatusCode};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{
+  audit::{AuditContext, AuditEntry},
+  branding,
+  db::{
+    DBTrait,
+    repository::{GroupRepository, UserRepository},
+  },
+  utils::{UpdateMessage, Updater, api_path},
+};
+
+pub use auth::{OrgAuth, current_organization, scoped_permissions};
+
+mod auth;
+
+/// Largest request or response inspected to scope routes of centaurus.
+const MAX_SCOPED_BODY: usize = 1024 * 1024;
+
+pub fn router() -> ApiRouter {
+  ApiRouter::new()
+    .api_route(
+      "/",
+      get_with(list_organizations, |op| op.id("listOrganizations")),
+    )
+    .api_route(
+      "/",
+      post_with(create_organization, |op| op.id("createOrganization")),
+    )
+    .api_route(
+      "/",
+      put_with(rename_organization, |op| op.id("renameOrganization")),
+    )
+    .api_route(
+      "/",
+      delete_with(delete_organization, |op| op.id("deleteOrganization")),
+    )
+    .api_route(
+      "/switch",
+      post_with(switch_organization, |op| op.id("switchOrganization")),
+    )
+    .api_route(
+      "/members",
+      post_with(add_member, |op| op.id("addOrganizationMember")),
+    )
+    .api_route(
+      "/members",
+      delete_with(remove_member, |op| op.id("removeOrganizationMember")),
+    )
+    .api_route(
+      "/super_admin",
+      get_with(list_super_admins, |op| op.id("listSuperAdmins")),
+    )
+    .api_route(
+      "/super_admin",
+      post_with(grant_super_admin, |op| op.id("grantSuperAdmin")),
+    )
+    .api_route(
+      "/super_admin",
+      delete_with(revoke_super_admin, |op| op.id("revokeSuperAdmin")),
+    )
+}
+
+/// Assigns groups created outside of any organization, like the admin group,
+/// to the default organization. Administrators of a setup that did not get to
+/// manage the instance, see [`middleware`], are made super admins.
+pub async fn init(db: &Connection) -> Result<()> {
+  let Some(default) = db.organization().default_organization().await? else {
+    bail!("Default organization is missing");
+  };
+  db.organization().adopt_groups(default.id).await?;
+
+  if db.organization().super_admins().await?.is_empty()
+    && let Some(admin_group) = db.setup().get_admin_group_id().await?
+  {
+    for user in db.group_members(admin_group).await? {
+      setup_super_admin(db, user).await?;
+    }
+  }
+  Ok(())
+}
+
+/// Adds a user that signed up on their own to the default organization.
+pub async fn join_default(db: &Connection, user: Uuid) -> Result<()> {
+  let Some(default) = db.organization().default_organization().await? else {
+    bail!("Default organization is missing");
+  };
+  db.organization().add_member(default.id, user).await
+}
+
+async fn ensure_super_admin(db: &Connection, user: Uuid) -> Result<()> {
+  if !db.organization().is_super_admin(user).await? {
+    bail!(FORBIDDEN, "Only super admins can do this");
+  }
+  Ok(())
+}
+
+#[derive(Serialize, JsonSchema)]
+struct OrganizationInfo {
+  uuid: Uuid,
+  name: String,
+  is_default: bool,
+  /// The organization the caller is currently working in.
+  current: bool,
+}
+
+/// Organizations the caller can switch to, every organization for super
+/// admins.
+async fn list_organizations(auth: JwtAuth, db: Connection) -> Result<Json<Vec<OrganizationInfo>>> {
+  let super_admin = db.organization().is_super_admin(auth.user_id).await?;
+  let organizations = if super_admin {
+    db.organization().list().await?
+  } else {
+    db.organization().list_for_user(auth.user_id).await?
+  };
+  let current = current_organization(&db, auth.user_id, super_admin).await?;
+
+  Ok(Json(
+    organizations
+      .into_iter()
+      .map(|organization| OrganizationInfo {
+        current: Some(organization.id) == current,
+        uuid: organization.id,
+        name: organization.name,
+        is_default: organization.is_default,
+      })
+      .collect(),
+  ))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct CreateOrganizationRequest {
+  name: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct OrganizationCreateResponse {
+  uuid: Uuid,
+}
+
+/// Organization as recorded in the audit log.
+#[derive(Serialize)]
+struct AuditedOrganization<'a> {
+  name: &'a str,
+}
+
+async fn create_organization(
+  auth: JwtAuth,
+  db: Connection,
+  updater: Updater,
+  audit: AuditContext,
+  Json(data): Json<CreateOrganizationRequest>,
+) -> Result<Json<OrganizationCreateResponse>> {
+  ensure_super_admin(&db, auth.user_id).await?;
+  let name = data.name.trim();
+  if name.is_empty() {
+    bail!(BAD_REQUEST, "Organization name cannot be empty");
+  }
+  if db.organization().find_by_name(name).await?.is_some() {
+    bail!(CONFLICT, "An organization with this name already exists");
+  }
+
+  let uuid = db.organization().create(name.to_string()).await?;
+
+  audit
+    .record(
+      &db,
+      &updater,
+      AuditEntry::new("organization.create")
+        .actor(auth.user_id)
+        .target("organization", uuid)
+        .after(&AuditedOrganization { name }),
+    )
+    .await;
+  updater
+    .broadcast(UpdateMessage::Organization { uuid })
+    .await;
+
+  Ok(Json(OrganizationCreateResponse { uuid }))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct RenameOrganizationRequest {
+  uuid: Uuid,
+  name: String,
+}
+
+async fn rename_organization(
+  auth: JwtAuth,
+  db: Connection,
+  updater: Updater,
+  audit: AuditContext,
+  Json(data): Json<RenameOrganizationRequest>,
+) -> Result<()> {
+  ensure_super_admin(&db, auth.user_id).await?;
+  let name = data.name.trim();
+  if name.is_empty() {
+    bail!(BAD_REQUEST, "Organization name cannot be empty");
+  }
+  let Some(existing) = db.organization().get(data.uuid).await? else {
+    bail!(NOT_FOUND, "Organization not found");
+  };
+  if let Some(other) = db.organization().find_by_name(name).await?
+    && other.id != data.uuid
+  {
+    bail!(CONFLICT, "An organization with this name already exists");
+  }
+
+  db.organization()
+    .rename(data.uuid, name.to_string())
+    .await?;
+
+  audit
+    .record(
+      &db,
+      &updater,
+      AuditEntry::new("organization.rename")
+        .actor(auth.user_id)
+        .target("organization", data.uuid)
+        .before(&AuditedOrganization {
+          name: &existing.name,
+        })
+        .after(&AuditedOrganization { name }),
+    )
+    .await;
+  updater
+    .broadcast(UpdateMessage::Organization { uuid: data.uuid })
+    .await;
+
+  Ok(())
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct OrganizationRequest {
+  uuid: Uuid,
+}
+
+/// Deletes an organization once all of its groups are gone, members stay
+/// around in their other organizations.
+async fn delete_organization(
+  auth: JwtAuth,
+  db: Connection,
+  updater: Updater,
+  audit: AuditContext,
+  Json(OrganizationRequest { uuid }): Json<OrganizationRequest>,
+) -> Result<()> {
+  ensure_super_admin(&db, auth.user_id).await?;
+  let Some(existing) = db.organization().get(uuid).await? else {
+    bail!(NOT_FOUND, "Organization not found");
+  };
+  if existing.is_default {
+    bail!(BAD_REQUEST, "The default organization cannot be deleted");
+  }
+  if db.organization().count_groups(uuid).await? > 0 {
+    bail!(CONFLICT, "Delete the groups of the organization first");
+  }
+
+  db.organization().delete(uuid).await?;
+  branding::forget(&db, auth.user_id, uuid).await?;
+
+  audit
+    .record(
+      &db,
+      &updater,
+      AuditEntry::new("organization.delete")
+        .actor(auth.user_id)
+        .target("organization", uuid)
+        .before(&AuditedOrganization {
+          name: &existing.name,
+        }),
+    )
+    .await;
+  updater
+    .broadcast(UpdateMessage::Organization { uuid })
+    .await;
+
+  Ok(())
+}
+
+async fn switch_organization(
+  auth: JwtAuth,
+  db: Connection,
+  updater: Updater,
+  Json(OrganizationRequest { uuid }): Json<OrganizationRequest>,
+) -> Result<()> {
+  let allowed = db.organization().is_member(uuid, auth.user_id).await?
+    || (db.organization().is_super_admin(auth.user_id).await?
+      && db.organization().get(uuid).await?.is_some());
+  if !allowed {
+    bail!(NOT_FOUND, "Organization not found");
+  }
+
+  db.organization().set_active(auth.user_id, uuid).await?;
+  updater
+    .send_to(auth.user_id, UpdateMessage::UserPermissions)
+    .await;
+
+  Ok(())
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct AddMemberRequest {
+  /// Email of an existing account.
+  email: String,
+}
+
+#[derive(Serialize)]
+struct AuditedMember {
+  organization: Uuid,
+}
+
+/// Adds an existing account to the current organization.
+async fn add_member(
+  auth: OrgAuth<UserEdit>,
+  db: Connection,
+  updater: Updater,
+  audit: AuditContext,
+  Json(data): Json<AddMemberRequest>,
+) -> Result<()> {
+  let Some(user) = db.find_user_by_email(data.email.trim()).await? else {
+    bail!(NOT_FOUND, "User not found");
+  };
+  if db
+    .organization()
+    .is_member(auth.organization, user.id)
+    .await?
+  {
+    bail!(CONFLICT, "User is already a member of the organization");
+  }
+
+  db.organization()
+    .add_member(auth.organization, user.id)
+    .await?;
+
+  audit
+    .record(
+      &db,
+      &updater,
+      AuditEntry::new("organization.member_add")
+        .actor(auth.user_id)
+        .target("user", user.id)
+        .after(&AuditedMember {
+          organization: auth.organization,
+        }),
+    )
+    .await;
+  updater
+    .broadcast(UpdateMessage::User { uuid: user.id })
+    .await;
+
+  Ok(())
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct RemoveMemberRequest {
+  uuid: Uuid,
+}
+
+/// Removes a user from the current organization and its groups. The account
+/// itself stays, like for members of other organizations.
+async fn remove_member(
+  auth: OrgAuth<UserEdit>,
+  db: Connection,
+  updater: Updater,
+  audit: AuditContext,
+  Json(RemoveMemberRequest { uuid }): Json<RemoveMemberRequest>,
+) -> Result<()> {
+  auth.ensure_member(&db, uuid).await?;
+
+  if let Some(admin_group) = db.setup().get_admin_group_id().await?
+    && db.is_last_member(admin_group, uuid).await?
+    && db.organization().group_organization(admin_group).await? == Some(auth.organization)
+  {
+    bail!(CONFLICT, "Cannot remove the last user of the admin group");
+  }
+
+  // removing someone must not take away more than the caller holds
+  let own = auth.permissions(&db).await?;
+  let theirs = db
+    .organization()
+    .user_permissions(auth.organization, uuid)
+    .await?;
+  if theirs.iter().any(|p| !own.contains(p)) {
+    bail!(
+      FORBIDDEN,
+      "Cannot remove a user with permissions you do not have"
+    );
+  }
+
+  db.organization()
+    .remove_member(auth.organization, uuid)
+    .await?;
+
+  audit
+    .record(
+      &db,
+      &updater,
+      AuditEntry::new("organization.member_remove")
+        .actor(auth.user_id)
+        .target("user", uuid)
+        .before(&AuditedMember {
+          organization: auth.organization,
+        }),
+    )
+    .await;
+  updater.broadcast(UpdateMessage::User { uuid }).await;
+  updater.send_to(uuid, UpdateMessage::UserPermissions).await;
+
+  Ok(())
+}
+
+async fn list_super_admins(auth: JwtAuth, db: Connection) -> Result<Json<Vec<Uuid>>> {
+  ensure_super_admin(&db, auth.user_id).await?;
+  Ok(Json(db.organization().super_admins().await?))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct SuperAdminRequest {
+  uuid: Uuid,
+}
+
+async fn grant_super_admin(
+  auth: JwtAuth,
+  db: Connection,
+  updater: Updater,
+  audit: AuditContext,
+  Json(SuperAdminRequest { uuid }): Json<SuperAdminRequest>,
+) -> Result<()> {
+  ensure_super_admin(&db, auth.user_id).await?;
+  let user = db.user().get_user_by_id(uuid).await?;
+  db.organization().add_super_admin(user.id).await?;
+
+  audit
+    .record(
+      &db,
+      &updater,
+      AuditEntry::new("organization.super_admin_grant")
+        .actor(auth.user_id)
+        .target("user", user.id),
+    )
+    .await;
+  updater
+    .send_to(user.id, UpdateMessage::UserPermissions)
+    .await;
+
+  Ok(())
+}
+
+async fn revoke_super_admin(
+  auth: JwtAuth,
+  db: Connection,
+  updater: Updater,
+  audit: AuditContext,
+  Json(SuperAdminRequest { uuid }): Json<SuperAdminRequest>,
+) -> Result<()> {
+  ensure_super_admin(&db, auth.user_id).await?;
+  let super_admins = db.organization().super_admins().await?;
+  if !super_admins.contains(&uuid) {
+    bail!(NOT_FOUND, "User is not a super admin");
+  }
+  if super_admins.len() == 1 {
+    bail!(CONFLICT, "Cannot revoke the last super admin");
+  }
+
+  db.organization().remove_super_admin(uuid).await?;
+
+  audit
+    .record(
+      &db,
+      &updater,
+      AuditEntry::new("organization.super_admin_revoke")
+        .actor(auth.user_id)
+        .target("user", uuid),
+    )
+    .await;
+  updater.send_to(uuid, UpdateMessage::UserPermissions).await;
+
+  Ok(())
+}
+
+#[derive(Clone, Copy)]
+enum Scope {
+  /// The administrator created by the setup joins the default organization
+  /// and manages the instance.
+  Setup,
+  /// The caller needs the permission in the current organization.
+  Permission(&'static str),
+  /// Instance wide settings.
+  SuperAdmin,
+}
+
+/// Endpoints provided by centaurus, which are scoped to organizations from
+/// the outside. The application's own handlers use [`OrgAuth`] directly, so
+/// everything that touches users or groups of an organization is one of them.
+fn scoped_route(method: &Method, path: &str) -> Option<Scope> {
+  let scope = match (method.as_str(), path) {
+    ("POST", "/setup") => Scope::Setup,
+    ("GET", "/settings/user") | ("GET", "/settings/mail") => {
+      Scope::Permission(SettingsView::name())
+    }
+    ("POST", "/settings/user") | ("POST", "/settings/mail") | ("POST", "/mail/test") => {
+      Scope::SuperAdmin
+    }
+    _ => return None,
+  };
+
+  Some(scope)
+}
+
+fn body_uuid(bytes: &[u8], key: &str) -> Option<Uuid> {
+  serde_json::from_slice::<Value>(bytes)
+    .ok()?
+    .get(key)?
+    .as_str()
+    .and_then(|id| Uuid::parse_str(id).ok())
+}
+
+pub async fn middleware(
+  db: Connection,
+  auth: Option<JwtAuth>,
+  request: Request,
+  next: Next,
+) -> Response {
+  let Some(scope) = scoped_route(request.method(), api_path(request.uri().path())) else {
+    return next.run(request).await;
+  };
+
+  match (scope, auth) {
+    (Scope::Setup, _) => {
+      let response = next.run(request).await;
+      if !response.status().is_success() {
+        return response;
+      }
+      let (parts, body) = response.into_parts();
+      let Ok(bytes) = to_bytes(body, MAX_SCOPED_BODY).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+      };
+      if let Some(user) = body_uuid(&bytes, "user")
+        && let Err(err) = setup_super_admin(&db, user).await
+      {
+        error!(
+          "Failed to add the setup user {} to the default organization, retrying on the next start: {:?}",
+          user, err
+        );
+      }
+      Response::from_parts(parts, Body::from(bytes))
+    }
+    // centaurus rejects the request on its own
+    (_, None) => next.run(request).await,
+    (Scope::SuperAdmin, Some(auth)) => {
+      if let Err(err) = ensure_super_admin(&db, auth.user_id).await {
+        return err.into_response();
+      }
+      next.run(request).await
+    }
+    (Scope::Permission(permission), Some(auth)) => {
+      if let Err(err) = auth::authorize(&db, auth.user_id, permission).await {
+        return err.into_response();
+      }
+      next.run(request).await
+    }
+  }
+}
+
+async fn setup_super_admin(db: &Connection, user: Uuid) -> Result<()> {
+  join_default(db, user).await?;
+  db.organization().add_super_admin(user).await?;
+  info!("Made setup user {} a super admin", user);
+  Ok(())
+}
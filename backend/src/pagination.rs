@@ -0,0 +1,181 @@
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use centaurus::{bail, error::Result};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use sea_orm::{
+  Condition, EntityTrait, Order, QueryFilter, QueryOrder, QuerySelect, Select, prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+const DEFAULT_PAGE_SIZE: u64 = 50;
+const MAX_PAGE_SIZE: u64 = 200;
+
+#[derive(Deserialize, JsonSchema, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+  #[default]
+  Asc,
+  Desc,
+}
+
+/// Query parameters shared by every paginated listing.
+#[derive(Deserialize, JsonSchema)]
+pub struct PageQuery {
+  /// `next_cursor` of the previous page, the first page if omitted.
+  cursor: Option<String>,
+  limit: Option<u64>,
+  #[serde(default)]
+  order: SortOrder,
+}
+
+/// Envelope of every paginated listing.
+#[derive(Serialize, JsonSchema)]
+pub struct Page<T> {
+  pub items: Vec<T>,
+  /// Cursor of the next page, none on the last page.
+  pub next_cursor: Option<String>,
+  /// Number of matching items over all pages.
+  pub total: u64,
+}
+
+impl<T> Page<T> {
+  pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+    Page {
+      items: self.items.into_iter().map(f).collect(),
+      next_cursor: self.next_cursor,
+      total: self.total,
+    }
+  }
+}
+
+/// Position after the last item of a page. Items are ordered by the sort key
+/// and then by id, so the position is unique even if sort keys repeat.
+#[derive(Serialize, Deserialize)]
+struct Cursor {
+  sort: String,
+  value: Option<String>,
+  id: Uuid,
+}
+
+pub struct PageRequest {
+  sort: &'static str,
+  cursor: Option<Cursor>,
+  limit: u64,
+  order: SortOrder,
+}
+
+impl PageRequest {
+  /// `sort` names the sort key, a cursor is only valid for the key it was
+  /// created with.
+  pub fn new(query: PageQuery, sort: &'static str) -> Result<Self> {
+    let cursor = match query.cursor {
+      Some(cursor) => {
+        let Some(cursor) = BASE64_URL_SAFE_NO_PAD
+          .decode(cursor)
+          .ok()
+          .and_then(|bytes| serde_json::from_slice::<Cursor>(&bytes).ok())
+        else {
+          bail!(BAD_REQUEST, "Invalid cursor");
+        };
+        if cursor.sort != sort {
+          bail!(BAD_REQUEST, "Cursor does not match the sort order");
+        }
+        Some(cursor)
+      }
+      None => None,
+    };
+
+    Ok(Self {
+      sort,
+      cursor,
+      limit: query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE),
+      order: query.order,
+    })
+  }
+
+  /// Orders the query, skips everything up to the cursor and fetches one item
+  /// more than requested to find out whether there is a next page. `sort` is
+  /// none when sorting by id only.
+  pub fn apply<E: EntityTrait>(
+    &self,
+    mut query: Select<E>,
+    sort: Option<E::Column>,
+    id: E::Column,
+  ) -> Select<E> {
+    let order = match self.order {
+      SortOrder::Asc => Order::Asc,
+      SortOrder::Desc => Order::Desc,
+    };
+    let after = |column: E::Column, value: Value| match self.order {
+      SortOrder::Asc => column.gt(value),
+      SortOrder::Desc => column.lt(value),
+    };
+
+    if let Some(cursor) = &self.cursor {
+      let condition = match (sort, &cursor.value) {
+        (Some(sort), Some(value)) => Condition::any().add(after(sort, value.clone().into())).add(
+          Condition::all()
+            .add(sort.eq(value.clone()))
+            .add(after(id, cursor.id.into())),
+        ),
+        _ => Condition::all().add(after(id, cursor.id.into())),
+      };
+      query = query.filter(condition);
+    }
+    if let Some(sort) = sort {
+      query = query.order_by(sort, order.clone());
+    }
+
+    query.order_by(id, order).limit(self.limit + 1)
+  }
+
+  /// Builds the page from the items fetched with [`Self::apply`].
+  pub fn page<T>(
+    &self,
+    mut items: Vec<T>,
+    total: u64,
+    position: impl Fn(&T) -> (Option<String>, Uuid),
+  ) -> Page<T> {
+    let next_cursor = if items.len() as u64 > self.limit {
+      items.truncate(self.limit as usize);
+      items.last().map(|item| {
+        let (value, id) = position(item);
+        let cursor = Cursor {
+          sort: self.sort.to_string(),
+          value,
+          id,
+        };
+        BASE64_URL_SAFE_NO_PAD.encode(serde_json::to_vec(&cursor).unwrap_or_default())
+      })
+    } else {
+      None
+    };
+
+    Page {
+      items,
+      next_cursor,
+      total,
+    }
+  }
+}
+
+/// Ids are UUIDv7, so they carry the time the row was created.
+pub fn created_at(id: Uuid) -> Option<DateTime<Utc>> {
+  let (secs, nanos) = id.get_timestamp()?.to_unix();
+  DateTime::from_timestamp(secs as i64, nanos)
+}
+
+/// `LIKE` pattern matching the term anywhere, with wildcards in the term
+/// escaped.
+pub fn contains_pattern(term: &str) -> String {
+  let escaped = term
+    .to_lowercase()
+    .replace('\\', "\\\\")
+    .replace('%', "\\%")
+    .replace('_', "\\_");
+  format!("%{escaped}%")
+}
@@ -0,0 +1,120 @@
+use std::collections::HashSet;
+
+use centaurus::backend::auth::permission::{Permission, UserEdit, UserView};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{attribute, audit, flag, group, impersonation, role, settings};
+
+/// Description of a single permission as shown in the group editor.
+#[derive(Serialize, JsonSchema, Clone, Debug)]
+pub struct PermissionInfo {
+  pub name: &'static str,
+  pub description: &'static str,
+  pub category: &'static str,
+  /// Permissions that are granted automatically together with this one.
+  pub implies: Vec<&'static str>,
+}
+
+impl PermissionInfo {
+  pub fn new(name: &'static str, description: &'static str, category: &'static str) -> Self {
+    Self {
+      name,
+      description,
+      category,
+      implies: Vec::new(),
+    }
+  }
+
+  pub fn implies(mut self, permissions: &[&'static str]) -> Self {
+    self.implies.extend_from_slice(permissions);
+    self
+  }
+}
+
+/// Every permission known to the application, contributed by the modules that
+/// guard their endpoints with it.
+pub fn registry() -> Vec<PermissionInfo> {
+  let mut permissions = user_permissions();
+  permissions.extend(impersonation::permissions());
+  permissions.extend(attribute::permissions());
+  permissions.extend(group::permissions());
+  permissions.extend(role::permissions());
+  permissions.extend(settings::permissions());
+  permissions.extend(audit::permissions());
+  permissions.extend(flag::permissions());
+  permissions
+}
+
+pub fn names() -> Vec<&'static str> {
+  registry().into_iter().map(|p| p.name).collect()
+}
+
+pub fn is_known(permission: &str) -> bool {
+  registry().iter().any(|p| p.name == permission)
+}
+
+/// Adds every permission implied by the given ones (transitively).
+pub fn expand(permissions: Vec<String>) -> Vec<String> {
+  let registry = registry();
+  let mut result: Vec<String> = Vec::new();
+  let mut seen = HashSet::new();
+  let mut queue = permissions;
+
+  while let Some(permission) = queue.pop() {
+    if !seen.insert(permission.clone()) {
+      continue;
+    }
+
+    if let Some(info) = registry.iter().find(|p| p.name == permission) {
+      queue.extend(info.implies.iter().map(|p| p.to_string()));
+    }
+    result.push(permission);
+  }
+
+  result.sort_unstable();
+  result
+}
+
+// user management is provided by centaurus directly
+fn user_permissions() -> Vec<PermissionInfo> {
+  vec![
+    PermissionInfo::new(UserView::name(), "View users and their groups", "Users"),
+    PermissionInfo::new(UserEdit::name(), "Create, edit and delete users", "Users")
+      .implies(&[UserView::name()]),
+  ]
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use centaurus::backend::auth::permission::GroupEdit;
+
+  #[test]
+  fn registry_is_consistent() {
+    let names = names();
+
+    // everything centaurus guards its endpoints with must be grantable
+    for permission in centaurus::backend::auth::permission::permissions() {
+      assert!(
+        names.contains(&permission),
+        "{permission} is not registered"
+      );
+    }
+
+    let unique: HashSet<_> = names.iter().collect();
+    assert_eq!(unique.len(), names.len(), "duplicate permission names");
+
+    for info in registry() {
+      for implied in info.implies {
+        assert!(names.contains(&implied), "{implied} is not registered");
+      }
+    }
+  }
+
+  #[test]
+  fn expand_adds_implied_permissions() {
+    let expanded = expand(vec![GroupEdit::name().to_string()]);
+    assert_eq!(expanded, vec!["group:edit", "group:view"]);
+  }
+}
@@ -0,0 +1,176 @@
+use std::{
+  collections::HashMap,
+  net::{IpAddr, SocketAddr},
+  sync::{Arc, Mutex},
+  time::{Duration, Instant},
+};
+
+use axum::{
+  Extension,
+  extract::{ConnectInfo, Request, State},
+  middleware::Next,
+  response::{IntoResponse, Response},
+};
+use http::{HeaderMap, StatusCode, header::RETRY_AFTER};
+
+use crate::config::Config;
+
+/// Buckets untouched for this long are full again and dropped.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Token buckets per route group and client. The rates are read from the
+/// configuration on every request, so reloading it applies them right away.
+#[derive(Clone, Default)]
+pub struct RateLimits {
+  buckets: Arc<Mutex<HashMap<(&'static str, IpAddr), Bucket>>>,
+}
+
+struct Bucket {
+  tokens: f64,
+  updated: Instant,
+}
+
+impl RateLimits {
+  pub fn new() -> Self {
+    let limits = Self::default();
+
+    let buckets = limits.buckets.clone();
+    tokio::spawn(async move {
+      let mut interval = tokio::time::interval(IDLE_TIMEOUT);
+      loop {
+        interval.tick().await;
+        let now = Instant::now();
+        buckets
+          .lock()
+          .expect("Rate limit state poisoned")
+          .retain(|_, bucket| now.duration_since(bucket.updated) < IDLE_TIMEOUT);
+      }
+    });
+
+    limits
+  }
+
+  /// Takes a request from the bucket, returns how long to wait if it is
+  /// empty. A request is added back every `replenish_seconds`.
+  fn take(
+    &self,
+    key: (&'static str, IpAddr),
+    replenish_seconds: u32,
+    burst: u32,
+  ) -> Option<Duration> {
+    let now = Instant::now();
+    let per_second = 1.0 / replenish_seconds.max(1) as f64;
+    let burst = burst.max(1) as f64;
+
+    let mut buckets = self.buckets.lock().expect("Rate limit state poisoned");
+    let bucket = buckets.entry(key).or_insert(Bucket {
+      tokens: burst,
+      updated: now,
+    });
+    let refilled = now.duration_since(bucket.updated).as_secs_f64() * per_second;
+    bucket.tokens = (bucket.tokens + refilled).min(burst);
+    bucket.updated = now;
+
+    if bucket.tokens >= 1.0 {
+      bucket.tokens -= 1.0;
+      None
+    } else {
+      Some(Duration::from_secs_f64((1.0 - bucket.tokens) / per_second))
+    }
+  }
+}
+
+/// Limits requests per client, the state is the name of the route group so
+/// every group has its own buckets. Used with
+/// `from_fn_with_state("group", rate_limit::limit)`.
+pub async fn limit(
+  State(group): State<&'static str>,
+  Extension(limits): Extension<RateLimits>,
+  config: Config,
+  req: Request,
+  next: Next,
+) -> Response {
+  let Some(ip) = client_ip(&req) else {
+    return next.run(req).await;
+  };
+
+  match limits.take(
+    (group, ip),
+    config.rate_limit_replenish_seconds,
+    config.rate_limit_burst,
+  ) {
+    None => next.run(req).await,
+    Some(wait) => (
+      StatusCode::TOO_MANY_REQUESTS,
+      [(RETRY_AFTER, wait.as_secs().max(1).to_string())],
+    )
+      .into_response(),
+  }
+}
+
+/// The client behind a proxy if it forwarded one, the peer otherwise.
+fn client_ip(req: &Request) -> Option<IpAddr> {
+  header_ip(req.headers(), "x-forwarded-for")
+    .or_else(|| header_ip(req.headers(), "x-real-ip"))
+    .or_else(|| {
+      req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|info| info.0.ip())
+    })
+}
+
+fn header_ip(headers: &HeaderMap, name: &str) -> Option<IpAddr> {
+  headers
+    .get(name)?
+    .to_str()
+    .ok()?
+    .split(',')
+    .next()?
+    .trim()
+    .parse()
+    .ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn bursts_are_limited_per_client_and_group() {
+    let limits = RateLimits::default();
+    let client: IpAddr = "10.0.0.1".parse().unwrap();
+    let other: IpAddr = "10.0.0.2".parse().unwrap();
+
+    for _ in 0..3 {
+      assert!(limits.take(("auth", client), 1, 3).is_none());
+    }
+    let wait = limits.take(("auth", client), 1, 3).unwrap();
+    assert!(wait <= Duration::from_secs(1));
+
+    assert!(limits.take(("auth", other), 1, 3).is_none());
+    assert!(limits.take(("account", client), 1, 3).is_none());
+  }
+
+  #[test]
+  fn defaults_allow_no_more_than_centaurus() {
+    // The limiter of centaurus adds a request every 10 seconds with a burst
+    // of 20.
+    let config = Config::default();
+    let limits = RateLimits::default();
+    let client: IpAddr = "10.0.0.1".parse().unwrap();
+    let take = || {
+      limits.take(
+        ("auth", client),
+        config.rate_limit_replenish_seconds,
+        config.rate_limit_burst,
+      )
+    };
+
+    for _ in 0..20 {
+      assert!(take().is_none());
+    }
+    let wait = take().unwrap();
+    assert!(wait > Duration::from_secs(9));
+  }
+}
@@ -0,0 +1,378 @@
+use aide::axum::{
+  ApiRouter,
+  routing::{delete_with, get_with, post_with, put_with},
+};
+use axum::{Json, extract::Path};
+use centaurus::{
+  backend::auth::permission::{GroupView, Permission, SettingsView, UserEdit, UserView},
+  bail,
+  db::{init::Connection, tables::ConnectionExt},
+  error::Result,
+  permission,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+  audit::{AuditContext, AuditEntry},
+  db::{
+    DBTrait,
+    repository::GroupRepository,
+    role::{RoleDetails, RoleInfo},
+  },
+  organization::OrgAuth,
+  permissions::{self, PermissionInfo},
+  utils::{UpdateMessage, Updater},
+};
+
+permission!(RoleView, "role:view");
+permission!(RoleEdit, "role:edit");
+
+pub fn router() -> ApiRouter {
+  ApiRouter::new()
+    .api_route("/", get_with(list_roles, |op| op.id("listRoles")))
+    .api_route("/", post_with(create_role, |op| op.id("createRole")))
+    .api_route("/", put_with(edit_role, |op| op.id("editRole")))
+    .api_route("/", delete_with(delete_role, |op| op.id("deleteRole")))
+    .api_route("/{uuid}", get_with(role_info, |op| op.id("roleInfo")))
+}
+
+pub fn permissions() -> Vec<PermissionInfo> {
+  vec![
+    PermissionInfo::new(
+      RoleView::name(),
+      "View roles and their permissions",
+      "Roles",
+    ),
+    PermissionInfo::new(RoleEdit::name(), "Create, edit and delete roles", "Roles")
+      .implies(&[RoleView::name()]),
+  ]
+}
+
+struct BuiltInRole {
+  name: &'static str,
+  description: &'static str,
+  permissions: Vec<&'static str>,
+}
+
+fn built_in_roles() -> Vec<BuiltInRole> {
+  vec![
+    BuiltInRole {
+      name: "Viewer",
+      description: "Read-only access to users, groups, roles and settings",
+      permissions: vec![
+        UserView::name(),
+        GroupView::name(),
+        RoleView::name(),
+        SettingsView::name(),
+      ],
+    },
+    BuiltInRole {
+      name: "User manager",
+      description: "Manage users and view groups",
+      permissions: vec![UserEdit::name(), GroupView::name()],
+    },
+  ]
+}
+
+/// Seeds the built-in roles, grants the admin group every registered
+/// permission directly and brings all effective group permissions up to date.
+pub async fn init(db: &Connection) -> Result<()> {
+  if let Some(admin_group) = db.setup().get_admin_group_id().await? {
+    let names = permissions::names().into_iter().map(String::from).collect();
+    db.role().add_direct_permissions(admin_group, names).await?;
+  }
+
+  for built_in in built_in_roles() {
+    let permissions = built_in.permissions.into_iter().map(String::from).collect();
+
+    match db.role().find_role_by_name(built_in.name).await? {
+      Some(role) if role.built_in => {
+        db.role()
+          .edit_role(
+            role.id,
+            built_in.name.to_string(),
+            built_in.description.to_string(),
+            permissions,
+          )
+          .await?;
+      }
+      Some(_) => warn!(
+        "A custom role named '{}' exists, skipping built-in role",
+        built_in.name
+      ),
+      None => {
+        db.role()
+          .create_role(
+            built_in.name.to_string(),
+            built_in.description.to_string(),
+            permissions,
+            true,
+          )
+          .await?;
+      }
+    }
+  }
+
+  db.role().sync_all_groups().await
+}
+
+async fn list_roles(_auth: OrgAuth<RoleView>, db: Connection) -> Result<Json<Vec<RoleInfo>>> {
+  Ok(Json(db.role().list_roles().await?))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct RoleViewPath {
+  uuid: Uuid,
+}
+
+/// A role together with the groups of the current organization using it.
+async fn role_info(
+  auth: OrgAuth<RoleView>,
+  db: Connection,
+  Path(path): Path<RoleViewPath>,
+) -> Result<Json<RoleDetails>> {
+  let Some(mut info) = db.role().role_info(path.uuid).await? else {
+    bail!(NOT_FOUND, "Role not found");
+  };
+  let groups: Vec<Uuid> = db
+    .organization()
+    .groups(auth.organization)
+    .await?
+    .into_iter()
+    .map(|g| g.id)
+    .collect();
+  info.groups.retain(|group| groups.contains(&group.uuid));
+
+  Ok(Json(info))
+}
+
+/// Roles are shared by every organization, so only super admins change them.
+fn ensure_super_admin(auth: &OrgAuth<RoleEdit>) -> Result<()> {
+  if !auth.super_admin {
+    bail!(FORBIDDEN, "Only super admins can change roles");
+  }
+  Ok(())
+}
+
+/// Rejects unknown permissions and permissions the editor does not hold
+/// themselves, so roles cannot be used to escalate privileges.
+async fn check_permissions(
+  db: &Connection,
+  auth: &OrgAuth<RoleEdit>,
+  requested: &[String],
+) -> Result<()> {
+  if let Some(unknown) = requested.iter().find(|p| !permissions::is_known(p)) {
+    bail!(BAD_REQUEST, "Unknown permission {}", unknown);
+  }
+
+  let user_permissions = auth.permissions(db).await?;
+  if permissions::expand(requested.to_vec())
+    .iter()
+    .any(|perm| !user_permissions.contains(perm))
+  {
+    bail!(
+      FORBIDDEN,
+      "Cannot assign permissions you do not have to a role"
+    );
+  }
+
+  Ok(())
+}
+
+/// Recomputes the effective permissions of the given groups and notifies
+/// members of every group whose permissions changed.
+async fn sync_groups(db: &Connection, updater: &Updater, groups: Vec<Uuid>) -> Result<()> {
+  for group_id in groups {
+    if !db.role().sync_group_permissions(group_id).await? {
+      continue;
+    }
+
+    updater
+      .broadcast(UpdateMessage::Group { uuid: group_id })
+      .await;
+    for user_id in db.group_members(group_id).await? {
+      updater
+        .send_to(user_id, UpdateMessage::UserPermissions)
+        .await;
+    }
+  }
+
+  Ok(())
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct CreateRoleRequest {
+  name: String,
+  #[serde(default)]
+  description: String,
+  permissions: Vec<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct RoleCreateResponse {
+  uuid: Uuid,
+}
+
+async fn create_role(
+  auth: OrgAuth<RoleEdit>,
+  db: Connection,
+  updater: Updater,
+  audit: AuditContext,
+  Json(data): Json<CreateRoleRequest>,
+) -> Result<Json<RoleCreateResponse>> {
+  ensure_super_admin(&auth)?;
+  if data.name.trim().is_empty() {
+    bail!(BAD_REQUEST, "Role name cannot be empty");
+  }
+  if db.role().find_role_by_name(&data.name).await?.is_some() {
+    bail!(CONFLICT, "A role with this name already exists");
+  }
+
+  let mut permissions = data.permissions;
+  permissions.sort_unstable();
+  permissions.dedup();
+  check_permissions(&db, &auth, &permissions).await?;
+
+  let uuid = db
+    .role()
+    .create_role(data.name, data.description, permissions, false)
+    .await?;
+
+  updater.broadcast(UpdateMessage::Role { uuid }).await;
+
+  let mut entry = AuditEntry::new("role.create")
+    .actor(auth.user_id)
+    .target("role", uuid);
+  if let Some(after) = db.role().role_info(uuid).await? {
+    entry = entry.after(&after);
+  }
+  audit.record(&db, &updater, entry).await;
+
+  Ok(Json(RoleCreateResponse { uuid }))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct EditRoleRequest {
+  uuid: Uuid,
+  name: String,
+  #[serde(default)]
+  description: String,
+  permissions: Vec<String>,
+}
+
+async fn edit_role(
+  auth: OrgAuth<RoleEdit>,
+  db: Connection,
+  updater: Updater,
+  audit: AuditContext,
+  Json(data): Json<EditRoleRequest>,
+) -> Result<()> {
+  ensure_super_admin(&auth)?;
+  if data.name.trim().is_empty() {
+    bail!(BAD_REQUEST, "Role name cannot be empty");
+  }
+
+  let Some(role) = db.role().role_info(data.uuid).await? else {
+    bail!(NOT_FOUND, "Role not found");
+  };
+  if role.built_in {
+    bail!(FORBIDDEN, "Built-in roles cannot be changed");
+  }
+
+  if let Some(existing_role) = db.role().find_role_by_name(&data.name).await?
+    && existing_role.id != data.uuid
+  {
+    bail!(CONFLICT, "A role with this name already exists");
+  }
+
+  let user_permissions = auth.permissions(&db).await?;
+  if permissions::expand(role.permissions.clone())
+    .iter()
+    .any(|perm| !user_permissions.contains(perm))
+  {
+    bail!(
+      FORBIDDEN,
+      "Cannot edit a role with permissions you do not have"
+    );
+  }
+
+  let mut permissions = data.permissions;
+  permissions.sort_unstable();
+  permissions.dedup();
+  check_permissions(&db, &auth, &permissions).await?;
+
+  db.role()
+    .edit_role(data.uuid, data.name, data.description, permissions)
+    .await?;
+
+  let mut entry = AuditEntry::new("role.edit")
+    .actor(auth.user_id)
+    .target("role", data.uuid)
+    .before(&role);
+  if let Some(after) = db.role().role_info(data.uuid).await? {
+    entry = entry.after(&after);
+  }
+  audit.record(&db, &updater, entry).await;
+
+  updater
+    .broadcast(UpdateMessage::Role { uuid: data.uuid })
+    .await;
+
+  let groups = db.role().get_role_group_ids(data.uuid).await?;
+  sync_groups(&db, &updater, groups).await
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct DeleteRoleRequest {
+  uuid: Uuid,
+}
+
+async fn delete_role(
+  auth: OrgAuth<RoleEdit>,
+  db: Connection,
+  updater: Updater,
+  audit: AuditContext,
+  Json(data): Json<DeleteRoleRequest>,
+) -> Result<()> {
+  ensure_super_admin(&auth)?;
+  let Some(role) = db.role().role_info(data.uuid).await? else {
+    bail!(NOT_FOUND, "Role not found");
+  };
+  if role.built_in {
+    bail!(FORBIDDEN, "Built-in roles cannot be deleted");
+  }
+
+  let user_permissions = auth.permissions(&db).await?;
+  if permissions::expand(role.permissions.clone())
+    .iter()
+    .any(|perm| !user_permissions.contains(perm))
+  {
+    bail!(
+      FORBIDDEN,
+      "Cannot delete a role with permissions you do not have"
+    );
+  }
+
+  let groups = db.role().get_role_group_ids(data.uuid).await?;
+  db.role().delete_role(data.uuid).await?;
+
+  audit
+    .record(
+      &db,
+      &updater,
+      AuditEntry::new("role.delete")
+        .actor(auth.user_id)
+        .target("role", data.uuid)
+        .before(&role),
+    )
+    .await;
+
+  updater
+    .broadcast(UpdateMessage::Role { uuid: data.uuid })
+    .await;
+
+  sync_groups(&db, &updater, groups).await
+}
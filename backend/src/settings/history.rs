@@ -0,0 +1,456 @@
+use std::collections::BTreeSet;
+
+use aide::axum::{
+  ApiRouter,
+  routing::{get_with, post_with},
+};
+use axum::{
+  Json,
+  extract::{Path, Query, Request},
+  middleware::Next,
+  response::Response,
+};
+use centaurus::{
+  backend::auth::{
+    jwt_auth::JwtAuth,
+    oidc::OidcState,
+    permission::{SettingsEdit, SettingsView},
+  },
+  bail,
+  db::{init::Connection, settings::Settings},
+  error::Result,
+  mail::Mailer,
+};
+use chrono::{DateTime, Utc};
+use entity::settings_revision;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{
+  audit::{self, AuditContext, AuditEntry},
+  config::Config,
+  db::{DBTrait, repository::SettingsRepositoryExt},
+  organization::OrgAuth,
+  settings::section::{Section, parse},
+  utils::{UpdateMessage, Updater, api_path},
+};
+
+const DEFAULT_PAGE_SIZE: u64 = 50;
+const MAX_PAGE_SIZE: u64 = 200;
+
+pub fn router() -> ApiRouter {
+  ApiRouter::new()
+    .api_route(
+      "/{section}",
+      get_with(list_revisions, |op| op.id("listSettingsRevisions")),
+    )
+    .api_route(
+      "/{section}/diff",
+      get_with(diff_revisions, |op| op.id("diffSettingsRevisions")),
+    )
+    .api_route(
+      "/{section}/rollback",
+      post_with(rollback, |op| op.id("rollbackSettings")),
+    )
+    .api_route(
+      "/{section}/{revision}",
+      get_with(get_revision, |op| op.id("getSettingsRevision")),
+    )
+}
+
+/// Saves the settings and keeps the change as a new revision.
+pub async fn save<S: Settings + Sync>(db: &Connection, author: Uuid, settings: &S) -> Result<()> {
+  baseline(db, S::id()).await?;
+  db.save_settings(settings).await?;
+  record(db, S::id(), Some(author), None).await?;
+  Ok(())
+}
+
+/// Replaces the stored settings with the given content, e.g. of an older
+/// revision, and keeps the change as a new revision.
+pub async fn replace(
+  db: &Connection,
+  id: i32,
+  content: String,
+  author: Uuid,
+  restored_from: Option<i32>,
+) -> Result<Option<i32>> {
+  baseline(db, id).await?;
+  db.settings_history().restore(id, content).await?;
+  record(db, id, Some(author), restored_from).await
+}
+
+/// Keeps settings saved before revisions existed as the first revision, so
+/// the first change can be rolled back as well.
+async fn baseline(db: &Connection, id: i32) -> Result<()> {
+  if db.settings_history().latest(id).await?.is_some() {
+    return Ok(());
+  }
+  if let Some(content) = db.settings_history().current(id).await? {
+    db.settings_history()
+      .append(id, content, None, None)
+      .await?;
+  }
+  Ok(())
+}
+
+/// Stores the current content as a revision unless it did not change.
+async fn record(
+  db: &Connection,
+  id: i32,
+  author: Option<Uuid>,
+  restored_from: Option<i32>,
+) -> Result<Option<i32>> {
+  let Some(content) = db.settings_history().current(id).await? else {
+    return Ok(None);
+  };
+  if let Some(latest) = db.settings_history().latest(id).await?
+    && latest.content == content
+  {
+    return Ok(None);
+  }
+
+  Ok(Some(
+    db.settings_history()
+      .append(id, content, author, restored_from)
+      .await?,
+  ))
+}
+
+/// Records revisions for the settings endpoints of centaurus. Failing to do so
+/// is logged but does not fail the already saved settings.
+pub async fn middleware(
+  db: Connection,
+  auth: Option<JwtAuth>,
+  request: Request,
+  next: Next,
+) -> Response {
+  let (Some(section), Some(auth)) = (
+    Section::saved_by(request.method(), api_path(request.uri().path())),
+    auth,
+  ) else {
+    return next.run(request).await;
+  };
+
+  if let Err(err) = baseline(&db, section.id()).await {
+    error!("Failed to keep the {} settings: {:?}", section.name(), err);
+  }
+  let response = next.run(request).await;
+  if response.status().is_success()
+    && let Err(err) = record(&db, section.id(), Some(auth.user_id), None).await
+  {
+    error!(
+      "Failed to record the {} settings: {:?}",
+      section.name(),
+      err
+    );
+  }
+
+  response
+}
+
+/// Revisions of every section span all organizations, so only super admins
+/// may see and restore them.
+fn ensure_super_admin<P: centaurus::backend::auth::permission::Permission>(
+  auth: &OrgAuth<P>,
+) -> Result<()> {
+  if !auth.super_admin {
+    bail!(
+      FORBIDDEN,
+      "Only super admins can access the settings history"
+    );
+  }
+  Ok(())
+}
+
+#[derive(Serialize, JsonSchema)]
+struct RevisionInfo {
+  revision: i32,
+  /// `None` for settings saved before their history was kept.
+  author: Option<Uuid>,
+  created_at: DateTime<Utc>,
+  /// Set if the revision was made by rolling back to an older one.
+  restored_from: Option<i32>,
+}
+
+impl From<&settings_revision::Model> for RevisionInfo {
+  fn from(model: &settings_revision::Model) -> Self {
+    Self {
+      revision: model.revision,
+      author: model.author,
+      created_at: model.created_at.and_utc(),
+      restored_from: model.restored_from,
+    }
+  }
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct SectionPath {
+  /// `user`, `mail`, `branding` or the key of module settings.
+  section: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct RevisionQuery {
+  limit: Option<u64>,
+}
+
+async fn list_revisions(
+  auth: OrgAuth<SettingsView>,
+  db: Connection,
+  Path(SectionPath { section }): Path<SectionPath>,
+  Query(query): Query<RevisionQuery>,
+) -> Result<Json<Vec<RevisionInfo>>> {
+  ensure_super_admin(&auth)?;
+  let section = Section::parse(&section)?;
+  let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+  let revisions = db.settings_history().list(section.id(), limit).await?;
+
+  Ok(Json(revisions.iter().map(RevisionInfo::from).collect()))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct RevisionPath {
+  section: String,
+  revision: i32,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct RevisionContent {
+  #[serde(flatten)]
+  info: RevisionInfo,
+  /// The stored settings without secrets.
+  content: Value,
+}
+
+async fn load(
+  db: &Connection,
+  section: Section,
+  revision: i32,
+) -> Result<settings_revision::Model> {
+  let Some(model) = db.settings_history().get(section.id(), revision).await? else {
+    bail!(NOT_FOUND, "Revision not found");
+  };
+  Ok(model)
+}
+
+async fn get_revision(
+  auth: OrgAuth<SettingsView>,
+  db: Connection,
+  Path(RevisionPath { section, revision }): Path<RevisionPath>,
+) -> Result<Json<RevisionContent>> {
+  ensure_super_admin(&auth)?;
+  let section = Section::parse(&section)?;
+  let model = load(&db, section, revision).await?;
+
+  Ok(Json(RevisionContent {
+    info: RevisionInfo::from(&model),
+    content: audit::redact(parse(&model.content)?),
+  }))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct DiffQuery {
+  from: i32,
+  /// Defaults to the latest revision.
+  to: Option<i32>,
+}
+
+#[derive(Serialize, JsonSchema, PartialEq, Debug)]
+pub struct SettingsChange {
+  /// Dot separated path of the changed value, e.g. `legal_links.0.url`.
+  pub path: String,
+  /// `None` if the value was added.
+  pub before: Option<Value>,
+  /// `None` if the value was removed.
+  pub after: Option<Value>,
+  /// The value is a secret and was left out.
+  pub redacted: bool,
+}
+
+async fn diff_revisions(
+  auth: OrgAuth<SettingsView>,
+  db: Connection,
+  Path(SectionPath { section }): Path<SectionPath>,
+  Query(query): Query<DiffQuery>,
+) -> Result<Json<Vec<SettingsChange>>> {
+  ensure_super_admin(&auth)?;
+  let section = Section::parse(&section)?;
+  let from = load(&db, section, query.from).await?;
+  let to = match query.to {
+    Some(revision) => load(&db, section, revision).await?,
+    None => {
+      let Some(latest) = db.settings_history().latest(section.id()).await? else {
+        bail!(NOT_FOUND, "Revision not found");
+      };
+      latest
+    }
+  };
+
+  let mut changes = Vec::new();
+  diff(
+    String::new(),
+    Some(&parse(&from.content)?),
+    Some(&parse(&to.content)?),
+    &mut changes,
+  );
+  Ok(Json(changes))
+}
+
+/// Collects the leaves that differ between both values.
+pub fn diff(
+  path: String,
+  before: Option<&Value>,
+  after: Option<&Value>,
+  changes: &mut Vec<SettingsChange>,
+) {
+  let join = |key: &str| {
+    if path.is_empty() {
+      key.to_string()
+    } else {
+      format!("{path}.{key}")
+    }
+  };
+
+  match (before, after) {
+    (Some(Value::Object(before)), Some(Value::Object(after))) => {
+      let keys: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+      for key in keys {
+        diff(join(key), before.get(key), after.get(key), changes);
+      }
+    }
+    (Some(Value::Array(before)), Some(Value::Array(after))) => {
+      for index in 0..before.len().max(after.len()) {
+        diff(
+          join(&index.to_string()),
+          before.get(index),
+          after.get(index),
+          changes,
+        );
+      }
+    }
+    (before, after) if before != after => {
+      let redacted = path.split('.').any(audit::is_secret);
+      changes.push(SettingsChange {
+        before: before.filter(|_| !redacted).cloned(),
+        after: after.filter(|_| !redacted).cloned(),
+        redacted,
+        path,
+      });
+    }
+    _ => (),
+  }
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct RollbackRequest {
+  revision: i32,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct RollbackResponse {
+  /// The revision created by the rollback, `None` if the settings already
+  /// matched.
+  revision: Option<i32>,
+}
+
+/// Restores the content of an older revision as a new one.
+async fn rollback(
+  auth: OrgAuth<SettingsEdit>,
+  db: Connection,
+  (config, oidc, mailer): (Config, OidcState, Mailer),
+  updater: Updater,
+  audit: AuditContext,
+  Path(SectionPath { section }): Path<SectionPath>,
+  Json(RollbackRequest { revision }): Json<RollbackRequest>,
+) -> Result<Json<RollbackResponse>> {
+  ensure_super_admin(&auth)?;
+  let section = Section::parse(&section)?;
+  let target = load(&db, section, revision).await?;
+  let before = db.settings_history().current(section.id()).await?;
+
+  section
+    .apply(&target.content, &config, &oidc, &mailer)
+    .await?;
+  let created = replace(
+    &db,
+    section.id(),
+    target.content.clone(),
+    auth.user_id,
+    Some(revision),
+  )
+  .await?;
+
+  audit
+    .record(
+      &db,
+      &updater,
+      AuditEntry::new("settings.rollback")
+        .actor(auth.user_id)
+        .target("settings", section.name())
+        .before(&before.as_deref().map(parse).transpose()?)
+        .after(&parse(&target.content)?),
+    )
+    .await;
+  updater.broadcast(UpdateMessage::Settings).await;
+
+  Ok(Json(RollbackResponse { revision: created }))
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  #[test]
+  fn diff_reports_changed_leaves_and_hides_secrets() {
+    let before = json!({
+      "smtp_server": "mail.example.com",
+      "smtp_password": "old",
+      "links": [{ "url": "https://a" }],
+    });
+    let after = json!({
+      "smtp_server": "smtp.example.com",
+      "smtp_password": "new",
+      "links": [{ "url": "https://a" }, { "url": "https://b" }],
+      "smtp_port": 587,
+    });
+
+    let mut changes = Vec::new();
+    diff(String::new(), Some(&before), Some(&after), &mut changes);
+
+    assert_eq!(
+      changes,
+      vec![
+        SettingsChange {
+          path: "links.1".into(),
+          before: None,
+          after: Some(json!({ "url": "https://b" })),
+          redacted: false,
+        },
+        SettingsChange {
+          path: "smtp_password".into(),
+          before: None,
+          after: None,
+          redacted: true,
+        },
+        SettingsChange {
+          path: "smtp_port".into(),
+          before: None,
+          after: Some(json!(587)),
+          redacted: false,
+        },
+        SettingsChange {
+          path: "smtp_server".into(),
+          before: Some(json!("mail.example.com")),
+          after: Some(json!("smtp.example.com")),
+          redacted: false,
+        },
+      ]
+    );
+  }
+}
@@ -0,0 +1,111 @@
+use aide::axum::ApiRouter;
+use aide::axum::routing::get_with;
+use axum::{Json, extract::Query};
+use centaurus::backend::auth::permission::{Permission, SettingsEdit, SettingsView};
+use centaurus::backend::{auth::jwt_auth::JwtAuth, endpoints::settings};
+use centaurus::db::init::Connection;
+use centaurus::error::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use url::Url;
+use uuid::Uuid;
+
+use crate::branding::{self, PublicBranding};
+use crate::config::Config;
+use crate::db::DBTrait;
+use crate::organization::current_organization;
+use crate::permissions::PermissionInfo;
+use crate::settings::registry::ModuleSettings;
+use crate::utils::UpdateMessage;
+
+pub mod history;
+pub mod registry;
+pub mod schema;
+pub mod section;
+pub mod transfer;
+
+pub fn router() -> ApiRouter {
+  ApiRouter::new()
+    .api_route(
+      "/general",
+      get_with(general_settings, |op| op.id("getGeneralSettings")),
+    )
+    .nest("/branding", branding::router())
+    .nest("/history", history::router())
+    .merge(transfer::router())
+    .merge(registry::router())
+    .merge(settings::router::<UpdateMessage>())
+}
+
+pub fn permissions() -> Vec<PermissionInfo> {
+  vec![
+    PermissionInfo::new(SettingsView::name(), "View instance settings", "Settings"),
+    PermissionInfo::new(SettingsEdit::name(), "Change instance settings", "Settings")
+      .implies(&[SettingsView::name()]),
+  ]
+}
+
+#[derive(Serialize, JsonSchema)]
+struct GeneralSettings {
+  site_url: Url,
+  branding: PublicBranding,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct GeneralSettingsQuery {
+  /// Organization to show the branding of, defaults to the current one of
+  /// signed in users and the default organization otherwise.
+  organization: Option<Uuid>,
+}
+
+/// Served without authentication, as the login page needs the branding.
+async fn general_settings(
+  auth: Option<JwtAuth>,
+  db: Connection,
+  config: Config,
+  Query(query): Query<GeneralSettingsQuery>,
+) -> Result<Json<GeneralSettings>> {
+  let organization = match (query.organization, auth) {
+    (Some(organization), _) => Some(organization),
+    (None, Some(auth)) => {
+      let super_admin = db.organization().is_super_admin(auth.user_id).await?;
+      current_organization(&db, auth.user_id, super_admin).await?
+    }
+    (None, None) => None,
+  };
+
+  Ok(Json(GeneralSettings {
+    site_url: config.site.site_url,
+    branding: branding::public_branding(&db, organization).await?,
+  }))
+}
+
+/// When a profile field is taken from the OIDC provider.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncPolicy {
+  /// On every login, changes made in the application are overwritten.
+  #[default]
+  Always,
+  /// Only when the account is created, afterwards it is managed here.
+  OnCreate,
+}
+
+/// Which profile fields of OIDC users follow the provider. Whether the
+/// picture is synced is part of the OIDC settings of centaurus.
+#[derive(
+  Serialize, Deserialize, JsonSchema, Clone, Default, PartialEq, Debug, centaurus::Settings,
+)]
+#[settings(id = 10)]
+pub struct ProfileSyncSettings {
+  #[serde(default)]
+  pub name: SyncPolicy,
+  #[serde(default)]
+  pub email: SyncPolicy,
+}
+
+impl ModuleSettings for ProfileSyncSettings {
+  const KEY: &'static str = "profile_sync";
+  type View = SettingsView;
+  type Edit = SettingsEdit;
+}
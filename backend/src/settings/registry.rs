@@ -0,0 +1,327 @@
+use aide::{
+  OperationIo,
+  axum::{
+    ApiRouter,
+    routing::{get_with, post_with},
+  },
+};
+use axum::{
+  Json,
+  extract::{FromRequest, Request},
+};
+use centaurus::{
+  backend::auth::permission::Permission,
+  bail,
+  db::{init::Connection, settings::Settings},
+  error::{ErrorReport, ErrorReportStatusExt, Result},
+};
+use http::StatusCode;
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{
+  audit::{AuditContext, AuditEntry},
+  config::Config,
+  db::repository::{SettingsRepository, SettingsRepositoryExt},
+  organization::OrgAuth,
+  settings::{ProfileSyncSettings, history, schema},
+  utils::{UpdateMessage, Updater},
+};
+
+/// Keys of settings with their own endpoints, which modules cannot use.
+const RESERVED_KEYS: &[&str] = &[
+  "general", "user", "mail", "branding", "history", "export", "import",
+];
+
+/// Settings of an application module. Registered settings are stored in the
+/// `settings` table under their id and get `GET` and `POST /settings/{key}`
+/// endpoints, submitted values are checked against their JSON schema. Until
+/// they are first saved, the defaults are returned.
+pub trait ModuleSettings: Settings + JsonSchema + Clone + Send + Sync + 'static {
+  /// Key of the endpoints and the settings history, in snake case.
+  const KEY: &'static str;
+  /// Permission needed to read the settings.
+  type View: Permission + Send + Sync + 'static;
+  /// Permission needed to change the settings. As they apply to every
+  /// organization, only super admins can change them in addition.
+  type Edit: Permission + Send + Sync + 'static;
+
+  /// Checks that cannot be expressed in the schema, e.g. between fields.
+  fn validate(&self) -> Result<()> {
+    Ok(())
+  }
+}
+
+/// Settings of every module, each module adds its own here.
+pub fn modules() -> Vec<RegisteredSettings> {
+  vec![RegisteredSettings::of::<ProfileSyncSettings>()]
+}
+
+/// Type erased entry of the registry.
+#[derive(Clone, Copy)]
+pub struct RegisteredSettings {
+  pub key: &'static str,
+  pub id: i32,
+  check: fn(Value) -> Result<()>,
+  defaults: fn() -> Value,
+  routes: fn(ApiRouter) -> ApiRouter,
+}
+
+impl RegisteredSettings {
+  fn of<S: ModuleSettings>() -> Self {
+    Self {
+      key: S::KEY,
+      id: S::id(),
+      check: |value| parse::<S>(value).map(|_| ()),
+      defaults: || serde_json::to_value(S::default()).unwrap_or_default(),
+      routes: routes::<S>,
+    }
+  }
+
+  /// Checks the value is valid for these settings.
+  pub fn check(&self, value: Value) -> Result<()> {
+    (self.check)(value)
+  }
+
+  pub fn defaults(&self) -> Value {
+    (self.defaults)()
+  }
+}
+
+/// Fields of the settings pinned by the configuration.
+pub fn pins<'c>(config: &'c Config, key: &str) -> Option<&'c Map<String, Value>> {
+  config.pinned_settings.get(key)
+}
+
+/// Overwrites the pinned fields and returns their names.
+fn apply_pins(value: &mut Value, pins: Option<&Map<String, Value>>) -> Vec<String> {
+  let (Value::Object(fields), Some(pins)) = (value, pins) else {
+    return Vec::new();
+  };
+  for (field, pinned) in pins {
+    fields.insert(field.clone(), pinned.clone());
+  }
+  pins.keys().cloned().collect()
+}
+
+/// Checks every pinned field belongs to registered settings and the pinned
+/// values are valid, so a typo fails at startup instead of being ignored.
+pub fn check_pins(config: &Config) -> Result<()> {
+  for (key, pins) in &config.pinned_settings {
+    let Some(settings) = find(key) else {
+      bail!("Pinned settings {} do not exist", key);
+    };
+    let mut value = settings.defaults();
+    if let Some(field) = pins
+      .keys()
+      .find(|field| value.get(field.as_str()).is_none())
+    {
+      bail!(
+        "Pinned field {} does not exist in the {} settings",
+        field,
+        key
+      );
+    }
+    apply_pins(&mut value, Some(pins));
+    settings.check(value)?;
+  }
+  Ok(())
+}
+
+/// The settings in effect, with the pinned fields applied. Modules read their
+/// settings through this.
+pub async fn load<S: ModuleSettings>(
+  settings: &dyn SettingsRepository,
+  config: &Config,
+) -> Result<S> {
+  let settings: S = settings.get_settings().await?;
+  let mut value = serde_json::to_value(settings)
+    .status_context(StatusCode::INTERNAL_SERVER_ERROR, "Invalid settings")?;
+  apply_pins(&mut value, pins(config, S::KEY));
+  serde_json::from_value(value)
+    .status_context(StatusCode::INTERNAL_SERVER_ERROR, "Invalid settings")
+}
+
+/// Looks up registered settings by their key.
+pub fn find(key: &str) -> Option<RegisteredSettings> {
+  modules().into_iter().find(|settings| settings.key == key)
+}
+
+pub fn router() -> ApiRouter {
+  let modules = modules();
+  for (index, settings) in modules.iter().enumerate() {
+    assert!(
+      !RESERVED_KEYS.contains(&settings.key),
+      "settings key {} is reserved",
+      settings.key
+    );
+    assert!(
+      modules[..index]
+        .iter()
+        .all(|other| other.key != settings.key && other.id != settings.id),
+      "settings {} are registered twice",
+      settings.key
+    );
+  }
+
+  modules.iter().fold(ApiRouter::new(), |router, settings| {
+    (settings.routes)(router)
+  })
+}
+
+/// `profile_sync` becomes `ProfileSync`, used in the operation ids.
+fn pascal_case(key: &str) -> String {
+  key
+    .split('_')
+    .map(|word| {
+      let mut chars = word.chars();
+      chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+        .unwrap_or_default()
+    })
+    .collect()
+}
+
+fn routes<S: ModuleSettings>(router: ApiRouter) -> ApiRouter {
+  let name = pascal_case(S::KEY);
+  router
+    .api_route(
+      &format!("/{}", S::KEY),
+      get_with(get_settings::<S>, |op| op.id(&format!("get{name}Settings"))),
+    )
+    .api_route(
+      &format!("/{}", S::KEY),
+      post_with(save_settings::<S>, |op| {
+        op.id(&format!("save{name}Settings"))
+      }),
+    )
+}
+
+fn parse<S: ModuleSettings>(value: Value) -> Result<S> {
+  schema::validate::<S>(S::KEY, &value)?;
+  let settings: S =
+    serde_json::from_value(value).status_context(StatusCode::BAD_REQUEST, "Invalid settings")?;
+  settings.validate()?;
+  Ok(settings)
+}
+
+/// JSON body checked against the schema of the settings before it is
+/// deserialized, so the reported error names the invalid field.
+#[derive(OperationIo)]
+#[aide(input_with = "Json<S>", json_schema)]
+pub struct ValidSettings<S: ModuleSettings>(pub S);
+
+impl<St: Send + Sync, S: ModuleSettings> FromRequest<St> for ValidSettings<S> {
+  type Rejection = ErrorReport;
+
+  async fn from_request(req: Request, state: &St) -> std::result::Result<Self, Self::Rejection> {
+    let Json(value) = Json::<Value>::from_request(req, state)
+      .await
+      .status_context(StatusCode::BAD_REQUEST, "Invalid JSON")?;
+    Ok(Self(parse(value)?))
+  }
+}
+
+#[derive(Serialize, JsonSchema)]
+struct ModuleSettingsResponse<S: ModuleSettings> {
+  settings: S,
+  /// Fields pinned by the configuration, shown read-only.
+  from_env: Vec<String>,
+}
+
+async fn get_settings<S: ModuleSettings>(
+  _auth: OrgAuth<S::View>,
+  db: Connection,
+  config: Config,
+) -> Result<Json<ModuleSettingsResponse<S>>> {
+  let mut from_env: Vec<String> = pins(&config, S::KEY)
+    .map(|pins| pins.keys().cloned().collect())
+    .unwrap_or_default();
+  from_env.sort_unstable();
+
+  Ok(Json(ModuleSettingsResponse {
+    settings: load(&db, &config).await?,
+    from_env,
+  }))
+}
+
+/// Pinned fields keep their configured value, like the settings of
+/// centaurus do.
+async fn save_settings<S: ModuleSettings>(
+  auth: OrgAuth<S::Edit>,
+  db: Connection,
+  config: Config,
+  updater: Updater,
+  audit: AuditContext,
+  ValidSettings(settings): ValidSettings<S>,
+) -> Result<()> {
+  if !auth.super_admin {
+    bail!(FORBIDDEN, "Only super admins can change instance settings");
+  }
+  let mut value = serde_json::to_value(settings)
+    .status_context(StatusCode::INTERNAL_SERVER_ERROR, "Invalid settings")?;
+  apply_pins(&mut value, pins(&config, S::KEY));
+  let settings = parse::<S>(value)?;
+
+  let before: S = db.get_settings().await?;
+  history::save(&db, auth.user_id, &settings).await?;
+
+  audit
+    .record(
+      &db,
+      &updater,
+      AuditEntry::new(format!("settings.{}", S::KEY))
+        .actor(auth.user_id)
+        .target("settings", S::KEY)
+        .before(&before)
+        .after(&settings),
+    )
+    .await;
+  updater.broadcast(UpdateMessage::Settings).await;
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+  use crate::{
+    db::repository::memory::MemoryRepository,
+    settings::{ProfileSyncSettings, SyncPolicy},
+  };
+
+  #[test]
+  fn operation_names_follow_the_key() {
+    assert_eq!(pascal_case("profile_sync"), "ProfileSync");
+    assert_eq!(pascal_case("mfa"), "Mfa");
+  }
+
+  #[tokio::test]
+  async fn pinned_fields_override_the_stored_settings() {
+    let repo = MemoryRepository::default();
+    let mut config = Config::default();
+
+    let loaded: ProfileSyncSettings = load(&repo, &config).await.unwrap();
+    assert_eq!(loaded, ProfileSyncSettings::default());
+
+    let stored = ProfileSyncSettings {
+      name: SyncPolicy::OnCreate,
+      email: SyncPolicy::OnCreate,
+    };
+    repo.save_settings(&stored).await.unwrap();
+    let loaded: ProfileSyncSettings = load(&repo, &config).await.unwrap();
+    assert_eq!(loaded, stored);
+
+    config.pinned_settings.insert(
+      "profile_sync".into(),
+      json!({ "email": "always" }).as_object().unwrap().clone(),
+    );
+    let loaded: ProfileSyncSettings = load(&repo, &config).await.unwrap();
+    assert_eq!(loaded.name, SyncPolicy::OnCreate);
+    assert_eq!(loaded.email, SyncPolicy::Always);
+  }
+}
@@ -0,0 +1,151 @@
+use std::{
+  collections::HashMap,
+  sync::{Arc, LazyLock, RwLock},
+};
+
+use centaurus::{bail, error::Result};
+use jsonschema::Validator;
+use schemars::{JsonSchema, generate::SchemaSettings};
+use serde_json::Value;
+
+/// Compiled schemas by settings key, each is generated once.
+static VALIDATORS: LazyLock<RwLock<HashMap<&'static str, Arc<Validator>>>> =
+  LazyLock::new(Default::default);
+
+/// JSON schema of the settings, as documented in the API.
+pub fn schema_for<S: JsonSchema>() -> Value {
+  SchemaSettings::draft2020_12()
+    .into_generator()
+    .into_root_schema_for::<S>()
+    .to_value()
+}
+
+/// The compiled schema of the settings stored under `key`.
+fn validator<S: JsonSchema>(key: &'static str) -> Result<Arc<Validator>> {
+  if let Some(validator) = VALIDATORS
+    .read()
+    .unwrap_or_else(|err| err.into_inner())
+    .get(key)
+  {
+    return Ok(validator.clone());
+  }
+
+  let validator = match jsonschema::validator_for(&schema_for::<S>()) {
+    Ok(validator) => Arc::new(validator),
+    Err(err) => bail!("Invalid schema of the {} settings: {}", key, err),
+  };
+  VALIDATORS
+    .write()
+    .unwrap_or_else(|err| err.into_inner())
+    .insert(key, validator.clone());
+  Ok(validator)
+}
+
+/// Checks the value against the schema of the settings stored under `key`.
+/// Values are left out of the error, settings may hold secrets.
+pub fn validate<S: JsonSchema>(key: &'static str, value: &Value) -> Result<()> {
+  let validator = validator::<S>(key)?;
+  if let Err(err) = validator.validate(value) {
+    let path = err
+      .instance_path()
+      .as_str()
+      .trim_start_matches('/')
+      .replace('/', ".");
+    if path.is_empty() {
+      bail!(BAD_REQUEST, "Invalid settings: {}", err.masked());
+    }
+    bail!(
+      BAD_REQUEST,
+      "Invalid settings at `{}`: {}",
+      path,
+      err.masked()
+    );
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use schemars::JsonSchema;
+  use serde::Deserialize;
+  use serde_json::json;
+
+  use super::*;
+
+  #[derive(Deserialize, JsonSchema)]
+  #[serde(deny_unknown_fields, rename_all = "snake_case")]
+  #[allow(dead_code)]
+  enum Mode {
+    Fast,
+    Safe,
+  }
+
+  #[derive(Deserialize, JsonSchema)]
+  #[serde(deny_unknown_fields)]
+  #[allow(dead_code)]
+  struct Example {
+    #[schemars(range(min = 1, max = 10))]
+    retries: u8,
+    #[schemars(length(min = 1), regex(pattern = r"^[a-z]+$"))]
+    name: String,
+    mode: Option<Mode>,
+    #[serde(default)]
+    tags: Vec<String>,
+  }
+
+  fn check(value: Value) -> std::result::Result<(), String> {
+    validate::<Example>("example", &value).map_err(|err| err.error.to_string())
+  }
+
+  #[test]
+  fn accepts_valid_values() {
+    assert_eq!(check(json!({ "retries": 3, "name": "main" })), Ok(()));
+    assert_eq!(
+      check(json!({ "retries": 10, "name": "a", "mode": "safe", "tags": ["x"] })),
+      Ok(())
+    );
+    assert_eq!(
+      check(json!({ "retries": 1, "name": "a", "mode": null })),
+      Ok(())
+    );
+  }
+
+  #[test]
+  fn reports_the_first_violation_with_its_path() {
+    assert_eq!(
+      check(json!({ "name": "main" })),
+      Err(r#"Invalid settings: "retries" is a required property"#.into())
+    );
+    assert_eq!(
+      check(json!({ "retries": 11, "name": "main" })),
+      Err("Invalid settings at `retries`: value is greater than the maximum of 10".into())
+    );
+    assert_eq!(
+      check(json!({ "retries": 1.5, "name": "main" })),
+      Err(r#"Invalid settings at `retries`: value is not of type "integer""#.into())
+    );
+    assert_eq!(
+      check(json!({ "retries": 1, "name": "Main" })),
+      Err(r#"Invalid settings at `name`: value does not match "^[a-z]+$""#.into())
+    );
+    assert_eq!(
+      check(json!({ "retries": 1, "name": "a", "tags": [1] })),
+      Err(r#"Invalid settings at `tags.0`: value is not of type "string""#.into())
+    );
+    assert!(check(json!({ "retries": 1, "name": "a", "mode": "slow" })).is_err());
+    assert_eq!(
+      check(json!({ "retries": 1, "name": "a", "other": true })),
+      Err(
+        "Invalid settings: Additional properties are not allowed ('other' was unexpected)".into()
+      )
+    );
+  }
+
+  #[test]
+  fn schemas_are_compiled_once() {
+    assert!(Arc::ptr_eq(
+      &validator::<Example>("example").unwrap(),
+      &validator::<Example>("example").unwrap()
+    ));
+  }
+}
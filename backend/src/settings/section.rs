@@ -0,0 +1,216 @@
+use centaurus::{
+  backend::auth::{oidc::OidcState, settings::UserSettings},
+  bail,
+  db::{init::Connection, settings::Settings, tables::ConnectionExt},
+  error::{ErrorReportStatusExt, Result},
+  mail::{MailSettings, Mailer},
+  overwrite_with_env_config,
+};
+use http::{Method, StatusCode};
+use serde_json::{Map, Value};
+
+use crate::{
+  branding::BrandingSettings,
+  config::Config,
+  db::DBTrait,
+  settings::registry::{self, RegisteredSettings},
+};
+
+/// Settings stored in the `settings` table which can be versioned, exported
+/// and imported as a whole.
+#[derive(Clone, Copy)]
+pub enum Section {
+  User,
+  Mail,
+  Branding,
+  Module(RegisteredSettings),
+}
+
+impl Section {
+  pub fn all() -> Vec<Self> {
+    let mut sections = vec![Section::User, Section::Mail, Section::Branding];
+    sections.extend(registry::modules().into_iter().map(Section::Module));
+    sections
+  }
+
+  pub fn find(key: &str) -> Option<Self> {
+    Some(match key {
+      "user" => Section::User,
+      "mail" => Section::Mail,
+      "branding" => Section::Branding,
+      key => Section::Module(registry::find(key)?),
+    })
+  }
+
+  pub fn parse(key: &str) -> Result<Self> {
+    let Some(section) = Self::find(key) else {
+      bail!(NOT_FOUND, "Unknown settings section");
+    };
+    Ok(section)
+  }
+
+  pub fn id(self) -> i32 {
+    match self {
+      Section::User => UserSettings::id(),
+      Section::Mail => MailSettings::id(),
+      Section::Branding => BrandingSettings::id(),
+      Section::Module(settings) => settings.id,
+    }
+  }
+
+  pub fn name(self) -> &'static str {
+    match self {
+      Section::User => "user",
+      Section::Mail => "mail",
+      Section::Branding => "branding",
+      Section::Module(settings) => settings.key,
+    }
+  }
+
+  /// Settings saved by endpoints of centaurus.
+  pub fn saved_by(method: &Method, path: &str) -> Option<Self> {
+    match (method.as_str(), path) {
+      ("POST", "/settings/user") => Some(Section::User),
+      ("POST", "/settings/mail") => Some(Section::Mail),
+      _ => None,
+    }
+  }
+
+  /// The stored settings, or the defaults if they were never saved.
+  pub async fn current(self, db: &Connection) -> Result<Value> {
+    match db.settings_history().current(self.id()).await? {
+      Some(content) => parse(&content),
+      None => Ok(self.default_value()),
+    }
+  }
+
+  fn default_value(self) -> Value {
+    let defaults = match self {
+      Section::User => serde_json::to_value(UserSettings::default()),
+      Section::Mail => serde_json::to_value(MailSettings::default()),
+      Section::Branding => serde_json::to_value(BrandingSettings::default()),
+      Section::Module(settings) => return settings.defaults(),
+    };
+    defaults.unwrap_or_default()
+  }
+
+  /// Fields set by the configuration, which take precedence over the stored
+  /// ones and cannot be changed through the API.
+  pub fn pins(self, config: &Config) -> Map<String, Value> {
+    let configured = match self {
+      Section::User => serde_json::to_value(&config.oidc),
+      Section::Mail => serde_json::to_value(&config.mail),
+      Section::Branding => return Map::new(),
+      Section::Module(settings) => {
+        return registry::pins(config, settings.key)
+          .cloned()
+          .unwrap_or_default();
+      }
+    };
+
+    match configured {
+      Ok(Value::Object(fields)) => fields
+        .into_iter()
+        .filter(|(_, value)| !value.is_null())
+        .collect(),
+      _ => Map::new(),
+    }
+  }
+
+  /// Checks the content is valid for the section without applying it.
+  pub fn validate(self, content: &str) -> Result<()> {
+    match self {
+      Section::User => drop(restored::<UserSettings>(content)?),
+      Section::Mail => drop(restored::<MailSettings>(content)?),
+      Section::Branding => drop(restored::<BrandingSettings>(content)?),
+      Section::Module(settings) => settings.check(parse(content)?)?,
+    }
+    Ok(())
+  }
+
+  /// Validates the content and applies it to the running services, the same
+  /// way saving the settings does.
+  pub async fn apply(
+    self,
+    content: &str,
+    config: &Config,
+    oidc: &OidcState,
+    mailer: &Mailer,
+  ) -> Result<()> {
+    match self {
+      Section::User => {
+        let mut settings: UserSettings = restored(content)?;
+        let env = Some(&config.oidc);
+        overwrite_with_env_config!(
+          settings,
+          env,
+          oidc_issuer,
+          oidc_client_id,
+          oidc_client_secret,
+          oidc_scopes,
+          oidc_group_claim,,
+          oidc_enabled,
+          oidc_group_sync,
+          oidc_image_sync,
+          oidc_pkce,
+          sso_create_user,
+          sso_instant_redirect
+        );
+        match settings.oidc_settings() {
+          Some(settings) => oidc.try_init(&settings).await.status_context(
+            StatusCode::NOT_ACCEPTABLE,
+            "Failed to initialize OIDC state",
+          )?,
+          None => oidc.deactivate().await,
+        }
+      }
+      Section::Mail => apply_mail(restored(content)?, config, mailer).await?,
+      Section::Branding | Section::Module(_) => self.validate(content)?,
+    }
+
+    Ok(())
+  }
+}
+
+/// Points the mailer at the settings with the configured fields on top.
+async fn apply_mail(mut settings: MailSettings, config: &Config, mailer: &Mailer) -> Result<()> {
+  let env = Some(&config.mail);
+  overwrite_with_env_config!(
+    settings,
+    env,
+    smtp_server,
+    smtp_port,
+    smtp_username,
+    smtp_password,
+    smtp_from_address,
+    smtp_from_name,
+    smtp_use_tls,,
+    smtp_enabled
+  );
+  match settings.smtp() {
+    Some(smtp) => mailer.try_init(&smtp).await?,
+    None => mailer.deactivate().await,
+  }
+  Ok(())
+}
+
+/// Applies the stored mail settings, on startup and when the configuration
+/// is reloaded.
+pub async fn init_mailer(db: &Connection, config: &Config, mailer: &Mailer) -> Result<()> {
+  let stored: MailSettings = db.settings().get_settings().await.unwrap_or_default();
+  apply_mail(stored, config, mailer).await
+}
+
+pub fn parse(content: &str) -> Result<Value> {
+  serde_json::from_str(content).status_context(
+    StatusCode::INTERNAL_SERVER_ERROR,
+    "Stored settings are not valid JSON",
+  )
+}
+
+fn restored<S: Settings>(content: &str) -> Result<S> {
+  serde_json::from_str(content).status_context(
+    StatusCode::CONFLICT,
+    "Settings do not match the current settings format",
+  )
+}
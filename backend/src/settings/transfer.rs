@@ -0,0 +1,300 @@
+use std::collections::BTreeMap;
+
+use aide::axum::{
+  ApiRouter,
+  routing::{get_with, post_with},
+};
+use axum::Json;
+use centaurus::{
+  backend::auth::{
+    oidc::OidcState,
+    permission::{Permission, SettingsEdit, SettingsView},
+  },
+  bail,
+  db::init::Connection,
+  error::{ErrorReportStatusExt, Result},
+  mail::Mailer,
+};
+use chrono::{DateTime, Utc};
+use http::StatusCode;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tracing::error;
+
+use crate::{
+  audit::{self, AuditContext, AuditEntry},
+  config::Config,
+  db::DBTrait,
+  organization::OrgAuth,
+  settings::{
+    history::{self, SettingsChange},
+    section::Section,
+  },
+  utils::{UpdateMessage, Updater},
+};
+
+const EXPORT_VERSION: u32 = 1;
+
+pub fn router() -> ApiRouter {
+  ApiRouter::new()
+    .api_route(
+      "/export",
+      get_with(export_settings, |op| op.id("exportSettings")),
+    )
+    .api_route(
+      "/import/preview",
+      post_with(preview_import, |op| op.id("previewSettingsImport")),
+    )
+    .api_route(
+      "/import",
+      post_with(import_settings, |op| op.id("importSettings")),
+    )
+}
+
+/// Settings of the whole instance, e.g. to promote them from staging to
+/// production. Secrets are left out, branding images are not included.
+#[derive(Serialize, Deserialize, JsonSchema)]
+struct SettingsExport {
+  /// Version of the format, other versions cannot be imported.
+  version: u32,
+  exported_at: DateTime<Utc>,
+  /// Settings by section, sections missing on import are left unchanged.
+  sections: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct SectionChanges {
+  section: &'static str,
+  changes: Vec<SettingsChange>,
+  /// Imported fields which keep their value pinned by the configuration.
+  pinned: Vec<String>,
+}
+
+/// Settings span all organizations, so only super admins may move them.
+fn ensure_super_admin<P: Permission>(auth: &OrgAuth<P>) -> Result<()> {
+  if !auth.super_admin {
+    bail!(
+      FORBIDDEN,
+      "Only super admins can transfer instance settings"
+    );
+  }
+  Ok(())
+}
+
+async fn export_settings(
+  auth: OrgAuth<SettingsView>,
+  db: Connection,
+) -> Result<Json<SettingsExport>> {
+  ensure_super_admin(&auth)?;
+
+  let mut sections = BTreeMap::new();
+  for section in Section::all() {
+    let current = section.current(&db).await?;
+    sections.insert(section.name().to_string(), audit::redact(current));
+  }
+
+  Ok(Json(SettingsExport {
+    version: EXPORT_VERSION,
+    exported_at: Utc::now(),
+    sections,
+  }))
+}
+
+/// Secrets left out of the export keep their current value.
+fn keep_secrets(current: &Value, imported: &mut Value) {
+  let (Value::Object(current), Value::Object(imported)) = (current, imported) else {
+    return;
+  };
+  for (key, value) in current {
+    if audit::is_secret(key) {
+      imported.entry(key.clone()).or_insert_with(|| value.clone());
+    } else if let Some(nested) = imported.get_mut(key) {
+      keep_secrets(value, nested);
+    }
+  }
+}
+
+struct PlannedSection {
+  section: Section,
+  content: String,
+  /// Content stored before the import, applied again if the import fails.
+  previous: String,
+  changes: SectionChanges,
+}
+
+/// Works out and validates the new content of every imported section before
+/// anything is changed. Pinned fields are stored as imported, the configured
+/// value takes precedence whenever the settings are applied.
+async fn plan(
+  db: &Connection,
+  config: &Config,
+  export: SettingsExport,
+) -> Result<Vec<PlannedSection>> {
+  if export.version != EXPORT_VERSION {
+    bail!(
+      BAD_REQUEST,
+      "Unsupported settings export version {}",
+      export.version
+    );
+  }
+
+  let mut planned = Vec::new();
+  for (key, mut imported) in export.sections {
+    let Some(section) = Section::find(&key) else {
+      bail!(BAD_REQUEST, "Unknown settings section {}", key);
+    };
+    let current = section.current(db).await?;
+    keep_secrets(&current, &mut imported);
+
+    let Value::Object(fields) = &imported else {
+      bail!(BAD_REQUEST, "Settings {} must be an object", key);
+    };
+    let pinned = section
+      .pins(config)
+      .into_iter()
+      .filter(|(field, value)| fields.get(field).is_some_and(|imported| imported != value))
+      .map(|(field, _)| field)
+      .collect();
+
+    let content = serde_json::to_string(&imported)
+      .status_context(StatusCode::BAD_REQUEST, "Invalid settings")?;
+    section.validate(&content)?;
+
+    let previous = serde_json::to_string(&current)
+      .status_context(StatusCode::INTERNAL_SERVER_ERROR, "Invalid settings")?;
+    let mut changes = Vec::new();
+    history::diff(String::new(), Some(&current), Some(&imported), &mut changes);
+    planned.push(PlannedSection {
+      section,
+      content,
+      previous,
+      changes: SectionChanges {
+        section: section.name(),
+        changes,
+        pinned,
+      },
+    });
+  }
+
+  Ok(planned)
+}
+
+/// Shows what an import would change without changing anything.
+async fn preview_import(
+  auth: OrgAuth<SettingsEdit>,
+  db: Connection,
+  config: Config,
+  Json(export): Json<SettingsExport>,
+) -> Result<Json<Vec<SectionChanges>>> {
+  ensure_super_admin(&auth)?;
+  let planned = plan(&db, &config, export).await?;
+
+  Ok(Json(planned.into_iter().map(|p| p.changes).collect()))
+}
+
+/// Applies an export, every changed section is kept as a new revision. The
+/// sections are applied and stored all together, if one of them fails the
+/// others are left unchanged as well.
+async fn import_settings(
+  auth: OrgAuth<SettingsEdit>,
+  db: Connection,
+  (config, oidc, mailer): (Config, OidcState, Mailer),
+  updater: Updater,
+  audit: AuditContext,
+  Json(export): Json<SettingsExport>,
+) -> Result<Json<Vec<SectionChanges>>> {
+  ensure_super_admin(&auth)?;
+  let planned = plan(&db, &config, export).await?;
+
+  let changed: Vec<PlannedSection> = planned
+    .into_iter()
+    .filter(|planned| !planned.changes.changes.is_empty())
+    .collect();
+
+  for (i, planned) in changed.iter().enumerate() {
+    if let Err(err) = planned
+      .section
+      .apply(&planned.content, &config, &oidc, &mailer)
+      .await
+    {
+      revert(&changed[..i], &config, &oidc, &mailer).await;
+      return Err(err);
+    }
+  }
+  let contents = changed
+    .iter()
+    .map(|planned| (planned.section.id(), planned.content.clone()))
+    .collect();
+  if let Err(err) = db
+    .settings_history()
+    .replace_all(contents, auth.user_id)
+    .await
+  {
+    revert(&changed, &config, &oidc, &mailer).await;
+    return Err(err);
+  }
+  let applied: Vec<SectionChanges> = changed.into_iter().map(|p| p.changes).collect();
+
+  if !applied.is_empty() {
+    let sections: Vec<&str> = applied.iter().map(|changes| changes.section).collect();
+    audit
+      .record(
+        &db,
+        &updater,
+        AuditEntry::new("settings.import")
+          .actor(auth.user_id)
+          .after(&json!({ "sections": sections })),
+      )
+      .await;
+    updater.broadcast(UpdateMessage::Settings).await;
+  }
+
+  Ok(Json(applied))
+}
+
+/// Applies the content from before the import again, newest first.
+async fn revert(applied: &[PlannedSection], config: &Config, oidc: &OidcState, mailer: &Mailer) {
+  for planned in applied.iter().rev() {
+    if let Err(err) = planned
+      .section
+      .apply(&planned.previous, config, oidc, mailer)
+      .await
+    {
+      error!(
+        "Failed to revert the {} settings after a failed import: {:?}",
+        planned.section.name(),
+        err
+      );
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn redacted_secrets_keep_their_value() {
+    let current = json!({
+      "smtp_server": "mail.example.com",
+      "smtp_password": "secret",
+      "nested": { "api_token": "token", "url": "https://a" },
+    });
+    let mut imported = json!({
+      "smtp_server": "smtp.example.com",
+      "nested": { "url": "https://b" },
+    });
+
+    keep_secrets(&current, &mut imported);
+
+    assert_eq!(
+      imported,
+      json!({
+        "smtp_server": "smtp.example.com",
+        "smtp_password": "secret",
+        "nested": { "api_token": "token", "url": "https://b" },
+      })
+    );
+  }
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+
+use aide::axum::{
+  ApiRouter,
+  routing::{get_with, put_with},
+};
+use axum::{Json, extract::Path};
+use centaurus::{
+  backend::auth::{
+    jwt_auth::JwtAuth,
+    permission::{UserEdit, UserView},
+  },
+  bail,
+  db::init::Connection,
+  error::Result,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+  attribute::{self, AttributeAccess, AttributeValue},
+  audit::AuditContext,
+  db::repository::UserRepository,
+  organization::OrgAuth,
+  utils::Updater,
+};
+
+pub fn account_router() -> ApiRouter {
+  ApiRouter::new()
+    .api_route(
+      "/",
+      get_with(own_attributes, |op| op.id("accountAttributes")),
+    )
+    .api_route(
+      "/",
+      put_with(update_own_attributes, |op| op.id("updateAccountAttributes")),
+    )
+}
+
+pub fn management_router() -> ApiRouter {
+  ApiRouter::new()
+    .api_route("/", get_with(user_attributes, |op| op.id("userAttributes")))
+    .api_route(
+      "/",
+      put_with(update_user_attributes, |op| op.id("updateUserAttributes")),
+    )
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct UpdateAttributesRequest {
+  /// New values by attribute key, `null` clears a value.
+  values: HashMap<String, Value>,
+}
+
+async fn own_access(users: &dyn UserRepository, user: Uuid) -> Result<AttributeAccess> {
+  let Some(user) = users.get_user(user).await? else {
+    bail!(NOT_FOUND, "User not found");
+  };
+  Ok(AttributeAccess::Own {
+    oidc_user: user.oidc_user,
+  })
+}
+
+async fn own_attributes(auth: JwtAuth, db: Connection) -> Result<Json<Vec<AttributeValue>>> {
+  let access = own_access(&db, auth.user_id).await?;
+  Ok(Json(
+    attribute::user_attributes(&db, auth.user_id, access).await?,
+  ))
+}
+
+async fn update_own_attributes(
+  auth: JwtAuth,
+  db: Connection,
+  updater: Updater,
+  context: AuditContext,
+  Json(data): Json<UpdateAttributesRequest>,
+) -> Result<()> {
+  let access = own_access(&db, auth.user_id).await?;
+  attribute::update_attributes(
+    &db,
+    &updater,
+    &context,
+    auth.user_id,
+    auth.user_id,
+    access,
+    data.values,
+  )
+  .await
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct UserAttributesPath {
+  uuid: Uuid,
+}
+
+async fn user_attributes(
+  auth: OrgAuth<UserView>,
+  db: Connection,
+  Path(path): Path<UserAttributesPath>,
+) -> Result<Json<Vec<AttributeValue>>> {
+  auth.ensure_member(&db, path.uuid).await?;
+  Ok(Json(
+    attribute::user_attributes(&db, path.uuid, AttributeAccess::Admin).await?,
+  ))
+}
+
+async fn update_user_attributes(
+  auth: OrgAuth<UserEdit>,
+  db: Connection,
+  updater: Updater,
+  context: AuditContext,
+  Path(path): Path<UserAttributesPath>,
+  Json(data): Json<UpdateAttributesRequest>,
+) -> Result<()> {
+  auth.ensure_managed(&db, path.uuid).await?;
+  attribute::update_attributes(
+    &db,
+    &updater,
+    &context,
+    auth.user_id,
+    path.uuid,
+    AttributeAccess::Admin,
+    data.values,
+  )
+  .await
+}
+
+#[cfg(test)]
+mod tests {
+  use entity::user;
+
+  use super::*;
+  use crate::db::repository::memory::MemoryRepository;
+
+  #[tokio::test]
+  async fn own_access_follows_the_account() {
+    let repo = MemoryRepository::default();
+    let id = Uuid::new_v4();
+    repo.insert_user(user::Model {
+      id,
+      name: "Jane".into(),
+      email: "jane@example.com".into(),
+      password: String::new(),
+      salt: String::new(),
+      oidc_user: true,
+      oidc_subject: Some("jane".into()),
+    });
+
+    assert!(matches!(
+      own_access(&repo, id).await.unwrap(),
+      AttributeAccess::Own { oidc_user: true }
+    ));
+    assert!(own_access(&repo, Uuid::new_v4()).await.is_err());
+  }
+}
@@ -0,0 +1,104 @@
+use axum::{Json, extract::Query};
+use centaurus::{
+  backend::auth::permission::UserView,
+  db::{init::Connection, tables::user::UserListInfo},
+  error::Result,
+};
+use chrono::{DateTime, Duration, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+  config::Config,
+  db::{
+    DBTrait,
+    directory::{UserFilter, UserSort},
+  },
+  organization::OrgAuth,
+  pagination::{Page, PageQuery, PageRequest, created_at},
+};
+
+#[derive(Deserialize, JsonSchema)]
+pub struct UserListQuery {
+  #[serde(default)]
+  sort: UserSort,
+  /// Matches name, email or attribute values, ignoring case.
+  search: Option<String>,
+  /// Only members of this group.
+  group: Option<Uuid>,
+  /// Only OIDC users if true, only local users if false.
+  oidc: Option<bool>,
+  #[serde(default = "default_true")]
+  include_disabled: bool,
+  #[serde(default)]
+  include_deleted: bool,
+}
+
+fn default_true() -> bool {
+  true
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ManagedUserInfo {
+  #[serde(flatten)]
+  user: UserListInfo,
+  oidc_user: bool,
+  created_at: Option<DateTime<Utc>>,
+  disabled_at: Option<DateTime<Utc>>,
+  deleted_at: Option<DateTime<Utc>>,
+  /// When a deleted user is removed for good.
+  purge_at: Option<DateTime<Utc>>,
+}
+
+pub async fn list_users(
+  auth: OrgAuth<UserView>,
+  db: Connection,
+  config: Config,
+  Query(query): Query<UserListQuery>,
+  Query(page): Query<PageQuery>,
+) -> Result<Json<Page<ManagedUserInfo>>> {
+  let page = PageRequest::new(page, query.sort.key())?;
+  let filter = UserFilter {
+    organization: Some(auth.organization),
+    search: query.search,
+    // the same as shown to them, see `AttributeAccess::Admin`
+    all_attributes: true,
+    group: query.group,
+    oidc: query.oidc,
+    include_disabled: query.include_disabled,
+    include_deleted: query.include_deleted,
+  };
+  let users = db.directory().list_users(filter, query.sort, &page).await?;
+
+  let mut groups = db
+    .directory()
+    .user_groups(auth.organization, &users.items)
+    .await?
+    .into_iter();
+  let mut statuses = db
+    .user_status()
+    .list_statuses(users.items.iter().map(|user| user.id).collect())
+    .await?;
+  let retention = Duration::days(config.user_retention_days);
+
+  Ok(Json(users.map(|user| {
+    let status = statuses.remove(&user.id);
+    let disabled_at = status.as_ref().and_then(|s| s.disabled_at);
+    let deleted_at = status.as_ref().and_then(|s| s.deleted_at);
+
+    ManagedUserInfo {
+      oidc_user: user.oidc_user,
+      created_at: created_at(user.id),
+      disabled_at: disabled_at.map(|at| at.and_utc()),
+      deleted_at: deleted_at.map(|at| at.and_utc()),
+      purge_at: deleted_at.map(|at| (at + retention).and_utc()),
+      user: UserListInfo {
+        uuid: user.id,
+        name: user.name,
+        email: user.email,
+        groups: groups.next().unwrap_or_default(),
+      },
+    }
+  })))
+}
@@ -0,0 +1,377 @@
+use axum::{Json, extract::Path};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use centaurus::{
+  backend::{
+    auth::{
+      permission::{UserEdit, UserView},
+      pw_state::PasswordState,
+    },
+    config::SiteConfig,
+    endpoints::user::template,
+  },
+  bail,
+  db::{
+    init::Connection,
+    tables::{
+      ConnectionExt,
+      user::{DetailUserInfo, SimpleGroupInfo},
+    },
+  },
+  error::{ErrorReportStatusExt, Result},
+  mail::Mailer,
+};
+use http::StatusCode;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::spawn;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+  db::{DBTrait, import::NewUser, repository::GroupRepository},
+  organization::{OrgAuth, scoped_permissions},
+  user::transfer::{generate_password, generate_salt},
+  utils::{UpdateMessage, Updater},
+};
+
+#[derive(Deserialize, JsonSchema)]
+pub struct UserViewPath {
+  uuid: Uuid,
+}
+
+/// A user with the groups and permissions they have in the current
+/// organization.
+pub async fn user_info(
+  auth: OrgAuth<UserView>,
+  db: Connection,
+  Path(path): Path<UserViewPath>,
+) -> Result<Json<DetailUserInfo>> {
+  auth.ensure_member(&db, path.uuid).await?;
+  let Some(mut info) = db.user().user_info(path.uuid).await? else {
+    bail!(NOT_FOUND, "User not found");
+  };
+
+  let groups = db
+    .organization()
+    .user_groups(auth.organization, path.uuid)
+    .await?;
+  info.groups.retain(|group| groups.contains(&group.uuid));
+  info.permissions = db
+    .organization()
+    .user_permissions(auth.organization, path.uuid)
+    .await?;
+
+  Ok(Json(info))
+}
+
+/// Groups users of the current organization can be added to.
+pub async fn list_groups_simple(
+  auth: OrgAuth<UserView>,
+  db: Connection,
+) -> Result<Json<Vec<SimpleGroupInfo>>> {
+  let groups = db.organization().groups(auth.organization).await?;
+
+  Ok(Json(
+    groups
+      .into_iter()
+      .map(|group| SimpleGroupInfo {
+        uuid: group.id,
+        name: group.name,
+      })
+      .collect(),
+  ))
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct UserEditRequest {
+  uuid: Uuid,
+  name: String,
+  /// Groups of the current organization, memberships in other organizations
+  /// are kept.
+  groups: Vec<Uuid>,
+}
+
+pub async fn edit_user(
+  auth: OrgAuth<UserEdit>,
+  db: Connection,
+  updater: Updater,
+  Json(req): Json<UserEditRequest>,
+) -> Result<()> {
+  if req.name.trim().is_empty() {
+    bail!(BAD_REQUEST, "Name cannot be empty");
+  }
+  auth.ensure_member(&db, req.uuid).await?;
+  let Some(user) = db.user().user_info(req.uuid).await? else {
+    bail!(NOT_FOUND, "User not found");
+  };
+  if user.name != req.name {
+    auth.ensure_managed(&db, req.uuid).await?;
+  }
+
+  if let Some(foreign) = db
+    .organization()
+    .find_foreign_group(auth.organization, &req.groups)
+    .await?
+  {
+    bail!(NOT_FOUND, "Group {} not found", foreign);
+  }
+
+  let self_permissions = auth.permissions(&db).await?;
+  let target_permissions = db
+    .group()
+    .get_groups_permissions(req.groups.clone())
+    .await?;
+  let current_permissions = db
+    .organization()
+    .user_permissions(auth.organization, req.uuid)
+    .await?;
+  if target_permissions
+    .iter()
+    .chain(&current_permissions)
+    .any(|p| !self_permissions.contains(p))
+  {
+    bail!(
+      FORBIDDEN,
+      "Cannot assign permissions that the editor does not have"
+    );
+  }
+
+  let Some(admin_group) = db.setup().get_admin_group_id().await? else {
+    bail!(INTERNAL_SERVER_ERROR, "Admin group is not set up");
+  };
+  if db.organization().group_organization(admin_group).await? == Some(auth.organization)
+    && !req.groups.contains(&admin_group)
+    && db.is_last_member(admin_group, req.uuid).await?
+  {
+    bail!(CONFLICT, "Cannot remove the last user from the admin group");
+  }
+
+  let current = db
+    .organization()
+    .user_groups(auth.organization, req.uuid)
+    .await?;
+  let mut groups: Vec<Uuid> = user
+    .groups
+    .into_iter()
+    .map(|group| group.uuid)
+    .filter(|group| !current.contains(group))
+    .collect();
+  groups.extend(req.groups);
+  groups.sort_unstable();
+  groups.dedup();
+
+  db.user().edit_user(req.uuid, req.name, groups).await?;
+  updater
+    .broadcast(UpdateMessage::User { uuid: req.uuid })
+    .await;
+  updater
+    .send_to(req.uuid, UpdateMessage::UserPermissions)
+    .await;
+
+  Ok(())
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CreateUser {
+  name: String,
+  email: String,
+  /// Encrypted with the public key, only used while mail is not set up.
+  password: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct CreateUserResponse {
+  uuid: Uuid,
+}
+
+/// Creates a user as a member of the current organization. With mail set up
+/// the user gets a generated password by mail instead of the given one.
+pub async fn create_user(
+  auth: OrgAuth<UserEdit>,
+  db: Connection,
+  updater: Updater,
+  mailer: Mailer,
+  passwords: PasswordState,
+  site: SiteConfig,
+  Json(req): Json<CreateUser>,
+) -> Result<Json<CreateUserResponse>> {
+  let name = req.name.trim().to_string();
+  let email = req.email.trim().to_string();
+  if name.is_empty() {
+    bail!(BAD_REQUEST, "Name cannot be empty");
+  }
+  if email.is_empty() {
+    bail!(BAD_REQUEST, "Email cannot be empty");
+  }
+  if db.user().try_get_user_by_email(&email).await?.is_some() {
+    bail!(CONFLICT, "User with this email already exists");
+  }
+
+  let mail_active = mailer.is_active().await;
+  let password = if mail_active {
+    generate_password()
+  } else if let Some(password) = req.password {
+    let bytes = BASE64_STANDARD
+      .decode(password)
+      .status(StatusCode::BAD_REQUEST)?;
+    let password = passwords.decrypt(&bytes).status(StatusCode::BAD_REQUEST)?;
+    String::from_utf8_lossy(&password).to_string()
+  } else {
+    bail!(
+      BAD_REQUEST,
+      "Password must be provided when mail service is not active"
+    );
+  };
+
+  let salt = generate_salt();
+  let uuid = db
+    .import()
+    .create_user(NewUser {
+      name: name.clone(),
+      email: email.clone(),
+      password: passwords.pw_hash_raw(&salt, &password)?,
+      salt,
+      organization: auth.organization,
+      groups: Vec::new(),
+    })
+    .await?;
+
+  if mail_active {
+    let body = template::init_password(site.site_url.as_str(), &password);
+    spawn(async move {
+      if let Err(err) = mailer
+        .send_mail(name, email.clone(), "Your new account".to_string(), body)
+        .await
+      {
+        warn!("Failed to send invitation to {}: {:?}", email, err);
+      }
+    });
+  }
+  updater.broadcast(UpdateMessage::User { uuid }).await;
+
+  Ok(Json(CreateUserResponse { uuid }))
+}
+
+/// Checks the caller may change the account of the user, see
+/// [`OrgAuth::ensure_managed`], and holds every permission the user has in the
+/// organization.
+async fn ensure_changeable(auth: &OrgAuth<UserEdit>, db: &Connection, user: Uuid) -> Result<()> {
+  auth.ensure_managed(db, user).await?;
+
+  let self_permissions = auth.permissions(db).await?;
+  let super_admin = db.organization().is_super_admin(user).await?;
+  let target_permissions = scoped_permissions(db, auth.organization, user, super_admin).await?;
+  if target_permissions
+    .iter()
+    .any(|p| !self_permissions.contains(p))
+  {
+    bail!(FORBIDDEN, "Cannot change a user with higher permissions");
+  }
+  Ok(())
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct UserAvatarResetRequest {
+  uuid: Uuid,
+}
+
+pub async fn reset_user_avatar(
+  auth: OrgAuth<UserEdit>,
+  db: Connection,
+  updater: Updater,
+  Json(req): Json<UserAvatarResetRequest>,
+) -> Result<()> {
+  ensure_changeable(&auth, &db, req.uuid).await?;
+
+  db.user().reset_avatar(req.uuid).await?;
+  updater
+    .broadcast(UpdateMessage::User { uuid: req.uuid })
+    .await;
+
+  Ok(())
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ResetUserPassword {
+  uuid: Uuid,
+  new_password: String,
+}
+
+pub async fn reset_user_password(
+  auth: OrgAuth<UserEdit>,
+  db: Connection,
+  passwords: PasswordState,
+  Json(req): Json<ResetUserPassword>,
+) -> Result<()> {
+  ensure_changeable(&auth, &db, req.uuid).await?;
+  let user = db.user().get_user_by_id(req.uuid).await?;
+  if user.oidc_user {
+    bail!(BAD_REQUEST, "Cannot reset password for an OIDC user");
+  }
+
+  let hash = passwords.pw_hash(&user.salt, &req.new_password)?;
+  db.user().update_user_password(req.uuid, hash).await?;
+
+  Ok(())
+}
+
+/// Turns an OIDC user into a local one signing in with the given password.
+pub async fn convert_oidc_user(
+  auth: OrgAuth<UserEdit>,
+  db: Connection,
+  passwords: PasswordState,
+  updater: Updater,
+  Json(req): Json<ResetUserPassword>,
+) -> Result<()> {
+  ensure_changeable(&auth, &db, req.uuid).await?;
+  let user = db.user().get_user_by_id(req.uuid).await?;
+  if !user.oidc_user {
+    bail!(BAD_REQUEST, "Cannot convert a non-OIDC user");
+  }
+
+  let hash = passwords.pw_hash(&user.salt, &req.new_password)?;
+  db.user().update_user_password(req.uuid, hash).await?;
+  db.user().to_local_user(req.uuid).await?;
+  updater
+    .broadcast(UpdateMessage::User { uuid: req.uuid })
+    .await;
+
+  Ok(())
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ChangeUserEmail {
+  uuid: Uuid,
+  new_email: String,
+}
+
+pub async fn change_user_email(
+  auth: OrgAuth<UserEdit>,
+  db: Connection,
+  updater: Updater,
+  Json(req): Json<ChangeUserEmail>,
+) -> Result<()> {
+  if req.new_email.trim().is_empty() {
+    bail!(BAD_REQUEST, "New email cannot be empty");
+  }
+  ensure_changeable(&auth, &db, req.uuid).await?;
+  let user = db.user().get_user_by_id(req.uuid).await?;
+  if user.oidc_user {
+    bail!(BAD_REQUEST, "Cannot change email for an OIDC user");
+  }
+  if db
+    .user()
+    .try_get_user_by_email(&req.new_email.to_lowercase())
+    .await?
+    .is_some()
+  {
+    bail!(CONFLICT, "Email is already in use");
+  }
+
+  db.user().change_email(req.uuid, req.new_email).await?;
+  updater
+    .broadcast(UpdateMessage::User { uuid: req.uuid })
+    .await;
+
+  Ok(())
+}
@@ -0,0 +1,198 @@
+use aide::axum::{
+  ApiRouter,
+  routing::{delete_with, get_with, post_with, put_with},
+};
+use axum::{Json, extract::DefaultBodyLimit, middleware::from_fn_with_state};
+use centaurus::{
+  backend::{
+    auth::jwt_auth::JwtAuth,
+    endpoints::user::{account, email, management as centaurus_management},
+  },
+  db::{init::Connection, tables::ConnectionExt},
+  error::Result,
+};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+  avatar,
+  db::DBTrait,
+  impersonation::{self, Impersonation},
+  login,
+  organization::{current_organization, scoped_permissions},
+  rate_limit,
+  utils::UpdateMessage,
+};
+
+pub mod attributes;
+pub mod list;
+pub mod management;
+pub mod privacy;
+pub mod status;
+pub mod template;
+pub mod transfer;
+
+pub fn router() -> ApiRouter {
+  ApiRouter::new()
+    .nest("/account", account_router())
+    .nest("/account/logins", login::router())
+    .nest("/account/attributes", attributes::account_router())
+    .api_route(
+      "/account/export",
+      get_with(privacy::export_account, |op| op.id("exportAccount")),
+    )
+    .nest("/account/deletion", privacy::deletion_router())
+    .api_route("/info", get_with(user_info, |op| op.id("info")))
+    .api_route(
+      "/info/avatar/{uuid}",
+      get_with(avatar::avatar, |op| op.id("avatarById")),
+    )
+    .nest("/management", management_router())
+    .nest("/impersonate", impersonation::router())
+}
+
+/// Account routes of centaurus, with avatar uploads going through the
+/// processing in [`avatar`].
+fn account_router() -> ApiRouter {
+  ApiRouter::new()
+    .api_route("/password", account::update_password_route())
+    .api_route("/email_change_start", email::start_email_change_route())
+    .layer(from_fn_with_state("account", rate_limit::limit))
+    .api_route("/update", account::update_account_route::<UpdateMessage>())
+    .api_route(
+      "/email_change_confirm",
+      email::confirm_email_change_route::<UpdateMessage>(),
+    )
+    .api_route(
+      "/avatar",
+      post_with(avatar::update_avatar, |op| op.id("updateAvatar"))
+        .layer(DefaultBodyLimit::max(avatar::MAX_UPLOAD)),
+    )
+}
+
+/// User management scoped to the current organization, only the mail status
+/// is left to centaurus. Deletion is the soft delete in [`status`].
+fn management_router() -> ApiRouter {
+  ApiRouter::new()
+    .api_route(
+      "/avatar",
+      delete_with(management::reset_user_avatar, |op| op.id("resetUserAvatar")),
+    )
+    .api_route("/", get_with(list::list_users, |op| op.id("listUsers")))
+    .api_route(
+      "/",
+      post_with(management::create_user, |op| op.id("createUser")),
+    )
+    .api_route(
+      "/",
+      delete_with(status::delete_user, |op| op.id("deleteUser")),
+    )
+    .api_route("/", put_with(management::edit_user, |op| op.id("editUser")))
+    .api_route(
+      "/{uuid}",
+      get_with(management::user_info, |op| op.id("userInfo")),
+    )
+    .nest("/{uuid}/attributes", attributes::management_router())
+    .api_route("/mail", centaurus_management::mail_active_route())
+    .api_route(
+      "/groups",
+      get_with(management::list_groups_simple, |op| {
+        op.id("listGroupsSimple")
+      }),
+    )
+    .api_route(
+      "/password",
+      put_with(management::reset_user_password, |op| {
+        op.id("resetUserPassword")
+      }),
+    )
+    .api_route(
+      "/email",
+      post_with(management::change_user_email, |op| op.id("changeUserEmail")),
+    )
+    .api_route(
+      "/convert-oidc",
+      put_with(management::convert_oidc_user, |op| op.id("convertOidcUser")),
+    )
+    .api_route(
+      "/disable",
+      post_with(status::disable_user, |op| op.id("disableUser")),
+    )
+    .api_route(
+      "/enable",
+      post_with(status::enable_user, |op| op.id("enableUser")),
+    )
+    .api_route(
+      "/restore",
+      post_with(status::restore_user, |op| op.id("restoreUser")),
+    )
+    .api_route(
+      "/import",
+      post_with(transfer::import_users, |op| op.id("importUsers")),
+    )
+    .api_route(
+      "/export",
+      get_with(transfer::export_users, |op| op.id("exportUsers")),
+    )
+}
+
+#[derive(Serialize, JsonSchema)]
+struct UserInfo {
+  uuid: Uuid,
+  name: String,
+  email: String,
+  permissions: Vec<String>,
+  oidc_user: bool,
+  /// Organization the permissions apply to.
+  organization: Option<Uuid>,
+  /// Administrator of the whole instance, across all organizations.
+  super_admin: bool,
+  /// Set while an administrator is viewing the application as this user.
+  impersonation: Option<ImpersonationInfo>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct ImpersonationInfo {
+  admin: Uuid,
+  admin_name: String,
+  expires_at: DateTime<Utc>,
+}
+
+async fn user_info(
+  auth: JwtAuth,
+  impersonation: Option<Impersonation>,
+  db: Connection,
+) -> Result<Json<UserInfo>> {
+  let user = db.user().get_user_by_id(auth.user_id).await?;
+  let super_admin = db.organization().is_super_admin(auth.user_id).await?;
+  let organization = current_organization(&db, auth.user_id, super_admin).await?;
+  let permissions = match organization {
+    Some(organization) => scoped_permissions(&db, organization, auth.user_id, super_admin).await?,
+    None => Vec::new(),
+  };
+
+  let impersonation = match impersonation {
+    Some(impersonation) => {
+      let admin = db.user().get_user_by_id(impersonation.admin).await?;
+      Some(ImpersonationInfo {
+        admin: admin.id,
+        admin_name: admin.name,
+        expires_at: impersonation.expires_at,
+      })
+    }
+    None => None,
+  };
+
+  Ok(Json(UserInfo {
+    uuid: user.id,
+    name: user.name,
+    email: user.email,
+    permissions,
+    oidc_user: user.oidc_user,
+    organization,
+    super_admin,
+    impersonation,
+  }))
+}
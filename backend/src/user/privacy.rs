@@ -0,0 +1,420 @@
+use std::time::Duration as StdDuration;
+
+use aide::{
+  OperationIo,
+  axum::{
+    ApiRouter,
+    routing::{delete_with, get_with, post_with},
+  },
+};
+use axum::{
+  Form, Json,
+  extract::{FromRequestParts, Query},
+  response::{Html, IntoResponse, Response},
+  routing::get,
+};
+use centaurus::{
+  backend::{auth::jwt_auth::JwtAuth, config::SiteConfig, request::redirect::Redirect},
+  bail,
+  db::{init::Connection, tables::ConnectionExt},
+  error::Result,
+  mail::Mailer,
+};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use entity::account_deletion;
+use http::header;
+use schemars::JsonSchema;
+use serde::Serialize;
+use tokio::{spawn, time::interval};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::{
+  archive::TarGz,
+  attribute::{self, AttributeAccess},
+  audit::{AuditContext, AuditEntry},
+  config::Config,
+  confirm::{self, TokenForm},
+  db::{DBTrait, repository::UserRepository},
+  pagination,
+  user::{status, template},
+  utils::{UpdateMessage, Updater, hash_token, site_link},
+};
+
+/// How long the confirmation link of a deletion request can be used.
+const CONFIRM_LINK_VALIDITY_HOURS: i64 = 24;
+/// How often due account deletions are carried out.
+const DELETION_INTERVAL_SECS: u64 = 60 * 60;
+
+pub fn deletion_router() -> ApiRouter {
+  ApiRouter::new()
+    .api_route(
+      "/",
+      get_with(deletion_status, |op| op.id("accountDeletionStatus")),
+    )
+    .api_route(
+      "/",
+      post_with(request_deletion, |op| op.id("requestAccountDeletion")),
+    )
+    .api_route(
+      "/",
+      delete_with(cancel_deletion, |op| op.id("cancelAccountDeletion")),
+    )
+    .route(
+      "/confirm",
+      get(confirm_deletion_page).post(confirm_deletion_link),
+    )
+}
+
+#[derive(Serialize)]
+struct ExportProfile {
+  uuid: Uuid,
+  name: String,
+  email: String,
+  oidc_user: bool,
+  created_at: Option<DateTime<Utc>>,
+  disabled_at: Option<DateTime<Utc>>,
+  deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+struct ExportSessions {
+  /// Sessions issued before this time were ended.
+  sessions_revoked_at: Option<DateTime<Utc>>,
+  logins: Vec<crate::db::login::LoginInfo>,
+}
+
+#[derive(Serialize)]
+struct ExportSettings {
+  attributes: Vec<attribute::AttributeValue>,
+  account_deletion: Option<DeletionStatus>,
+}
+
+/// File extension of an image by its signature.
+fn image_extension(data: &[u8]) -> &'static str {
+  match data {
+    [0x89, b'P', b'N', b'G', ..] => "png",
+    [0xFF, 0xD8, 0xFF, ..] => "jpg",
+    [b'G', b'I', b'F', b'8', ..] => "gif",
+    [
+      b'R',
+      b'I',
+      b'F',
+      b'F',
+      _,
+      _,
+      _,
+      _,
+      b'W',
+      b'E',
+      b'B',
+      b'P',
+      ..,
+    ] => "webp",
+    _ => "bin",
+  }
+}
+
+fn utc(time: NaiveDateTime) -> DateTime<Utc> {
+  time.and_utc()
+}
+
+/// Everything stored about the user as a `tar.gz` archive with one JSON file
+/// per topic and the avatar.
+pub async fn export_account(auth: JwtAuth, db: Connection) -> Result<Response> {
+  let user = db.user().get_user_by_id(auth.user_id).await?;
+  let status = db.user_status().get_status(user.id).await?;
+
+  let mut archive = TarGz::new();
+  archive.add_json(
+    "profile.json",
+    &ExportProfile {
+      uuid: user.id,
+      name: user.name.clone(),
+      email: user.email.clone(),
+      oidc_user: user.oidc_user,
+      created_at: pagination::created_at(user.id),
+      disabled_at: status.as_ref().and_then(|s| s.disabled_at).map(utc),
+      deleted_at: status.as_ref().and_then(|s| s.deleted_at).map(utc),
+    },
+  );
+  archive.add_json("groups.json", &db.user().get_user_groups(user.id).await?);
+
+  let access = AttributeAccess::Own {
+    oidc_user: user.oidc_user,
+  };
+  archive.add_json(
+    "settings.json",
+    &ExportSettings {
+      attributes: attribute::user_attributes(&db, user.id, access).await?,
+      account_deletion: db
+        .account_deletion()
+        .get(user.id)
+        .await?
+        .map(DeletionStatus::from),
+    },
+  );
+  archive.add_json(
+    "sessions.json",
+    &ExportSessions {
+      sessions_revoked_at: db.login().sessions_revoked_at(user.id).await?.map(utc),
+      logins: db.login().all_logins(user.id).await?,
+    },
+  );
+  archive.add_json("audit.json", &db.audit().events_about_user(user.id).await?);
+
+  if let Some(avatar) = db.user().get_user_avatar(user.id).await? {
+    archive.add(&format!("avatar.{}", image_extension(&avatar)), &avatar);
+  }
+
+  let body = archive.finish()?;
+  Ok(
+    (
+      [
+        (header::CONTENT_TYPE, "application/gzip".to_string()),
+        (
+          header::CONTENT_DISPOSITION,
+          "attachment; filename=\"account-export.tar.gz\"".to_string(),
+        ),
+      ],
+      body,
+    )
+      .into_response(),
+  )
+}
+
+#[derive(Serialize, JsonSchema)]
+struct DeletionStatus {
+  requested_at: DateTime<Utc>,
+  /// Unconfirmed requests wait for the link sent by mail.
+  confirmed: bool,
+  delete_at: Option<DateTime<Utc>>,
+}
+
+impl From<account_deletion::Model> for DeletionStatus {
+  fn from(deletion: account_deletion::Model) -> Self {
+    Self {
+      requested_at: utc(deletion.requested_at),
+      confirmed: deletion.confirmed_at.is_some(),
+      delete_at: deletion.delete_at.map(utc),
+    }
+  }
+}
+
+/// Everything needed to handle a deletion request.
+#[derive(Clone, FromRequestParts, OperationIo)]
+struct DeletionState {
+  mailer: Mailer,
+  site: SiteConfig,
+  config: Config,
+  updater: Updater,
+  context: AuditContext,
+}
+
+impl DeletionState {
+  fn delete_at(&self) -> NaiveDateTime {
+    Utc::now().naive_utc() + Duration::days(self.config.account_deletion_grace_days)
+  }
+
+  async fn record(&self, db: &Connection, action: &'static str, user: Uuid) -> Result<()> {
+    let status = db
+      .account_deletion()
+      .get(user)
+      .await?
+      .map(DeletionStatus::from);
+    let entry = AuditEntry::new(action)
+      .actor(user)
+      .target("user", user)
+      .after(&status);
+    self.context.record(db, &self.updater, entry).await;
+    self
+      .updater
+      .broadcast(UpdateMessage::User { uuid: user })
+      .await;
+    Ok(())
+  }
+}
+
+async fn deletion_status(auth: JwtAuth, db: Connection) -> Result<Json<Option<DeletionStatus>>> {
+  Ok(Json(
+    db.account_deletion()
+      .get(auth.user_id)
+      .await?
+      .map(DeletionStatus::from),
+  ))
+}
+
+/// Asks for the account to be deleted. With mail set up the request has to be
+/// confirmed through a mailed link, otherwise it is confirmed right away. The
+/// account is deleted once the grace period after the confirmation is over.
+async fn request_deletion(
+  auth: JwtAuth,
+  db: Connection,
+  state: DeletionState,
+) -> Result<Json<DeletionStatus>> {
+  if db
+    .account_deletion()
+    .get(auth.user_id)
+    .await?
+    .is_some_and(|deletion| deletion.confirmed_at.is_some())
+  {
+    bail!(CONFLICT, "Account deletion is already scheduled");
+  }
+  status::check_deactivate(&db, auth.user_id, auth.user_id).await?;
+
+  let user = db.user().get_user_by_id(auth.user_id).await?;
+  if state.mailer.is_active().await {
+    let token = Uuid::new_v4().to_string();
+    db.account_deletion()
+      .request(user.id, Some(hash_token(&token)), state.delete_at())
+      .await?;
+
+    let confirm_link = site_link(
+      &state.site,
+      &["api", "user", "account", "deletion", "confirm"],
+      Some(&token),
+    );
+    let body = template::confirm_deletion(
+      confirm_link.as_str(),
+      state.config.account_deletion_grace_days,
+      state.site.site_url.as_str(),
+    );
+    let mailer = state.mailer.clone();
+    spawn(async move {
+      if let Err(err) = mailer
+        .send_mail(
+          user.name,
+          user.email.clone(),
+          "Confirm Account Deletion".to_string(),
+          body,
+        )
+        .await
+      {
+        warn!(
+          "Failed to send account deletion confirmation to {}: {:?}",
+          user.email, err
+        );
+      }
+    });
+  } else {
+    db.account_deletion()
+      .request(user.id, None, state.delete_at())
+      .await?;
+  }
+
+  state
+    .record(&db, "user.deletion_request", auth.user_id)
+    .await?;
+
+  let Some(deletion) = db.account_deletion().get(auth.user_id).await? else {
+    bail!(INTERNAL_SERVER_ERROR, "Deletion request was not stored");
+  };
+  Ok(Json(deletion.into()))
+}
+
+async fn cancel_deletion(auth: JwtAuth, db: Connection, state: DeletionState) -> Result<()> {
+  if db.account_deletion().get(auth.user_id).await?.is_none() {
+    bail!(NOT_FOUND, "No account deletion was requested");
+  }
+  db.account_deletion().cancel(auth.user_id).await?;
+
+  state
+    .record(&db, "user.deletion_cancel", auth.user_id)
+    .await
+}
+
+async fn find_requested_deletion(db: &Connection, token: &str) -> Result<account_deletion::Model> {
+  let deletion = db
+    .account_deletion()
+    .find_by_token(&hash_token(token))
+    .await?;
+  let valid_after = Utc::now().naive_utc() - Duration::hours(CONFIRM_LINK_VALIDITY_HOURS);
+  let Some(deletion) = deletion.filter(|deletion| deletion.requested_at > valid_after) else {
+    bail!(NOT_FOUND, "Invalid or expired link");
+  };
+  Ok(deletion)
+}
+
+/// Target of the link in the confirmation mail, only asks for confirmation.
+async fn confirm_deletion_page(
+  db: Connection,
+  state: DeletionState,
+  Query(TokenForm { token }): Query<TokenForm>,
+) -> Result<Html<String>> {
+  find_requested_deletion(&db, &token).await?;
+
+  Ok(confirm::page(
+    "Delete Account",
+    &format!(
+      "Once confirmed, your account and all of its data are deleted after {} days. You can cancel the deletion until then by signing in.",
+      state.config.account_deletion_grace_days
+    ),
+    "Delete my account",
+    "confirm",
+    &token,
+  ))
+}
+
+/// Submitted from the page behind the mailed link, works without a session.
+async fn confirm_deletion_link(
+  db: Connection,
+  state: DeletionState,
+  Form(TokenForm { token }): Form<TokenForm>,
+) -> Result<Redirect> {
+  let deletion = find_requested_deletion(&db, &token).await?;
+  // the user may have become the last administrator in the meantime
+  status::check_deactivate(&db, deletion.user_id, deletion.user_id).await?;
+
+  db.account_deletion()
+    .confirm(deletion.user_id, state.delete_at())
+    .await?;
+  state
+    .record(&db, "user.deletion_confirm", deletion.user_id)
+    .await?;
+
+  let account_page = site_link(&state.site, &["account", "general"], None);
+  Ok(Redirect::found(account_page.to_string()))
+}
+
+/// Deletes the accounts whose grace period is over. The last administrator is
+/// skipped, their request stays until someone else can take over. A failed
+/// deletion is retried on the next run and does not hold up the others.
+pub async fn delete_due(db: &Connection, context: &AuditContext, updater: &Updater) -> Result<u64> {
+  let users = db.account_deletion().due(Utc::now().naive_utc()).await?;
+
+  let mut deleted = 0;
+  for user in users {
+    if let Err(err) = status::check_deactivate(db, user, user).await {
+      warn!("Not deleting user {}: {:?}", user, err);
+      continue;
+    }
+    if let Err(err) = db.delete_user(user).await {
+      error!("Failed to delete user {}: {:?}", user, err);
+      continue;
+    }
+    info!("Deleted user {} on their own request", user);
+    deleted += 1;
+
+    let entry = AuditEntry::new("user.deletion_complete")
+      .actor(user)
+      .target("user", user);
+    context.record(db, updater, entry).await;
+    updater.broadcast(UpdateMessage::User { uuid: user }).await;
+  }
+
+  Ok(deleted)
+}
+
+/// Carries out due deletions once at startup and then periodically in the
+/// background.
+pub fn start_deletion(db: Connection, context: AuditContext, updater: Updater) {
+  spawn(async move {
+    let mut interval = interval(StdDuration::from_secs(DELETION_INTERVAL_SECS));
+    loop {
+      interval.tick().await;
+      if let Err(err) = delete_due(&db, &context, &updater).await {
+        error!("Failed to delete accounts: {:?}", err);
+      }
+    }
+  });
+}
@@ -0,0 +1,243 @@
+use std::time::Duration as StdDuration;
+
+use axum::Json;
+use centaurus::{
+  backend::auth::permission::UserEdit,
+  bail,
+  db::{init::Connection, tables::ConnectionExt},
+  error::Result,
+};
+use chrono::{Duration, Utc};
+use entity::user_status;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tokio::{spawn, time::interval};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{
+  audit::{AuditContext, AuditEntry},
+  db::{DBTrait, repository::UserRepository},
+  organization::OrgAuth,
+  utils::{UpdateMessage, Updater},
+};
+
+/// How often users past their retention period are purged.
+const PURGE_INTERVAL_SECS: u64 = 60 * 60;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct UserStatusRequest {
+  uuid: Uuid,
+}
+
+/// Rules shared by everything that takes a user out of service: the last
+/// active administrator has to stay and only administrators may act on other
+/// administrators.
+pub async fn check_deactivate(db: &Connection, actor: Uuid, user: Uuid) -> Result<()> {
+  let Some(admin_group) = db.setup().get_admin_group_id().await? else {
+    bail!(INTERNAL_SERVER_ERROR, "Admin group is not set up");
+  };
+  let Some(admins) = db.group().group_info(admin_group).await? else {
+    bail!(INTERNAL_SERVER_ERROR, "Admin group is not set up");
+  };
+
+  if admins.users.iter().any(|admin| admin.id == user) {
+    if !admins.users.iter().any(|admin| admin.id == actor) {
+      bail!(
+        FORBIDDEN,
+        "User cannot deactivate another user with higher permissions"
+      );
+    }
+
+    let mut active = 0;
+    for admin in &admins.users {
+      if admin.id != user && db.user_status().is_active(admin.id).await? {
+        active += 1;
+      }
+    }
+    if active == 0 {
+      bail!(CONFLICT, "Cannot deactivate the last active administrator");
+    }
+  }
+
+  Ok(())
+}
+
+async fn get_status(db: &Connection, user: Uuid) -> Result<Option<user_status::Model>> {
+  // fails with not found for unknown users
+  db.user().get_user_by_id(user).await?;
+  db.user_status().get_status(user).await
+}
+
+fn status_json(status: Option<user_status::Model>) -> serde_json::Value {
+  serde_json::json!({
+    "disabled_at": status.as_ref().and_then(|s| s.disabled_at),
+    "deleted_at": status.as_ref().and_then(|s| s.deleted_at),
+  })
+}
+
+/// Changes the status of a user and records it, the before and after state of
+/// the audit record only contain the status.
+async fn change_status(
+  db: &Connection,
+  updater: &Updater,
+  context: &AuditContext,
+  actor: Uuid,
+  user: Uuid,
+  action: &'static str,
+  before: Option<user_status::Model>,
+) -> Result<()> {
+  let after = db.user_status().get_status(user).await?;
+  let entry = AuditEntry::new(action)
+    .actor(actor)
+    .target("user", user)
+    .before(&status_json(before))
+    .after(&status_json(after));
+  context.record(db, updater, entry).await;
+  updater.broadcast(UpdateMessage::User { uuid: user }).await;
+
+  Ok(())
+}
+
+/// Blocks logins and ends every session of the user. Group memberships are
+/// kept so enabling the user restores their access.
+pub async fn disable_user(
+  auth: OrgAuth<UserEdit>,
+  db: Connection,
+  updater: Updater,
+  context: AuditContext,
+  Json(UserStatusRequest { uuid }): Json<UserStatusRequest>,
+) -> Result<()> {
+  auth.ensure_managed(&db, uuid).await?;
+  let before = get_status(&db, uuid).await?;
+  if before.as_ref().is_some_and(|s| s.disabled_at.is_some()) {
+    bail!(CONFLICT, "User is already disabled");
+  }
+  check_deactivate(&db, auth.user_id, uuid).await?;
+
+  db.user_status().set_disabled(uuid, true).await?;
+  db.login().revoke_sessions(uuid).await?;
+
+  change_status(
+    &db,
+    &updater,
+    &context,
+    auth.user_id,
+    uuid,
+    "user.disable",
+    before,
+  )
+  .await
+}
+
+pub async fn enable_user(
+  auth: OrgAuth<UserEdit>,
+  db: Connection,
+  updater: Updater,
+  context: AuditContext,
+  Json(UserStatusRequest { uuid }): Json<UserStatusRequest>,
+) -> Result<()> {
+  auth.ensure_managed(&db, uuid).await?;
+  let before = get_status(&db, uuid).await?;
+  if before.as_ref().is_none_or(|s| s.disabled_at.is_none()) {
+    bail!(CONFLICT, "User is not disabled");
+  }
+
+  db.user_status().set_disabled(uuid, false).await?;
+
+  change_status(
+    &db,
+    &updater,
+    &context,
+    auth.user_id,
+    uuid,
+    "user.enable",
+    before,
+  )
+  .await
+}
+
+/// Soft deletes the user. The account and its history stay until the
+/// retention period is over and can be restored until then.
+pub async fn delete_user(
+  auth: OrgAuth<UserEdit>,
+  db: Connection,
+  updater: Updater,
+  context: AuditContext,
+  Json(UserStatusRequest { uuid }): Json<UserStatusRequest>,
+) -> Result<()> {
+  auth.ensure_managed(&db, uuid).await?;
+  let before = get_status(&db, uuid).await?;
+  if before.as_ref().is_some_and(|s| s.deleted_at.is_some()) {
+    bail!(CONFLICT, "User is already deleted");
+  }
+  check_deactivate(&db, auth.user_id, uuid).await?;
+
+  db.user_status().set_deleted(uuid, true).await?;
+  db.login().revoke_sessions(uuid).await?;
+
+  change_status(
+    &db,
+    &updater,
+    &context,
+    auth.user_id,
+    uuid,
+    "user.delete",
+    before,
+  )
+  .await
+}
+
+pub async fn restore_user(
+  auth: OrgAuth<UserEdit>,
+  db: Connection,
+  updater: Updater,
+  context: AuditContext,
+  Json(UserStatusRequest { uuid }): Json<UserStatusRequest>,
+) -> Result<()> {
+  auth.ensure_managed(&db, uuid).await?;
+  let before = get_status(&db, uuid).await?;
+  if before.as_ref().is_none_or(|s| s.deleted_at.is_none()) {
+    bail!(CONFLICT, "User is not deleted");
+  }
+
+  db.user_status().set_deleted(uuid, false).await?;
+
+  change_status(
+    &db,
+    &updater,
+    &context,
+    auth.user_id,
+    uuid,
+    "user.restore",
+    before,
+  )
+  .await
+}
+
+/// Removes users whose retention period is over, together with everything
+/// that cascades from them.
+pub async fn purge_deleted(db: &Connection, retention_days: i64) -> Result<u64> {
+  let cutoff = Utc::now().naive_utc() - Duration::days(retention_days);
+  let users = db.user_status().deleted_before(cutoff).await?;
+
+  for user in &users {
+    db.delete_user(*user).await?;
+    info!("Purged user {} after the retention period", user);
+  }
+
+  Ok(users.len() as u64)
+}
+
+/// Purges once at startup and then periodically in the background.
+pub fn start_purge(db: Connection, retention_days: i64) {
+  spawn(async move {
+    let mut interval = interval(StdDuration::from_secs(PURGE_INTERVAL_SECS));
+    loop {
+      interval.tick().await;
+      if let Err(err) = purge_deleted(&db, retention_days).await {
+        error!("Failed to purge deleted users: {:?}", err);
+      }
+    }
+  });
+}
@@ -0,0 +1,30 @@
+pub fn confirm_deletion(confirm_link: &str, grace_days: i64, link: &str) -> String {
+  format!(
+    r#"
+  <!DOCTYPE html>
+  <html lang="en">
+    <head>
+      <meta charset="UTF-8">
+      <meta name="viewport" content="width=device-width, initial-scale=1.0">
+      <title>Confirm Account Deletion</title>
+    </head>
+    <body>
+      <div style="display: flex; flex-direction: column;">
+        <header style="padding: 1rem; display: flex; flex-direction: column; align-items: center; justify-content: center;">
+          <h2 style="margin: 0;">Confirm Account Deletion</h2>
+          <p style="margin: 0;">You asked us to delete your account</p>
+        </header>
+        <div style="display: flex; align-items: center; justify-content: center; flex-direction: column;">
+          <p>Once confirmed, your account and all of its data are deleted after {grace_days} days. You can cancel the deletion until then by signing in.</p>
+          <a href="{confirm_link}">Delete my account</a>
+          <p>If you did not ask for this, you can ignore this email.</p>
+        </div>
+        <footer style="display: flex; align-items: center; justify-content: center;">
+          <p>Mail send from <a href="{link}">{link}</a></p>
+        </footer>
+      </div>
+    </body>
+  </html>
+  "#
+  )
+}
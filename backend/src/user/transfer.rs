@@ -0,0 +1,489 @@
+use std::collections::{HashMap, HashSet};
+
+use aide::OperationIo;
+use axum::{
+  Json,
+  extract::{FromRequestParts, Query},
+  response::{IntoResponse, Response},
+};
+use base64::{Engine, prelude::BASE64_STANDARD_NO_PAD};
+use centaurus::{
+  backend::{
+    auth::{
+      permission::{UserEdit, UserView},
+      pw_state::PasswordState,
+    },
+    config::SiteConfig,
+    endpoints::user::template,
+  },
+  bail,
+  db::{init::Connection, tables::ConnectionExt},
+  error::Result,
+  mail::Mailer,
+};
+use chrono::Utc;
+use http::header;
+use rsa::rand_core::{OsRng, RngCore};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::spawn;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+  audit::{AuditContext, AuditEntry},
+  csv,
+  db::{
+    DBTrait,
+    import::NewUser,
+    repository::{GroupRepository, UserRepository},
+  },
+  organization::OrgAuth,
+  utils::{UpdateMessage, Updater},
+};
+
+const CSV_COLUMNS: &[&str] = &["name", "email", "groups", "password"];
+/// Separates group names within the groups column of a CSV file.
+const GROUP_SEPARATOR: char = ';';
+
+#[derive(Deserialize, JsonSchema, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferFormat {
+  #[default]
+  Json,
+  Csv,
+}
+
+/// One user of an import or export. Groups are referenced by name so files
+/// can be moved between instances.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct UserRecord {
+  name: String,
+  email: String,
+  #[serde(default)]
+  groups: Vec<String>,
+  /// Users without a password are invited by mail with a generated one.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  password: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ExportQuery {
+  #[serde(default)]
+  format: TransferFormat,
+}
+
+/// Every user of the current organization that is not deleted, in the format
+/// accepted by the import.
+pub async fn export_users(
+  auth: OrgAuth<UserView>,
+  db: Connection,
+  Query(query): Query<ExportQuery>,
+) -> Result<Response> {
+  let members: HashSet<Uuid> = db
+    .organization()
+    .members(auth.organization)
+    .await?
+    .into_iter()
+    .map(|user| user.id)
+    .collect();
+  let groups: HashSet<Uuid> = db
+    .organization()
+    .groups(auth.organization)
+    .await?
+    .into_iter()
+    .map(|group| group.id)
+    .collect();
+  let mut users = db.user().list_users().await?;
+  users.retain(|user| members.contains(&user.uuid));
+  let statuses = db
+    .user_status()
+    .list_statuses(users.iter().map(|user| user.uuid).collect())
+    .await?;
+
+  let records: Vec<UserRecord> = users
+    .into_iter()
+    .filter(|user| {
+      statuses
+        .get(&user.uuid)
+        .is_none_or(|status| status.deleted_at.is_none())
+    })
+    .map(|user| UserRecord {
+      name: user.name,
+      email: user.email,
+      groups: user
+        .groups
+        .into_iter()
+        .filter(|group| groups.contains(&group.uuid))
+        .map(|group| group.name)
+        .collect(),
+      password: None,
+    })
+    .collect();
+
+  let (content_type, extension, body) = match query.format {
+    TransferFormat::Json => ("application/json", "json", serde_json::to_string(&records)?),
+    TransferFormat::Csv => ("text/csv", "csv", to_csv(&records)),
+  };
+  let disposition = format!(
+    "attachment; filename=\"users-{}.{}\"",
+    Utc::now().format("%Y%m%d%H%M%S"),
+    extension
+  );
+
+  Ok(
+    (
+      [
+        (header::CONTENT_TYPE, content_type.to_string()),
+        (header::CONTENT_DISPOSITION, disposition),
+      ],
+      body,
+    )
+      .into_response(),
+  )
+}
+
+fn to_csv(records: &[UserRecord]) -> String {
+  let mut out = csv::row(CSV_COLUMNS);
+  for record in records {
+    out.push_str(&csv::row(&[
+      record.name.as_str(),
+      record.email.as_str(),
+      &record.groups.join(&GROUP_SEPARATOR.to_string()),
+      record.password.as_deref().unwrap_or_default(),
+    ]));
+  }
+  out
+}
+
+fn from_csv(text: &str) -> Result<Vec<UserRecord>> {
+  let rows = match csv::parse(text) {
+    Ok(rows) => rows,
+    Err(err) => bail!(BAD_REQUEST, "Invalid CSV: {}", err),
+  };
+  let mut rows = rows.into_iter();
+  let Some(header) = rows.next() else {
+    return Ok(Vec::new());
+  };
+  let column = |name: &str| {
+    header
+      .iter()
+      .position(|c| c.trim().eq_ignore_ascii_case(name))
+  };
+  let (Some(name), Some(email)) = (column("name"), column("email")) else {
+    bail!(BAD_REQUEST, "CSV needs at least a name and an email column");
+  };
+  let (groups, password) = (column("groups"), column("password"));
+
+  Ok(
+    rows
+      .map(|row| {
+        let get = |index: Option<usize>| {
+          index
+            .and_then(|i| row.get(i))
+            .map(|value| value.trim().to_string())
+            .unwrap_or_default()
+        };
+        UserRecord {
+          name: get(Some(name)),
+          email: get(Some(email)),
+          groups: get(groups)
+            .split(GROUP_SEPARATOR)
+            .map(str::trim)
+            .filter(|group| !group.is_empty())
+            .map(String::from)
+            .collect(),
+          password: Some(get(password)).filter(|p| !p.is_empty()),
+        }
+      })
+      .collect(),
+  )
+}
+
+#[derive(Deserialize, JsonSchema, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+  /// Nothing is created unless every row is valid.
+  #[default]
+  Transactional,
+  /// Valid rows are created, invalid ones are skipped.
+  BestEffort,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ImportQuery {
+  #[serde(default)]
+  format: TransferFormat,
+  #[serde(default)]
+  mode: ImportMode,
+  /// Only validate the rows.
+  #[serde(default)]
+  dry_run: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ImportRow {
+  /// Position in the file, starting at 1 with the first user.
+  row: usize,
+  email: String,
+  /// Set once the user was created.
+  uuid: Option<Uuid>,
+  errors: Vec<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ImportReport {
+  dry_run: bool,
+  valid: usize,
+  invalid: usize,
+  created: usize,
+  rows: Vec<ImportRow>,
+}
+
+/// Checks every row on its own and against the rest of the file. Groups are
+/// looked up within the organization the users are imported into and may only
+/// grant permissions the actor holds, like when editing a user.
+async fn validate(
+  db: &Connection,
+  organization: Uuid,
+  actor: Uuid,
+  actor_permissions: &[String],
+  records: &[UserRecord],
+  mail_active: bool,
+) -> Result<(Vec<ImportRow>, Vec<Vec<Uuid>>)> {
+  let admin_group = db.setup().get_admin_group_id().await?;
+  let actor_is_admin = match admin_group {
+    Some(admin_group) => db.is_member(admin_group, actor).await?,
+    None => false,
+  };
+
+  let mut groups: HashMap<String, Option<Uuid>> = HashMap::new();
+  let mut seen: HashMap<String, usize> = HashMap::new();
+  let mut rows = Vec::with_capacity(records.len());
+  let mut memberships = Vec::with_capacity(records.len());
+
+  for (index, record) in records.iter().enumerate() {
+    let row = index + 1;
+    let mut errors = Vec::new();
+
+    if record.name.trim().is_empty() {
+      errors.push("Name cannot be empty".to_string());
+    }
+    let email = record.email.trim();
+    if email.is_empty() || !email.contains('@') {
+      errors.push("Invalid email".to_string());
+    } else if let Some(first) = seen.get(&email.to_lowercase()) {
+      errors.push(format!("Duplicate email, first used in row {first}"));
+    } else {
+      seen.insert(email.to_lowercase(), row);
+      if db.find_user_by_email(email).await?.is_some() {
+        errors.push("User with this email already exists".to_string());
+      }
+    }
+    if record.password.is_none() && !mail_active {
+      errors.push("Password must be provided when mail service is not active".to_string());
+    }
+
+    let mut ids = HashSet::new();
+    for name in &record.groups {
+      let id = match groups.get(name) {
+        Some(id) => *id,
+        None => {
+          let id = db
+            .organization()
+            .find_group_by_name(organization, name)
+            .await?;
+          groups.insert(name.clone(), id);
+          id
+        }
+      };
+      match id {
+        None => errors.push(format!("Group {name} not found")),
+        Some(id) if Some(id) == admin_group && !actor_is_admin => {
+          errors.push("Only administrators can add users to the admin group".to_string())
+        }
+        Some(id) => {
+          ids.insert(id);
+        }
+      }
+    }
+    let ids: Vec<Uuid> = ids.into_iter().collect();
+    let mut missing: Vec<String> = db
+      .group()
+      .get_groups_permissions(ids.clone())
+      .await?
+      .into_iter()
+      .filter(|permission| !actor_permissions.contains(permission))
+      .collect();
+    if !missing.is_empty() {
+      missing.sort_unstable();
+      missing.dedup();
+      errors.push(format!(
+        "The groups grant permissions you do not have: {}",
+        missing.join(", ")
+      ));
+    }
+
+    rows.push(ImportRow {
+      row,
+      email: email.to_string(),
+      uuid: None,
+      errors,
+    });
+    memberships.push(ids);
+  }
+
+  Ok((rows, memberships))
+}
+
+pub fn generate_password() -> String {
+  let mut bytes = [0u8; 12];
+  OsRng.fill_bytes(&mut bytes);
+  BASE64_STANDARD_NO_PAD.encode(bytes)
+}
+
+pub fn generate_salt() -> String {
+  let mut bytes = [0u8; 16];
+  OsRng.fill_bytes(&mut bytes);
+  BASE64_STANDARD_NO_PAD.encode(bytes)
+}
+
+/// Everything needed to create and notify imported users.
+#[derive(Clone, FromRequestParts, OperationIo)]
+pub struct ImportState {
+  mailer: Mailer,
+  passwords: PasswordState,
+  site: SiteConfig,
+  updater: Updater,
+  context: AuditContext,
+}
+
+/// Creates users of the current organization from a CSV or JSON file. Users
+/// without a password get an invitation mail like users created one by one.
+pub async fn import_users(
+  auth: OrgAuth<UserEdit>,
+  db: Connection,
+  ImportState {
+    mailer,
+    passwords,
+    site,
+    updater,
+    context,
+  }: ImportState,
+  Query(query): Query<ImportQuery>,
+  body: String,
+) -> Result<Json<ImportReport>> {
+  let records = match query.format {
+    TransferFormat::Json => match serde_json::from_str::<Vec<UserRecord>>(&body) {
+      Ok(records) => records,
+      Err(err) => bail!(BAD_REQUEST, "Invalid JSON: {}", err),
+    },
+    TransferFormat::Csv => from_csv(&body)?,
+  };
+
+  let mail_active = mailer.is_active().await;
+  let permissions = auth.permissions(&db).await?;
+  let (mut rows, memberships) = validate(
+    &db,
+    auth.organization,
+    auth.user_id,
+    &permissions,
+    &records,
+    mail_active,
+  )
+  .await?;
+  let invalid = rows.iter().filter(|row| !row.errors.is_empty()).count();
+  let mut report = ImportReport {
+    dry_run: query.dry_run,
+    valid: rows.len() - invalid,
+    invalid,
+    created: 0,
+    rows: Vec::new(),
+  };
+  if query.dry_run || (query.mode == ImportMode::Transactional && invalid > 0) {
+    report.rows = rows;
+    return Ok(Json(report));
+  }
+
+  let mut pending = Vec::new();
+  let mut invites = Vec::new();
+  for ((row, record), groups) in rows.iter().zip(records).zip(memberships) {
+    if !row.errors.is_empty() {
+      continue;
+    }
+    let invite = record.password.is_none();
+    let password = record.password.unwrap_or_else(generate_password);
+    let salt = generate_salt();
+    let new = NewUser {
+      name: record.name.trim().to_string(),
+      email: row.email.clone(),
+      password: passwords.pw_hash_raw(&salt, &password)?,
+      salt,
+      organization: auth.organization,
+      groups,
+    };
+    if invite {
+      invites.push((new.name.clone(), new.email.clone(), password));
+    }
+    pending.push((row.row, new));
+  }
+
+  let mut created = Vec::new();
+  match query.mode {
+    ImportMode::Transactional => {
+      let (indices, users): (Vec<_>, Vec<_>) = pending.into_iter().unzip();
+      let ids = db.import().create_users(users).await?;
+      created.extend(indices.into_iter().zip(ids));
+    }
+    ImportMode::BestEffort => {
+      for (index, new) in pending {
+        match db.import().create_user(new).await {
+          Ok(id) => created.push((index, id)),
+          Err(err) => {
+            warn!("Failed to import user in row {}: {:?}", index, err);
+            rows[index - 1]
+              .errors
+              .push("Failed to create user".to_string());
+          }
+        }
+      }
+    }
+  }
+
+  for (index, id) in &created {
+    rows[index - 1].uuid = Some(*id);
+    updater.broadcast(UpdateMessage::User { uuid: *id }).await;
+  }
+  let created_emails: HashSet<&str> = created
+    .iter()
+    .map(|(index, _)| rows[index - 1].email.as_str())
+    .collect();
+  for (name, email, password) in invites {
+    if !created_emails.contains(email.as_str()) {
+      continue;
+    }
+    let mailer = mailer.clone();
+    let body = template::init_password(site.site_url.as_str(), &password);
+    spawn(async move {
+      if let Err(err) = mailer
+        .send_mail(name, email.clone(), "Your new account".to_string(), body)
+        .await
+      {
+        warn!("Failed to send invitation to {}: {:?}", email, err);
+      }
+    });
+  }
+
+  let entry = AuditEntry::new("user.import")
+    .actor(auth.user_id)
+    .after(&serde_json::json!({
+      "users": created.iter().map(|(_, id)| id).collect::<Vec<_>>(),
+      "skipped": rows.len() - created.len(),
+    }));
+  context.record(&db, &updater, entry).await;
+
+  report.created = created.len();
+  report.invalid = rows.iter().filter(|row| !row.errors.is_empty()).count();
+  report.rows = rows;
+  Ok(Json(report))
+}
@@ -1,11 +1,19 @@
+use axum::response::Response;
+use axum_extra::extract::cookie::Cookie;
 use centaurus::{
   UpdateMessage,
-  backend::{auth::permission, endpoints::websocket},
+  backend::{
+    auth::jwt_state::{JWT_COOKIE_NAME, JwtState},
+    config::SiteConfig,
+    endpoints::websocket,
+  },
 };
+use http::header;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use url::Url;
 use uuid::Uuid;
 
-#[allow(unused)]
 pub type Updater = websocket::state::Updater<UpdateMessage>;
 
 #[derive(Serialize, Deserialize, Clone, Copy, Debug, UpdateMessage)]
@@ -14,13 +22,74 @@ pub enum UpdateMessage {
   #[update_message(settings)]
   Settings,
   #[update_message(user)]
-  User { uuid: Uuid },
+  User {
+    uuid: Uuid,
+  },
   #[update_message(user_permissions)]
   UserPermissions,
   #[update_message(group)]
-  Group { uuid: Uuid },
+  Group {
+    uuid: Uuid,
+  },
+  Role {
+    uuid: Uuid,
+  },
+  Audit {
+    uuid: Uuid,
+  },
+  Attribute {
+    uuid: Uuid,
+  },
+  Organization {
+    uuid: Uuid,
+  },
+  FeatureFlag {
+    uuid: Uuid,
+  },
 }
 
-pub fn permissions() -> Vec<&'static str> {
-  permission::permissions()
+/// Request path relative to the api router, without trailing slash.
+pub fn api_path(path: &str) -> &str {
+  let path = path.strip_prefix("/api").unwrap_or(path);
+  path.strip_suffix('/').unwrap_or(path)
+}
+
+/// The user a response logged in, taken from the session cookie it sets.
+pub fn issued_session(jwt: &JwtState, response: &Response) -> Option<Uuid> {
+  response
+    .headers()
+    .get_all(header::SET_COOKIE)
+    .iter()
+    .filter_map(|value| Cookie::parse(value.to_str().ok()?).ok())
+    .find(|cookie| cookie.name() == JWT_COOKIE_NAME && !cookie.value().is_empty())
+    .and_then(|cookie| jwt.validate_token(cookie.value()).ok())
+    .map(|claims| claims.sub)
+}
+
+/// Tokens sent by mail are only stored hashed.
+pub fn hash_token(token: &str) -> String {
+  format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Link to a page of the site, optionally carrying a token.
+pub fn site_link(site: &SiteConfig, path: &[&str], token: Option<&str>) -> Url {
+  let mut link = site.site_url.clone();
+  if let Ok(segments) = &mut link.path_segments_mut() {
+    segments.pop_if_empty();
+    segments.extend(path);
+  }
+  if let Some(token) = token {
+    link.query_pairs_mut().append_pair("token", token);
+  }
+  link
+}
+
+/// Escapes text for HTML pages and mails, e.g. values sent by the client.
+pub fn escape_html(value: &str) -> String {
+  value
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+    .replace('\'', "&#39;")
 }
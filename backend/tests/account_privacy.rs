@@ -0,0 +1,90 @@
+mod common;
+
+use common::{TestServer, USER_PASSWORD, unique_email, unpack_tar_gz};
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+#[tokio::test]
+async fn account_export_contains_the_users_data() {
+  let (server, _) = TestServer::start_with_admin().await;
+  let email = unique_email("privacy");
+  server.create_user("Member", &email).await;
+  server.clear_cookies();
+  assert_eq!(
+    server.login(&email, USER_PASSWORD).await.status(),
+    StatusCode::OK
+  );
+
+  let resp = server.get("/user/account/export").await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  assert_eq!(resp.headers()["content-type"], "application/gzip");
+  let files = unpack_tar_gz(&resp.bytes().await.unwrap());
+
+  let json = |name: &str| -> Value { serde_json::from_slice(&files[name]).unwrap() };
+  assert_eq!(json("profile.json")["email"], email);
+  assert!(json("groups.json").is_array());
+  assert!(json("settings.json")["attributes"].is_array());
+  let logins = json("sessions.json")["logins"].as_array().unwrap().clone();
+  assert_eq!(logins.len(), 1);
+  assert_eq!(logins[0]["method"], "password");
+  assert!(
+    json("audit.json")
+      .as_array()
+      .unwrap()
+      .iter()
+      .any(|event| event["action"] == "auth.login")
+  );
+}
+
+#[tokio::test]
+async fn users_can_request_and_cancel_their_deletion() {
+  let (server, _) = TestServer::start_with_admin().await;
+  let email = unique_email("privacy");
+  server.create_user("Member", &email).await;
+  server.clear_cookies();
+  server.login(&email, USER_PASSWORD).await;
+
+  let resp = server.get("/user/account/deletion").await;
+  assert_eq!(resp.json::<Value>().await.unwrap(), Value::Null);
+
+  // without mail the request is confirmed right away
+  let resp = server.post("/user/account/deletion", json!({})).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let status: Value = resp.json().await.unwrap();
+  assert_eq!(status["confirmed"], true);
+  assert!(status["delete_at"].is_string());
+
+  let resp = server.post("/user/account/deletion", json!({})).await;
+  assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+  let resp = server.delete("/user/account/deletion", json!({})).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let resp = server.get("/user/account/deletion").await;
+  assert_eq!(resp.json::<Value>().await.unwrap(), Value::Null);
+  let resp = server.delete("/user/account/deletion", json!({})).await;
+  assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+  // The unauthenticated mail link rejects unknown tokens, on the page as well
+  // as when the form on it is submitted.
+  let resp = server
+    .get("/user/account/deletion/confirm?token=not-a-token")
+    .await;
+  assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+  let resp = server
+    .post_form(
+      "/user/account/deletion/confirm",
+      &[("token", "not-a-token")],
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn last_administrator_cannot_delete_themselves() {
+  let (server, _) = TestServer::start_with_admin().await;
+
+  let resp = server.post("/user/account/deletion", json!({})).await;
+  assert_eq!(resp.status(), StatusCode::CONFLICT);
+  let resp = server.get("/user/account/deletion").await;
+  assert_eq!(resp.json::<Value>().await.unwrap(), Value::Null);
+}
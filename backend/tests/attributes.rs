@@ -0,0 +1,272 @@
+mod common;
+
+use common::{TestServer, USER_PASSWORD, oidc::OidcProvider, unique, unique_email};
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+async fn define(server: &TestServer, definition: Value) -> Uuid {
+  let resp = server.post("/attribute", definition).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let body: Value = resp.json().await.unwrap();
+  Uuid::parse_str(body["uuid"].as_str().unwrap()).unwrap()
+}
+
+async fn attributes(server: &TestServer, path: &str) -> Vec<Value> {
+  let resp = server.get(path).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  resp.json().await.unwrap()
+}
+
+fn find<'a>(attributes: &'a [Value], key: &str) -> Option<&'a Value> {
+  attributes.iter().find(|a| a["key"] == key)
+}
+
+#[tokio::test]
+async fn attribute_definitions_are_validated() {
+  let (server, _) = TestServer::start_with_admin().await;
+
+  let uuid = define(
+    &server,
+    json!({ "key": "department", "label": "Department", "kind": "string", "visibility": "self_editable" }),
+  )
+  .await;
+
+  let resp = server
+    .post(
+      "/attribute",
+      json!({ "key": "department", "label": "Other", "kind": "string", "visibility": "hidden" }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+  let resp = server
+    .post(
+      "/attribute",
+      json!({ "key": "locale", "label": "Locale", "kind": "enum", "visibility": "self_editable" }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+  let resp = server
+    .put(
+      "/attribute",
+      json!({ "uuid": uuid, "key": "department", "label": "Department", "kind": "number", "visibility": "self_editable" }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+  let resp = server
+    .put(
+      "/attribute",
+      json!({ "uuid": uuid, "key": "department", "label": "Team", "kind": "string", "visibility": "admin_only" }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  let resp = server.get("/attribute").await;
+  let list: Vec<Value> = resp.json().await.unwrap();
+  assert_eq!(list.len(), 1);
+  assert_eq!(list[0]["label"], "Team");
+  assert_eq!(list[0]["visibility"], "admin_only");
+
+  let resp = server.delete("/attribute", json!({ "uuid": uuid })).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let resp = server.get("/attribute").await;
+  let list: Vec<Value> = resp.json().await.unwrap();
+  assert!(list.is_empty());
+}
+
+#[tokio::test]
+async fn users_only_edit_their_self_editable_attributes() {
+  let (server, _) = TestServer::start_with_admin().await;
+  define(
+    &server,
+    json!({ "key": "phone", "label": "Phone", "kind": "string", "pattern": "\\+?[0-9 ]+", "visibility": "self_editable" }),
+  )
+  .await;
+  define(
+    &server,
+    json!({ "key": "employee_id", "label": "Employee id", "kind": "number", "min": 1, "visibility": "admin_only" }),
+  )
+  .await;
+  define(
+    &server,
+    json!({ "key": "notes", "label": "Notes", "kind": "string", "visibility": "hidden" }),
+  )
+  .await;
+
+  let email = unique_email("attr");
+  let member = server.create_user("Member", &email).await;
+  let resp = server
+    .put(
+      &format!("/user/management/{member}/attributes"),
+      json!({ "values": { "employee_id": 4711, "notes": "VIP" } }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  // attribute values are part of the user search
+  let resp = server.get("/user/management?search=4711").await;
+  let page: Value = resp.json().await.unwrap();
+  assert_eq!(page["total"], 1);
+  assert_eq!(page["items"][0]["uuid"], member.to_string());
+
+  server.clear_cookies();
+  assert_eq!(
+    server.login(&email, USER_PASSWORD).await.status(),
+    StatusCode::OK
+  );
+
+  let own = attributes(&server, "/user/account/attributes").await;
+  assert!(find(&own, "notes").is_none());
+  let employee_id = find(&own, "employee_id").unwrap();
+  assert_eq!(employee_id["value"], 4711);
+  assert_eq!(employee_id["editable"], false);
+
+  let resp = server
+    .put(
+      "/user/account/attributes",
+      json!({ "values": { "employee_id": 1 } }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+  let resp = server
+    .put(
+      "/user/account/attributes",
+      json!({ "values": { "notes": "" } }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+  let resp = server
+    .put(
+      "/user/account/attributes",
+      json!({ "values": { "phone": "call me" } }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+  let resp = server
+    .put(
+      "/user/account/attributes",
+      json!({ "values": { "phone": "+49 123" } }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let own = attributes(&server, "/user/account/attributes").await;
+  assert_eq!(find(&own, "phone").unwrap()["value"], "+49 123");
+}
+
+#[tokio::test]
+async fn attributes_are_taken_from_oidc_claims() {
+  let provider = OidcProvider::start(json!({
+    "sub": "oidc-user",
+    "email": "oidc@example.com",
+    "name": "Oidc User",
+    "department": "Sales",
+    "locale": "en",
+  }))
+  .await;
+  provider.configure_env();
+
+  let (server, _) = TestServer::start_with_admin().await;
+  provider.wait_until_enabled(&server).await;
+  define(
+    &server,
+    json!({ "key": "department", "label": "Department", "kind": "string", "visibility": "self_editable", "oidc_claim": "department" }),
+  )
+  .await;
+  define(
+    &server,
+    json!({ "key": "locale", "label": "Locale", "kind": "enum", "options": ["de", "en"], "visibility": "self_editable", "oidc_claim": "locale" }),
+  )
+  .await;
+
+  server.clear_cookies();
+  let resp = provider.login(&server).await;
+  assert_eq!(resp.status(), StatusCode::FOUND);
+  assert_eq!(resp.headers()["location"], "http://localhost/");
+
+  let own = attributes(&server, "/user/account/attributes").await;
+  let department = find(&own, "department").unwrap();
+  assert_eq!(department["value"], "Sales");
+  // values from the provider would be replaced on the next login
+  assert_eq!(department["editable"], false);
+  assert_eq!(find(&own, "locale").unwrap()["value"], "en");
+
+  // invalid claims leave the current value untouched
+  provider.set_claims(json!({
+    "sub": "oidc-user",
+    "email": "oidc@example.com",
+    "name": "Oidc User",
+    "department": "Support",
+    "locale": "fr",
+  }));
+  server.clear_cookies();
+  let resp = provider.login(&server).await;
+  assert_eq!(resp.headers()["location"], "http://localhost/");
+
+  let own = attributes(&server, "/user/account/attributes").await;
+  assert_eq!(find(&own, "department").unwrap()["value"], "Support");
+  assert_eq!(find(&own, "locale").unwrap()["value"], "en");
+}
+
+#[tokio::test]
+async fn hidden_attributes_cannot_be_searched_without_seeing_them() {
+  let (server, _) = TestServer::start_with_admin().await;
+  define(
+    &server,
+    json!({ "key": "clearance", "label": "Clearance", "kind": "string", "visibility": "hidden" }),
+  )
+  .await;
+  define(
+    &server,
+    json!({ "key": "nickname", "label": "Nickname", "kind": "string", "visibility": "self_editable" }),
+  )
+  .await;
+
+  let member = server.create_user("Member", &unique_email("attr")).await;
+  let resp = server
+    .put(
+      &format!("/user/management/{member}/attributes"),
+      json!({ "values": { "clearance": "topsecret", "nickname": "sparrow" } }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  let search = async |server: &TestServer, value: &str| {
+    let resp = server.get(&format!("/group/users?search={value}")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    resp.json::<Value>().await.unwrap()["total"].clone()
+  };
+  // administrators see every attribute
+  assert_eq!(search(&server, "topsecret").await, 1);
+
+  let email = unique_email("viewer");
+  let viewer = server.create_user("Viewer", &email).await;
+  let resp = server
+    .post("/group", json!({ "name": unique("viewers") }))
+    .await;
+  let group: Value = resp.json().await.unwrap();
+  let resp = server
+    .put(
+      "/group",
+      json!({
+        "uuid": group["uuid"],
+        "name": unique("viewers"),
+        "permissions": ["group:view"],
+        "users": [viewer],
+      }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  server.clear_cookies();
+  assert_eq!(
+    server.login(&email, USER_PASSWORD).await.status(),
+    StatusCode::OK
+  );
+  assert_eq!(search(&server, "topsecret").await, 0);
+  assert_eq!(search(&server, "top").await, 0);
+  assert_eq!(search(&server, "sparrow").await, 1);
+}
@@ -0,0 +1,220 @@
+mod common;
+
+use common::{TestServer, unique};
+use reqwest::StatusCode;
+use serde_json::Value;
+use uuid::Uuid;
+
+async fn events(server: &TestServer, query: &str) -> Value {
+  let resp = server.get(&format!("/audit?{query}")).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  resp.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn setup_and_login_are_audited() {
+  let (server, admin_id) = TestServer::start_with_admin().await;
+
+  let resp = server.login("admin@example.com", "hunter2pass").await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  let setup = events(&server, "action=setup").await;
+  let event = &setup["events"][0];
+  assert_eq!(event["action"], "setup.complete");
+  assert_eq!(event["actor"], admin_id.to_string());
+  // Credentials never end up in the log.
+  assert!(event["after"].get("admin_password").is_none());
+  assert_eq!(event["after"]["admin_email"], "admin@example.com");
+
+  let login = events(&server, "action=auth.login").await;
+  assert_eq!(login["total"], 1);
+  let event = &login["events"][0];
+  assert_eq!(event["actor"], admin_id.to_string());
+  assert_eq!(event["target_id"], admin_id.to_string());
+  assert_eq!(event["status"], 200);
+  assert!(event["ip"].is_string());
+  assert!(event["after"].get("password").is_none());
+}
+
+#[tokio::test]
+async fn failed_login_is_audited() {
+  let (server, _) = TestServer::start_with_admin().await;
+
+  server.clear_cookies();
+  let resp = server.login("admin@example.com", "wrongpass").await;
+  assert!(!resp.status().is_success());
+  let resp = server.login("admin@example.com", "hunter2pass").await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  let login = events(&server, "action=auth.login").await;
+  assert_eq!(login["total"], 2);
+  // Newest first.
+  let failed = &login["events"][1];
+  assert!(failed["actor"].is_null());
+  assert_ne!(failed["status"], 200);
+  assert_eq!(failed["after"]["email"], "admin@example.com");
+}
+
+#[tokio::test]
+async fn group_edit_records_before_and_after() {
+  let (server, admin_id) = TestServer::start_with_admin().await;
+
+  let name = unique("group");
+  let resp = server
+    .post("/group", serde_json::json!({ "name": name }))
+    .await;
+  let created: Value = resp.json().await.unwrap();
+  let group_id = Uuid::parse_str(created["uuid"].as_str().unwrap()).unwrap();
+
+  let resp = server
+    .put(
+      "/group",
+      serde_json::json!({
+        "uuid": group_id,
+        "name": name,
+        "permissions": ["group:view"],
+        "users": [admin_id],
+      }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  let created = events(&server, "action=group.create").await;
+  assert_eq!(created["events"][0]["after"]["name"], name);
+
+  let edited = events(&server, &format!("target_id={group_id}")).await;
+  assert_eq!(edited["total"], 1);
+  let event = &edited["events"][0];
+  assert_eq!(event["action"], "group.edit");
+  assert_eq!(event["actor"], admin_id.to_string());
+  assert_eq!(event["before"]["permissions"], serde_json::json!([]));
+  assert_eq!(
+    event["after"]["permissions"],
+    serde_json::json!(["group:view"])
+  );
+  assert_eq!(
+    event["after"]["users"],
+    serde_json::json!([admin_id.to_string()])
+  );
+}
+
+#[tokio::test]
+async fn audit_log_is_filterable_and_paginated() {
+  let (server, admin_id) = TestServer::start_with_admin().await;
+
+  for _ in 0..3 {
+    let resp = server
+      .post("/group", serde_json::json!({ "name": unique("group") }))
+      .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+  }
+
+  let page = events(&server, "action=group&limit=2").await;
+  assert_eq!(page["total"], 3);
+  assert_eq!(page["events"].as_array().unwrap().len(), 2);
+
+  let rest = events(&server, "action=group&limit=2&offset=2").await;
+  assert_eq!(rest["events"].as_array().unwrap().len(), 1);
+  assert_ne!(rest["events"][0]["uuid"], page["events"][0]["uuid"]);
+
+  let by_actor = events(&server, &format!("actor={admin_id}&action=group.create")).await;
+  assert_eq!(by_actor["total"], 3);
+
+  let none = events(&server, &format!("actor={}", Uuid::new_v4())).await;
+  assert_eq!(none["total"], 0);
+
+  let future = events(&server, "from=2999-01-01T00:00:00Z").await;
+  assert_eq!(future["total"], 0);
+}
+
+#[tokio::test]
+async fn audit_log_requires_permission() {
+  let server = TestServer::start().await;
+  assert!(!server.get("/audit").await.status().is_success());
+}
+
+#[tokio::test]
+async fn audit_chain_verifies() {
+  let (server, _) = TestServer::start_with_admin().await;
+
+  let resp = server
+    .post("/group", serde_json::json!({ "name": unique("group") }))
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  let resp = server.get("/audit/verify").await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let report: Value = resp.json().await.unwrap();
+  assert_eq!(report["valid"], true);
+  assert_eq!(report["signed"], 0);
+  assert_eq!(report["issues"], serde_json::json!([]));
+
+  let all = events(&server, "limit=200").await;
+  assert_eq!(report["checked"], all["total"]);
+  // Newest first, so the head of the chain comes first.
+  let head = &all["events"][0];
+  assert_eq!(report["last_sequence"], head["sequence"]);
+  assert_eq!(report["last_hash"], head["hash"]);
+  let prev = &all["events"][1];
+  assert_eq!(head["prev_hash"], prev["hash"]);
+}
+
+#[tokio::test]
+async fn audit_records_are_signed_when_enabled() {
+  unsafe {
+    std::env::set_var("AUDIT_SIGNING", "true");
+  }
+  let (server, _) = TestServer::start_with_admin().await;
+
+  let report: Value = server.get("/audit/verify").await.json().await.unwrap();
+  assert_eq!(report["valid"], true);
+  assert_eq!(report["signed"], report["checked"]);
+
+  let all = events(&server, "").await;
+  let event = &all["events"][0];
+  assert!(event["signature"].is_string());
+  assert!(event["key_id"].is_string());
+}
+
+#[tokio::test]
+async fn audit_log_exports_jsonl_and_csv() {
+  let (server, _) = TestServer::start_with_admin().await;
+
+  let name = unique("group");
+  let resp = server
+    .post("/group", serde_json::json!({ "name": name }))
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let total = events(&server, "").await["total"].as_u64().unwrap();
+
+  let resp = server.get("/audit/export").await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  assert_eq!(resp.headers()["content-type"], "application/jsonl");
+  assert!(
+    resp.headers()["content-disposition"]
+      .to_str()
+      .unwrap()
+      .ends_with(".jsonl\"")
+  );
+  let body = resp.text().await.unwrap();
+  let lines: Vec<Value> = body
+    .lines()
+    .map(|line| serde_json::from_str(line).unwrap())
+    .collect();
+  assert_eq!(lines.len() as u64, total);
+  // Chain order, oldest first.
+  assert_eq!(lines[0]["sequence"], 1);
+  assert_eq!(lines.last().unwrap()["after"]["name"], name);
+
+  let resp = server.get("/audit/export?format=csv").await;
+  assert_eq!(resp.headers()["content-type"], "text/csv");
+  let body = resp.text().await.unwrap();
+  let mut rows = body.lines();
+  assert!(rows.next().unwrap().starts_with("sequence,uuid,created_at"));
+  assert_eq!(rows.count() as u64, total);
+
+  let resp = server
+    .get("/audit/export?from=2999-01-01T00:00:00Z&to=2999-12-31T00:00:00Z")
+    .await;
+  assert_eq!(resp.text().await.unwrap(), "");
+}
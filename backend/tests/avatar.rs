@@ -0,0 +1,140 @@
+mod common;
+
+use std::io::Cursor;
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use common::TestServer;
+use image::{ImageBuffer, ImageFormat, Rgb};
+use reqwest::StatusCode;
+use serde_json::json;
+
+fn jpeg(width: u32, height: u32) -> Vec<u8> {
+  let image = ImageBuffer::from_fn(width, height, |x, y| Rgb([x as u8, y as u8, 128]));
+  let mut buf = Cursor::new(Vec::new());
+  image.write_to(&mut buf, ImageFormat::Jpeg).unwrap();
+  buf.into_inner()
+}
+
+#[tokio::test]
+async fn uploads_are_served_as_webp_in_the_requested_size() {
+  let (server, admin) = TestServer::start_with_admin().await;
+
+  let resp = server
+    .post(
+      "/user/account/avatar",
+      json!({ "avatar": BASE64_STANDARD.encode(jpeg(400, 300)) }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  for (requested, served) in [
+    (None, 128),
+    (Some(32), 32),
+    (Some(100), 128),
+    (Some(999), 256),
+  ] {
+    let query = requested
+      .map(|size| format!("?size={size}"))
+      .unwrap_or_default();
+    let resp = server
+      .get(&format!("/user/info/avatar/{admin}{query}"))
+      .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()["content-type"], "image/webp");
+    let image = image::load_from_memory(&resp.bytes().await.unwrap()).unwrap();
+    assert_eq!((image.width(), image.height()), (served, served));
+  }
+}
+
+#[tokio::test]
+async fn uploads_must_be_decodable_images() {
+  let (server, admin) = TestServer::start_with_admin().await;
+
+  let mut truncated = jpeg(64, 64);
+  truncated.truncate(64);
+  for data in [b"GIF89a not really".to_vec(), truncated] {
+    let resp = server
+      .post(
+        "/user/account/avatar",
+        json!({ "avatar": BASE64_STANDARD.encode(data) }),
+      )
+      .await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+  }
+
+  let resp = server.get(&format!("/user/info/avatar/{admin}")).await;
+  assert_eq!(resp.headers()["content-type"], "image/svg+xml");
+}
+
+#[tokio::test]
+async fn avatars_are_revalidated_with_their_etag() {
+  let (server, admin) = TestServer::start_with_admin().await;
+  server
+    .post(
+      "/user/account/avatar",
+      json!({ "avatar": BASE64_STANDARD.encode(jpeg(64, 64)) }),
+    )
+    .await;
+
+  let path = format!("/user/info/avatar/{admin}");
+  let resp = server.get(&path).await;
+  assert_eq!(resp.headers()["cache-control"], "private, no-cache");
+  let etag = resp.headers()["etag"].to_str().unwrap().to_string();
+
+  let resp = server.get_with_header(&path, "if-none-match", &etag).await;
+  assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+  assert!(resp.bytes().await.unwrap().is_empty());
+
+  // a new upload changes the tag
+  server
+    .post(
+      "/user/account/avatar",
+      json!({ "avatar": BASE64_STANDARD.encode(jpeg(80, 64)) }),
+    )
+    .await;
+  let resp = server.get_with_header(&path, "if-none-match", &etag).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  assert_ne!(resp.headers()["etag"], etag.as_str());
+}
+
+#[tokio::test]
+async fn users_without_avatar_get_a_generated_one() {
+  let (server, admin) = TestServer::start_with_admin().await;
+
+  let resp = server.get(&format!("/user/info/avatar/{admin}")).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  assert_eq!(resp.headers()["content-type"], "image/svg+xml");
+  assert!(resp.headers().contains_key("etag"));
+  let svg = resp.text().await.unwrap();
+  assert!(svg.starts_with("<svg"));
+  assert!(svg.contains("<text"));
+
+  let resp = server
+    .get(&format!("/user/info/avatar/{admin}?fallback=identicon"))
+    .await;
+  let identicon = resp.text().await.unwrap();
+  assert!(!identicon.contains("<text"));
+
+  let resp = server
+    .get(&format!("/user/info/avatar/{admin}?fallback=none"))
+    .await;
+  assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+  let resp = server
+    .get(&format!("/user/info/avatar/{}", uuid::Uuid::new_v4()))
+    .await;
+  assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+  // resetting an uploaded avatar brings the generated one back
+  server
+    .post(
+      "/user/account/avatar",
+      json!({ "avatar": BASE64_STANDARD.encode(jpeg(64, 64)) }),
+    )
+    .await;
+  let resp = server
+    .delete("/user/management/avatar", json!({ "uuid": admin }))
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let resp = server.get(&format!("/user/info/avatar/{admin}")).await;
+  assert_eq!(resp.headers()["content-type"], "image/svg+xml");
+}
@@ -0,0 +1,151 @@
+mod common;
+
+use std::io::Cursor;
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use common::{TestServer, unique};
+use image::{ImageBuffer, ImageFormat, Rgb};
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+fn png(width: u32, height: u32) -> Vec<u8> {
+  let image = ImageBuffer::from_fn(width, height, |x, y| Rgb([x as u8, y as u8, 64]));
+  let mut buf = Cursor::new(Vec::new());
+  image.write_to(&mut buf, ImageFormat::Png).unwrap();
+  buf.into_inner()
+}
+
+async fn general(server: &TestServer, query: &str) -> Value {
+  let resp = server.get(&format!("/settings/general{query}")).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  resp.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn branding_is_served_to_the_login_page() {
+  let (server, _) = TestServer::start_with_admin().await;
+
+  let resp = server
+    .post(
+      "/settings/branding",
+      json!({
+        "application_name": "Acme ID",
+        "primary_color": "#1e66f5",
+        "login_message": "Use your work account",
+        "support_email": "help@example.com",
+        "legal_links": [{ "label": "Privacy", "url": "https://example.com/privacy" }],
+      }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let resp = server
+    .post(
+      "/settings/branding/logo",
+      json!({ "image": BASE64_STANDARD.encode(png(1024, 256)) }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  server.clear_cookies();
+  let settings = general(&server, "").await;
+  let branding = &settings["branding"];
+  assert_eq!(branding["application_name"], "Acme ID");
+  assert_eq!(branding["primary_color"], "#1e66f5");
+  assert_eq!(branding["legal_links"][0]["label"], "Privacy");
+  assert!(branding["favicon"].is_null());
+
+  // the logo keeps its aspect ratio
+  let logo = branding["logo"].as_str().unwrap();
+  let path = logo.strip_prefix("/api").unwrap();
+  let resp = server.get(path).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  assert_eq!(resp.headers()["content-type"], "image/webp");
+  let etag = resp.headers()["etag"].to_str().unwrap().to_string();
+  let image = image::load_from_memory(&resp.bytes().await.unwrap()).unwrap();
+  assert_eq!((image.width(), image.height()), (512, 128));
+
+  let resp = server.get_with_header(path, "if-none-match", &etag).await;
+  assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+}
+
+#[tokio::test]
+async fn invalid_branding_is_rejected() {
+  let (server, _) = TestServer::start_with_admin().await;
+
+  for branding in [
+    json!({ "primary_color": "blue" }),
+    json!({ "application_name": " " }),
+    json!({ "support_url": "javascript:alert(1)" }),
+    json!({ "legal_links": [{ "label": "", "url": "https://example.com" }] }),
+  ] {
+    let resp = server.post("/settings/branding", branding).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+  }
+
+  let resp = server
+    .post(
+      "/settings/branding/favicon",
+      json!({ "image": BASE64_STANDARD.encode(b"not an image") }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn organizations_inherit_until_they_have_own_branding() {
+  let (server, _) = TestServer::start_with_admin().await;
+  let resp = server
+    .post(
+      "/settings/branding",
+      json!({ "application_name": "Instance" }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  let resp = server
+    .post("/organization", json!({ "name": unique("org") }))
+    .await;
+  let created: Value = resp.json().await.unwrap();
+  let acme = Uuid::parse_str(created["uuid"].as_str().unwrap()).unwrap();
+  let resp = server
+    .post("/organization/switch", json!({ "uuid": acme }))
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  let resp = server.get("/settings/branding").await;
+  let own: Value = resp.json().await.unwrap();
+  assert_eq!(own["inherited"], true);
+  assert_eq!(own["branding"]["application_name"], "Instance");
+
+  let resp = server
+    .post("/settings/branding", json!({ "application_name": "Acme" }))
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let resp = server
+    .post(
+      "/settings/branding/favicon",
+      json!({ "image": BASE64_STANDARD.encode(png(300, 200)) }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  let settings = general(&server, "").await;
+  assert_eq!(settings["branding"]["application_name"], "Acme");
+  let favicon = settings["branding"]["favicon"].as_str().unwrap();
+  let resp = server
+    .get(&format!(
+      "{}&size=32",
+      favicon.strip_prefix("/api").unwrap()
+    ))
+    .await;
+  assert_eq!(resp.headers()["content-type"], "image/png");
+  let image = image::load_from_memory(&resp.bytes().await.unwrap()).unwrap();
+  assert_eq!((image.width(), image.height()), (32, 32));
+
+  server.clear_cookies();
+  let settings = general(&server, "").await;
+  assert_eq!(settings["branding"]["application_name"], "Instance");
+  let settings = general(&server, &format!("?organization={acme}")).await;
+  assert_eq!(settings["branding"]["application_name"], "Acme");
+}
@@ -22,8 +22,11 @@
 //! ```
 #![allow(dead_code)]
 
+pub mod oidc;
+
 use std::{
   collections::HashMap,
+  io::Read,
   sync::{
     Mutex,
     atomic::{AtomicU32, Ordering},
@@ -33,7 +36,8 @@ use std::{
 
 use backend::App;
 use base64::{Engine, prelude::BASE64_STANDARD};
-use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use flate2::read::GzDecoder;
+use reqwest::{Client, RequestBuilder, Response, StatusCode, redirect::Policy};
 use rsa::{Pkcs1v15Encrypt, RsaPublicKey, pkcs1::DecodeRsaPublicKey, rand_core::OsRng};
 use serde_json::Value;
 use tokio::{spawn, time::sleep};
@@ -41,6 +45,8 @@ use uuid::Uuid;
 
 /// The auth cookie centaurus sets on a successful login/setup.
 pub const JWT_COOKIE_NAME: &str = "centaurus_jwt";
+/// Password of the users made by [`TestServer::create_user`].
+pub const USER_PASSWORD: &str = "memberpass1";
 
 /// Configure the process environment for a throwaway in-memory server.
 ///
@@ -76,6 +82,8 @@ impl TestServer {
     let client = Client::builder()
       .timeout(Duration::from_secs(30))
       .connect_timeout(Duration::from_secs(30))
+      // redirects (e.g. of the OIDC callback) are inspected by the tests
+      .redirect(Policy::none())
       .build()
       .expect("build reqwest client");
 
@@ -172,6 +180,12 @@ impl TestServer {
     self.send(self.client.get(self.url(path))).await
   }
 
+  pub async fn get_with_header(&self, path: &str, name: &str, value: &str) -> Response {
+    self
+      .send(self.client.get(self.url(path)).header(name, value))
+      .await
+  }
+
   pub async fn get_root(&self, path: &str) -> Response {
     self.send(self.client.get(self.root_url(path))).await
   }
@@ -197,6 +211,18 @@ impl TestServer {
     self.send(self.client.put(self.url(path)).body(body)).await
   }
 
+  /// POST an url-encoded form, like the confirmation pages behind mail links.
+  pub async fn post_form(&self, path: &str, form: &[(&str, &str)]) -> Response {
+    self.send(self.client.post(self.url(path)).form(form)).await
+  }
+
+  /// POST a raw text body, e.g. a CSV file.
+  pub async fn post_text(&self, path: &str, body: &str) -> Response {
+    self
+      .send(self.client.post(self.url(path)).body(body.to_string()))
+      .await
+  }
+
   /// Fetch the password-transfer RSA public key and encrypt `plaintext` the
   /// same way the frontend does (RSA-PKCS1v15 + base64).
   pub async fn encrypt_password(&self, plaintext: &str) -> String {
@@ -239,6 +265,26 @@ impl TestServer {
     Uuid::parse_str(body["user"].as_str().expect("user id")).expect("parse user id")
   }
 
+  /// Create a user through the management endpoint as the logged in admin,
+  /// the password is [`USER_PASSWORD`]. Returns the new user id.
+  pub async fn create_user(&self, name: &str, email: &str) -> Uuid {
+    let password = self.encrypt_password(USER_PASSWORD).await;
+    let resp = self
+      .post(
+        "/user/management",
+        serde_json::json!({ "name": name, "email": email, "password": password }),
+      )
+      .await;
+    assert_eq!(
+      resp.status(),
+      StatusCode::OK,
+      "user creation should succeed"
+    );
+
+    let body: Value = resp.json().await.expect("user json");
+    Uuid::parse_str(body["uuid"].as_str().expect("user id")).expect("parse user id")
+  }
+
   /// Log in via the password endpoint (cookies captured automatically).
   pub async fn login(&self, email: &str, password: &str) -> Response {
     let encrypted = self.encrypt_password(password).await;
@@ -250,6 +296,20 @@ impl TestServer {
       .await
   }
 
+  /// Log in via the password endpoint from a specific client.
+  pub async fn login_with_agent(&self, email: &str, password: &str, user_agent: &str) -> Response {
+    let encrypted = self.encrypt_password(password).await;
+    self
+      .send(
+        self
+          .client
+          .post(self.url("/auth/password"))
+          .header("User-Agent", user_agent)
+          .json(&serde_json::json!({ "email": email, "password": encrypted })),
+      )
+      .await
+  }
+
   pub fn has_cookie(&self, name: &str) -> bool {
     self.cookies.lock().unwrap().contains_key(name)
   }
@@ -281,3 +341,30 @@ static COUNTER: AtomicU32 = AtomicU32::new(0);
 pub fn unique(prefix: &str) -> String {
   format!("{prefix}-{}", COUNTER.fetch_add(1, Ordering::Relaxed))
 }
+
+/// A fresh address for a user created within a test.
+pub fn unique_email(prefix: &str) -> String {
+  format!("{}@example.com", unique(prefix))
+}
+
+/// Files of a `tar.gz` archive, e.g. an account export, by path.
+pub fn unpack_tar_gz(archive: &[u8]) -> HashMap<String, Vec<u8>> {
+  let mut tar = Vec::new();
+  GzDecoder::new(archive).read_to_end(&mut tar).unwrap();
+
+  let mut files = HashMap::new();
+  let mut offset = 0;
+  while tar[offset] != 0 {
+    let header = &tar[offset..offset + 512];
+    let name = header[..100].split(|b| *b == 0).next().unwrap();
+    let size = std::str::from_utf8(&header[124..135]).unwrap();
+    let size = usize::from_str_radix(size, 8).unwrap();
+    offset += 512;
+    files.insert(
+      String::from_utf8(name.to_vec()).unwrap(),
+      tar[offset..offset + size].to_vec(),
+    );
+    offset += size.div_ceil(512) * 512;
+  }
+  files
+}
@@ -0,0 +1,239 @@
+//! A stand-in OIDC provider for the login tests.
+//!
+//! Serves discovery, the JWK set, an authorize endpoint that immediately
+//! redirects back with a code, the token endpoint (RS256 signed id tokens) and
+//! userinfo, all on an OS-assigned local port. The claims handed out for the
+//! next login can be changed at any time through [`OidcProvider::set_claims`],
+//! a profile picture is served once set with [`OidcProvider::set_picture`].
+
+use std::{
+  collections::HashMap,
+  sync::{Arc, Mutex},
+  time::Duration,
+};
+
+use axum::{
+  Json, Router,
+  extract::{Form, Query, State},
+  http::{StatusCode, header},
+  response::{IntoResponse, Response},
+  routing::{get, post},
+};
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use rsa::{
+  RsaPrivateKey,
+  pkcs1::{EncodeRsaPrivateKey, LineEnding},
+  rand_core::OsRng,
+  traits::PublicKeyParts,
+};
+use serde_json::{Value, json};
+use tokio::{net::TcpListener, spawn, time::sleep};
+
+use super::TestServer;
+
+pub const CLIENT_ID: &str = "test-client";
+pub const CLIENT_SECRET: &str = "test-secret";
+const KEY_ID: &str = "test-key";
+
+struct ProviderState {
+  issuer: String,
+  key: EncodingKey,
+  jwks: Value,
+  /// Where the authorize endpoint sends the browser back to.
+  callback: Mutex<String>,
+  claims: Mutex<Value>,
+  /// Nonce of every issued code.
+  codes: Mutex<HashMap<String, String>>,
+  /// Content type and body served as the picture.
+  picture: Mutex<Option<(String, Vec<u8>)>>,
+}
+
+#[derive(Clone)]
+pub struct OidcProvider {
+  state: Arc<ProviderState>,
+}
+
+impl OidcProvider {
+  /// Starts the provider handing out the given userinfo claims, which must
+  /// at least contain `sub`, `email` and `name`.
+  pub async fn start(claims: Value) -> OidcProvider {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let issuer = format!("http://127.0.0.1:{}", listener.local_addr().unwrap().port());
+
+    let private_key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+    let pem = private_key.to_pkcs1_pem(LineEnding::LF).unwrap();
+    let jwks = json!({
+      "keys": [{
+        "kty": "RSA",
+        "use": "sig",
+        "alg": "RS256",
+        "kid": KEY_ID,
+        "n": BASE64_URL_SAFE_NO_PAD.encode(private_key.n().to_bytes_be()),
+        "e": BASE64_URL_SAFE_NO_PAD.encode(private_key.e().to_bytes_be()),
+      }]
+    });
+
+    let state = Arc::new(ProviderState {
+      issuer,
+      key: EncodingKey::from_rsa_pem(pem.as_bytes()).unwrap(),
+      jwks,
+      callback: Mutex::new(String::new()),
+      claims: Mutex::new(claims),
+      codes: Mutex::new(HashMap::new()),
+      picture: Mutex::new(None),
+    });
+
+    let app = Router::new()
+      .route("/.well-known/openid-configuration", get(discovery))
+      .route("/jwks", get(jwks_handler))
+      .route("/authorize", get(authorize))
+      .route("/token", post(token))
+      .route("/userinfo", get(userinfo))
+      .route("/picture", get(picture))
+      .with_state(state.clone());
+    spawn(async move { axum::serve(listener, app).await });
+
+    OidcProvider { state }
+  }
+
+  /// Issuer url the backend has to be configured with.
+  pub fn issuer(&self) -> String {
+    format!("{}/", self.state.issuer)
+  }
+
+  /// Points the backend at this provider, must run before the server starts.
+  pub fn configure_env(&self) {
+    unsafe {
+      std::env::set_var("OIDC_ENABLED", "true");
+      std::env::set_var("OIDC_ISSUER", self.issuer());
+      std::env::set_var("OIDC_CLIENT_ID", CLIENT_ID);
+      std::env::set_var("OIDC_CLIENT_SECRET", CLIENT_SECRET);
+      std::env::set_var("SSO_CREATE_USER", "true");
+    }
+  }
+
+  /// Waits until the backend has picked up the provider.
+  pub async fn wait_until_enabled(&self, server: &TestServer) {
+    for _ in 0..100 {
+      let config: Value = server.get("/auth/config").await.json().await.unwrap();
+      if config["sso_type"] == "Oidc" {
+        return;
+      }
+      sleep(Duration::from_millis(50)).await;
+    }
+    panic!("OIDC did not become enabled in time");
+  }
+
+  /// Runs a full login with the current claims and returns the response of
+  /// the callback, a redirect back to the site.
+  pub async fn login(&self, server: &TestServer) -> reqwest::Response {
+    *self.state.callback.lock().unwrap() = server.url("/auth/oidc/callback");
+
+    let resp = server.get("/auth/oidc/url").await;
+    assert_eq!(resp.status(), reqwest::StatusCode::OK, "oidc url");
+    let body: Value = resp.json().await.unwrap();
+
+    let client = reqwest::Client::builder()
+      .redirect(reqwest::redirect::Policy::none())
+      .build()
+      .unwrap();
+    let resp = client
+      .get(body["url"].as_str().unwrap())
+      .send()
+      .await
+      .unwrap();
+    let location = resp.headers()["location"].to_str().unwrap().to_string();
+    let path = location.strip_prefix(&server.url("")).unwrap();
+
+    server.get(path).await
+  }
+
+  pub fn set_claims(&self, claims: Value) {
+    *self.state.claims.lock().unwrap() = claims;
+  }
+
+  /// Url of the picture to hand out in the `picture` claim.
+  pub fn picture_url(&self) -> String {
+    format!("{}/picture", self.state.issuer)
+  }
+
+  pub fn set_picture(&self, content_type: &str, data: Vec<u8>) {
+    *self.state.picture.lock().unwrap() = Some((content_type.to_string(), data));
+  }
+}
+
+async fn discovery(State(state): State<Arc<ProviderState>>) -> Json<Value> {
+  let issuer = &state.issuer;
+  Json(json!({
+    "issuer": issuer,
+    "authorization_endpoint": format!("{issuer}/authorize"),
+    "token_endpoint": format!("{issuer}/token"),
+    "userinfo_endpoint": format!("{issuer}/userinfo"),
+    "jwks_uri": format!("{issuer}/jwks"),
+  }))
+}
+
+async fn jwks_handler(State(state): State<Arc<ProviderState>>) -> Json<Value> {
+  Json(state.jwks.clone())
+}
+
+async fn authorize(
+  State(state): State<Arc<ProviderState>>,
+  Query(query): Query<HashMap<String, String>>,
+) -> Response {
+  let code = uuid::Uuid::new_v4().to_string();
+  state
+    .codes
+    .lock()
+    .unwrap()
+    .insert(code.clone(), query["nonce"].clone());
+
+  let location = format!(
+    "{}?code={}&state={}",
+    state.callback.lock().unwrap(),
+    code,
+    query["state"]
+  );
+  (StatusCode::FOUND, [(header::LOCATION, location)]).into_response()
+}
+
+async fn token(
+  State(state): State<Arc<ProviderState>>,
+  Form(form): Form<HashMap<String, String>>,
+) -> Response {
+  let Some(nonce) = state.codes.lock().unwrap().remove(&form["code"]) else {
+    return (
+      StatusCode::BAD_REQUEST,
+      Json(json!({ "error": "invalid_grant" })),
+    )
+      .into_response();
+  };
+
+  let claims = state.claims.lock().unwrap().clone();
+  let now = chrono::Utc::now().timestamp();
+  let id_claims = json!({
+    "iss": state.issuer,
+    "aud": CLIENT_ID,
+    "sub": claims["sub"],
+    "iat": now,
+    "exp": now + 300,
+    "nonce": nonce,
+  });
+  let mut header = Header::new(Algorithm::RS256);
+  header.kid = Some(KEY_ID.to_string());
+  let id_token = jsonwebtoken::encode(&header, &id_claims, &state.key).unwrap();
+
+  Json(json!({ "id_token": id_token, "token_type": "Bearer" })).into_response()
+}
+
+async fn userinfo(State(state): State<Arc<ProviderState>>) -> Json<Value> {
+  Json(state.claims.lock().unwrap().clone())
+}
+
+async fn picture(State(state): State<Arc<ProviderState>>) -> Response {
+  match state.picture.lock().unwrap().clone() {
+    Some((content_type, data)) => ([(header::CONTENT_TYPE, content_type)], data).into_response(),
+    None => StatusCode::NOT_FOUND.into_response(),
+  }
+}
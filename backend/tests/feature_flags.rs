@@ -0,0 +1,152 @@
+mod common;
+
+use common::{TestServer, unique};
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn flag_crud_and_validation() {
+  let (server, _) = TestServer::start_with_admin().await;
+  let key = unique("new_editor").to_lowercase();
+
+  let resp = server
+    .post(
+      "/flag",
+      json!({ "key": key, "kind": "boolean", "enabled": true, "rollout": 100 }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let created: Value = resp.json().await.unwrap();
+  let uuid = Uuid::parse_str(created["uuid"].as_str().unwrap()).unwrap();
+
+  // Keys are unique.
+  let resp = server
+    .post(
+      "/flag",
+      json!({ "key": key, "kind": "boolean", "enabled": false }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+  // Multivariate flags need variants.
+  let resp = server
+    .post(
+      "/flag",
+      json!({ "key": unique("theme").to_lowercase(), "kind": "multivariate", "enabled": true }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+  let resp = server.get("/flag").await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let flags: Value = resp.json().await.unwrap();
+  let listed = flags
+    .as_array()
+    .unwrap()
+    .iter()
+    .find(|f| f["uuid"] == json!(uuid))
+    .unwrap();
+  assert_eq!(listed["key"], json!(key));
+  assert_eq!(listed["rollout"], 100);
+
+  let resp = server.get("/flag/evaluated").await;
+  let evaluated: Value = resp.json().await.unwrap();
+  assert_eq!(evaluated[&key], true);
+  let check: Value = server
+    .get(&format!("/flag/evaluated/{key}"))
+    .await
+    .json()
+    .await
+    .unwrap();
+  assert_eq!(check, json!({ "enabled": true, "variant": null }));
+
+  // Disabling turns it off for everyone.
+  let resp = server
+    .put(
+      "/flag",
+      json!({ "uuid": uuid, "key": key, "kind": "boolean", "enabled": false, "rollout": 100 }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let evaluated: Value = server.get("/flag/evaluated").await.json().await.unwrap();
+  assert_eq!(evaluated[&key], false);
+
+  let resp = server.delete("/flag", json!({ "uuid": uuid })).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let evaluated: Value = server.get("/flag/evaluated").await.json().await.unwrap();
+  assert!(evaluated.get(&key).is_none());
+  let check: Value = server
+    .get(&format!("/flag/evaluated/{key}"))
+    .await
+    .json()
+    .await
+    .unwrap();
+  assert_eq!(check["enabled"], false);
+
+  let resp = server.delete("/flag", json!({ "uuid": uuid })).await;
+  assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn flags_target_group_members() {
+  let (server, admin_id) = TestServer::start_with_admin().await;
+
+  let resp = server
+    .post("/group", json!({ "name": unique("beta") }))
+    .await;
+  let created: Value = resp.json().await.unwrap();
+  let group = Uuid::parse_str(created["uuid"].as_str().unwrap()).unwrap();
+
+  let key = unique("theme").to_lowercase();
+  let resp = server
+    .post(
+      "/flag",
+      json!({
+        "key": key,
+        "kind": "multivariate",
+        "enabled": true,
+        "variants": [{ "name": "light", "weight": 1 }, { "name": "dark", "weight": 1 }],
+        "rollout": 0,
+        "groups": [{ "group": group, "variant": "dark" }],
+      }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  // Outside the rollout and the group the first variant is served.
+  let evaluated: Value = server.get("/flag/evaluated").await.json().await.unwrap();
+  assert_eq!(evaluated[&key], "light");
+
+  let resp = server
+    .put(
+      "/group",
+      json!({ "uuid": group, "name": unique("beta"), "permissions": [], "users": [admin_id] }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  let evaluated: Value = server.get("/flag/evaluated").await.json().await.unwrap();
+  assert_eq!(evaluated[&key], "dark");
+  let check: Value = server
+    .get(&format!("/flag/evaluated/{key}"))
+    .await
+    .json()
+    .await
+    .unwrap();
+  assert_eq!(check, json!({ "enabled": false, "variant": "dark" }));
+
+  // Unknown groups cannot be targeted.
+  let resp = server
+    .post(
+      "/flag",
+      json!({
+        "key": unique("other").to_lowercase(),
+        "kind": "boolean",
+        "enabled": true,
+        "groups": [{ "group": Uuid::new_v4() }],
+      }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
@@ -13,7 +13,7 @@ async fn group_crud_flow() {
   let resp = server.get("/group").await;
   assert_eq!(resp.status(), StatusCode::OK);
   let initial: Value = resp.json().await.unwrap();
-  let initial_len = initial["groups"].as_array().unwrap().len();
+  let initial_len = initial["items"].as_array().unwrap().len();
   assert!(initial_len >= 1);
 
   // Create a group.
@@ -28,7 +28,7 @@ async fn group_crud_flow() {
   // It shows up in the list.
   let resp = server.get("/group").await;
   let listed: Value = resp.json().await.unwrap();
-  assert_eq!(listed["groups"].as_array().unwrap().len(), initial_len + 1);
+  assert_eq!(listed["items"].as_array().unwrap().len(), initial_len + 1);
 
   // Fetch its details.
   let resp = server.get(&format!("/group/{group_id}")).await;
@@ -56,7 +56,7 @@ async fn group_crud_flow() {
 
   let resp = server.get("/group").await;
   let after: Value = resp.json().await.unwrap();
-  assert_eq!(after["groups"].as_array().unwrap().len(), initial_len);
+  assert_eq!(after["items"].as_array().unwrap().len(), initial_len);
 }
 
 #[tokio::test]
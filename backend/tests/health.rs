@@ -18,3 +18,20 @@ async fn protected_route_without_auth_is_rejected() {
   let resp = server.get("/user/info/").await;
   assert!(!resp.status().is_success());
 }
+
+#[tokio::test]
+async fn metrics_are_hidden_while_disabled() {
+  let server = TestServer::start().await;
+
+  let resp = server.get("/metrics").await;
+  assert_eq!(resp.status(), 404);
+}
+
+#[tokio::test]
+async fn metrics_are_exported_when_enabled() {
+  unsafe { std::env::set_var("METRICS_ENABLED", "true") };
+  let server = TestServer::start().await;
+
+  let resp = server.get("/metrics").await;
+  assert!(resp.status().is_success());
+}
@@ -0,0 +1,167 @@
+mod common;
+
+use common::{TestServer, USER_PASSWORD, unique, unique_email};
+use reqwest::StatusCode;
+use serde_json::Value;
+
+async fn user_info(server: &TestServer) -> Value {
+  let resp = server.get("/user/info").await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  resp.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn admin_can_view_as_user() {
+  let (server, admin_id) = TestServer::start_with_admin().await;
+  let user_id = server.create_user("Member", &unique_email("member")).await;
+
+  let resp = server
+    .post("/user/impersonate", serde_json::json!({ "uuid": user_id }))
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let started: Value = resp.json().await.unwrap();
+  assert!(started["expires_at"].is_string());
+
+  let info = user_info(&server).await;
+  assert_eq!(info["uuid"], user_id.to_string());
+  assert_eq!(info["impersonation"]["admin"], admin_id.to_string());
+  assert_eq!(info["impersonation"]["admin_name"], "admin");
+
+  // The user's own view applies, and sensitive actions are blocked.
+  assert_eq!(server.get("/audit").await.status(), StatusCode::FORBIDDEN);
+  let resp = server
+    .post(
+      "/user/account/password",
+      serde_json::json!({ "old_password": "x", "new_password": "y" }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+  let resp = server
+    .post(
+      "/user/account/update",
+      serde_json::json!({ "username": "Renamed" }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  let resp = server.delete("/user/impersonate", Value::Null).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let ended: Value = resp.json().await.unwrap();
+  assert_eq!(ended["user"], admin_id.to_string());
+
+  let info = user_info(&server).await;
+  assert_eq!(info["uuid"], admin_id.to_string());
+  assert!(info["impersonation"].is_null());
+
+  let resp = server.get("/audit?action=user").await;
+  let events: Value = resp.json().await.unwrap();
+  let actions: Vec<&str> = events["events"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|event| event["action"].as_str().unwrap())
+    .collect();
+  assert_eq!(
+    actions[..4],
+    [
+      "user.impersonate_end",
+      "user.account_update",
+      "user.password_change",
+      "user.impersonate_start"
+    ]
+  );
+  // The blocked attempt is still on record.
+  assert_eq!(events["events"][2]["status"], 403);
+  // Changes made while impersonating name both the user and the admin.
+  let update = &events["events"][1];
+  assert_eq!(update["actor"], user_id.to_string());
+  assert_eq!(update["impersonator"], admin_id.to_string());
+  let start = &events["events"][3];
+  assert_eq!(start["actor"], admin_id.to_string());
+  assert_eq!(start["target_id"], user_id.to_string());
+}
+
+#[tokio::test]
+async fn impersonation_requires_permission() {
+  let (server, admin_id) = TestServer::start_with_admin().await;
+  let email = unique_email("member");
+  let user_id = server.create_user("Member", &email).await;
+
+  let resp = server
+    .post("/user/impersonate", serde_json::json!({ "uuid": admin_id }))
+    .await;
+  assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+  // Allowed to impersonate, but the admin holds more permissions.
+  let resp = server
+    .post("/group", serde_json::json!({ "name": unique("group") }))
+    .await;
+  let group: Value = resp.json().await.unwrap();
+  let resp = server
+    .put(
+      "/group",
+      serde_json::json!({
+        "uuid": group["uuid"],
+        "name": unique("group"),
+        "permissions": ["user:impersonate"],
+        "users": [user_id],
+      }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  server.clear_cookies();
+  let resp = server.login(&email, USER_PASSWORD).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let resp = server
+    .post("/user/impersonate", serde_json::json!({ "uuid": admin_id }))
+    .await;
+  assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+  // Ending without impersonating is rejected.
+  let resp = server.delete("/user/impersonate", Value::Null).await;
+  assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn organization_admins_cannot_impersonate_super_admins() {
+  let (server, admin_id) = TestServer::start_with_admin().await;
+  let email = unique_email("member");
+  let user_id = server.create_user("Member", &email).await;
+
+  // Every permission there is, but not a super admin.
+  let permissions: Value = server.get("/group/permissions").await.json().await.unwrap();
+  let permissions: Vec<&Value> = permissions
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|permission| &permission["name"])
+    .collect();
+  let resp = server
+    .post("/group", serde_json::json!({ "name": unique("group") }))
+    .await;
+  let group: Value = resp.json().await.unwrap();
+  let resp = server
+    .put(
+      "/group",
+      serde_json::json!({
+        "uuid": group["uuid"],
+        "name": unique("group"),
+        "permissions": permissions,
+        "users": [user_id],
+      }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  server.clear_cookies();
+  let resp = server.login(&email, USER_PASSWORD).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let info: Value = server.get("/user/info").await.json().await.unwrap();
+  assert_eq!(info["super_admin"], false);
+
+  let resp = server
+    .post("/user/impersonate", serde_json::json!({ "uuid": admin_id }))
+    .await;
+  assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use common::{TestServer, unique, unique_email};
+use reqwest::StatusCode;
+use serde_json::Value;
+
+async fn list(server: &TestServer, path: &str) -> Value {
+  let resp = server.get(path).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  resp.json().await.unwrap()
+}
+
+fn emails(page: &Value) -> Vec<String> {
+  page["items"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|u| u["email"].as_str().unwrap().to_string())
+    .collect()
+}
+
+#[tokio::test]
+async fn users_are_paginated_with_cursors() {
+  let (server, _) = TestServer::start_with_admin().await;
+  let prefix = unique("page");
+  for i in 0..5 {
+    server
+      .create_user("Member", &format!("{prefix}-{i}@example.com"))
+      .await;
+  }
+
+  let mut seen = Vec::new();
+  let mut path = format!("/user/management?sort=email&search={prefix}&limit=2");
+  loop {
+    let page = list(&server, &path).await;
+    assert_eq!(page["total"], 5);
+    seen.extend(emails(&page));
+    let Some(cursor) = page["next_cursor"].as_str() else {
+      break;
+    };
+    path = format!("/user/management?sort=email&search={prefix}&limit=2&cursor={cursor}");
+  }
+  let expected: Vec<String> = (0..5)
+    .map(|i| format!("{prefix}-{i}@example.com"))
+    .collect();
+  assert_eq!(seen, expected);
+
+  let page = list(
+    &server,
+    &format!("/user/management?sort=created&order=desc&search={prefix}&limit=1"),
+  )
+  .await;
+  assert_eq!(emails(&page), vec![format!("{prefix}-4@example.com")]);
+  assert!(page["items"][0]["created_at"].is_string());
+
+  // A cursor only works with the sort order it was created for.
+  let cursor = page["next_cursor"].as_str().unwrap();
+  let resp = server
+    .get(&format!("/user/management?sort=name&cursor={cursor}"))
+    .await;
+  assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+  let resp = server.get("/user/management?cursor=garbage").await;
+  assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn users_can_be_searched_and_filtered() {
+  let (server, admin) = TestServer::start_with_admin().await;
+  let name = unique("Searchable");
+  let member = server.create_user(&name, &unique_email("x")).await;
+
+  // Search ignores case and matches the name.
+  let page = list(
+    &server,
+    &format!("/user/management?search={}", name.to_uppercase()),
+  )
+  .await;
+  assert_eq!(page["total"], 1);
+  assert_eq!(page["items"][0]["uuid"], serde_json::json!(member));
+
+  let page = list(&server, "/user/management?oidc=true").await;
+  assert_eq!(page["total"], 0);
+  let page = list(&server, "/user/management?oidc=false").await;
+  assert_eq!(page["total"], 2);
+
+  let groups = list(&server, "/group").await;
+  let admin_group = groups["admin_group"].as_str().unwrap().to_string();
+  let page = list(&server, &format!("/user/management?group={admin_group}")).await;
+  assert_eq!(page["total"], 1);
+  assert_eq!(page["items"][0]["uuid"], serde_json::json!(admin));
+
+  let page = list(&server, &format!("/group/users?group={admin_group}")).await;
+  assert_eq!(page["items"][0]["id"], serde_json::json!(admin));
+}
+
+#[tokio::test]
+async fn groups_are_paginated_and_searchable() {
+  let (server, _) = TestServer::start_with_admin().await;
+  let prefix = unique("team");
+  for i in 0..3 {
+    let resp = server
+      .post(
+        "/group",
+        serde_json::json!({ "name": format!("{prefix}-{i}") }),
+      )
+      .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+  }
+
+  let page = list(
+    &server,
+    &format!("/group?search={prefix}&limit=2&order=desc"),
+  )
+  .await;
+  assert_eq!(page["total"], 3);
+  assert!(page["admin_group"].is_string());
+  let names: Vec<&str> = page["items"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|g| g["name"].as_str().unwrap())
+    .collect();
+  assert_eq!(names, vec![format!("{prefix}-2"), format!("{prefix}-1")]);
+
+  let cursor = page["next_cursor"].as_str().unwrap();
+  let page = list(
+    &server,
+    &format!("/group?search={prefix}&limit=2&order=desc&cursor={cursor}"),
+  )
+  .await;
+  assert_eq!(page["items"][0]["name"], format!("{prefix}-0"));
+  assert!(page["next_cursor"].is_null());
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use common::TestServer;
+use reqwest::StatusCode;
+use serde_json::Value;
+
+async fn logins(server: &TestServer) -> Value {
+  let resp = server.get("/user/account/logins").await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  resp.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn logins_are_recorded() {
+  let (server, _) = TestServer::start_with_admin().await;
+
+  let resp = server.login("admin@example.com", "wrongpass").await;
+  assert!(!resp.status().is_success());
+  let resp = server.login("admin@example.com", "hunter2pass").await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  let history = logins(&server).await;
+  assert_eq!(history["total"], 2);
+  // Newest first.
+  let success = &history["logins"][0];
+  assert_eq!(success["method"], "password");
+  assert_eq!(success["success"], true);
+  assert!(success["ip"].is_string());
+  let failed = &history["logins"][1];
+  assert_eq!(failed["success"], false);
+  assert_eq!(failed["new_device"], false);
+}
+
+#[tokio::test]
+async fn logins_from_new_devices_are_flagged() {
+  let (server, _) = TestServer::start_with_admin().await;
+
+  for agent in ["laptop", "laptop", "phone", "phone"] {
+    let resp = server
+      .login_with_agent("admin@example.com", "hunter2pass", agent)
+      .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+  }
+
+  let history = logins(&server).await;
+  let flags: Vec<(&str, bool)> = history["logins"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .rev()
+    .map(|login| {
+      (
+        login["user_agent"].as_str().unwrap(),
+        login["new_device"].as_bool().unwrap(),
+      )
+    })
+    .collect();
+  // The first login of an account is never reported.
+  assert_eq!(
+    flags,
+    vec![
+      ("laptop", false),
+      ("laptop", false),
+      ("phone", true),
+      ("phone", false)
+    ]
+  );
+}
+
+#[tokio::test]
+async fn reporting_a_login_locks_the_account() {
+  let (server, _) = TestServer::start_with_admin().await;
+
+  server
+    .login_with_agent("admin@example.com", "hunter2pass", "laptop")
+    .await;
+  server
+    .login_with_agent("admin@example.com", "hunter2pass", "unknown")
+    .await;
+  let history = logins(&server).await;
+  let suspicious = history["logins"][0]["uuid"].clone();
+
+  let resp = server
+    .post(
+      "/user/account/logins/report",
+      serde_json::json!({ "uuid": suspicious }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  // Every existing session is revoked...
+  let resp = server.get("/user/account/logins").await;
+  assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+  // ...and the old password no longer works.
+  let resp = server.login("admin@example.com", "hunter2pass").await;
+  assert!(!resp.status().is_success());
+
+  // The unauthenticated mail link rejects unknown tokens, on the page as well
+  // as when the form on it is submitted.
+  let resp = server
+    .get("/user/account/logins/report?token=not-a-token")
+    .await;
+  assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+  let resp = server
+    .post_form(
+      "/user/account/logins/report/confirm",
+      &[("token", "not-a-token")],
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use std::io::Cursor;
+
+use common::{TestServer, oidc::OidcProvider};
+use image::{ImageBuffer, ImageFormat, Rgb};
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+fn png(color: [u8; 3]) -> Vec<u8> {
+  let image = ImageBuffer::from_pixel(64, 64, Rgb(color));
+  let mut buf = Cursor::new(Vec::new());
+  image.write_to(&mut buf, ImageFormat::Png).unwrap();
+  buf.into_inner()
+}
+
+fn claims(provider: &OidcProvider, name: &str, email: &str) -> Value {
+  json!({
+    "sub": "oidc-user",
+    "email": email,
+    "name": name,
+    "picture": provider.picture_url(),
+  })
+}
+
+async fn start(provider: &OidcProvider) -> TestServer {
+  provider.configure_env();
+  unsafe {
+    std::env::set_var("OIDC_IMAGE_SYNC", "true");
+  }
+  let (server, _) = TestServer::start_with_admin().await;
+  provider.wait_until_enabled(&server).await;
+  server
+}
+
+async fn login(provider: &OidcProvider, server: &TestServer) -> Value {
+  server.clear_cookies();
+  let resp = provider.login(server).await;
+  assert_eq!(resp.headers()["location"], "http://localhost/");
+  server.get("/user/info").await.json().await.unwrap()
+}
+
+async fn avatar_etag(server: &TestServer, user: &Value) -> (String, String) {
+  let resp = server
+    .get(&format!(
+      "/user/info/avatar/{}",
+      user["uuid"].as_str().unwrap()
+    ))
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  (
+    resp.headers()["content-type"].to_str().unwrap().to_string(),
+    resp.headers()["etag"].to_str().unwrap().to_string(),
+  )
+}
+
+#[tokio::test]
+async fn pictures_of_the_provider_become_the_avatar() {
+  let provider = OidcProvider::start(json!({})).await;
+  provider.set_claims(claims(&provider, "Oidc User", "oidc@example.com"));
+  provider.set_picture("image/png", png([200, 0, 0]));
+  let server = start(&provider).await;
+
+  let user = login(&provider, &server).await;
+  let (content_type, first) = avatar_etag(&server, &user).await;
+  assert_eq!(content_type, "image/webp");
+
+  provider.set_picture("image/png", png([0, 200, 0]));
+  login(&provider, &server).await;
+  let (_, second) = avatar_etag(&server, &user).await;
+  assert_ne!(first, second);
+
+  // anything that is not an image of acceptable size keeps the current avatar
+  provider.set_picture("text/html", png([0, 0, 200]));
+  login(&provider, &server).await;
+  provider.set_picture("image/png", b"not an image".to_vec());
+  login(&provider, &server).await;
+  provider.set_picture("image/png", vec![0; 6 * 1024 * 1024]);
+  login(&provider, &server).await;
+  assert_eq!(avatar_etag(&server, &user).await.1, second);
+}
+
+#[tokio::test]
+async fn profile_fields_follow_the_sync_policy() {
+  let provider = OidcProvider::start(json!({})).await;
+  provider.set_claims(claims(&provider, "Oidc User", "oidc@example.com"));
+  let server = start(&provider).await;
+
+  let settings: Value = server
+    .get("/settings/profile_sync")
+    .await
+    .json()
+    .await
+    .unwrap();
+  assert_eq!(
+    settings["settings"],
+    json!({ "name": "always", "email": "always" })
+  );
+
+  let user = login(&provider, &server).await;
+  assert_eq!(user["name"], "Oidc User");
+
+  provider.set_claims(claims(&provider, "Renamed", "renamed@example.com"));
+  let user = login(&provider, &server).await;
+  assert_eq!(user["name"], "Renamed");
+  assert_eq!(user["email"], "renamed@example.com");
+
+  server.clear_cookies();
+  server.login("admin@example.com", "hunter2pass").await;
+  let resp = server
+    .post(
+      "/settings/profile_sync",
+      json!({ "name": "on_create", "email": "always" }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  provider.set_claims(claims(&provider, "Provider Name", "other@example.com"));
+  let user = login(&provider, &server).await;
+  assert_eq!(user["name"], "Renamed");
+  assert_eq!(user["email"], "other@example.com");
+}
@@ -0,0 +1,304 @@
+mod common;
+
+use common::{TestServer, USER_PASSWORD, unique, unique_email};
+use reqwest::StatusCode;
+use serde_json::Value;
+use uuid::Uuid;
+
+async fn create_organization(server: &TestServer) -> Uuid {
+  let resp = server
+    .post(
+      "/organization",
+      serde_json::json!({ "name": unique("org") }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let created: Value = resp.json().await.unwrap();
+  Uuid::parse_str(created["uuid"].as_str().unwrap()).unwrap()
+}
+
+async fn switch(server: &TestServer, organization: Uuid) {
+  let resp = server
+    .post(
+      "/organization/switch",
+      serde_json::json!({ "uuid": organization }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+}
+
+/// Creates a group in the current organization holding the permissions and
+/// the users.
+async fn create_group(server: &TestServer, permissions: &[&str], users: &[Uuid]) -> Uuid {
+  let name = unique("group");
+  let resp = server
+    .post("/group", serde_json::json!({ "name": name }))
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let created: Value = resp.json().await.unwrap();
+  let id = Uuid::parse_str(created["uuid"].as_str().unwrap()).unwrap();
+
+  let resp = server
+    .put(
+      "/group",
+      serde_json::json!({
+        "uuid": id,
+        "name": name,
+        "permissions": permissions,
+        "users": users,
+      }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  id
+}
+
+async fn listed_users(server: &TestServer) -> Vec<Uuid> {
+  let resp = server.get("/user/management").await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let page: Value = resp.json().await.unwrap();
+  page["items"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|user| Uuid::parse_str(user["uuid"].as_str().unwrap()).unwrap())
+    .collect()
+}
+
+#[tokio::test]
+async fn setup_admin_is_a_super_admin_of_the_default_organization() {
+  let (server, admin_id) = TestServer::start_with_admin().await;
+
+  let resp = server.get("/organization").await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let organizations: Value = resp.json().await.unwrap();
+  let organizations = organizations.as_array().unwrap();
+  assert_eq!(organizations.len(), 1);
+  assert_eq!(organizations[0]["is_default"], true);
+  assert_eq!(organizations[0]["current"], true);
+
+  let info: Value = server.get("/user/info").await.json().await.unwrap();
+  assert_eq!(info["super_admin"], true);
+  assert_eq!(info["organization"], organizations[0]["uuid"]);
+
+  let resp = server.get("/organization/super_admin").await;
+  let super_admins: Value = resp.json().await.unwrap();
+  assert_eq!(super_admins, serde_json::json!([admin_id]));
+}
+
+#[tokio::test]
+async fn users_and_groups_are_scoped_to_the_current_organization() {
+  let (server, admin_id) = TestServer::start_with_admin().await;
+  let default_user = server.create_user("Member", &unique_email("member")).await;
+
+  let acme = create_organization(&server).await;
+  switch(&server, acme).await;
+  assert!(listed_users(&server).await.is_empty());
+
+  let acme_user = server.create_user("Member", &unique_email("member")).await;
+  assert_eq!(listed_users(&server).await, vec![acme_user]);
+  let group = create_group(&server, &["user:view"], &[acme_user]).await;
+
+  // users of other organizations look like missing ones
+  let resp = server
+    .get(&format!("/user/management/{default_user}"))
+    .await;
+  assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+  let resp = server.get(&format!("/user/management/{acme_user}")).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let info: Value = resp.json().await.unwrap();
+  assert_eq!(info["permissions"], serde_json::json!(["user:view"]));
+
+  // groups cannot be given to users of another organization
+  let resp = server
+    .put(
+      "/group",
+      serde_json::json!({
+        "uuid": group,
+        "name": unique("group"),
+        "permissions": [],
+        "users": [default_user],
+      }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+  // an organization cannot be deleted while it still owns groups
+  let resp = server
+    .delete("/organization", serde_json::json!({ "uuid": acme }))
+    .await;
+  assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+  let default = server
+    .get("/organization")
+    .await
+    .json::<Value>()
+    .await
+    .unwrap();
+  let default = default
+    .as_array()
+    .unwrap()
+    .iter()
+    .find(|organization| organization["is_default"] == true)
+    .unwrap()["uuid"]
+    .clone();
+  let default = Uuid::parse_str(default.as_str().unwrap()).unwrap();
+  switch(&server, default).await;
+
+  let users = listed_users(&server).await;
+  assert!(users.contains(&admin_id));
+  assert!(users.contains(&default_user));
+  assert!(!users.contains(&acme_user));
+  let resp = server.get(&format!("/group/{group}")).await;
+  assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn organization_admins_cannot_change_the_instance() {
+  let (server, admin_id) = TestServer::start_with_admin().await;
+  let acme = create_organization(&server).await;
+  switch(&server, acme).await;
+  let email = unique_email("member");
+  let org_admin = server.create_user("Member", &email).await;
+  create_group(
+    &server,
+    &[
+      "user:view",
+      "user:edit",
+      "settings:view",
+      "settings:edit",
+      "role:view",
+      "role:edit",
+      "audit:view",
+    ],
+    &[org_admin],
+  )
+  .await;
+
+  server.clear_cookies();
+  assert_eq!(
+    server.login(&email, USER_PASSWORD).await.status(),
+    StatusCode::OK
+  );
+
+  let info: Value = server.get("/user/info").await.json().await.unwrap();
+  assert_eq!(info["super_admin"], false);
+  assert_eq!(info["organization"], serde_json::json!(acme));
+  assert!(
+    info["permissions"]
+      .as_array()
+      .unwrap()
+      .contains(&serde_json::json!("user:edit"))
+  );
+
+  // other organizations are neither listed nor reachable
+  let organizations: Value = server.get("/organization").await.json().await.unwrap();
+  assert_eq!(organizations.as_array().unwrap().len(), 1);
+  let resp = server.get(&format!("/user/management/{admin_id}")).await;
+  assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+  let resp = server
+    .put(
+      "/user/management/password",
+      serde_json::json!({ "uuid": admin_id, "new_password": "not-encrypted" }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+  // users created by the organization admin join the organization
+  let created = server.create_user("Member", &unique_email("member")).await;
+  assert!(listed_users(&server).await.contains(&created));
+
+  assert_eq!(server.get("/settings/user").await.status(), StatusCode::OK);
+  let resp = server
+    .post(
+      "/settings/profile_sync",
+      serde_json::json!({ "name": "always", "email": "always" }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+  let resp = server
+    .post(
+      "/role",
+      serde_json::json!({
+        "name": unique("role"),
+        "description": "org role",
+        "permissions": ["user:view"],
+      }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+  assert_eq!(server.get("/audit").await.status(), StatusCode::FORBIDDEN);
+  let resp = server
+    .post(
+      "/organization",
+      serde_json::json!({ "name": unique("org") }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn shared_accounts_are_only_changed_by_super_admins() {
+  let (server, _) = TestServer::start_with_admin().await;
+  let shared_email = unique_email("member");
+  let shared = server.create_user("Member", &shared_email).await;
+  let acme = create_organization(&server).await;
+  switch(&server, acme).await;
+  let email = unique_email("member");
+  let org_admin = server.create_user("Member", &email).await;
+  create_group(&server, &["user:view", "user:edit"], &[org_admin]).await;
+  let resp = server
+    .post(
+      "/organization/members",
+      serde_json::json!({ "email": shared_email }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  server.clear_cookies();
+  assert_eq!(
+    server.login(&email, USER_PASSWORD).await.status(),
+    StatusCode::OK
+  );
+
+  // the account also belongs to the default organization
+  assert!(listed_users(&server).await.contains(&shared));
+  let resp = server
+    .post(
+      "/user/management/disable",
+      serde_json::json!({ "uuid": shared }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+  let resp = server
+    .post(
+      "/user/management/email",
+      serde_json::json!({ "uuid": shared, "new_email": unique_email("renamed") }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+  let resp = server
+    .put(
+      "/user/management",
+      serde_json::json!({ "uuid": shared, "name": "Renamed", "groups": [] }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+  // memberships within the organization are still managed by it
+  let resp = server
+    .put(
+      "/user/management",
+      serde_json::json!({ "uuid": shared, "name": "Member", "groups": [] }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let resp = server
+    .delete(
+      "/organization/members",
+      serde_json::json!({ "uuid": shared }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  assert!(!listed_users(&server).await.contains(&shared));
+}
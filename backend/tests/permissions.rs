@@ -0,0 +1,97 @@
+mod common;
+
+use common::{TestServer, unique};
+use reqwest::StatusCode;
+use serde_json::Value;
+use uuid::Uuid;
+
+async fn create_group(server: &TestServer) -> (Uuid, String) {
+  let name = unique("group");
+  let resp = server
+    .post("/group", serde_json::json!({ "name": name }))
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let created: Value = resp.json().await.unwrap();
+  let id = Uuid::parse_str(created["uuid"].as_str().unwrap()).unwrap();
+  (id, name)
+}
+
+#[tokio::test]
+async fn registry_lists_described_permissions() {
+  let (server, _) = TestServer::start_with_admin().await;
+
+  let resp = server.get("/group/permissions").await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let registry: Value = resp.json().await.unwrap();
+  let registry = registry.as_array().unwrap();
+
+  let group_edit = registry
+    .iter()
+    .find(|p| p["name"] == "group:edit")
+    .expect("group:edit is registered");
+  assert_eq!(group_edit["category"], "Groups");
+  assert!(!group_edit["description"].as_str().unwrap().is_empty());
+  assert_eq!(group_edit["implies"], serde_json::json!(["group:view"]));
+
+  // The admin group is granted everything in the registry at startup.
+  let resp = server.get("/user/info").await;
+  let info: Value = resp.json().await.unwrap();
+  let granted = info["permissions"].as_array().unwrap();
+  for permission in registry {
+    assert!(granted.contains(&permission["name"]));
+  }
+}
+
+#[tokio::test]
+async fn edit_group_grants_implied_permissions() {
+  let (server, admin_id) = TestServer::start_with_admin().await;
+  let (group_id, name) = create_group(&server).await;
+
+  let resp = server
+    .put(
+      "/group",
+      serde_json::json!({
+        "uuid": group_id,
+        "name": name,
+        "permissions": ["group:edit", "user:edit"],
+        "users": [admin_id],
+      }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  let resp = server.get(&format!("/group/{group_id}")).await;
+  let details: Value = resp.json().await.unwrap();
+  let mut permissions: Vec<String> =
+    serde_json::from_value(details["group"]["permissions"].clone()).unwrap();
+  permissions.sort();
+  assert_eq!(
+    permissions,
+    vec!["group:edit", "group:view", "user:edit", "user:view"]
+  );
+}
+
+#[tokio::test]
+async fn edit_group_rejects_unknown_permissions() {
+  let (server, _) = TestServer::start_with_admin().await;
+  let (group_id, name) = create_group(&server).await;
+
+  let resp = server
+    .put(
+      "/group",
+      serde_json::json!({
+        "uuid": group_id,
+        "name": name,
+        "permissions": ["group:delete"],
+        "users": [],
+      }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn registry_requires_group_view() {
+  let server = TestServer::start().await;
+  assert!(!server.get("/group/permissions").await.status().is_success());
+}
@@ -0,0 +1,37 @@
+mod common;
+
+use common::TestServer;
+use reqwest::StatusCode;
+use serde_json::json;
+
+#[tokio::test]
+async fn rate_limits_follow_the_configuration() {
+  unsafe {
+    std::env::set_var("RATE_LIMIT_REPLENISH_SECONDS", "60");
+    std::env::set_var("RATE_LIMIT_BURST", "2");
+  }
+  let server = TestServer::start().await;
+
+  for _ in 0..2 {
+    let resp = server.get("/auth/oidc/url").await;
+    assert_ne!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+  }
+  let resp = server.get("/auth/oidc/url").await;
+  assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+  assert!(resp.headers().contains_key("retry-after"));
+
+  // Every group of routes has its own budget.
+  let resp = server
+    .get_with_header("/user/account/password", "x-forwarded-for", "10.1.2.3")
+    .await;
+  assert_ne!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+
+  // Password logins are limited by the configuration as well.
+  let login = json!({ "email": "nobody@example.com", "password": "wrong" });
+  for _ in 0..2 {
+    let resp = server.post("/auth/password", login.clone()).await;
+    assert_ne!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+  }
+  let resp = server.post("/auth/password", login).await;
+  assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+}
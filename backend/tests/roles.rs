@@ -0,0 +1,243 @@
+mod common;
+
+use common::{TestServer, unique};
+use reqwest::StatusCode;
+use serde_json::Value;
+use uuid::Uuid;
+
+async fn create_role(server: &TestServer, permissions: &[&str]) -> Uuid {
+  let resp = server
+    .post(
+      "/role",
+      serde_json::json!({
+        "name": unique("role"),
+        "description": "test role",
+        "permissions": permissions,
+      }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let created: Value = resp.json().await.unwrap();
+  Uuid::parse_str(created["uuid"].as_str().unwrap()).unwrap()
+}
+
+async fn group_details(server: &TestServer, group_id: Uuid) -> Value {
+  let resp = server.get(&format!("/group/{group_id}")).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let details: Value = resp.json().await.unwrap();
+  details["group"].clone()
+}
+
+fn strings(value: &Value) -> Vec<String> {
+  let mut list: Vec<String> = serde_json::from_value(value.clone()).unwrap();
+  list.sort();
+  list
+}
+
+#[tokio::test]
+async fn built_in_roles_are_seeded_and_read_only() {
+  let (server, _) = TestServer::start_with_admin().await;
+
+  let resp = server.get("/role").await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let roles: Value = resp.json().await.unwrap();
+  let viewer = roles
+    .as_array()
+    .unwrap()
+    .iter()
+    .find(|r| r["name"] == "Viewer")
+    .expect("Viewer role is seeded")
+    .clone();
+  assert_eq!(viewer["built_in"], true);
+  assert!(
+    roles
+      .as_array()
+      .unwrap()
+      .iter()
+      .any(|r| r["name"] == "User manager" && r["built_in"] == true)
+  );
+
+  let resp = server
+    .put(
+      "/role",
+      serde_json::json!({
+        "uuid": viewer["uuid"],
+        "name": "Viewer",
+        "permissions": ["settings:edit"],
+      }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+  let resp = server
+    .delete("/role", serde_json::json!({ "uuid": viewer["uuid"] }))
+    .await;
+  assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn role_crud_flow() {
+  let (server, _) = TestServer::start_with_admin().await;
+  let role_id = create_role(&server, &["group:view"]).await;
+
+  let resp = server.get(&format!("/role/{role_id}")).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let role: Value = resp.json().await.unwrap();
+  assert_eq!(role["permissions"], serde_json::json!(["group:view"]));
+  assert_eq!(role["built_in"], false);
+
+  let name = unique("renamed");
+  let resp = server
+    .put(
+      "/role",
+      serde_json::json!({
+        "uuid": role_id,
+        "name": name,
+        "permissions": ["user:edit"],
+      }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  let resp = server.get(&format!("/role/{role_id}")).await;
+  let role: Value = resp.json().await.unwrap();
+  assert_eq!(role["name"], name);
+  assert_eq!(role["permissions"], serde_json::json!(["user:edit"]));
+
+  let resp = server
+    .delete("/role", serde_json::json!({ "uuid": role_id }))
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  let resp = server.get(&format!("/role/{role_id}")).await;
+  assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn create_role_validates_input() {
+  let (server, _) = TestServer::start_with_admin().await;
+
+  let resp = server
+    .post(
+      "/role",
+      serde_json::json!({ "name": unique("role"), "permissions": ["group:delete"] }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+  let resp = server
+    .post(
+      "/role",
+      serde_json::json!({ "name": "Viewer", "permissions": [] }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn group_combines_direct_and_role_permissions() {
+  let (server, _) = TestServer::start_with_admin().await;
+  let role_id = create_role(&server, &["user:edit"]).await;
+
+  let name = unique("group");
+  let resp = server
+    .post("/group", serde_json::json!({ "name": name }))
+    .await;
+  let created: Value = resp.json().await.unwrap();
+  let group_id = Uuid::parse_str(created["uuid"].as_str().unwrap()).unwrap();
+
+  let resp = server
+    .put(
+      "/group",
+      serde_json::json!({
+        "uuid": group_id,
+        "name": name,
+        "permissions": ["settings:view"],
+        "roles": [role_id],
+        "users": [],
+      }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  let group = group_details(&server, group_id).await;
+  assert_eq!(strings(&group["direct_permissions"]), vec!["settings:view"]);
+  assert_eq!(group["roles"][0]["uuid"], role_id.to_string());
+  assert_eq!(
+    strings(&group["permissions"]),
+    vec!["settings:view", "user:edit", "user:view"]
+  );
+
+  // Omitting roles keeps the attached ones.
+  let resp = server
+    .put(
+      "/group",
+      serde_json::json!({
+        "uuid": group_id,
+        "name": name,
+        "permissions": [],
+        "users": [],
+      }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let group = group_details(&server, group_id).await;
+  assert_eq!(
+    strings(&group["permissions"]),
+    vec!["user:edit", "user:view"]
+  );
+
+  // Editing the role updates every group it is attached to.
+  let resp = server
+    .put(
+      "/role",
+      serde_json::json!({
+        "uuid": role_id,
+        "name": unique("role"),
+        "permissions": ["group:view"],
+      }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let group = group_details(&server, group_id).await;
+  assert_eq!(strings(&group["permissions"]), vec!["group:view"]);
+
+  let resp = server
+    .delete("/role", serde_json::json!({ "uuid": role_id }))
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let group = group_details(&server, group_id).await;
+  assert!(group["permissions"].as_array().unwrap().is_empty());
+  assert!(group["roles"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn edit_group_rejects_unknown_roles() {
+  let (server, _) = TestServer::start_with_admin().await;
+
+  let name = unique("group");
+  let resp = server
+    .post("/group", serde_json::json!({ "name": name }))
+    .await;
+  let created: Value = resp.json().await.unwrap();
+
+  let resp = server
+    .put(
+      "/group",
+      serde_json::json!({
+        "uuid": created["uuid"],
+        "name": name,
+        "permissions": [],
+        "roles": [Uuid::new_v4()],
+        "users": [],
+      }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn roles_require_auth() {
+  let server = TestServer::start().await;
+  assert!(!server.get("/role").await.status().is_success());
+}
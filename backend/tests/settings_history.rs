@@ -0,0 +1,124 @@
+mod common;
+
+use common::TestServer;
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+async fn revisions(server: &TestServer, section: &str) -> Vec<Value> {
+  let resp = server.get(&format!("/settings/history/{section}")).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let revisions: Value = resp.json().await.unwrap();
+  revisions.as_array().unwrap().clone()
+}
+
+#[tokio::test]
+async fn changes_can_be_compared_and_rolled_back() {
+  let (server, admin_id) = TestServer::start_with_admin().await;
+
+  for policy in ["always", "on_create"] {
+    let resp = server
+      .post(
+        "/settings/profile_sync",
+        json!({ "name": policy, "email": "always" }),
+      )
+      .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+  }
+
+  let listed = revisions(&server, "profile_sync").await;
+  assert_eq!(listed.len(), 2);
+  assert_eq!(listed[0]["revision"], 2);
+  assert_eq!(listed[0]["author"], json!(admin_id));
+
+  let resp = server
+    .get("/settings/history/profile_sync/diff?from=1&to=2")
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let changes: Value = resp.json().await.unwrap();
+  assert_eq!(
+    changes,
+    json!([{ "path": "name", "before": "always", "after": "on_create", "redacted": false }])
+  );
+
+  let resp = server
+    .post(
+      "/settings/history/profile_sync/rollback",
+      json!({ "revision": 1 }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let rolled_back: Value = resp.json().await.unwrap();
+  assert_eq!(rolled_back["revision"], 3);
+
+  let settings: Value = server
+    .get("/settings/profile_sync")
+    .await
+    .json()
+    .await
+    .unwrap();
+  assert_eq!(settings["settings"]["name"], "always");
+  let listed = revisions(&server, "profile_sync").await;
+  assert_eq!(listed[0]["restored_from"], 1);
+
+  let resp = server
+    .get("/settings/history/profile_sync/diff?from=1")
+    .await;
+  let changes: Value = resp.json().await.unwrap();
+  assert_eq!(changes, json!([]));
+
+  let resp = server.get("/settings/history/profile_sync/9").await;
+  assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn centaurus_settings_are_kept_without_exposing_secrets() {
+  let (server, _) = TestServer::start_with_admin().await;
+
+  for (server_name, password) in [
+    ("mail.example.com", "first"),
+    ("smtp.example.com", "second"),
+  ] {
+    let resp = server
+      .post(
+        "/settings/mail",
+        json!({
+          "smtp_enabled": false,
+          "smtp_server": server_name,
+          "smtp_password": password,
+        }),
+      )
+      .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+  }
+
+  let listed = revisions(&server, "mail").await;
+  assert_eq!(listed.len(), 2);
+
+  let resp = server.get("/settings/history/mail/1").await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let revision: Value = resp.json().await.unwrap();
+  assert_eq!(revision["content"]["smtp_server"], "mail.example.com");
+  assert!(revision["content"].get("smtp_password").is_none());
+
+  let resp = server.get("/settings/history/mail/diff?from=1").await;
+  let changes: Value = resp.json().await.unwrap();
+  assert_eq!(
+    changes,
+    json!([
+      { "path": "smtp_password", "before": null, "after": null, "redacted": true },
+      {
+        "path": "smtp_server",
+        "before": "mail.example.com",
+        "after": "smtp.example.com",
+        "redacted": false,
+      },
+    ])
+  );
+
+  let resp = server
+    .post("/settings/history/mail/rollback", json!({ "revision": 1 }))
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let settings: Value = server.get("/settings/mail").await.json().await.unwrap();
+  assert_eq!(settings["settings"]["smtp_server"], "mail.example.com");
+}
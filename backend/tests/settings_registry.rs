@@ -0,0 +1,58 @@
+mod common;
+
+use common::TestServer;
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+#[tokio::test]
+async fn module_settings_default_until_saved() {
+  let (server, _) = TestServer::start_with_admin().await;
+
+  let resp = server.get("/settings/profile_sync").await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let settings: Value = resp.json().await.unwrap();
+  assert_eq!(
+    settings,
+    json!({ "settings": { "name": "always", "email": "always" }, "from_env": [] })
+  );
+
+  // missing fields fall back to their defaults
+  let resp = server
+    .post("/settings/profile_sync", json!({ "email": "on_create" }))
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let settings: Value = server
+    .get("/settings/profile_sync")
+    .await
+    .json()
+    .await
+    .unwrap();
+  assert_eq!(
+    settings["settings"],
+    json!({ "name": "always", "email": "on_create" })
+  );
+
+  let resp = server.get("/audit?action=settings.profile_sync").await;
+  let events: Value = resp.json().await.unwrap();
+  assert_eq!(events["events"][0]["after"]["email"], "on_create");
+}
+
+#[tokio::test]
+async fn invalid_module_settings_are_rejected() {
+  let (server, _) = TestServer::start_with_admin().await;
+
+  for settings in [
+    json!({ "name": "sometimes" }),
+    json!({ "email": 1 }),
+    json!([]),
+  ] {
+    let resp = server.post("/settings/profile_sync", settings).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+  }
+
+  let resp = server.get("/settings/history/profile_sync").await;
+  let revisions: Value = resp.json().await.unwrap();
+  assert_eq!(revisions, json!([]));
+  let resp = server.get("/settings/history/unknown").await;
+  assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
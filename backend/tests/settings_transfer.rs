@@ -0,0 +1,210 @@
+mod common;
+
+use common::TestServer;
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+
+async fn export(server: &TestServer) -> Value {
+  let resp = server.get("/settings/export").await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  resp.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn exported_settings_can_be_previewed_and_imported() {
+  let (server, _) = TestServer::start_with_admin().await;
+  let resp = server
+    .post(
+      "/settings/mail",
+      json!({
+        "smtp_enabled": false,
+        "smtp_server": "mail.example.com",
+        "smtp_password": "secret",
+      }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  let mut exported = export(&server).await;
+  assert_eq!(exported["version"], 1);
+  let mail = &exported["sections"]["mail"];
+  assert_eq!(mail["smtp_server"], "mail.example.com");
+  assert!(mail.get("smtp_password").is_none());
+  assert_eq!(exported["sections"]["profile_sync"]["name"], "always");
+
+  exported["sections"]["mail"]["smtp_server"] = json!("smtp.example.com");
+  exported["sections"]["profile_sync"]["name"] = json!("on_create");
+
+  let resp = server
+    .post("/settings/import/preview", exported.clone())
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let preview: Value = resp.json().await.unwrap();
+  let mail = preview
+    .as_array()
+    .unwrap()
+    .iter()
+    .find(|section| section["section"] == "mail")
+    .unwrap();
+  assert_eq!(
+    mail["changes"],
+    json!([{
+      "path": "smtp_server",
+      "before": "mail.example.com",
+      "after": "smtp.example.com",
+      "redacted": false,
+    }])
+  );
+
+  // the preview changes nothing
+  let settings: Value = server
+    .get("/settings/profile_sync")
+    .await
+    .json()
+    .await
+    .unwrap();
+  assert_eq!(settings["settings"]["name"], "always");
+
+  let resp = server.post("/settings/import", exported).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let applied: Value = resp.json().await.unwrap();
+  let sections: Vec<&str> = applied
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|section| section["section"].as_str().unwrap())
+    .collect();
+  assert_eq!(sections, ["mail", "profile_sync"]);
+
+  let settings: Value = server
+    .get("/settings/profile_sync")
+    .await
+    .json()
+    .await
+    .unwrap();
+  assert_eq!(settings["settings"]["name"], "on_create");
+
+  // the password left out of the export was kept
+  let resp = server.get("/settings/history/mail/diff?from=1").await;
+  let changes: Value = resp.json().await.unwrap();
+  assert_eq!(changes.as_array().unwrap().len(), 1);
+  assert_eq!(changes[0]["path"], "smtp_server");
+}
+
+#[tokio::test]
+async fn invalid_imports_change_nothing() {
+  let (server, _) = TestServer::start_with_admin().await;
+  let exported = export(&server).await;
+
+  let mut unknown = exported.clone();
+  unknown["sections"]["unknown"] = json!({});
+  let mut invalid = exported.clone();
+  invalid["sections"]["profile_sync"]["name"] = json!("sometimes");
+  let mut version = exported.clone();
+  version["version"] = json!(2);
+
+  for body in [unknown, invalid, version] {
+    let resp = server.post("/settings/import", body).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+  }
+
+  let resp = server.get("/settings/history/profile_sync").await;
+  let revisions: Value = resp.json().await.unwrap();
+  assert_eq!(revisions, json!([]));
+}
+
+#[tokio::test]
+async fn pinned_settings_cannot_drift() {
+  unsafe {
+    std::env::set_var("SETTINGS_PIN_PROFILE_SYNC__NAME", "on_create");
+  }
+  let (server, _) = TestServer::start_with_admin().await;
+
+  let settings: Value = server
+    .get("/settings/profile_sync")
+    .await
+    .json()
+    .await
+    .unwrap();
+  assert_eq!(
+    settings,
+    json!({
+      "settings": { "name": "on_create", "email": "always" },
+      "from_env": ["name"],
+    })
+  );
+
+  let resp = server
+    .post(
+      "/settings/profile_sync",
+      json!({ "name": "always", "email": "on_create" }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let settings: Value = server
+    .get("/settings/profile_sync")
+    .await
+    .json()
+    .await
+    .unwrap();
+  assert_eq!(
+    settings["settings"],
+    json!({ "name": "on_create", "email": "on_create" })
+  );
+
+  let mut exported = export(&server).await;
+  exported["sections"]["profile_sync"]["name"] = json!("always");
+  let resp = server
+    .post("/settings/import/preview", exported.clone())
+    .await;
+  let preview: Value = resp.json().await.unwrap();
+  let profile_sync = preview
+    .as_array()
+    .unwrap()
+    .iter()
+    .find(|section| section["section"] == "profile_sync")
+    .unwrap();
+  assert_eq!(profile_sync["pinned"], json!(["name"]));
+
+  // the imported value is stored, the pinned one still takes precedence
+  let resp = server.post("/settings/import", exported).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let settings: Value = server
+    .get("/settings/profile_sync")
+    .await
+    .json()
+    .await
+    .unwrap();
+  assert_eq!(
+    settings["settings"],
+    json!({ "name": "on_create", "email": "on_create" })
+  );
+  let exported = export(&server).await;
+  assert_eq!(exported["sections"]["profile_sync"]["name"], "always");
+}
+
+#[tokio::test]
+async fn failing_sections_undo_the_whole_import() {
+  let (server, _) = TestServer::start_with_admin().await;
+  let mut exported = export(&server).await;
+  exported["sections"]["mail"]["smtp_server"] = json!("smtp.example.com");
+  // nothing listens there, so the OIDC settings cannot be applied
+  exported["sections"]["user"]["oidc_enabled"] = json!(true);
+  exported["sections"]["user"]["oidc_issuer"] = json!("http://127.0.0.1:1");
+  exported["sections"]["user"]["oidc_client_id"] = json!("client");
+  exported["sections"]["user"]["oidc_client_secret"] = json!("secret");
+
+  let resp = server.post("/settings/import", exported).await;
+  assert_eq!(resp.status(), StatusCode::NOT_ACCEPTABLE);
+
+  let exported = export(&server).await;
+  assert_ne!(
+    exported["sections"]["mail"]["smtp_server"],
+    "smtp.example.com"
+  );
+  for section in ["mail", "user"] {
+    let resp = server.get(&format!("/settings/history/{section}")).await;
+    let revisions: Value = resp.json().await.unwrap();
+    assert_eq!(revisions, json!([]));
+  }
+}
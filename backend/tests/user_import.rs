@@ -0,0 +1,153 @@
+mod common;
+
+use common::{TestServer, USER_PASSWORD, unique, unique_email};
+use reqwest::StatusCode;
+use serde_json::Value;
+use uuid::Uuid;
+
+async fn import(server: &TestServer, query: &str, body: &str) -> Value {
+  let resp = server
+    .post_text(&format!("/user/management/import?{query}"), body)
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  resp.json().await.unwrap()
+}
+
+fn errors(report: &Value, row: usize) -> String {
+  report["rows"][row - 1]["errors"].to_string()
+}
+
+#[tokio::test]
+async fn dry_run_reports_row_errors() {
+  let (server, _) = TestServer::start_with_admin().await;
+  let email = format!("{}@example.com", unique("import"));
+  let csv = format!(
+    "name,email,groups,password\n\
+     Valid,{email},Admin,secretpass1\n\
+     Dup,{},,secretpass1\n\
+     ,empty-name@example.com,,secretpass1\n\
+     Existing,admin@example.com,,secretpass1\n\
+     Lost,lost@example.com,Nowhere,secretpass1\n",
+    email.to_uppercase()
+  );
+
+  let report = import(&server, "format=csv&dry_run=true", &csv).await;
+  assert_eq!(report["valid"], 1);
+  assert_eq!(report["invalid"], 4);
+  assert_eq!(report["created"], 0);
+  assert_eq!(report["rows"][0]["errors"], serde_json::json!([]));
+  assert!(errors(&report, 2).contains("Duplicate email"));
+  assert!(errors(&report, 3).contains("Name cannot be empty"));
+  assert!(errors(&report, 4).contains("already exists"));
+  assert!(errors(&report, 5).contains("Group Nowhere not found"));
+
+  // Nothing was created.
+  let resp = server
+    .get(&format!("/user/management?search={email}"))
+    .await;
+  let page: Value = resp.json().await.unwrap();
+  assert_eq!(page["total"], 0);
+}
+
+#[tokio::test]
+async fn import_modes_apply_all_or_valid_rows() {
+  let (server, _) = TestServer::start_with_admin().await;
+  let email = format!("{}@example.com", unique("import"));
+  let records = serde_json::json!([
+    { "name": "Imported", "email": email, "groups": ["Admin"], "password": "secretpass1" },
+    { "name": "Broken", "email": "not-an-email", "password": "secretpass1" },
+  ])
+  .to_string();
+
+  // One invalid row stops a transactional import.
+  let report = import(&server, "", &records).await;
+  assert_eq!(report["created"], 0);
+  assert!(report["rows"][0]["uuid"].is_null());
+
+  let report = import(&server, "mode=best_effort", &records).await;
+  assert_eq!(report["created"], 1);
+  assert!(report["rows"][0]["uuid"].is_string());
+  assert!(report["rows"][1]["uuid"].is_null());
+
+  let resp = server
+    .get(&format!("/user/management?search={email}"))
+    .await;
+  let page: Value = resp.json().await.unwrap();
+  assert_eq!(page["items"][0]["groups"][0]["name"], "Admin");
+
+  server.clear_cookies();
+  let resp = server.login(&email, "secretpass1").await;
+  assert_eq!(resp.status(), StatusCode::OK);
+}
+
+/// Creates a group holding the permissions and the users.
+async fn create_group(server: &TestServer, permissions: &[&str], users: &[Uuid]) -> String {
+  let name = unique("group");
+  let resp = server
+    .post("/group", serde_json::json!({ "name": name }))
+    .await;
+  let created: Value = resp.json().await.unwrap();
+  let resp = server
+    .put(
+      "/group",
+      serde_json::json!({
+        "uuid": created["uuid"],
+        "name": name,
+        "permissions": permissions,
+        "users": users,
+      }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  name
+}
+
+#[tokio::test]
+async fn imported_groups_cannot_grant_more_than_the_importer_has() {
+  let (server, _) = TestServer::start_with_admin().await;
+  let importer_email = unique_email("importer");
+  let importer = server.create_user("Importer", &importer_email).await;
+  let importers = create_group(&server, &["user:view", "user:edit"], &[importer]).await;
+  let powerful = create_group(&server, &["settings:edit"], &[]).await;
+
+  server.clear_cookies();
+  let resp = server.login(&importer_email, USER_PASSWORD).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  let records = serde_json::json!([
+    { "name": "Peer", "email": unique_email("peer"), "groups": [importers], "password": "secretpass1" },
+    { "name": "Raised", "email": unique_email("raised"), "groups": [powerful], "password": "secretpass1" },
+  ])
+  .to_string();
+  let report = import(&server, "dry_run=true", &records).await;
+  assert_eq!(report["rows"][0]["errors"], serde_json::json!([]));
+  assert!(errors(&report, 2).contains("permissions you do not have: settings:edit"));
+
+  let report = import(&server, "", &records).await;
+  assert_eq!(report["created"], 0);
+}
+
+#[tokio::test]
+async fn export_matches_the_import_format() {
+  let (server, _) = TestServer::start_with_admin().await;
+
+  let resp = server.get("/user/management/export").await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let body = resp.text().await.unwrap();
+  let records: Value = serde_json::from_str(&body).unwrap();
+  assert_eq!(records[0]["email"], "admin@example.com");
+  assert_eq!(records[0]["groups"], serde_json::json!(["Admin"]));
+
+  let resp = server.get("/user/management/export?format=csv").await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let csv = resp.text().await.unwrap();
+  assert!(csv.starts_with("name,email,groups,password\n"));
+  assert!(csv.contains("admin,admin@example.com,Admin,"));
+
+  // Both exports are accepted by the import again.
+  for (format, body) in [("json", body), ("csv", csv)] {
+    let report = import(&server, &format!("format={format}&dry_run=true"), &body).await;
+    assert_eq!(report["rows"].as_array().unwrap().len(), 1);
+    assert!(errors(&report, 1).contains("already exists"));
+  }
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use common::{JWT_COOKIE_NAME, TestServer, USER_PASSWORD, unique_email};
+use reqwest::StatusCode;
+use serde_json::Value;
+use uuid::Uuid;
+
+async fn listed(server: &TestServer, query: &str, user: Uuid) -> Option<Value> {
+  let resp = server.get(&format!("/user/management{query}")).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let users: Value = resp.json().await.unwrap();
+  users["items"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .find(|u| u["uuid"] == serde_json::json!(user))
+    .cloned()
+}
+
+#[tokio::test]
+async fn disabled_users_cannot_log_in() {
+  let (server, _) = TestServer::start_with_admin().await;
+  let email = unique_email("member");
+  let member = server.create_user("Member", &email).await;
+
+  let resp = server
+    .post(
+      "/user/management/disable",
+      serde_json::json!({ "uuid": member }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  let info = listed(&server, "", member).await.unwrap();
+  assert!(info["disabled_at"].is_string());
+  assert!(
+    listed(&server, "?include_disabled=false", member)
+      .await
+      .is_none()
+  );
+
+  server.clear_cookies();
+  let resp = server.login(&email, USER_PASSWORD).await;
+  assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+  assert!(!server.has_cookie(JWT_COOKIE_NAME));
+
+  server.login("admin@example.com", "hunter2pass").await;
+  let resp = server
+    .post(
+      "/user/management/enable",
+      serde_json::json!({ "uuid": member }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+
+  server.clear_cookies();
+  let resp = server.login(&email, USER_PASSWORD).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  // Memberships and the rest of the account survive.
+  let resp = server.get("/user/info").await;
+  assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn deleted_users_are_kept_until_restored() {
+  let (server, _) = TestServer::start_with_admin().await;
+  let email = unique_email("member");
+  let member = server.create_user("Member", &email).await;
+
+  let resp = server
+    .delete("/user/management", serde_json::json!({ "uuid": member }))
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  assert!(listed(&server, "", member).await.is_none());
+  let info = listed(&server, "?include_deleted=true", member)
+    .await
+    .unwrap();
+  assert!(info["deleted_at"].is_string());
+  assert!(info["purge_at"].is_string());
+
+  let resp = server
+    .delete("/user/management", serde_json::json!({ "uuid": member }))
+    .await;
+  assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+  let resp = server
+    .post(
+      "/user/management/restore",
+      serde_json::json!({ "uuid": member }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::OK);
+  assert!(listed(&server, "", member).await.is_some());
+
+  let resp = server.get("/audit").await;
+  let audit = resp.text().await.unwrap();
+  assert!(audit.contains("user.delete"));
+  assert!(audit.contains("user.restore"));
+
+  server.clear_cookies();
+  let resp = server.login(&email, USER_PASSWORD).await;
+  assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn last_active_admin_cannot_be_deactivated() {
+  let (server, admin) = TestServer::start_with_admin().await;
+
+  let resp = server
+    .post(
+      "/user/management/disable",
+      serde_json::json!({ "uuid": admin }),
+    )
+    .await;
+  assert_eq!(resp.status(), StatusCode::CONFLICT);
+  let resp = server
+    .delete("/user/management", serde_json::json!({ "uuid": admin }))
+    .await;
+  assert_eq!(resp.status(), StatusCode::CONFLICT);
+}
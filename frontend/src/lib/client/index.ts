@@ -138,6 +138,7 @@ export {
   type GroupInfoErrors,
   type GroupInfoResponse,
   type GroupInfoResponses,
+  GroupSort,
   type GroupViewPath,
   type InfoData,
   type InfoErrors,
@@ -183,9 +184,12 @@ export {
   type MailActiveResponses,
   type MailSettings,
   type MailSettingsResponse,
+  type ManagedUserInfo,
   type OidcCallbackQuery,
   type OidcSetupResponse,
   type OidcUrlQuery,
+  type Page,
+  type Page2,
   type PasswordUpdate,
   type ResetPasswordData,
   type ResetPasswordError,
@@ -217,6 +221,7 @@ export {
   type SetupPayload,
   type SimpleGroupInfo,
   type SimpleUserInfo,
+  SortOrder,
   SsoType,
   type StartEmailChangeData,
   type StartEmailChangeError,
@@ -248,8 +253,8 @@ export {
   type UserInfoErrors,
   type UserInfoResponse,
   type UserInfoResponses,
-  type UserListInfo,
   type UserSettings,
   type UserSettingsResponse,
+  UserSort,
   type UserViewPath
 } from './types.gen';
@@ -34,6 +34,9 @@ export type CreateGroupRequest = {
 export type CreateUser = {
   email: string;
   name: string;
+  /**
+   * Encrypted with the public key, only used while mail is not set up.
+   */
   password?: string | null;
 };
 
@@ -101,6 +104,10 @@ export type GroupInfo = {
   users: Array<SimpleUserInfo>;
 };
 
+export const GroupSort = { NAME: 'name', CREATED: 'created' } as const;
+
+export type GroupSort = (typeof GroupSort)[keyof typeof GroupSort];
+
 export type GroupViewPath = {
   uuid: string;
 };
@@ -114,9 +121,20 @@ export type KeyRes = {
   key: string;
 };
 
+/**
+ * Envelope of every paginated listing.
+ */
 export type ListGroupResponse = {
   admin_group?: string | null;
-  groups: Array<GroupInfo>;
+  items: Array<GroupInfo>;
+  /**
+   * Cursor of the next page, none on the last page.
+   */
+  next_cursor?: string | null;
+  /**
+   * Number of matching items over all pages.
+   */
+  total: number;
 };
 
 export type LoginReq = {
@@ -144,6 +162,21 @@ export type MailSettingsResponse = {
   settings: MailSettings;
 };
 
+export type ManagedUserInfo = {
+  created_at?: string | null;
+  deleted_at?: string | null;
+  disabled_at?: string | null;
+  email: string;
+  groups: Array<SimpleGroupInfo>;
+  name: string;
+  oidc_user: boolean;
+  /**
+   * When a deleted user is removed for good.
+   */
+  purge_at?: string | null;
+  uuid: string;
+};
+
 export type OidcCallbackQuery = {
   code?: string | null;
   error?: string | null;
@@ -160,6 +193,36 @@ export type OidcUrlQuery = {
   redirect_to?: string | null;
 };
 
+/**
+ * Envelope of every paginated listing.
+ */
+export type Page = {
+  items: Array<ManagedUserInfo>;
+  /**
+   * Cursor of the next page, none on the last page.
+   */
+  next_cursor?: string | null;
+  /**
+   * Number of matching items over all pages.
+   */
+  total: number;
+};
+
+/**
+ * Envelope of every paginated listing.
+ */
+export type Page2 = {
+  items: Array<SimpleUserInfo>;
+  /**
+   * Cursor of the next page, none on the last page.
+   */
+  next_cursor?: string | null;
+  /**
+   * Number of matching items over all pages.
+   */
+  total: number;
+};
+
 export type PasswordUpdate = {
   new_password: string;
   old_password: string;
@@ -199,6 +262,10 @@ export type SimpleUserInfo = {
   name: string;
 };
 
+export const SortOrder = { ASC: 'asc', DESC: 'desc' } as const;
+
+export type SortOrder = (typeof SortOrder)[keyof typeof SortOrder];
+
 export type UserAvatarResetRequest = {
   uuid: string;
 };
@@ -217,12 +284,13 @@ export type UserInfo = {
   uuid: string;
 };
 
-export type UserListInfo = {
-  email: string;
-  groups: Array<SimpleGroupInfo>;
-  name: string;
-  uuid: string;
-};
+export const UserSort = {
+  NAME: 'name',
+  EMAIL: 'email',
+  CREATED: 'created'
+} as const;
+
+export type UserSort = (typeof UserSort)[keyof typeof UserSort];
 
 export type UserSettings = {
   oidc_client_id?: string | null;
@@ -794,7 +862,29 @@ export type DeleteUserResponses = {
 export type ListUsersData = {
   body?: never;
   path?: never;
-  query?: never;
+  query?: {
+    /**
+     * Only members of this group.
+     */
+    group?: string;
+    include_deleted?: boolean;
+    include_disabled?: boolean;
+    /**
+     * Only OIDC users if true, only local users if false.
+     */
+    oidc?: boolean;
+    /**
+     * Matches name, email or attribute values, ignoring case.
+     */
+    search?: string;
+    sort?: UserSort;
+    /**
+     * `next_cursor` of the previous page, the first page if omitted.
+     */
+    cursor?: string;
+    limit?: number;
+    order?: SortOrder;
+  };
   url: '/api/user/management';
 };
 
@@ -810,7 +900,7 @@ export type ListUsersErrors = {
 };
 
 export type ListUsersResponses = {
-  200: Array<UserListInfo>;
+  200: Page;
 };
 
 export type ListUsersResponse = ListUsersResponses[keyof ListUsersResponses];
@@ -1373,7 +1463,19 @@ export type DeleteGroupResponses = {
 export type ListGroupsData = {
   body?: never;
   path?: never;
-  query?: never;
+  query?: {
+    /**
+     * Matches the group name, ignoring case.
+     */
+    search?: string;
+    sort?: GroupSort;
+    /**
+     * `next_cursor` of the previous page, the first page if omitted.
+     */
+    cursor?: string;
+    limit?: number;
+    order?: SortOrder;
+  };
   url: '/api/group';
 };
 
@@ -1501,7 +1603,24 @@ export type GroupInfoResponse = GroupInfoResponses[keyof GroupInfoResponses];
 export type ListUsersSimpleData = {
   body?: never;
   path?: never;
-  query?: never;
+  query?: {
+    /**
+     * Only members of this group.
+     */
+    group?: string;
+    /**
+     * Matches name, email or the attribute values the caller may see, ignoring
+     * case.
+     */
+    search?: string;
+    sort?: UserSort;
+    /**
+     * `next_cursor` of the previous page, the first page if omitted.
+     */
+    cursor?: string;
+    limit?: number;
+    order?: SortOrder;
+  };
   url: '/api/group/users';
 };
 
@@ -1517,7 +1636,7 @@ export type ListUsersSimpleErrors = {
 };
 
 export type ListUsersSimpleResponses = {
-  200: Array<SimpleUserInfo>;
+  200: Page2;
 };
 
 export type ListUsersSimpleResponse =
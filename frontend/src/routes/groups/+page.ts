@@ -6,6 +6,6 @@ export const load: PageLoad = ({ fetch, url }) => {
   return {
     admin_group: groups.then((g) => g?.admin_group ?? undefined),
     error: url.searchParams.get('error'),
-    groups: groups.then((g) => g?.groups ?? [])
+    groups: groups.then((g) => g?.items ?? [])
   };
 };
@@ -1,7 +1,7 @@
 import type { ColumnDef } from '@tanstack/table-core';
 import * as DataTable from '@profidev/pleiades/components/ui/data-table';
 import { createColumn } from '@profidev/pleiades/components/table/helpers.svelte';
-import type { SimpleGroupInfo, UserListInfo } from '$lib/client';
+import type { SimpleGroupInfo, ManagedUserInfo } from '$lib/client';
 import Actions from '@profidev/pleiades/components/table/actions.svelte';
 import UserAvatar from '@profidev/pleiades/components/util/user-avatar.svelte';
 
@@ -9,9 +9,9 @@ export const columns = ({
   deleteUser,
   canEdit
 }: {
-  deleteUser: (user: UserListInfo) => void;
+  deleteUser: (user: ManagedUserInfo) => void;
   canEdit: boolean;
-}): ColumnDef<UserListInfo>[] => [
+}): ColumnDef<ManagedUserInfo>[] => [
   {
     accessorKey: 'avatar',
     cell: ({ row }) =>
@@ -95,7 +95,7 @@ export const oidcSettings = {
 export const groups = {
   default: {
     admin_group: 'group-admins',
-    groups: [
+    items: [
       {
         id: 'group-admins',
         name: 'Admins',
@@ -103,27 +103,33 @@ export const groups = {
         users: [simpleUser]
       },
       { id: 'group-staff', name: 'Staff', permissions: [], users: [] }
-    ]
+    ],
+    total: 2
   },
-  empty: { admin_group: undefined, groups: [] as unknown[] }
+  empty: { admin_group: undefined, items: [] as unknown[], total: 0 }
 };
 
 export const users = {
-  default: [
-    {
-      email: 'bob@example.com',
-      groups: [simpleGroup],
-      name: 'Bob User',
-      uuid: 'user-1'
-    },
-    {
-      email: 'cara@example.com',
-      groups: [],
-      name: 'Cara User',
-      uuid: 'user-2'
-    }
-  ],
-  empty: [] as unknown[]
+  default: {
+    items: [
+      {
+        email: 'bob@example.com',
+        groups: [simpleGroup],
+        name: 'Bob User',
+        oidc_user: false,
+        uuid: 'user-1'
+      },
+      {
+        email: 'cara@example.com',
+        groups: [],
+        name: 'Cara User',
+        oidc_user: false,
+        uuid: 'user-2'
+      }
+    ],
+    total: 2
+  },
+  empty: { items: [] as unknown[], total: 0 }
 };
 
 // Detail payloads.
@@ -158,7 +164,10 @@ export const userDetails = {
   uuid: 'user-1'
 };
 
-export const simpleUsers = { default: [simpleUser], empty: [] as unknown[] };
+export const simpleUsers = {
+  default: { items: [simpleUser], total: 1 },
+  empty: { items: [] as unknown[], total: 0 }
+};
 // The note owner (simpleUser) plus another user, so the note's share control
 // Has someone to share with (the owner is filtered out of the options).
 export const noteUsers = {